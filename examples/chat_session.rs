@@ -15,13 +15,13 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create a client
     let client = create_client(
         "openai",
-        std::env::var("OPENAI_API_KEY")?,
+        &std::env::var("OPENAI_API_KEY")?,
         "gpt-4",
         config,
     )?;
 
     // Create a chat session
-    let mut session = ChatSession::new(Box::new(client));
+    let mut session = ChatSession::new(client);
 
     // First message
     println!("User: What are the main benefits of Rust's ownership system?");
@@ -13,15 +13,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .temperature(0.7)
         .build();
 
-    let azure_client = create_client(
+    let _azure_client = create_client(
         "openai",
-        std::env::var("AZURE_OPENAI_API_KEY").unwrap_or_else(|_| "demo-key".to_string()),
+        &std::env::var("AZURE_OPENAI_API_KEY").unwrap_or_else(|_| "demo-key".to_string()),
         "gpt-4", // Model name (deployment name for Azure)
         azure_config,
     )?;
 
     // Note: This would work with a real Azure endpoint
-    // let response = azure_client.send_prompt("Hello from Azure!").await?;
+    // let response = _azure_client.send_prompt("Hello from Azure!").await?;
     // println!("Azure Response: {}", response);
 
     // Example 2: Local model server (e.g., Ollama, LocalAI, or custom server)
@@ -33,7 +33,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .max_tokens(500)
         .build();
 
-    let local_client = create_client(
+    let _local_client = create_client(
         "openai", // Use OpenAI client for OpenAI-compatible APIs
         "no-key-needed-for-local", // Local servers often don't need API keys
         "llama2", // Or any model available on your local server
@@ -41,7 +41,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     )?;
 
     // Note: This would work with a real local server
-    // let response = local_client.send_prompt("Tell me a joke about Rust programming").await?;
+    // let response = _local_client.send_prompt("Tell me a joke about Rust programming").await?;
     // println!("Local Model Response: {}", response);
 
     // Example 3: Custom proxy or gateway
@@ -53,15 +53,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .retries(3) // Retry on gateway errors
         .build();
 
-    let gateway_client = create_client(
+    let _gateway_client = create_client(
         "openai",
-        std::env::var("COMPANY_API_KEY").unwrap_or_else(|_| "demo-key".to_string()),
+        &std::env::var("COMPANY_API_KEY").unwrap_or_else(|_| "demo-key".to_string()),
         "gpt-4",
         gateway_config,
     )?;
 
     // Note: This would work with a real gateway
-    // let response = gateway_client.send_prompt("Hello through the gateway!").await?;
+    // let response = _gateway_client.send_prompt("Hello through the gateway!").await?;
     // println!("Gateway Response: {}", response);
 
     println!("\nNote: These examples show how to configure custom endpoints.");
@@ -1,6 +1,6 @@
 //! Example demonstrating token usage reporting and response metadata
 
-use chatdelta::{create_client, ClientConfig, AiResponse};
+use chatdelta::{create_client, ClientConfig, AiResponse, Pricing};
 use std::time::Duration;
 
 #[tokio::main]
@@ -15,7 +15,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create an OpenAI client
     let client = create_client(
         "openai",
-        std::env::var("OPENAI_API_KEY")?,
+        &std::env::var("OPENAI_API_KEY")?,
         "gpt-4",
         config,
     )?;
@@ -42,16 +42,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     if let Some(total_tokens) = response.metadata.total_tokens {
         println!("Total tokens: {}", total_tokens);
-        
-        // Estimate cost (example rates, adjust based on actual pricing)
-        let cost_per_1k_prompt = 0.03; // $0.03 per 1K tokens for GPT-4
-        let cost_per_1k_completion = 0.06; // $0.06 per 1K tokens for GPT-4
-        
-        let prompt_cost = (prompt_tokens.unwrap_or(0) as f64 / 1000.0) * cost_per_1k_prompt;
-        let completion_cost = (completion_tokens.unwrap_or(0) as f64 / 1000.0) * cost_per_1k_completion;
-        let total_cost = prompt_cost + completion_cost;
-        
-        println!("Estimated cost: ${:.4}", total_cost);
+    }
+
+    // Estimate cost from published rates (override with Pricing::with_rates)
+    if let Some(cost) = response.metadata.estimated_cost(&Pricing::current()) {
+        println!("Estimated cost: ${:.4}", cost);
     }
     
     if let Some(finish_reason) = &response.metadata.finish_reason {
@@ -1,8 +1,8 @@
 //! Demonstration of AI Orchestration capabilities
 
 use chatdelta::{
-    AiOrchestrator, OrchestrationStrategy, PromptOptimizer,
-    create_client, ClientConfig,
+    AiClient, AiOrchestrator, OrchestrationStrategy, PromptOptimizer,
+    create_client, ClientConfig, ClientError,
 };
 use std::time::Duration;
 
@@ -15,22 +15,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .timeout(Duration::from_secs(30))
         .build();
     
-    let mut clients = Vec::new();
-    
-    // Add available AI models
-    if let Ok(key) = std::env::var("OPENAI_API_KEY") {
-        clients.push(create_client("openai", &key, "gpt-4", config.clone())?);
-        println!("✅ Added GPT-4");
-    }
-    
-    if let Ok(key) = std::env::var("ANTHROPIC_API_KEY") {
-        clients.push(create_client("claude", &key, "claude-3-opus", config.clone())?);
-        println!("✅ Added Claude 3 Opus");
-    }
-    
-    if let Ok(key) = std::env::var("GEMINI_API_KEY") {
-        clients.push(create_client("gemini", &key, "gemini-1.5-pro", config.clone())?);
-        println!("✅ Added Gemini 1.5 Pro");
+    let clients = build_clients(&config)?;
+    for client in &clients {
+        println!("✅ Added {} ({})", client.name(), client.model());
     }
     
     if clients.is_empty() {
@@ -55,7 +42,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     ];
     
     for query in queries {
-        println!("\n{'='*60}");
+        println!("\n{}", "=".repeat(60));
         println!("📝 Original Query: {}", query);
         
         // Optimize the prompt
@@ -110,7 +97,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     for strategy in strategies {
         println!("\n📍 Strategy: {:?}", strategy);
         
-        let orchestrator = AiOrchestrator::new(clients.clone())
+        let orchestrator = AiOrchestrator::new(build_clients(&config)?)
             .with_strategy(strategy);
         
         if let Ok(response) = orchestrator.query(test_prompt).await {
@@ -123,4 +110,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("\n🎉 Demo Complete!");
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Create a client for every provider that has an API key configured
+fn build_clients(config: &ClientConfig) -> Result<Vec<Box<dyn AiClient>>, ClientError> {
+    let mut clients = Vec::new();
+    
+    if let Ok(key) = std::env::var("OPENAI_API_KEY") {
+        clients.push(create_client("openai", &key, "gpt-4", config.clone())?);
+    }
+    
+    if let Ok(key) = std::env::var("ANTHROPIC_API_KEY") {
+        clients.push(create_client("claude", &key, "claude-3-opus", config.clone())?);
+    }
+    
+    if let Ok(key) = std::env::var("GEMINI_API_KEY") {
+        clients.push(create_client("gemini", &key, "gemini-1.5-pro", config.clone())?);
+    }
+    
+    Ok(clients)
+}
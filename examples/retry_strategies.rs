@@ -14,9 +14,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .retry_strategy(RetryStrategy::Fixed(Duration::from_secs(2)))
         .build();
 
-    let client = create_client(
+    let _client = create_client(
         "openai",
-        std::env::var("OPENAI_API_KEY").unwrap_or_else(|_| "demo-key".to_string()),
+        &std::env::var("OPENAI_API_KEY").unwrap_or_else(|_| "demo-key".to_string()),
         "gpt-4",
         fixed_config,
     )?;
@@ -31,9 +31,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .retry_strategy(RetryStrategy::Linear(Duration::from_secs(1)))
         .build();
 
-    let client = create_client(
+    let _client = create_client(
         "openai",
-        std::env::var("OPENAI_API_KEY").unwrap_or_else(|_| "demo-key".to_string()),
+        &std::env::var("OPENAI_API_KEY").unwrap_or_else(|_| "demo-key".to_string()),
         "gpt-4",
         linear_config,
     )?;
@@ -48,9 +48,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .retry_strategy(RetryStrategy::Exponential(Duration::from_secs(1)))
         .build();
 
-    let client = create_client(
+    let _client = create_client(
         "openai",
-        std::env::var("OPENAI_API_KEY").unwrap_or_else(|_| "demo-key".to_string()),
+        &std::env::var("OPENAI_API_KEY").unwrap_or_else(|_| "demo-key".to_string()),
         "gpt-4",
         exponential_config,
     )?;
@@ -65,9 +65,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .retry_strategy(RetryStrategy::ExponentialWithJitter(Duration::from_millis(500)))
         .build();
 
-    let client = create_client(
+    let _client = create_client(
         "openai",
-        std::env::var("OPENAI_API_KEY").unwrap_or_else(|_| "demo-key".to_string()),
+        &std::env::var("OPENAI_API_KEY").unwrap_or_else(|_| "demo-key".to_string()),
         "gpt-4",
         jitter_config,
     )?;
@@ -81,9 +81,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .retry_strategy(RetryStrategy::Fixed(Duration::from_millis(500))) // Short delays
         .build();
 
-    let critical_client = create_client(
+    let _critical_client = create_client(
         "openai",
-        std::env::var("OPENAI_API_KEY").unwrap_or_else(|_| "demo-key".to_string()),
+        &std::env::var("OPENAI_API_KEY").unwrap_or_else(|_| "demo-key".to_string()),
         "gpt-4",
         aggressive_config,
     )?;
@@ -97,9 +97,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .retry_strategy(RetryStrategy::Exponential(Duration::from_secs(5))) // Longer base delay
         .build();
 
-    let rate_limited_client = create_client(
+    let _rate_limited_client = create_client(
         "openai",
-        std::env::var("OPENAI_API_KEY").unwrap_or_else(|_| "demo-key".to_string()),
+        &std::env::var("OPENAI_API_KEY").unwrap_or_else(|_| "demo-key".to_string()),
         "gpt-4",
         conservative_config,
     )?;
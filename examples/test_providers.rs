@@ -1,4 +1,4 @@
-use chatdelta::{create_client, ClientConfig, AiClient};
+use chatdelta::{create_client, ClientConfig};
 use std::env;
 use std::time::Duration;
 
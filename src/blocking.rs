@@ -0,0 +1,129 @@
+//! Blocking wrapper for synchronous callers
+//!
+//! [`BlockingClient`] owns a single-threaded Tokio runtime and drives an
+//! [`AiClient`] on it, so CLI tools and other synchronous code can use any client
+//! without setting up async themselves. Do not call it from inside an async
+//! context: blocking on a runtime from within another runtime panics.
+
+use crate::{AiClient, AiResponse, ClientError, Conversation, StreamChunk};
+use futures::StreamExt;
+use tokio::runtime::{Builder, Runtime};
+
+/// Synchronous facade over an [`AiClient`]
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use chatdelta::{create_client, BlockingClient, ClientConfig};
+///
+/// # fn example() -> Result<(), chatdelta::ClientError> {
+/// let client = create_client("openai", "your-api-key", "gpt-4o", ClientConfig::default())?;
+/// let client = BlockingClient::new(client)?;
+/// for chunk in client.stream_prompt("Tell me a story")? {
+///     print!("{}", chunk?.content);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct BlockingClient {
+    client: Box<dyn AiClient>,
+    runtime: Runtime,
+}
+
+impl BlockingClient {
+    /// Wrap `client`, starting the runtime its requests run on
+    pub fn new(client: Box<dyn AiClient>) -> Result<Self, ClientError> {
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| ClientError::config(format!("Failed to start runtime: {e}"), None))?;
+        Ok(Self { client, runtime })
+    }
+
+    /// The wrapped async client
+    pub fn inner(&self) -> &dyn AiClient {
+        self.client.as_ref()
+    }
+
+    /// Send a prompt and wait for the reply
+    pub fn send_prompt(&self, prompt: &str) -> Result<String, ClientError> {
+        self.runtime.block_on(self.client.send_prompt(prompt))
+    }
+
+    /// Send a prompt and wait for the reply with its metadata
+    pub fn send_prompt_with_metadata(&self, prompt: &str) -> Result<AiResponse, ClientError> {
+        self.runtime.block_on(self.client.send_prompt_with_metadata(prompt))
+    }
+
+    /// Send a conversation and wait for the reply
+    pub fn send_conversation(&self, conversation: &Conversation) -> Result<String, ClientError> {
+        self.runtime.block_on(self.client.send_conversation(conversation))
+    }
+
+    /// Stream a prompt, yielding chunks as they arrive
+    ///
+    /// Each call to `next` blocks until the provider sends the next chunk.
+    pub fn stream_prompt(
+        &self,
+        prompt: &str,
+    ) -> Result<impl Iterator<Item = Result<StreamChunk, ClientError>> + '_, ClientError> {
+        let mut stream = self.runtime.block_on(self.client.stream_prompt(prompt))?;
+        Ok(std::iter::from_fn(move || self.runtime.block_on(stream.next())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use futures::stream::BoxStream;
+
+    struct ChunkClient;
+
+    #[async_trait]
+    impl AiClient for ChunkClient {
+        async fn send_prompt(&self, prompt: &str) -> Result<String, ClientError> {
+            Ok(format!("echo: {prompt}"))
+        }
+
+        async fn stream_prompt(
+            &self,
+            _prompt: &str,
+        ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
+            let chunks = ["Hel", "lo", ""].into_iter().enumerate().map(|(i, content)| {
+                Ok(StreamChunk {
+                    content: content.to_string(),
+                    finished: i == 2,
+                    metadata: None,
+                })
+            });
+            Ok(Box::pin(futures::stream::iter(chunks).then(|chunk| async move {
+                tokio::task::yield_now().await;
+                chunk
+            })))
+        }
+
+        fn name(&self) -> &str {
+            "Chunks"
+        }
+
+        fn model(&self) -> &str {
+            "chunks-1"
+        }
+    }
+
+    #[test]
+    fn test_blocking_stream_prompt() {
+        let client = BlockingClient::new(Box::new(ChunkClient)).unwrap();
+        assert_eq!(client.send_prompt("Hi").unwrap(), "echo: Hi");
+
+        let chunks: Vec<StreamChunk> = client
+            .stream_prompt("Hi")
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let content: String = chunks.iter().map(|chunk| chunk.content.as_str()).collect();
+        assert_eq!(content, "Hello");
+        assert!(chunks.last().unwrap().finished);
+    }
+}
@@ -1,53 +1,60 @@
 //! Anthropic Claude client implementation
 
 use crate::{
-    execute_with_retry, sse::sse_stream, AiClient, AiResponse, ApiError, ApiErrorType,
-    ClientConfig, ClientError, Conversation, Message, ResponseMetadata, StreamChunk,
-    StreamError, StreamErrorType,
+    utils::{idempotency_key, IDEMPOTENCY_KEY_HEADER},
+    execute_with_retry, observability::ObservabilityContext, rate_limit::RateLimitTracker,
+    sse::{client_sse_stream, SseEvent}, with_idle_timeout, AiClient, AiResponse, ApiKey, ApiError, ApiErrorType, AuthError, AuthErrorType, ClientConfig, ClientError,
+    Conversation, Message, RateLimitStatus, RequestPreview, ResponseFormat, ResponseMetadata, StreamChunk, StreamRequest,
 };
 use async_trait::async_trait;
 use futures::stream::{BoxStream, StreamExt};
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::time::Instant;
+use tracing::Instrument;
+
+/// Base URL of the public Anthropic API, used when no `base_url` is configured
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com/v1";
 
 /// Client for Anthropic's Claude models
+#[derive(Debug)]
 pub struct Claude {
     /// Reqwest HTTP client used for requests
     http: Client,
     /// API key for Anthropic
-    key: String,
+    key: ApiKey,
     /// Name of the Claude model to invoke
     model: String,
     /// Configuration for the client
     config: ClientConfig,
+    /// Rate limit status from the most recent response
+    rate_limit: RateLimitTracker,
 }
 
 impl Claude {
     /// Create a new Claude client
     pub fn new(http: Client, key: String, model: String, config: ClientConfig) -> Self {
+        config.log_unsupported_penalties("Claude");
         Self {
             http,
-            key,
+            key: ApiKey::new(key),
             model,
             config,
+            rate_limit: RateLimitTracker::default(),
         }
     }
-}
 
-#[async_trait]
-impl AiClient for Claude {
-    async fn send_prompt(&self, prompt: &str) -> Result<String, ClientError> {
-        let mut conversation = Conversation::new();
-        if let Some(system_msg) = &self.config.system_message {
-            conversation.add_message(Message::system(system_msg));
-        }
-        conversation.add_user(prompt);
-        self.send_conversation(&conversation).await
+    /// URL of the messages endpoint this client sends requests to
+    ///
+    /// Reflects the configured `base_url`, or the public Anthropic API without one.
+    pub fn endpoint_url(&self) -> String {
+        let base_url = self.config.base_url.as_deref().unwrap_or(DEFAULT_BASE_URL);
+        format!("{}/messages", base_url.trim_end_matches('/'))
     }
 
-    async fn send_conversation(&self, conversation: &Conversation) -> Result<String, ClientError> {
+    /// Build the messages request for `conversation` on `model`, ready to send
+    fn messages_request(&self, conversation: &Conversation, model: &str) -> RequestBuilder {
         #[derive(Serialize)]
         struct ClaudeMessage {
             role: String,
@@ -65,109 +72,41 @@ impl AiClient for Claude {
             system: Option<String>,
         }
 
-        #[derive(Deserialize)]
-        struct Response {
-            content: Vec<ContentBlock>,
-        }
-
-        #[derive(Deserialize)]
-        struct ContentBlock {
-            text: String,
-        }
-
         // Claude API requires system messages to be handled separately
-        let (system_message, messages): (Option<String>, Vec<_>) = {
-            let mut system_msg = None;
-            let mut regular_messages = Vec::new();
-
-            for msg in &conversation.messages {
-                if msg.role == "system" {
-                    system_msg = Some(msg.content.clone());
-                } else {
-                    regular_messages.push(ClaudeMessage {
-                        role: msg.role.clone(),
-                        content: msg.content.clone(),
-                    });
-                }
-            }
-            (system_msg, regular_messages)
-        };
+        let (system_messages, regular_messages) = conversation.split_system();
+        let system_message = system_prompt(system_messages, &self.config);
+        let messages = regular_messages
+            .into_iter()
+            .map(|msg| ClaudeMessage {
+                role: msg.role,
+                content: msg.content,
+            })
+            .collect();
 
         let body = Request {
-            model: self.model.clone(),
+            model: model.to_string(),
             messages,
             max_tokens: self.config.max_tokens.unwrap_or(1024),
             temperature: self.config.temperature,
             system: system_message,
         };
 
-        execute_with_retry(self.config.retries, || async {
-            let response = self
-                .http
-                .post("https://api.anthropic.com/v1/messages")
-                .header("x-api-key", &self.key)
-                .header("anthropic-version", "2023-06-01")
-                .header("content-type", "application/json")
-                .json(&body)
-                .send()
-                .await?;
-
-            let resp: Response = response.json().await?;
-            Ok(resp
-                .content
-                .first()
-                .map(|c| c.text.clone())
-                .unwrap_or_else(|| "No response from Claude".to_string()))
-        })
-        .await
-    }
-
-    fn supports_conversations(&self) -> bool {
-        true
-    }
-
-    fn supports_streaming(&self) -> bool {
-        true
-    }
-
-    fn name(&self) -> &str {
-        "Claude"
-    }
-
-    fn model(&self) -> &str {
-        &self.model
-    }
-
-    async fn send_prompt_with_metadata(&self, prompt: &str) -> Result<AiResponse, ClientError> {
-        let mut conversation = Conversation::new();
-        if let Some(system_msg) = &self.config.system_message {
-            conversation.add_message(Message::system(system_msg));
-        }
-        conversation.add_user(prompt);
-        self.send_conversation_with_metadata(&conversation).await
+        let request = self
+            .http
+            .post(self.endpoint_url())
+            .header("x-api-key", self.key.expose())
+            .header("anthropic-version", "2023-06-01")
+            .header("content-type", "application/json")
+            .json(&body);
+        self.config.prepare_request(request)
     }
 
-    async fn send_conversation_with_metadata(
+    /// Send a conversation to `model` and parse the response, without recording metrics
+    async fn send_request(
         &self,
         conversation: &Conversation,
+        model: &str,
     ) -> Result<AiResponse, ClientError> {
-        #[derive(Serialize)]
-        struct ClaudeMessage {
-            role: String,
-            content: String,
-        }
-
-        #[derive(Serialize)]
-        struct Request {
-            model: String,
-            messages: Vec<ClaudeMessage>,
-            max_tokens: u32,
-            #[serde(skip_serializing_if = "Option::is_none")]
-            temperature: Option<f32>,
-            #[serde(skip_serializing_if = "Option::is_none")]
-            system: Option<String>,
-        }
-
         #[derive(Deserialize)]
         struct Response {
             content: Vec<ContentBlock>,
@@ -176,6 +115,8 @@ impl AiClient for Claude {
             #[serde(default)]
             model: Option<String>,
             #[serde(default)]
+            stop_reason: Option<String>,
+            #[serde(default)]
             usage: Option<Usage>,
         }
 
@@ -190,63 +131,35 @@ impl AiClient for Claude {
             output_tokens: Option<u32>,
         }
 
-        // Claude API requires system messages to be handled separately
-        let (system_message, messages): (Option<String>, Vec<_>) = {
-            let mut system_msg = None;
-            let mut regular_messages = Vec::new();
-
-            for msg in &conversation.messages {
-                if msg.role == "system" {
-                    system_msg = Some(msg.content.clone());
-                } else {
-                    regular_messages.push(ClaudeMessage {
-                        role: msg.role.clone(),
-                        content: msg.content.clone(),
-                    });
-                }
-            }
-            (system_msg, regular_messages)
-        };
-
-        let body = Request {
-            model: self.model.clone(),
-            messages,
-            max_tokens: self.config.max_tokens.unwrap_or(1024),
-            temperature: self.config.temperature,
-            system: system_message,
-        };
-
+        let idempotency_key = idempotency_key();
         let start_time = Instant::now();
 
-        let (content, resp) = execute_with_retry(self.config.retries, || async {
+        let (content, resp, rate_limit) = execute_with_retry(self.config.retries, || async {
+            self.config.throttle(conversation).await;
             let response = self
-                .http
-                .post("https://api.anthropic.com/v1/messages")
-                .header("x-api-key", &self.key)
-                .header("anthropic-version", "2023-06-01")
-                .header("content-type", "application/json")
-                .json(&body)
+                .messages_request(conversation, model)
+                .header(IDEMPOTENCY_KEY_HEADER, &idempotency_key)
                 .send()
                 .await?;
+            let rate_limit = self.rate_limit.record(response.headers());
 
             if !response.status().is_success() {
-                let status = response.status();
+                let status = response.status().as_u16();
                 let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-                return Err(ClientError::Api(ApiError {
-                    message: format!("Claude API error ({}): {}", status, error_text),
-                    status_code: Some(status.as_u16()),
-                    error_type: ApiErrorType::Other,
-                }));
+                return Err(api_error(status, &error_text));
             }
 
-            let resp: Response = response.json().await?;
+            let response_text = response.text().await?;
+            let resp: Response = serde_json::from_str(&response_text).map_err(|e| {
+                ClientError::from(e).with_raw_content(&response_text, self.config.raw_content_limit)
+            })?;
             let content = resp
                 .content
                 .first()
                 .map(|c| c.text.clone())
                 .unwrap_or_else(|| "No response from Claude".to_string());
             
-            Ok((content, resp))
+            Ok((content, resp, rate_limit))
         })
         .await?;
 
@@ -261,14 +174,146 @@ impl AiClient for Claude {
                     .zip(u.output_tokens)
                     .map(|(i, o)| i + o)
             }),
-            finish_reason: None,
+            finish_reason: resp.stop_reason,
             safety_ratings: None,
             request_id: resp.id,
             latency_ms: Some(latency_ms),
+            rate_limit,
+            idempotency_key: Some(idempotency_key),
+            ..Default::default()
         };
 
         Ok(AiResponse::with_metadata(content, metadata))
     }
+
+    /// Send a streaming messages request and return the successful response
+    async fn open_stream(&self, conversation: &Conversation) -> Result<reqwest::Response, ClientError> {
+        let conversation = self.config.sanitize(conversation);
+        let conversation = conversation.as_ref();
+        #[derive(Serialize)]
+        struct ClaudeMessage {
+            role: String,
+            content: String,
+        }
+
+        #[derive(Serialize)]
+        struct Request {
+            model: String,
+            messages: Vec<ClaudeMessage>,
+            max_tokens: u32,
+            stream: bool,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            temperature: Option<f32>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            system: Option<String>,
+        }
+
+        // Claude API requires system messages to be handled separately
+        let (system_messages, regular_messages) = conversation.split_system();
+        let system_message = system_prompt(system_messages, &self.config);
+        let messages = regular_messages
+            .into_iter()
+            .map(|msg| ClaudeMessage {
+                role: msg.role,
+                content: msg.content,
+            })
+            .collect();
+
+        let body = Request {
+            model: self.model.clone(),
+            messages,
+            max_tokens: self.config.max_tokens.unwrap_or(1024),
+            stream: true,
+            temperature: self.config.temperature,
+            system: system_message,
+        };
+
+        let context = ObservabilityContext::new(
+            self.name().to_string(),
+            self.model.clone(),
+            Arc::new(self.config.metrics.clone().unwrap_or_default()),
+        );
+        self.config.throttle(conversation).await;
+        let request = self
+            .http
+            .post(self.endpoint_url())
+            .header("x-api-key", self.key.expose())
+            .header("anthropic-version", "2023-06-01")
+            .header("content-type", "application/json")
+            .json(&body);
+        let response = self
+            .config
+            .prepare_request(request)
+            .send()
+            .instrument(context.span())
+            .await?;
+        self.rate_limit.record(response.headers());
+
+        if !response.status().is_success() {
+            let status = response.status().as_u16();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(api_error(status, &error_text));
+        }
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl AiClient for Claude {
+    async fn send_prompt(&self, prompt: &str) -> Result<String, ClientError> {
+        let mut conversation = Conversation::new();
+        conversation.add_user(prompt);
+        self.send_conversation(&conversation).await
+    }
+
+    async fn send_conversation(&self, conversation: &Conversation) -> Result<String, ClientError> {
+        let response = self.send_conversation_with_metadata(conversation).await?;
+        Ok(response.content)
+    }
+
+    fn supports_conversations(&self) -> bool {
+        true
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
+    fn name(&self) -> &str {
+        "Claude"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn last_rate_limit(&self) -> Option<RateLimitStatus> {
+        self.rate_limit.last()
+    }
+
+    async fn send_prompt_with_metadata(&self, prompt: &str) -> Result<AiResponse, ClientError> {
+        let mut conversation = Conversation::new();
+        conversation.add_user(prompt);
+        self.send_conversation_with_metadata(&conversation).await
+    }
+
+    async fn send_conversation_with_metadata(
+        &self,
+        conversation: &Conversation,
+    ) -> Result<AiResponse, ClientError> {
+        let conversation = self.config.sanitize(conversation);
+        let conversation = conversation.as_ref();
+        let context = ObservabilityContext::new(
+            self.name().to_string(),
+            self.model.clone(),
+            Arc::new(self.config.metrics.clone().unwrap_or_default()),
+        );
+        let request = self.config.with_fallback_model(&self.model, |model| {
+            self.send_request(conversation, model)
+        });
+        context.track_conversation(&self.config, conversation, request).await
+    }
     
     async fn send_prompt_streaming(
         &self,
@@ -299,35 +344,39 @@ impl AiClient for Claude {
         prompt: &str,
     ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
         let mut conversation = Conversation::new();
-        if let Some(system_msg) = &self.config.system_message {
-            conversation.add_message(Message::system(system_msg));
-        }
         conversation.add_user(prompt);
         self.stream_conversation(&conversation).await
     }
 
-    async fn stream_conversation(
+    fn build_request_preview(
         &self,
         conversation: &Conversation,
-    ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
-        #[derive(Serialize)]
-        struct ClaudeMessage {
-            role: String,
-            content: String,
-        }
+    ) -> Result<RequestPreview, ClientError> {
+        let conversation = self.config.sanitize(conversation);
+        RequestPreview::from_request(self.messages_request(&conversation, &self.model), self.key.expose())
+    }
 
-        #[derive(Serialize)]
-        struct Request {
-            model: String,
-            messages: Vec<ClaudeMessage>,
-            max_tokens: u32,
-            stream: bool,
-            #[serde(skip_serializing_if = "Option::is_none")]
-            temperature: Option<f32>,
-            #[serde(skip_serializing_if = "Option::is_none")]
-            system: Option<String>,
-        }
+    /// System messages anywhere, and the other turns alternating from the user
+    fn validate_conversation(&self, conversation: &Conversation) -> Result<(), ClientError> {
+        crate::require_user_message(&conversation.messages, "Claude")?;
+        crate::check_turn_order(&conversation.messages, true)
+    }
 
+    async fn debug_stream(
+        &self,
+        prompt: &str,
+    ) -> Result<BoxStream<'_, Result<SseEvent, ClientError>>, ClientError> {
+        let mut conversation = Conversation::new();
+        conversation.add_user(prompt);
+        let response = self.open_stream(&conversation).await?;
+        Ok(client_sse_stream(response, &self.config))
+    }
+
+    async fn stream_conversation(
+        &self,
+        conversation: &Conversation,
+    ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
+        // Fields the stream doesn't use are left out and ignored when deserializing
         #[derive(Deserialize, Debug)]
         #[serde(tag = "type")]
         enum StreamEvent {
@@ -336,19 +385,13 @@ impl AiClient for Claude {
                 message: MessageInfo,
             },
             #[serde(rename = "content_block_start")]
-            ContentBlockStart {
-                index: usize,
-                content_block: ContentBlock,
-            },
+            ContentBlockStart,
             #[serde(rename = "content_block_delta")]
             ContentBlockDelta {
-                index: usize,
                 delta: Delta,
             },
             #[serde(rename = "content_block_stop")]
-            ContentBlockStop {
-                index: usize,
-            },
+            ContentBlockStop,
             #[serde(rename = "message_delta")]
             MessageDelta {
                 delta: MessageDeltaInfo,
@@ -364,20 +407,10 @@ impl AiClient for Claude {
         struct MessageInfo {
             id: Option<String>,
             model: Option<String>,
-            usage: Option<Usage>,
-        }
-
-        #[derive(Deserialize, Debug)]
-        struct ContentBlock {
-            #[serde(rename = "type")]
-            block_type: String,
-            text: Option<String>,
         }
 
         #[derive(Deserialize, Debug)]
         struct Delta {
-            #[serde(rename = "type")]
-            delta_type: Option<String>,
             text: Option<String>,
         }
 
@@ -392,55 +425,10 @@ impl AiClient for Claude {
             output_tokens: Option<u32>,
         }
 
-        // Claude API requires system messages to be handled separately
-        let (system_message, messages): (Option<String>, Vec<_>) = {
-            let mut system_msg = None;
-            let mut regular_messages = Vec::new();
-
-            for msg in &conversation.messages {
-                if msg.role == "system" {
-                    system_msg = Some(msg.content.clone());
-                } else {
-                    regular_messages.push(ClaudeMessage {
-                        role: msg.role.clone(),
-                        content: msg.content.clone(),
-                    });
-                }
-            }
-            (system_msg, regular_messages)
-        };
-
-        let body = Request {
-            model: self.model.clone(),
-            messages,
-            max_tokens: self.config.max_tokens.unwrap_or(1024),
-            stream: true,
-            temperature: self.config.temperature,
-            system: system_message,
-        };
-
-        let response = self
-            .http
-            .post("https://api.anthropic.com/v1/messages")
-            .header("x-api-key", &self.key)
-            .header("anthropic-version", "2023-06-01")
-            .header("content-type", "application/json")
-            .json(&body)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(ClientError::Api(ApiError {
-                message: format!("Claude API error ({}): {}", status, error_text),
-                status_code: Some(status.as_u16()),
-                error_type: ApiErrorType::Other,
-            }));
-        }
+        let response = self.open_stream(conversation).await?;
 
         // Parse SSE stream
-        let sse_stream = sse_stream(response);
+        let sse_stream = client_sse_stream(response, &self.config);
         let start_time = Arc::new(std::sync::Mutex::new(Instant::now()));
         let message_info = Arc::new(std::sync::Mutex::new(None));
         let usage_info = Arc::new(std::sync::Mutex::new(None));
@@ -493,6 +481,7 @@ impl AiClient for Claude {
                                                     safety_ratings: None,
                                                     request_id: msg_info.as_ref().and_then(|m| m.id.clone()),
                                                     latency_ms: Some(latency_ms),
+                                                    ..Default::default()
                                                 };
                                                 
                                                 Some(Ok(StreamChunk {
@@ -514,14 +503,230 @@ impl AiClient for Claude {
                                 }
                             }
                         }
-                        Err(e) => Some(Err(ClientError::Stream(StreamError {
-                            message: format!("SSE stream error: {}", e),
-                            error_type: StreamErrorType::Other,
-                        }))),
+                        Err(e) => Some(Err(e)),
                     }
                 }
             });
 
         Ok(Box::pin(stream))
     }
+
+    async fn stream_conversation_with(
+        &self,
+        conversation: &Conversation,
+        request: &StreamRequest,
+    ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
+        let stream = self.stream_conversation(conversation).await?;
+        Ok(with_idle_timeout(stream, request.idle_timeout, self.config.metrics.clone()))
+    }
+}
+
+/// Join the conversation's system messages into Claude's `system` field
+///
+/// System messages in the conversation take precedence; without any, the configured
+/// system message is used instead, as with the other providers. Claude has no JSON
+/// mode, so a requested [`ResponseFormat`] is appended as an instruction.
+fn system_prompt(system_messages: Vec<Message>, config: &ClientConfig) -> Option<String> {
+    let instruction = config
+        .response_format
+        .as_ref()
+        .and_then(ResponseFormat::instruction);
+    let system: Vec<String> = if system_messages.is_empty() {
+        config.system_message.iter().cloned().collect()
+    } else {
+        system_messages.into_iter().map(|msg| msg.content).collect()
+    };
+    let parts: Vec<String> = system.into_iter().chain(instruction).collect();
+    (!parts.is_empty()).then(|| parts.join("\n\n"))
+}
+
+/// Map a non-2xx Claude response to a client error
+///
+/// Claude reports failures as `{"error": {"type": "...", "message": "..."}}`; the
+/// `type` decides the error kind. Bodies without one fall back to the HTTP status.
+fn api_error(status: u16, body: &str) -> ClientError {
+    #[derive(Deserialize)]
+    struct ErrorBody {
+        error: ErrorInfo,
+    }
+
+    #[derive(Deserialize)]
+    struct ErrorInfo {
+        #[serde(rename = "type")]
+        error_type: String,
+        message: String,
+    }
+
+    let (kind, detail) = match serde_json::from_str::<ErrorBody>(body) {
+        Ok(body) => (body.error.error_type, body.error.message),
+        Err(_) => (String::new(), body.to_string()),
+    };
+    let message = format!("Claude API error ({status}): {detail}");
+
+    let auth_error = match kind.as_str() {
+        "authentication_error" => Some(AuthErrorType::InvalidApiKey),
+        "permission_error" => Some(AuthErrorType::InsufficientPermissions),
+        _ if status == 401 => Some(AuthErrorType::InvalidApiKey),
+        _ => None,
+    };
+    if let Some(error_type) = auth_error {
+        return ClientError::Authentication(AuthError {
+            message,
+            error_type,
+            source: None,
+        });
+    }
+
+    let error_type = match kind.as_str() {
+        "rate_limit_error" => ApiErrorType::RateLimit,
+        "overloaded_error" | "api_error" => ApiErrorType::ServerError,
+        "invalid_request_error" | "request_too_large" => ApiErrorType::BadRequest,
+        "not_found_error" => ApiErrorType::InvalidModel,
+        _ => match status {
+            429 => ApiErrorType::RateLimit,
+            400 | 413 => ApiErrorType::BadRequest,
+            500.. => ApiErrorType::ServerError,
+            _ => ApiErrorType::Other,
+        },
+    };
+    ClientError::Api(ApiError {
+        message,
+        status_code: Some(status),
+        error_type,
+        source: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_configured_timeout_overrides_shared_client_timeout() {
+        // Claude's pooled client defaults to 45 seconds
+        let config = ClientConfig::builder().timeout(std::time::Duration::from_secs(30)).build();
+        let http = crate::http_client_for("claude", &config).unwrap();
+        let client = Claude::new(http, "test-key".into(), "claude-3-5-sonnet-20241022".into(), config);
+
+        let request = client
+            .messages_request(&Conversation::new(), "claude-3-5-sonnet-20241022")
+            .build()
+            .unwrap();
+        assert_eq!(request.timeout(), Some(&std::time::Duration::from_secs(30)));
+    }
+
+    #[test]
+    fn test_api_error_from_error_body() {
+        let body = r#"{"type": "error", "error": {"type": "overloaded_error", "message": "Overloaded"}}"#;
+        let err = api_error(529, body);
+        assert!(matches!(
+            err,
+            ClientError::Api(ApiError { error_type: ApiErrorType::ServerError, status_code: Some(529), .. })
+        ));
+        assert!(err.is_retryable());
+        assert_eq!(err.to_string(), "API error (529): Claude API error (529): Overloaded");
+
+        let body = r#"{"type": "error", "error": {"type": "rate_limit_error", "message": "Number of request tokens has exceeded your per-minute rate limit"}}"#;
+        let err = api_error(429, body);
+        assert!(err.is_rate_limit());
+
+        let body = r#"{"type": "error", "error": {"type": "authentication_error", "message": "invalid x-api-key"}}"#;
+        assert!(matches!(
+            api_error(401, body),
+            ClientError::Authentication(AuthError { error_type: AuthErrorType::InvalidApiKey, .. })
+        ));
+
+        let body = r#"{"type": "error", "error": {"type": "permission_error", "message": "Your API key does not have permission to use the specified resource."}}"#;
+        assert!(matches!(
+            api_error(403, body),
+            ClientError::Authentication(AuthError {
+                error_type: AuthErrorType::InsufficientPermissions,
+                ..
+            })
+        ));
+
+        let body = r#"{"type": "error", "error": {"type": "invalid_request_error", "message": "max_tokens: Field required"}}"#;
+        let err = api_error(400, body);
+        assert!(matches!(err, ClientError::Api(ApiError { error_type: ApiErrorType::BadRequest, .. })));
+        assert!(!err.is_retryable());
+
+        // Bodies that are not Claude errors fall back to the status code
+        let err = api_error(502, "<html>Bad Gateway</html>");
+        assert!(matches!(err, ClientError::Api(ApiError { error_type: ApiErrorType::ServerError, .. })));
+    }
+
+    #[test]
+    fn test_unsupported_penalties_are_logged() {
+        use std::sync::{Arc, Mutex};
+
+        #[derive(Clone, Default)]
+        struct Captured(Arc<Mutex<Vec<u8>>>);
+
+        impl std::io::Write for Captured {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        Claude::new(Client::new(), "key".into(), "claude-3-5-sonnet".into(), ClientConfig::default());
+        assert!(captured.0.lock().unwrap().is_empty());
+
+        let config = ClientConfig::builder().frequency_penalty(0.5).build();
+        Claude::new(Client::new(), "key".into(), "claude-3-5-sonnet".into(), config);
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("does not support frequency or presence penalties"), "{output}");
+        assert!(output.contains("provider=\"Claude\""), "{output}");
+        assert!(output.contains("frequency_penalty=Some(0.5)"), "{output}");
+    }
+
+    #[test]
+    fn test_endpoint_url() {
+        let client = Claude::new(Client::new(), "key".into(), "claude-3-5-sonnet".into(), ClientConfig::default());
+        assert_eq!(client.endpoint_url(), "https://api.anthropic.com/v1/messages");
+
+        let config = ClientConfig::builder().base_url("http://localhost:8080/v1/").build();
+        let client = Claude::new(Client::new(), "key".into(), "claude-3-5-sonnet".into(), config);
+        assert_eq!(client.endpoint_url(), "http://localhost:8080/v1/messages");
+    }
+
+    #[test]
+    fn test_system_prompt_adds_json_instruction() {
+        let system = vec![Message::system("Be brief.")];
+        assert_eq!(
+            system_prompt(system.clone(), &ClientConfig::default()).as_deref(),
+            Some("Be brief.")
+        );
+        assert_eq!(system_prompt(Vec::new(), &ClientConfig::default()), None);
+
+        let config = ClientConfig::builder()
+            .response_format(ResponseFormat::JsonObject)
+            .build();
+        let prompt = system_prompt(system, &config).unwrap();
+        assert!(prompt.starts_with("Be brief.\n\nRespond only with a valid JSON object"));
+        assert!(system_prompt(Vec::new(), &config).is_some());
+    }
+
+    #[test]
+    fn test_config_system_message_is_fallback() {
+        let config = ClientConfig::builder().system_message("Answer in French.").build();
+        assert_eq!(system_prompt(Vec::new(), &config).as_deref(), Some("Answer in French."));
+
+        // System messages in the conversation take precedence over the config
+        let system = vec![Message::system("Be brief."), Message::system("Stay polite.")];
+        assert_eq!(system_prompt(system, &config).as_deref(), Some("Be brief.\n\nStay polite."));
+    }
 }
@@ -1,19 +1,28 @@
 //! Google Gemini client implementation
 
 use crate::{
-    execute_with_retry, AiClient, ApiErrorType, ClientConfig, ClientError, Conversation,
-    Message,
+    utils::{idempotency_key, IDEMPOTENCY_KEY_HEADER},
+    embeddings, execute_with_retry, observability::ObservabilityContext, AiClient, AiResponse, ApiKey,
+    ApiErrorType, AuthError, AuthErrorType, Citation, ClientConfig, ClientError, Conversation, EmbeddingClient,
+    EmbeddingResponse, Message, RequestPreview, ResponseFormat, ResponseMetadata,
 };
 use async_trait::async_trait;
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder};
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Base URL of the public Gemini API, used when no `base_url` is configured
+const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
 
 /// Client for Google Gemini models
+#[derive(Debug)]
 pub struct Gemini {
     /// Reqwest HTTP client used for requests
     http: Client,
     /// API key for Gemini access
-    key: String,
+    key: ApiKey,
     /// Model identifier such as `"gemini-1.5-pro"`
     model: String,
     /// Configuration for the client
@@ -23,153 +32,817 @@ pub struct Gemini {
 impl Gemini {
     /// Create a new Gemini client
     pub fn new(http: Client, key: String, model: String, config: ClientConfig) -> Self {
+        config.log_unsupported_penalties("Gemini");
         Self {
             http,
-            key,
+            key: ApiKey::new(key),
             model,
             config,
         }
     }
-}
 
-#[async_trait]
-impl AiClient for Gemini {
-    async fn send_prompt(&self, prompt: &str) -> Result<String, ClientError> {
+    /// URL of the content generation endpoint this client sends requests to
+    ///
+    /// Reflects the configured `base_url`, or the public Gemini API without one.
+    pub fn endpoint_url(&self) -> String {
+        self.model_url(&self.model, "generateContent")
+    }
+
+    /// URL of `method` on `model` under the configured base URL
+    fn model_url(&self, model: &str, method: &str) -> String {
+        let base_url = self.config.base_url.as_deref().unwrap_or(DEFAULT_BASE_URL);
+        format!("{}/models/{model}:{method}", base_url.trim_end_matches('/'))
+    }
+
+    /// Send a conversation to `model` and parse the response, without recording metrics
+    async fn send_request(
+        &self,
+        conversation: &Conversation,
+        model: &str,
+    ) -> Result<AiResponse, ClientError> {
+        let idempotency_key = idempotency_key();
+        let (mut response, latency_ms) = self
+            .generate(conversation, model, &idempotency_key, parse_response)
+            .await?;
+        response.metadata.latency_ms = Some(latency_ms);
+        response.metadata.idempotency_key = Some(idempotency_key);
+        Ok(response)
+    }
+
+    /// Build the `generateContent` request for `conversation` on `model`, ready to send
+    fn generate_request(&self, conversation: &Conversation, model: &str) -> RequestBuilder {
+        let request = self
+            .http
+            .post(self.model_url(model, "generateContent"))
+            .header("X-goog-api-key", self.key.expose())
+            .header("Content-Type", "application/json")
+            .json(&build_request(conversation, &self.config));
+        self.config.prepare_request(request)
+    }
+
+    /// Send a conversation to `model` and parse the body with `parse`
+    ///
+    /// Every attempt carries `idempotency_key`. Returns the parsed body and the
+    /// latency, in milliseconds, of the successful attempt.
+    async fn generate<T>(
+        &self,
+        conversation: &Conversation,
+        model: &str,
+        idempotency_key: &str,
+        parse: fn(&str) -> Result<T, ClientError>,
+    ) -> Result<(T, u64), ClientError> {
+        execute_with_retry(self.config.retries, || async {
+            self.config.throttle(conversation).await;
+            let start = Instant::now();
+            let response = self
+                .generate_request(conversation, model)
+                .header(IDEMPOTENCY_KEY_HEADER, idempotency_key)
+                .send()
+                .await?;
+
+            let status = response.status();
+            let response_text = response.text().await?;
+            if !status.is_success() {
+                return Err(http_error(status.as_u16(), &response_text));
+            }
+            let parsed = parse(&response_text)
+                .map_err(|e| e.with_raw_content(&response_text, self.config.raw_content_limit))?;
+            Ok((parsed, start.elapsed().as_millis() as u64))
+        })
+        .await
+    }
+
+    /// The conversation for a single prompt, led by the configured system message
+    fn prompt_conversation(&self, prompt: &str) -> Conversation {
         let mut conversation = Conversation::new();
         if let Some(system_msg) = &self.config.system_message {
             conversation.add_message(Message::system(system_msg));
         }
         conversation.add_user(prompt);
-        self.send_conversation(&conversation).await
+        conversation
+    }
+}
+
+#[async_trait]
+impl AiClient for Gemini {
+    async fn send_prompt(&self, prompt: &str) -> Result<String, ClientError> {
+        self.send_conversation(&self.prompt_conversation(prompt)).await
+    }
+
+    async fn send_prompt_with_metadata(&self, prompt: &str) -> Result<AiResponse, ClientError> {
+        self.send_conversation_with_metadata(&self.prompt_conversation(prompt)).await
+    }
+
+    async fn send_prompt_multi(&self, prompt: &str) -> Result<Vec<String>, ClientError> {
+        let conversation = self.prompt_conversation(prompt);
+        let conversation = self.config.sanitize(&conversation);
+        let (candidates, _) = self
+            .generate(&conversation, &self.model, &idempotency_key(), parse_candidates)
+            .await?;
+        Ok(candidates)
     }
 
     async fn send_conversation(&self, conversation: &Conversation) -> Result<String, ClientError> {
-        #[derive(Serialize)]
-        struct Part<'a> {
-            text: &'a str,
-        }
+        Ok(self.send_conversation_with_metadata(conversation).await?.content)
+    }
 
-        #[derive(Serialize)]
-        struct Content<'a> {
-            parts: Vec<Part<'a>>,
-        }
+    async fn send_conversation_with_metadata(
+        &self,
+        conversation: &Conversation,
+    ) -> Result<AiResponse, ClientError> {
+        let conversation = self.config.sanitize(conversation);
+        let conversation = conversation.as_ref();
+        let context = ObservabilityContext::new(
+            self.name().to_string(),
+            self.model.clone(),
+            Arc::new(self.config.metrics.clone().unwrap_or_default()),
+        );
+        let request = self.config.with_fallback_model(&self.model, |model| {
+            self.send_request(conversation, model)
+        });
+        context.track_conversation(&self.config, conversation, request).await
+    }
 
-        #[derive(Serialize)]
-        struct Request<'a> {
-            contents: Vec<Content<'a>>,
-            #[serde(skip_serializing_if = "Option::is_none")]
-            generation_config: Option<GenerationConfig>,
-        }
+    fn build_request_preview(
+        &self,
+        conversation: &Conversation,
+    ) -> Result<RequestPreview, ClientError> {
+        let conversation = self.config.sanitize(conversation);
+        RequestPreview::from_request(self.generate_request(&conversation, &self.model), self.key.expose())
+    }
 
-        #[derive(Serialize)]
-        struct GenerationConfig {
-            #[serde(skip_serializing_if = "Option::is_none")]
-            temperature: Option<f32>,
-        }
+    /// Only the latest user message is sent, so there must be one
+    fn validate_conversation(&self, conversation: &Conversation) -> Result<(), ClientError> {
+        crate::require_user_message(&conversation.messages, "Gemini")
+    }
 
-        #[derive(Deserialize)]
-        struct Response {
-            #[serde(default)]
-            candidates: Vec<Candidate>,
-            error: Option<ApiError>,
-        }
+    fn supports_conversations(&self) -> bool {
+        true
+    }
 
-        #[derive(Deserialize)]
-        struct ApiError {
-            code: u32,
-            message: String,
-            #[allow(dead_code)]
-            status: String,
-        }
+    fn supports_streaming(&self) -> bool {
+        false
+    }
 
-        #[derive(Deserialize)]
-        struct Candidate {
-            content: CandContent,
+    fn name(&self) -> &str {
+        "Gemini"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+}
+
+#[async_trait]
+impl EmbeddingClient for Gemini {
+    /// Embeds a single input with `:embedContent` and batches with `:batchEmbedContents`
+    ///
+    /// Gemini does not report token usage for embeddings.
+    async fn embed_with_metadata(&self, inputs: &[String]) -> Result<EmbeddingResponse, ClientError> {
+        #[derive(Serialize)]
+        struct EmbedRequest<'a> {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            model: Option<String>,
+            content: Content<'a>,
         }
 
-        #[derive(Deserialize)]
-        struct CandContent {
-            parts: Vec<CandPart>,
+        #[derive(Serialize)]
+        struct BatchRequest<'a> {
+            requests: Vec<EmbedRequest<'a>>,
         }
 
-        #[derive(Deserialize)]
-        struct CandPart {
-            text: String,
+        fn content(input: &str) -> Content<'_> {
+            Content {
+                parts: vec![Part {
+                    text: Cow::Borrowed(input),
+                }],
+            }
         }
 
-        // Convert conversation to Gemini format - for now just use the last user message
-        let user_content = conversation
-            .messages
-            .iter()
-            .rev()
-            .find(|msg| msg.role == "user")
-            .map(|msg| msg.content.as_str())
-            .unwrap_or("");
-
-        let body = Request {
-            contents: vec![Content {
-                parts: vec![Part { text: user_content }],
-            }],
-            generation_config: self.config.temperature.map(|temp| GenerationConfig {
-                temperature: Some(temp),
-            }),
+        let (url, body) = match inputs {
+            [] => {
+                return Ok(EmbeddingResponse {
+                    embeddings: Vec::new(),
+                    metadata: ResponseMetadata::default(),
+                })
+            }
+            [input] => (
+                self.model_url(&self.model, "embedContent"),
+                serde_json::to_value(EmbedRequest {
+                    model: None,
+                    content: content(input),
+                })?,
+            ),
+            _ => (
+                self.model_url(&self.model, "batchEmbedContents"),
+                serde_json::to_value(BatchRequest {
+                    requests: inputs
+                        .iter()
+                        .map(|input| EmbedRequest {
+                            model: Some(format!("models/{}", self.model)),
+                            content: content(input),
+                        })
+                        .collect(),
+                })?,
+            ),
         };
 
-        let url = format!(
-            "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent",
-            self.model
-        );
-
         execute_with_retry(self.config.retries, || async {
-            let response = self
+            self.config
+                .throttle_tokens(embeddings::estimated_tokens(inputs))
+                .await;
+            let start = Instant::now();
+            let request = self
                 .http
                 .post(&url)
-                .header("X-goog-api-key", &self.key)
+                .header("X-goog-api-key", self.key.expose())
                 .header("Content-Type", "application/json")
-                .json(&body)
-                .send()
-                .await?;
+                .json(&body);
+            let response = self.config.prepare_request(request).send().await?;
 
+            let status = response.status();
             let response_text = response.text().await?;
-            let resp: Response = serde_json::from_str(&response_text)?;
-
-            if let Some(error) = resp.error {
-                let error_type = match error.code {
-                    429 => ApiErrorType::RateLimit,
-                    403 => ApiErrorType::QuotaExceeded,
-                    400 => ApiErrorType::BadRequest,
-                    _ => ApiErrorType::Other,
-                };
-                return Err(ClientError::Api(crate::ApiError {
-                    message: format!("Gemini API Error ({}): {}", error.code, error.message),
-                    status_code: Some(error.code as u16),
-                    error_type,
-                }));
+            if !status.is_success() {
+                return Err(http_error(status.as_u16(), &response_text));
             }
-
-            Ok(resp
-                .candidates
-                .first()
-                .and_then(|c| c.content.parts.first())
-                .map(|p| p.text.clone())
-                .unwrap_or_else(|| "No response from Gemini".to_string()))
+            let mut response = parse_embeddings(&response_text)
+                .map_err(|e| e.with_raw_content(&response_text, self.config.raw_content_limit))?;
+            response.metadata.model_used = Some(self.model.clone());
+            response.metadata.latency_ms = Some(start.elapsed().as_millis() as u64);
+            Ok(response)
         })
         .await
     }
+}
 
-    fn supports_conversations(&self) -> bool {
-        true
+#[derive(Serialize)]
+struct Request<'a> {
+    contents: Vec<Content<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<Content<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generation_config: Option<GenerationConfig<'a>>,
+}
+
+#[derive(Serialize)]
+struct Content<'a> {
+    parts: Vec<Part<'a>>,
+}
+
+#[derive(Serialize)]
+struct Part<'a> {
+    text: Cow<'a, str>,
+}
+
+#[derive(Serialize)]
+struct GenerationConfig<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_mime_type: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_schema: Option<&'a serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    candidate_count: Option<u32>,
+}
+
+/// Build the request body for a conversation
+///
+/// System messages in the conversation become the `system_instruction`, joined by
+/// blank lines; without any, the configured system message is used instead.
+fn build_request<'a>(conversation: &'a Conversation, config: &'a ClientConfig) -> Request<'a> {
+    // Convert conversation to Gemini format - for now just use the last user message
+    let user_content = conversation
+        .messages
+        .iter()
+        .rev()
+        .find(|msg| msg.role == "user")
+        .map(|msg| msg.content.as_str())
+        .unwrap_or("");
+
+    let system_messages: Vec<&str> = conversation
+        .messages
+        .iter()
+        .filter(|msg| msg.role == "system")
+        .map(|msg| msg.content.as_str())
+        .collect();
+    let system_instruction = if system_messages.is_empty() {
+        config.system_message.as_deref().map(Cow::Borrowed)
+    } else {
+        Some(Cow::Owned(system_messages.join("\n\n")))
+    };
+
+    Request {
+        contents: vec![Content {
+            parts: vec![Part { text: Cow::Borrowed(user_content) }],
+        }],
+        system_instruction: system_instruction.map(|text| Content {
+            parts: vec![Part { text }],
+        }),
+        generation_config: generation_config(config),
     }
+}
 
-    fn supports_streaming(&self) -> bool {
-        false
+/// Generation settings, or `None` when everything is left at the provider default
+fn generation_config(config: &ClientConfig) -> Option<GenerationConfig<'_>> {
+    let (response_mime_type, response_schema) = match &config.response_format {
+        None | Some(ResponseFormat::Text) => (None, None),
+        Some(ResponseFormat::JsonObject) => (Some("application/json"), None),
+        Some(ResponseFormat::JsonSchema(schema)) => (Some("application/json"), Some(schema)),
+    };
+    if config.temperature.is_none() && response_mime_type.is_none() && config.candidate_count.is_none() {
+        return None;
     }
+    Some(GenerationConfig {
+        temperature: config.temperature,
+        response_mime_type,
+        response_schema,
+        candidate_count: config.candidate_count,
+    })
+}
 
-    fn name(&self) -> &str {
-        "Gemini"
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Response {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    usage_metadata: Option<UsageMetadata>,
+    model_version: Option<String>,
+    error: Option<ApiError>,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    code: u32,
+    message: String,
+    #[serde(default)]
+    status: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    content: CandContent,
+    finish_reason: Option<String>,
+    grounding_metadata: Option<GroundingMetadata>,
+}
+
+#[derive(Deserialize)]
+struct CandContent {
+    parts: Vec<CandPart>,
+}
+
+#[derive(Deserialize)]
+struct CandPart {
+    text: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    prompt_token_count: Option<u32>,
+    candidates_token_count: Option<u32>,
+    total_token_count: Option<u32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GroundingMetadata {
+    #[serde(default)]
+    grounding_chunks: Vec<GroundingChunk>,
+    #[serde(default)]
+    grounding_supports: Vec<GroundingSupport>,
+}
+
+#[derive(Deserialize)]
+struct GroundingChunk {
+    web: Option<WebSource>,
+}
+
+#[derive(Deserialize)]
+struct WebSource {
+    uri: String,
+    title: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GroundingSupport {
+    segment: Option<Segment>,
+    #[serde(default)]
+    grounding_chunk_indices: Vec<usize>,
+}
+
+#[derive(Deserialize)]
+struct Segment {
+    #[serde(default)]
+    text: String,
+}
+
+/// Parse a `generateContent` response body into content and metadata
+fn parse_response(body: &str) -> Result<AiResponse, ClientError> {
+    let resp: Response = serde_json::from_str(body)?;
+
+    if let Some(error) = resp.error {
+        return Err(api_error(error));
     }
 
-    fn model(&self) -> &str {
-        &self.model
+    let candidate = resp.candidates.into_iter().next();
+    let content = candidate
+        .as_ref()
+        .and_then(|c| c.content.parts.first())
+        .map(|p| p.text.clone())
+        .unwrap_or_else(|| "No response from Gemini".to_string());
+    let (finish_reason, grounding) = match candidate {
+        Some(c) => (c.finish_reason, c.grounding_metadata),
+        None => (None, None),
+    };
+    let usage = resp.usage_metadata;
+
+    let metadata = ResponseMetadata {
+        model_used: resp.model_version,
+        prompt_tokens: usage.as_ref().and_then(|u| u.prompt_token_count),
+        completion_tokens: usage.as_ref().and_then(|u| u.candidates_token_count),
+        total_tokens: usage.as_ref().and_then(|u| u.total_token_count),
+        finish_reason,
+        citations: grounding.map(citations_from_grounding),
+        ..Default::default()
+    };
+
+    Ok(AiResponse::with_metadata(content, metadata))
+}
+
+/// Parse the text of every candidate in a `generateContent` response body, in order
+fn parse_candidates(body: &str) -> Result<Vec<String>, ClientError> {
+    let resp: Response = serde_json::from_str(body)?;
+
+    if let Some(error) = resp.error {
+        return Err(api_error(error));
+    }
+
+    Ok(resp
+        .candidates
+        .into_iter()
+        .map(|candidate| {
+            candidate
+                .content
+                .parts
+                .into_iter()
+                .map(|part| part.text)
+                .collect::<String>()
+        })
+        .collect())
+}
+
+/// Parse an `:embedContent` or `:batchEmbedContents` response body
+fn parse_embeddings(body: &str) -> Result<EmbeddingResponse, ClientError> {
+    #[derive(Deserialize)]
+    struct Response {
+        embedding: Option<Values>,
+        embeddings: Option<Vec<Values>>,
+        error: Option<ApiError>,
+    }
+
+    #[derive(Deserialize)]
+    struct Values {
+        values: Vec<f32>,
+    }
+
+    let resp: Response = serde_json::from_str(body)?;
+    if let Some(error) = resp.error {
+        return Err(api_error(error));
+    }
+
+    let embeddings = resp
+        .embeddings
+        .or_else(|| resp.embedding.map(|embedding| vec![embedding]))
+        .ok_or_else(|| {
+            ClientError::Parse(crate::ParseError {
+                message: "Gemini response missing 'embedding' field".to_string(),
+                error_type: crate::ParseErrorType::MissingField,
+                raw_content: None,
+                source: None,
+            })
+        })?;
+
+    Ok(EmbeddingResponse {
+        embeddings: embeddings.into_iter().map(|e| e.values).collect(),
+        metadata: ResponseMetadata::default(),
+    })
+}
+
+/// Map a non-2xx Gemini response to a client error
+///
+/// Bodies carrying a Gemini error object are mapped by `api_error`; others, such as
+/// an HTML page from a gateway, are mapped by the HTTP status alone.
+fn http_error(status: u16, body: &str) -> ClientError {
+    #[derive(Deserialize)]
+    struct ErrorBody {
+        error: ApiError,
+    }
+
+    let error = serde_json::from_str::<ErrorBody>(body)
+        .map(|body| body.error)
+        .unwrap_or_else(|_| ApiError {
+            code: u32::from(status),
+            message: body.to_string(),
+            status: String::new(),
+        });
+    api_error(error)
+}
+
+/// Map an error object from a Gemini response body to a client error
+///
+/// The canonical `status` string decides the error kind; errors without one fall
+/// back to the numeric code.
+fn api_error(error: ApiError) -> ClientError {
+    let message = format!("Gemini API Error ({}): {}", error.code, error.message);
+
+    let auth_error = match error.status.as_str() {
+        "UNAUTHENTICATED" => Some(AuthErrorType::InvalidApiKey),
+        "PERMISSION_DENIED" => Some(AuthErrorType::InsufficientPermissions),
+        "" if error.code == 401 => Some(AuthErrorType::InvalidApiKey),
+        _ => None,
+    };
+    if let Some(error_type) = auth_error {
+        return ClientError::Authentication(AuthError {
+            message,
+            error_type,
+            source: None,
+        });
+    }
+
+    let error_type = match error.status.as_str() {
+        "RESOURCE_EXHAUSTED" => ApiErrorType::RateLimit,
+        "INVALID_ARGUMENT" | "FAILED_PRECONDITION" | "OUT_OF_RANGE" => ApiErrorType::BadRequest,
+        "NOT_FOUND" => ApiErrorType::InvalidModel,
+        "INTERNAL" | "UNAVAILABLE" | "DEADLINE_EXCEEDED" => ApiErrorType::ServerError,
+        _ => match error.code {
+            429 => ApiErrorType::RateLimit,
+            403 => ApiErrorType::QuotaExceeded,
+            400 => ApiErrorType::BadRequest,
+            500.. => ApiErrorType::ServerError,
+            _ => ApiErrorType::Other,
+        },
+    };
+    ClientError::Api(crate::ApiError {
+        message,
+        status_code: Some(error.code as u16),
+        error_type,
+        source: None,
+    })
+}
+
+/// Turn grounding chunks into citations, attaching the response segments each one supports
+fn citations_from_grounding(grounding: GroundingMetadata) -> Vec<Citation> {
+    let mut citations: Vec<Option<Citation>> = grounding
+        .grounding_chunks
+        .into_iter()
+        .map(|chunk| {
+            chunk.web.map(|web| Citation {
+                uri: web.uri,
+                title: web.title,
+                supported_text: Vec::new(),
+            })
+        })
+        .collect();
+
+    for support in grounding.grounding_supports {
+        let Some(segment) = support.segment else {
+            continue;
+        };
+        for index in support.grounding_chunk_indices {
+            if let Some(Some(citation)) = citations.get_mut(index) {
+                citation.supported_text.push(segment.text.clone());
+            }
+        }
+    }
+
+    citations.into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint_url() {
+        let client = Gemini::new(Client::new(), "key".into(), "gemini-1.5-pro".into(), ClientConfig::default());
+        assert_eq!(
+            client.endpoint_url(),
+            "https://generativelanguage.googleapis.com/v1beta/models/gemini-1.5-pro:generateContent"
+        );
+
+        let config = ClientConfig::builder().base_url("http://localhost:8080/v1beta").build();
+        let client = Gemini::new(Client::new(), "key".into(), "gemini-1.5-pro".into(), config);
+        assert_eq!(
+            client.endpoint_url(),
+            "http://localhost:8080/v1beta/models/gemini-1.5-pro:generateContent"
+        );
+    }
+
+    #[test]
+    fn test_parse_grounded_response() {
+        let body = r#"{
+            "candidates": [{
+                "content": {
+                    "parts": [{"text": "Spain won Euro 2024, beating England 2-1 in the final."}],
+                    "role": "model"
+                },
+                "finishReason": "STOP",
+                "groundingMetadata": {
+                    "webSearchQueries": ["who won euro 2024"],
+                    "groundingChunks": [
+                        {"web": {"uri": "https://example.com/euro-2024", "title": "example.com"}},
+                        {"web": {"uri": "https://example.org/final", "title": "example.org"}}
+                    ],
+                    "groundingSupports": [
+                        {
+                            "segment": {"startIndex": 0, "endIndex": 18, "text": "Spain won Euro 2024"},
+                            "groundingChunkIndices": [0, 1],
+                            "confidenceScores": [0.98, 0.95]
+                        },
+                        {
+                            "segment": {"startIndex": 20, "endIndex": 54, "text": "beating England 2-1 in the final"},
+                            "groundingChunkIndices": [1],
+                            "confidenceScores": [0.9]
+                        }
+                    ]
+                }
+            }],
+            "usageMetadata": {"promptTokenCount": 6, "candidatesTokenCount": 14, "totalTokenCount": 20},
+            "modelVersion": "gemini-1.5-pro-002"
+        }"#;
+
+        let response = parse_response(body).unwrap();
+        assert_eq!(response.content, "Spain won Euro 2024, beating England 2-1 in the final.");
+        assert_eq!(response.metadata.finish_reason.as_deref(), Some("STOP"));
+        assert_eq!(response.metadata.total_tokens, Some(20));
+
+        let citations = response.metadata.citations.unwrap();
+        assert_eq!(citations.len(), 2);
+        assert_eq!(citations[0].uri, "https://example.com/euro-2024");
+        assert_eq!(citations[0].title.as_deref(), Some("example.com"));
+        assert_eq!(citations[0].supported_text, vec!["Spain won Euro 2024"]);
+        assert_eq!(
+            citations[1].supported_text,
+            vec!["Spain won Euro 2024", "beating England 2-1 in the final"]
+        );
+    }
+
+    #[test]
+    fn test_parse_ungrounded_response_has_no_citations() {
+        let body = r#"{"candidates": [{"content": {"parts": [{"text": "Hello"}]}}]}"#;
+        let response = parse_response(body).unwrap();
+        assert_eq!(response.content, "Hello");
+        assert!(response.metadata.citations.is_none());
+    }
+
+    #[test]
+    fn test_parse_multiple_candidates() {
+        let body = r#"{
+            "candidates": [
+                {"content": {"parts": [{"text": "Roses are red"}]}, "finishReason": "STOP", "index": 0},
+                {"content": {"parts": [{"text": "Violets "}, {"text": "are blue"}]}, "finishReason": "STOP", "index": 1},
+                {"content": {"parts": [{"text": "Sugar is sweet"}]}, "finishReason": "STOP", "index": 2}
+            ],
+            "usageMetadata": {"promptTokenCount": 4, "candidatesTokenCount": 12, "totalTokenCount": 16}
+        }"#;
+        assert_eq!(
+            parse_candidates(body).unwrap(),
+            vec!["Roses are red", "Violets are blue", "Sugar is sweet"]
+        );
+
+        let config = ClientConfig::builder().candidate_count(3).build();
+        let body = serde_json::to_value(build_request(&Conversation::new(), &config)).unwrap();
+        assert_eq!(body["generation_config"]["candidate_count"], 3);
+    }
+
+    #[test]
+    fn test_parse_error_statuses() {
+        let body = r#"{"error": {"code": 429, "message": "Resource has been exhausted (e.g. check quota).", "status": "RESOURCE_EXHAUSTED"}}"#;
+        let err = parse_response(body).unwrap_err();
+        assert!(err.is_rate_limit());
+        assert!(err.is_retryable());
+
+        let body = r#"{"error": {"code": 403, "message": "Method doesn't allow unregistered callers.", "status": "PERMISSION_DENIED"}}"#;
+        assert!(matches!(
+            parse_response(body).unwrap_err(),
+            ClientError::Authentication(AuthError {
+                error_type: AuthErrorType::InsufficientPermissions,
+                ..
+            })
+        ));
+
+        let body = r#"{"error": {"code": 404, "message": "models/gemini-9 is not found for API version v1beta", "status": "NOT_FOUND"}}"#;
+        assert!(matches!(
+            parse_response(body).unwrap_err(),
+            ClientError::Api(crate::ApiError { error_type: ApiErrorType::InvalidModel, .. })
+        ));
+
+        let body = r#"{"error": {"code": 503, "message": "The model is overloaded. Please try again later.", "status": "UNAVAILABLE"}}"#;
+        let err = parse_response(body).unwrap_err();
+        assert!(matches!(err, ClientError::Api(crate::ApiError { error_type: ApiErrorType::ServerError, .. })));
+        assert!(err.is_retryable());
+
+        // Without a status, the code decides
+        let body = r#"{"error": {"code": 400, "message": "Invalid JSON payload"}}"#;
+        assert!(matches!(
+            parse_response(body).unwrap_err(),
+            ClientError::Api(crate::ApiError { error_type: ApiErrorType::BadRequest, .. })
+        ));
+    }
+
+    #[test]
+    fn test_http_error_without_error_body_uses_status() {
+        let err = http_error(502, "<html><body>502 Bad Gateway</body></html>");
+        assert!(matches!(err, ClientError::Api(crate::ApiError { error_type: ApiErrorType::ServerError, .. })));
+        assert_eq!(err.status_code(), Some(502));
+        assert!(err.is_retryable());
+
+        let err = http_error(429, "Too Many Requests");
+        assert!(err.is_rate_limit());
+        assert!(err.is_retryable());
+
+        // An error object in the body still decides the kind
+        let body = r#"{"error": {"code": 400, "message": "API key not valid.", "status": "UNAUTHENTICATED"}}"#;
+        assert!(matches!(
+            http_error(400, body),
+            ClientError::Authentication(AuthError { error_type: AuthErrorType::InvalidApiKey, .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_embeddings() {
+        // Recorded from the Gemini API, vectors truncated to three dimensions
+        let single = r#"{"embedding": {"values": [0.0131, -0.0087, -0.0612]}}"#;
+        let response = parse_embeddings(single).unwrap();
+        assert_eq!(response.embeddings, vec![vec![0.0131, -0.0087, -0.0612]]);
+
+        let batch = r#"{
+            "embeddings": [
+                {"values": [0.0131, -0.0087, -0.0612]},
+                {"values": [-0.0254, 0.0418, 0.0039]}
+            ]
+        }"#;
+        let response = parse_embeddings(batch).unwrap();
+        assert_eq!(response.embeddings.len(), 2);
+        assert_eq!(response.embeddings[1], vec![-0.0254, 0.0418, 0.0039]);
+
+        let error = r#"{"error": {"code": 400, "message": "Invalid model", "status": "INVALID_ARGUMENT"}}"#;
+        assert!(matches!(
+            parse_embeddings(error),
+            Err(ClientError::Api(crate::ApiError { error_type: ApiErrorType::BadRequest, .. }))
+        ));
+    }
+
+    #[test]
+    fn test_config_system_message_is_fallback_instruction() {
+        let config = ClientConfig::builder()
+            .system_message("Answer in French.")
+            .build();
+
+        let mut conversation = Conversation::new();
+        conversation.add_user("Hello");
+        conversation.add_assistant("Bonjour !");
+        conversation.add_user("How are you?");
+        let body = serde_json::to_value(build_request(&conversation, &config)).unwrap();
+        assert_eq!(
+            body["system_instruction"],
+            serde_json::json!({"parts": [{"text": "Answer in French."}]})
+        );
+        assert_eq!(body["contents"][0]["parts"][0]["text"], "How are you?");
+
+        // System messages in the conversation take precedence over the config
+        let mut conversation = Conversation::with_system("Be brief.");
+        conversation.add_message(Message::system("Stay polite."));
+        conversation.add_user("Hello");
+        let body = serde_json::to_value(build_request(&conversation, &config)).unwrap();
+        assert_eq!(
+            body["system_instruction"]["parts"][0]["text"],
+            "Be brief.\n\nStay polite."
+        );
+
+        let body = serde_json::to_value(build_request(&conversation, &ClientConfig::default())).unwrap();
+        assert!(body.get("system_instruction").is_some());
+        let body = serde_json::to_value(build_request(&Conversation::new(), &ClientConfig::default())).unwrap();
+        assert!(body.get("system_instruction").is_none());
+    }
+
+    #[test]
+    fn test_json_response_format() {
+        let conversation = Conversation::new();
+        let body = serde_json::to_value(build_request(&conversation, &ClientConfig::default())).unwrap();
+        assert!(body.get("generation_config").is_none());
+
+        let schema = serde_json::json!({"type": "object", "properties": {"name": {"type": "string"}}});
+        let config = ClientConfig::builder()
+            .response_format(ResponseFormat::JsonSchema(schema.clone()))
+            .build();
+        let body = serde_json::to_value(build_request(&conversation, &config)).unwrap();
+        assert_eq!(
+            body["generation_config"],
+            serde_json::json!({"response_mime_type": "application/json", "response_schema": schema})
+        );
     }
 }
@@ -1,27 +1,43 @@
 //! OpenAI ChatGPT client implementation
 
 use crate::{
-    execute_with_retry, sse::sse_stream, AiClient, AiResponse, ApiError, ApiErrorType, ClientConfig,
-    ClientError, Conversation, Message, ParseError, ParseErrorType, ResponseMetadata,
-    StreamChunk,
+    utils::{idempotency_key, IDEMPOTENCY_KEY_HEADER},
+    embeddings, execute_with_retry, observability::ObservabilityContext,
+    rate_limit::RateLimitTracker, sse::{client_sse_stream, SseEvent}, with_idle_timeout, AiClient, AiResponse, ApiKey, ApiError, ApiErrorType,
+    AuthError, AuthErrorType, ClientConfig, ClientError, Conversation, EmbeddingClient, EmbeddingResponse, Message,
+    ModerationClient, ModerationResult, ParseError, ParseErrorType, RateLimitStatus, RequestPreview,
+    ResponseFormat, ResponseMetadata, StreamChunk, StreamRequest, TokenLogprob,
 };
+#[cfg(feature = "orchestration")]
+use crate::ModelCapabilities;
 use async_trait::async_trait;
 use futures::stream::{BoxStream, StreamExt};
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::Instant;
 use std::sync::Arc;
+use tracing::Instrument;
+
+/// Base URL of the public OpenAI API, used when no `base_url` is configured
+const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
 
 /// Client for OpenAI's ChatGPT models
+#[derive(Debug)]
 pub struct ChatGpt {
     /// Reqwest HTTP client used for requests
     http: Client,
     /// API key for authenticating with OpenAI
-    key: String,
+    key: ApiKey,
     /// Model name to call, e.g. `"gpt-4"`
     model: String,
     /// Configuration for the client
     config: ClientConfig,
+    /// Rate limit status from the most recent response
+    rate_limit: RateLimitTracker,
+    /// Capabilities fetched by `fetch_model_info`, by model id
+    #[cfg(feature = "orchestration")]
+    model_info: std::sync::Mutex<HashMap<String, ModelCapabilities>>,
 }
 
 impl ChatGpt {
@@ -29,47 +45,30 @@ impl ChatGpt {
     pub fn new(http: Client, key: String, model: String, config: ClientConfig) -> Self {
         Self {
             http,
-            key,
+            key: ApiKey::new(key),
             model,
             config,
+            rate_limit: RateLimitTracker::default(),
+            #[cfg(feature = "orchestration")]
+            model_info: Default::default(),
         }
     }
-}
-
-#[async_trait]
-impl AiClient for ChatGpt {
-    async fn send_prompt(&self, prompt: &str) -> Result<String, ClientError> {
-        let response = self.send_prompt_with_metadata(prompt).await?;
-        Ok(response.content)
-    }
 
-    fn supports_conversations(&self) -> bool {
-        true
+    /// URL of the chat completions endpoint this client sends requests to
+    ///
+    /// Reflects the configured `base_url`, or the public OpenAI API without one.
+    pub fn endpoint_url(&self) -> String {
+        self.url("chat/completions")
     }
 
-    fn supports_streaming(&self) -> bool {
-        true
-    }
-
-    fn name(&self) -> &str {
-        "ChatGPT"
-    }
-
-    fn model(&self) -> &str {
-        &self.model
-    }
-    
-    async fn send_prompt_with_metadata(&self, prompt: &str) -> Result<AiResponse, ClientError> {
-        let conversation = Conversation {
-            messages: vec![Message::user(prompt)],
-        };
-        self.send_conversation_with_metadata(&conversation).await
+    /// URL of `path` under the configured base URL
+    fn url(&self, path: &str) -> String {
+        let base_url = self.config.base_url.as_deref().unwrap_or(DEFAULT_BASE_URL);
+        format!("{}/{path}", base_url.trim_end_matches('/'))
     }
 
-    async fn send_conversation_with_metadata(
-        &self,
-        conversation: &Conversation,
-    ) -> Result<AiResponse, ClientError> {
+    /// Build the chat completion request for `conversation` on `model`, ready to send
+    fn chat_request(&self, conversation: &Conversation, model: &str) -> RequestBuilder {
         #[derive(Serialize)]
         struct ApiMessage<'a> {
             role: &'a str,
@@ -90,40 +89,15 @@ impl AiClient for ChatGpt {
             frequency_penalty: Option<f32>,
             #[serde(skip_serializing_if = "Option::is_none")]
             presence_penalty: Option<f32>,
-        }
-
-        #[derive(Deserialize)]
-        struct Response {
-            choices: Option<Vec<Choice>>,
-            error: Option<ErrorInfo>,
-            usage: Option<Usage>,
-            model: Option<String>,
-            id: Option<String>,
-        }
-
-        #[derive(Deserialize)]
-        struct ErrorInfo {
-            message: String,
-            #[serde(rename = "type")]
-            error_type: Option<String>,
-        }
-
-        #[derive(Deserialize)]
-        struct Choice {
-            message: RespMessage,
-            finish_reason: Option<String>,
-        }
-
-        #[derive(Deserialize)]
-        struct RespMessage {
-            content: String,
-        }
-
-        #[derive(Deserialize)]
-        struct Usage {
-            prompt_tokens: Option<u32>,
-            completion_tokens: Option<u32>,
-            total_tokens: Option<u32>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            response_format: Option<serde_json::Value>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            logprobs: Option<bool>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            top_logprobs: Option<u8>,
+            /// Token ids become string keys, as the API expects
+            #[serde(skip_serializing_if = "Option::is_none")]
+            logit_bias: Option<&'a HashMap<u32, f32>>,
         }
 
         let mut messages = Vec::new();
@@ -145,49 +119,72 @@ impl AiClient for ChatGpt {
         }
 
         let body = Request {
-            model: &self.model,
+            model,
             messages,
             temperature: self.config.temperature,
             max_tokens: self.config.max_tokens,
             top_p: self.config.top_p,
             frequency_penalty: self.config.frequency_penalty,
             presence_penalty: self.config.presence_penalty,
+            response_format: self.config.response_format.as_ref().map(ResponseFormat::to_openai),
+            logprobs: self.config.logprobs.map(|_| true),
+            top_logprobs: self.config.logprobs,
+            logit_bias: self.config.logit_bias.as_ref(),
         };
 
+        let request = self.http.post(self.endpoint_url()).bearer_auth(self.key.expose()).json(&body);
+        self.config.prepare_request(request)
+    }
+
+    /// Send a conversation to `model` and parse the response, without recording metrics
+    async fn send_request(
+        &self,
+        conversation: &Conversation,
+        model: &str,
+    ) -> Result<AiResponse, ClientError> {
+        #[derive(Deserialize)]
+        struct Response {
+            choices: Option<Vec<Choice>>,
+            error: Option<ErrorInfo>,
+            usage: Option<Usage>,
+            model: Option<String>,
+            id: Option<String>,
+        }
+
+        #[derive(Deserialize)]
+        struct Usage {
+            prompt_tokens: Option<u32>,
+            completion_tokens: Option<u32>,
+            total_tokens: Option<u32>,
+        }
+
+        let idempotency_key = idempotency_key();
         let start_time = Instant::now();
 
-        let (content, resp) = execute_with_retry(self.config.retries, || async {
-            let url = if let Some(base_url) = &self.config.base_url {
-                format!("{}/chat/completions", base_url.trim_end_matches('/'))
-            } else {
-                "https://api.openai.com/v1/chat/completions".to_string()
-            };
-            
+        let (content, resp, rate_limit) = execute_with_retry(self.config.retries, || async {
+            self.config.throttle(conversation).await;
             let response = self
-                .http
-                .post(&url)
-                .bearer_auth(&self.key)
-                .json(&body)
+                .chat_request(conversation, model)
+                .header(IDEMPOTENCY_KEY_HEADER, &idempotency_key)
                 .send()
                 .await?;
+            let rate_limit = self.rate_limit.record(response.headers());
 
             if !response.status().is_success() {
-                return Err(response.error_for_status().unwrap_err().into());
+                return Err(error_from_response(response).await);
             }
 
-            let resp: Response = response.json().await?;
+            let response_text = response.text().await?;
+            let resp: Response = serde_json::from_str(&response_text).map_err(|e| {
+                ClientError::from(e).with_raw_content(&response_text, self.config.raw_content_limit)
+            })?;
 
             if let Some(error) = resp.error {
-                let error_type = match error.error_type.as_deref() {
-                    Some("insufficient_quota") => ApiErrorType::QuotaExceeded,
-                    Some("model_not_found") => ApiErrorType::InvalidModel,
-                    Some("content_filter") => ApiErrorType::ContentFilter,
-                    _ => ApiErrorType::Other,
-                };
                 return Err(ClientError::Api(ApiError {
                     message: format!("OpenAI API error: {}", error.message),
                     status_code: None,
-                    error_type,
+                    error_type: error.api_error_type(None),
+                    source: None,
                 }));
             }
 
@@ -196,6 +193,7 @@ impl AiClient for ChatGpt {
                     message: "OpenAI response missing 'choices' field".to_string(),
                     error_type: ParseErrorType::MissingField,
                     raw_content: None,
+                    source: None,
                 })
             })?;
 
@@ -204,36 +202,234 @@ impl AiClient for ChatGpt {
                     message: "OpenAI returned empty choices array".to_string(),
                     status_code: None,
                     error_type: ApiErrorType::Other,
+                    source: None,
                 }));
             }
 
             let content = choices
                 .first()
-                .map(|c| c.message.content.clone())
+                .map(|c| c.message.content.text())
                 .unwrap_or_else(|| "No response from ChatGPT".to_string());
 
-            Ok((content, resp))
+            Ok((content, resp, rate_limit))
         })
         .await?;
 
         let latency_ms = start_time.elapsed().as_millis() as u64;
+        let (finish_reason, logprobs) = match resp.choices.and_then(|c| c.into_iter().next()) {
+            Some(choice) => (choice.finish_reason, choice.logprobs.and_then(|l| l.content)),
+            None => (None, None),
+        };
 
         let metadata = ResponseMetadata {
             model_used: resp.model,
             prompt_tokens: resp.usage.as_ref().and_then(|u| u.prompt_tokens),
             completion_tokens: resp.usage.as_ref().and_then(|u| u.completion_tokens),
             total_tokens: resp.usage.as_ref().and_then(|u| u.total_tokens),
-            finish_reason: resp
-                .choices
-                .and_then(|c| c.first().and_then(|ch| ch.finish_reason.clone())),
+            finish_reason,
             safety_ratings: None,
             request_id: resp.id,
             latency_ms: Some(latency_ms),
+            logprobs,
+            rate_limit,
+            idempotency_key: Some(idempotency_key),
+            ..Default::default()
         };
 
         Ok(AiResponse::with_metadata(content, metadata))
     }
 
+    /// Send a streaming chat completion request and return the successful response
+    async fn open_stream(
+        &self,
+        conversation: &Conversation,
+        stream_request: &StreamRequest,
+    ) -> Result<reqwest::Response, ClientError> {
+        let conversation = self.config.sanitize(conversation);
+        let conversation = conversation.as_ref();
+        #[derive(Serialize)]
+        struct ApiMessage<'a> {
+            role: &'a str,
+            content: &'a str,
+        }
+
+        #[derive(Serialize)]
+        struct Request<'a> {
+            model: &'a str,
+            messages: Vec<ApiMessage<'a>>,
+            stream: bool,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            stream_options: Option<serde_json::Value>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            temperature: Option<f32>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            max_tokens: Option<u32>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            top_p: Option<f32>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            frequency_penalty: Option<f32>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            presence_penalty: Option<f32>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            response_format: Option<serde_json::Value>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            logit_bias: Option<&'a HashMap<u32, f32>>,
+        }
+
+        let mut messages = Vec::new();
+        
+        // Add system message if configured
+        if let Some(system_msg) = &self.config.system_message {
+            messages.push(ApiMessage {
+                role: "system",
+                content: system_msg,
+            });
+        }
+        
+        // Add conversation messages
+        for msg in &conversation.messages {
+            messages.push(ApiMessage {
+                role: &msg.role,
+                content: &msg.content,
+            });
+        }
+
+        let body = Request {
+            model: &self.model,
+            messages,
+            stream: true,
+            stream_options: stream_request.to_openai(),
+            temperature: self.config.temperature,
+            max_tokens: self.config.max_tokens,
+            top_p: self.config.top_p,
+            frequency_penalty: self.config.frequency_penalty,
+            presence_penalty: self.config.presence_penalty,
+            response_format: self.config.response_format.as_ref().map(ResponseFormat::to_openai),
+            logit_bias: self.config.logit_bias.as_ref(),
+        };
+
+        let url = self.endpoint_url();
+
+        let context = ObservabilityContext::new(
+            self.name().to_string(),
+            self.model.clone(),
+            Arc::new(self.config.metrics.clone().unwrap_or_default()),
+        );
+        self.config.throttle(conversation).await;
+        let request = self.http.post(&url).bearer_auth(self.key.expose()).json(&body);
+        let response = self
+            .config
+            .prepare_request(request)
+            .send()
+            .instrument(context.span())
+            .await?;
+        self.rate_limit.record(response.headers());
+
+        if !response.status().is_success() {
+            return Err(error_from_response(response).await);
+        }
+
+        Ok(response)
+    }
+}
+
+#[cfg(feature = "orchestration")]
+impl ChatGpt {
+    /// Fetch the capabilities of `model` from the `/models/{id}` endpoint
+    ///
+    /// Results are cached for the lifetime of this client, so each model is fetched
+    /// at most once. OpenAI reports only the model's id; OpenAI-compatible servers
+    /// that also report a context window (`context_length`, `context_window`, or
+    /// `max_model_len`) have it used. Otherwise the context window comes from a
+    /// built-in table of OpenAI models, and a model in neither fails with a parse
+    /// error. The cost is the mean of the input and output rates in [`Pricing::current`].
+    pub async fn fetch_model_info(&self, model: &str) -> Result<ModelCapabilities, ClientError> {
+        if let Some(info) = self.model_info.lock().unwrap().get(model) {
+            return Ok(info.clone());
+        }
+
+        let url = self.url(&format!("models/{model}"));
+        let info = execute_with_retry(self.config.retries, || async {
+            let request = self.http.get(&url).bearer_auth(self.key.expose());
+            let response = self.config.prepare_request(request).send().await?;
+
+            if response.status() == reqwest::StatusCode::NOT_FOUND {
+                return Err(ClientError::Api(ApiError {
+                    message: format!("OpenAI model not found: {model}"),
+                    status_code: Some(404),
+                    error_type: ApiErrorType::InvalidModel,
+                    source: None,
+                }));
+            }
+            if !response.status().is_success() {
+                return Err(error_from_response(response).await);
+            }
+
+            let response_text = response.text().await?;
+            parse_model_info(&response_text)
+                .map_err(|e| e.with_raw_content(&response_text, self.config.raw_content_limit))
+        })
+        .await?;
+
+        self.model_info
+            .lock()
+            .unwrap()
+            .insert(model.to_string(), info.clone());
+        Ok(info)
+    }
+}
+
+#[async_trait]
+impl AiClient for ChatGpt {
+    async fn send_prompt(&self, prompt: &str) -> Result<String, ClientError> {
+        let response = self.send_prompt_with_metadata(prompt).await?;
+        Ok(response.content)
+    }
+
+    fn supports_conversations(&self) -> bool {
+        true
+    }
+
+    fn supports_streaming(&self) -> bool {
+        true
+    }
+
+    fn name(&self) -> &str {
+        "ChatGPT"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn last_rate_limit(&self) -> Option<RateLimitStatus> {
+        self.rate_limit.last()
+    }
+    
+    async fn send_prompt_with_metadata(&self, prompt: &str) -> Result<AiResponse, ClientError> {
+        let conversation = Conversation {
+            messages: vec![Message::user(prompt)],
+        };
+        self.send_conversation_with_metadata(&conversation).await
+    }
+
+    async fn send_conversation_with_metadata(
+        &self,
+        conversation: &Conversation,
+    ) -> Result<AiResponse, ClientError> {
+        let conversation = self.config.sanitize(conversation);
+        let conversation = conversation.as_ref();
+        let context = ObservabilityContext::new(
+            self.name().to_string(),
+            self.model.clone(),
+            Arc::new(self.config.metrics.clone().unwrap_or_default()),
+        );
+        let request = self.config.with_fallback_model(&self.model, |model| {
+            self.send_request(conversation, model)
+        });
+        context.track_conversation(&self.config, conversation, request).await
+    }
+
     async fn send_conversation(&self, conversation: &Conversation) -> Result<String, ClientError> {
         let response = self.send_conversation_with_metadata(conversation).await?;
         Ok(response.content)
@@ -273,33 +469,42 @@ impl AiClient for ChatGpt {
         self.stream_conversation(&conversation).await
     }
 
+    fn build_request_preview(
+        &self,
+        conversation: &Conversation,
+    ) -> Result<RequestPreview, ClientError> {
+        let conversation = self.config.sanitize(conversation);
+        RequestPreview::from_request(self.chat_request(&conversation, &self.model), self.key.expose())
+    }
+
+    /// An optional system message first, then turns alternating from the user
+    fn validate_conversation(&self, conversation: &Conversation) -> Result<(), ClientError> {
+        crate::check_turn_order(&conversation.messages, false)
+    }
+
+    async fn debug_stream(
+        &self,
+        prompt: &str,
+    ) -> Result<BoxStream<'_, Result<SseEvent, ClientError>>, ClientError> {
+        let conversation = Conversation {
+            messages: vec![Message::user(prompt)],
+        };
+        let response = self.open_stream(&conversation, &StreamRequest::default()).await?;
+        Ok(client_sse_stream(response, &self.config))
+    }
+
     async fn stream_conversation(
         &self,
         conversation: &Conversation,
     ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
-        #[derive(Serialize)]
-        struct ApiMessage<'a> {
-            role: &'a str,
-            content: &'a str,
-        }
-
-        #[derive(Serialize)]
-        struct Request<'a> {
-            model: &'a str,
-            messages: Vec<ApiMessage<'a>>,
-            stream: bool,
-            #[serde(skip_serializing_if = "Option::is_none")]
-            temperature: Option<f32>,
-            #[serde(skip_serializing_if = "Option::is_none")]
-            max_tokens: Option<u32>,
-            #[serde(skip_serializing_if = "Option::is_none")]
-            top_p: Option<f32>,
-            #[serde(skip_serializing_if = "Option::is_none")]
-            frequency_penalty: Option<f32>,
-            #[serde(skip_serializing_if = "Option::is_none")]
-            presence_penalty: Option<f32>,
-        }
+        self.stream_conversation_with(conversation, &StreamRequest::default()).await
+    }
 
+    async fn stream_conversation_with(
+        &self,
+        conversation: &Conversation,
+        stream_request: &StreamRequest,
+    ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
         #[derive(Deserialize, Debug)]
         struct StreamResponse {
             choices: Vec<StreamChoice>,
@@ -331,66 +536,20 @@ impl AiClient for ChatGpt {
             total_tokens: Option<u32>,
         }
 
-        let mut messages = Vec::new();
-        
-        // Add system message if configured
-        if let Some(system_msg) = &self.config.system_message {
-            messages.push(ApiMessage {
-                role: "system",
-                content: system_msg,
-            });
-        }
-        
-        // Add conversation messages
-        for msg in &conversation.messages {
-            messages.push(ApiMessage {
-                role: &msg.role,
-                content: &msg.content,
-            });
-        }
-
-        let body = Request {
-            model: &self.model,
-            messages,
-            stream: true,
-            temperature: self.config.temperature,
-            max_tokens: self.config.max_tokens,
-            top_p: self.config.top_p,
-            frequency_penalty: self.config.frequency_penalty,
-            presence_penalty: self.config.presence_penalty,
-        };
-
-        let url = if let Some(base_url) = &self.config.base_url {
-            format!("{}/chat/completions", base_url.trim_end_matches('/'))
-        } else {
-            "https://api.openai.com/v1/chat/completions".to_string()
-        };
-
-        let response = self
-            .http
-            .post(&url)
-            .bearer_auth(&self.key)
-            .json(&body)
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(ClientError::Api(ApiError {
-                message: format!("OpenAI API error ({}): {}", status, error_text),
-                status_code: Some(status.as_u16()),
-                error_type: ApiErrorType::Other,
-            }));
-        }
+        let response = self.open_stream(conversation, stream_request).await?;
 
         // Parse SSE stream
-        let sse_stream = sse_stream(response);
+        let sse_stream = client_sse_stream(response, &self.config);
         let start_time = Arc::new(std::sync::Mutex::new(Instant::now()));
+        // With include_usage, usage arrives in a trailing chunk without choices after the
+        // finish reason, so that chunk is the one reported as finished
+        let include_usage = stream_request.include_usage;
+        let pending_finish_reason = Arc::new(std::sync::Mutex::new(None));
         
         let stream = sse_stream
             .filter_map(move |event| {
                 let start_time = Arc::clone(&start_time);
+                let pending_finish_reason = Arc::clone(&pending_finish_reason);
                 async move {
                     match event {
                         Ok(sse_event) => {
@@ -404,7 +563,10 @@ impl AiClient for ChatGpt {
                                 Ok(response) => {
                                     if let Some(choice) = response.choices.first() {
                                         let content = choice.delta.content.clone().unwrap_or_default();
-                                        let finished = choice.finish_reason.is_some();
+                                        if include_usage && choice.finish_reason.is_some() {
+                                            *pending_finish_reason.lock().unwrap() = choice.finish_reason.clone();
+                                        }
+                                        let finished = choice.finish_reason.is_some() && !include_usage;
                                         
                                         // Build metadata if this is the final chunk
                                         let metadata = if finished {
@@ -418,6 +580,7 @@ impl AiClient for ChatGpt {
                                                 safety_ratings: None,
                                                 request_id: response.id,
                                                 latency_ms: Some(latency_ms),
+                                                ..Default::default()
                                             })
                                         } else {
                                             None
@@ -428,6 +591,22 @@ impl AiClient for ChatGpt {
                                             finished,
                                             metadata,
                                         }))
+                                    } else if let Some(usage) = response.usage.filter(|_| include_usage) {
+                                        let latency_ms = start_time.lock().unwrap().elapsed().as_millis() as u64;
+                                        Some(Ok(StreamChunk {
+                                            content: String::new(),
+                                            finished: true,
+                                            metadata: Some(ResponseMetadata {
+                                                model_used: response.model,
+                                                prompt_tokens: usage.prompt_tokens,
+                                                completion_tokens: usage.completion_tokens,
+                                                total_tokens: usage.total_tokens,
+                                                finish_reason: pending_finish_reason.lock().unwrap().take(),
+                                                request_id: response.id,
+                                                latency_ms: Some(latency_ms),
+                                                ..Default::default()
+                                            }),
+                                        }))
                                     } else {
                                         None
                                     }
@@ -439,14 +618,563 @@ impl AiClient for ChatGpt {
                                 }
                             }
                         }
-                        Err(e) => Some(Err(ClientError::Stream(crate::StreamError {
-                            message: format!("SSE stream error: {}", e),
-                            error_type: crate::StreamErrorType::Other,
-                        }))),
+                        Err(e) => Some(Err(e)),
                     }
                 }
             });
 
-        Ok(Box::pin(stream))
+        Ok(with_idle_timeout(Box::pin(stream), stream_request.idle_timeout, self.config.metrics.clone()))
+    }
+}
+
+#[async_trait]
+impl EmbeddingClient for ChatGpt {
+    async fn embed_with_metadata(&self, inputs: &[String]) -> Result<EmbeddingResponse, ClientError> {
+        #[derive(Serialize)]
+        struct Request<'a> {
+            model: &'a str,
+            input: &'a [String],
+        }
+
+        if inputs.is_empty() {
+            return Ok(EmbeddingResponse {
+                embeddings: Vec::new(),
+                metadata: ResponseMetadata::default(),
+            });
+        }
+
+        let url = self.url("embeddings");
+        let body = Request {
+            model: &self.model,
+            input: inputs,
+        };
+
+        execute_with_retry(self.config.retries, || async {
+            self.config
+                .throttle_tokens(embeddings::estimated_tokens(inputs))
+                .await;
+            let start = Instant::now();
+            let request = self.http.post(&url).bearer_auth(self.key.expose()).json(&body);
+            let response = self.config.prepare_request(request).send().await?;
+            let rate_limit = self.rate_limit.record(response.headers());
+
+            if !response.status().is_success() {
+                return Err(error_from_response(response).await);
+            }
+
+            let response_text = response.text().await?;
+            let mut response = parse_embeddings(&response_text)
+                .map_err(|e| e.with_raw_content(&response_text, self.config.raw_content_limit))?;
+            response.metadata.latency_ms = Some(start.elapsed().as_millis() as u64);
+            response.metadata.rate_limit = rate_limit;
+            Ok(response)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl ModerationClient for ChatGpt {
+    async fn moderate(&self, text: &str) -> Result<ModerationResult, ClientError> {
+        #[derive(Serialize)]
+        struct Request<'a> {
+            model: &'a str,
+            input: &'a str,
+        }
+
+        let url = self.url("moderations");
+        let body = Request {
+            model: &self.model,
+            input: text,
+        };
+
+        execute_with_retry(self.config.retries, || async {
+            self.config.throttle_tokens(text.len() / 4).await;
+            let request = self.http.post(&url).bearer_auth(self.key.expose()).json(&body);
+            let response = self.config.prepare_request(request).send().await?;
+            self.rate_limit.record(response.headers());
+
+            if !response.status().is_success() {
+                return Err(error_from_response(response).await);
+            }
+
+            let response_text = response.text().await?;
+            parse_moderation(&response_text)
+                .map_err(|e| e.with_raw_content(&response_text, self.config.raw_content_limit))
+        })
+        .await
+    }
+}
+
+/// Context windows of OpenAI models
+///
+/// A model id matches an entry that equals it or that it extends with a `-` suffix,
+/// such as a date, preferring the longest entry. Ids such as `gpt-4.1` that merely
+/// start with an entry are not matched, since they are different models.
+#[cfg(feature = "orchestration")]
+const KNOWN_CONTEXT_WINDOWS: &[(&str, usize)] = &[
+    ("gpt-4", 8192),
+    ("gpt-4-32k", 32768),
+    ("gpt-4-turbo", 128000),
+    ("gpt-4-1106-preview", 128000),
+    ("gpt-4-0125-preview", 128000),
+    ("gpt-4o", 128000),
+    ("gpt-3.5-turbo", 16385),
+    ("gpt-3.5-turbo-instruct", 4096),
+];
+
+/// Parse a `/models/{id}` response body into model capabilities
+#[cfg(feature = "orchestration")]
+fn parse_model_info(body: &str) -> Result<ModelCapabilities, ClientError> {
+    #[derive(Deserialize)]
+    struct Model {
+        id: String,
+        #[serde(alias = "context_window", alias = "max_model_len")]
+        context_length: Option<usize>,
+    }
+
+    let model: Model = serde_json::from_str(body)?;
+    let known_window = || {
+        KNOWN_CONTEXT_WINDOWS
+            .iter()
+            .filter(|(known, _)| {
+                model.id.strip_prefix(known).is_some_and(|rest| rest.is_empty() || rest.starts_with('-'))
+            })
+            .max_by_key(|(known, _)| known.len())
+            .map(|(_, window)| *window)
+    };
+    let Some(max_context_length) = model.context_length.or_else(known_window) else {
+        return Err(ClientError::Parse(ParseError {
+            message: format!("OpenAI model {} reports no context window and is not a known model", model.id),
+            error_type: ParseErrorType::MissingField,
+            raw_content: None,
+            source: None,
+        }));
+    };
+    let cost_per_1k_tokens = crate::Pricing::current()
+        .rates(&model.id)
+        .map_or(crate::orchestration::DEFAULT_COST_PER_1K_TOKENS, |rates| {
+            ((rates.input_per_1k + rates.output_per_1k) / 2.0) as f32
+        });
+    let supports_vision = model.id.starts_with("gpt-4o")
+        || model.id.starts_with("gpt-4-turbo")
+        || model.id.contains("vision");
+
+    Ok(ModelCapabilities {
+        name: model.id,
+        strengths: Vec::new(),
+        avg_latency_ms: 0,
+        cost_per_1k_tokens,
+        max_context_length,
+        supports_streaming: true,
+        supports_vision,
+        supports_function_calling: true,
+        quality_factor: 1.0,
+    })
+}
+
+/// Error for an unsuccessful response, mapped from its status and body by `api_error`
+async fn error_from_response(response: reqwest::Response) -> ClientError {
+    let status = response.status().as_u16();
+    let body = response.text().await.unwrap_or_default();
+    api_error(status, &body)
+}
+
+/// The `error` object OpenAI includes in failed responses
+#[derive(Deserialize)]
+struct ErrorInfo {
+    message: String,
+    #[serde(rename = "type")]
+    error_type: Option<String>,
+    code: Option<String>,
+}
+
+impl ErrorInfo {
+    /// Error kind for this error's `type` or `code`, else for the HTTP `status`
+    fn api_error_type(&self, status: Option<u16>) -> ApiErrorType {
+        let kinds = [self.error_type.as_deref(), self.code.as_deref()];
+        let kind = |name: &str| kinds.contains(&Some(name));
+        if kind("insufficient_quota") {
+            ApiErrorType::QuotaExceeded
+        } else if kind("model_not_found") {
+            ApiErrorType::InvalidModel
+        } else if kind("content_filter") {
+            ApiErrorType::ContentFilter
+        } else {
+            status.map_or(ApiErrorType::Other, status_error_type)
+        }
+    }
+}
+
+/// Error kind for an HTTP status without a more specific error body
+fn status_error_type(status: u16) -> ApiErrorType {
+    match status {
+        429 => ApiErrorType::RateLimit,
+        400 | 413 => ApiErrorType::BadRequest,
+        404 => ApiErrorType::InvalidModel,
+        500.. => ApiErrorType::ServerError,
+        _ => ApiErrorType::Other,
+    }
+}
+
+/// Map a non-2xx OpenAI response to a client error
+///
+/// OpenAI reports failures as `{"error": {"message": "...", "type": "...", "code": "..."}}`;
+/// a `type` or `code` such as `insufficient_quota` decides the error kind. Other
+/// bodies, including ones that are not JSON, fall back to the HTTP status.
+fn api_error(status: u16, body: &str) -> ClientError {
+    #[derive(Deserialize)]
+    struct ErrorBody {
+        error: ErrorInfo,
+    }
+
+    let (error_type, detail) = match serde_json::from_str::<ErrorBody>(body) {
+        Ok(body) => (body.error.api_error_type(Some(status)), body.error.message),
+        Err(_) => (status_error_type(status), body.to_string()),
+    };
+    let message = format!("OpenAI API error ({status}): {detail}");
+
+    if status == 401 {
+        return ClientError::Authentication(AuthError {
+            message,
+            error_type: AuthErrorType::InvalidApiKey,
+            source: None,
+        });
+    }
+    ClientError::Api(ApiError {
+        message,
+        status_code: Some(status),
+        error_type,
+        source: None,
+    })
+}
+
+/// Parse a `/moderations` response body for a single input
+fn parse_moderation(body: &str) -> Result<ModerationResult, ClientError> {
+    #[derive(Deserialize)]
+    struct Response {
+        results: Vec<ModerationOutcome>,
+    }
+
+    #[derive(Deserialize)]
+    struct ModerationOutcome {
+        flagged: bool,
+        #[serde(default)]
+        categories: HashMap<String, bool>,
+        #[serde(default)]
+        category_scores: HashMap<String, f64>,
+    }
+
+    let resp: Response = serde_json::from_str(body)?;
+    let outcome = resp.results.into_iter().next().ok_or_else(|| {
+        ClientError::Parse(ParseError {
+            message: "OpenAI moderation response has no results".to_string(),
+            error_type: ParseErrorType::MissingField,
+            raw_content: None,
+            source: None,
+        })
+    })?;
+
+    let mut flagged_categories: Vec<String> = outcome
+        .categories
+        .into_iter()
+        .filter_map(|(category, flagged)| flagged.then_some(category))
+        .collect();
+    flagged_categories.sort();
+
+    Ok(ModerationResult {
+        flagged: outcome.flagged,
+        flagged_categories,
+        category_scores: outcome.category_scores,
+    })
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: RespMessage,
+    finish_reason: Option<String>,
+    logprobs: Option<ChoiceLogprobs>,
+}
+
+#[derive(Deserialize)]
+struct ChoiceLogprobs {
+    /// Log probabilities of the message content tokens; `top_logprobs` are ignored
+    content: Option<Vec<TokenLogprob>>,
+}
+
+#[derive(Deserialize)]
+struct RespMessage {
+    #[serde(default)]
+    content: MessageContent,
+}
+
+/// Message content, either plain text or an array of typed parts
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+    /// `null`, e.g. when the model only made tool calls
+    Empty(()),
+}
+
+impl Default for MessageContent {
+    fn default() -> Self {
+        MessageContent::Empty(())
+    }
+}
+
+#[derive(Deserialize)]
+struct ContentPart {
+    #[serde(rename = "type")]
+    kind: String,
+    text: Option<String>,
+}
+
+impl MessageContent {
+    /// The text content, concatenating the text parts of multimodal content
+    fn text(&self) -> String {
+        match self {
+            MessageContent::Text(text) => text.clone(),
+            MessageContent::Parts(parts) => parts
+                .iter()
+                .filter(|part| part.kind == "text")
+                .filter_map(|part| part.text.as_deref())
+                .collect(),
+            MessageContent::Empty(()) => String::new(),
+        }
+    }
+}
+
+/// Parse an `/embeddings` response body into vectors ordered like the inputs
+fn parse_embeddings(body: &str) -> Result<EmbeddingResponse, ClientError> {
+    #[derive(Deserialize)]
+    struct Response {
+        data: Vec<Embedding>,
+        model: Option<String>,
+        usage: Option<Usage>,
+    }
+
+    #[derive(Deserialize)]
+    struct Embedding {
+        index: usize,
+        embedding: Vec<f32>,
+    }
+
+    #[derive(Deserialize)]
+    struct Usage {
+        prompt_tokens: Option<u32>,
+        total_tokens: Option<u32>,
+    }
+
+    let mut resp: Response = serde_json::from_str(body)?;
+    resp.data.sort_by_key(|embedding| embedding.index);
+
+    Ok(EmbeddingResponse {
+        embeddings: resp.data.into_iter().map(|e| e.embedding).collect(),
+        metadata: ResponseMetadata {
+            model_used: resp.model,
+            prompt_tokens: resp.usage.as_ref().and_then(|u| u.prompt_tokens),
+            total_tokens: resp.usage.as_ref().and_then(|u| u.total_tokens),
+            ..Default::default()
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_endpoint_url() {
+        let client = ChatGpt::new(Client::new(), "key".into(), "gpt-4o".into(), ClientConfig::default());
+        assert_eq!(client.endpoint_url(), "https://api.openai.com/v1/chat/completions");
+
+        let config = ClientConfig::builder().base_url("https://example.openai.azure.com/v1/").build();
+        let client = ChatGpt::new(Client::new(), "key".into(), "gpt-4o".into(), config);
+        assert_eq!(client.endpoint_url(), "https://example.openai.azure.com/v1/chat/completions");
+    }
+
+    #[test]
+    fn test_api_error_from_error_body() {
+        // Recorded from an account whose credits have run out
+        let body = r#"{
+            "error": {
+                "message": "You exceeded your current quota, please check your plan and billing details.",
+                "type": "insufficient_quota",
+                "param": null,
+                "code": "insufficient_quota"
+            }
+        }"#;
+        let err = api_error(429, body);
+        assert!(err.is_quota_exceeded());
+        assert!(!err.is_rate_limit());
+        assert!(!err.is_retryable());
+        assert_eq!(err.status_code(), Some(429));
+        assert!(err.to_string().contains("You exceeded your current quota"), "{err}");
+
+        let body = r#"{"error": {"message": "Rate limit reached for gpt-4o", "type": "requests", "param": null, "code": "rate_limit_exceeded"}}"#;
+        let err = api_error(429, body);
+        assert!(err.is_rate_limit());
+        assert!(err.is_retryable());
+
+        let body = r#"{"error": {"message": "The model `gpt-5-turbo` does not exist", "type": "invalid_request_error", "param": null, "code": "model_not_found"}}"#;
+        let err = api_error(404, body);
+        assert!(matches!(err, ClientError::Api(ApiError { error_type: ApiErrorType::InvalidModel, .. })));
+        assert!(err.to_string().contains("`gpt-5-turbo` does not exist"), "{err}");
+
+        let body = r#"{"error": {"message": "Incorrect API key provided", "type": "invalid_request_error", "param": null, "code": "invalid_api_key"}}"#;
+        assert!(matches!(
+            api_error(401, body),
+            ClientError::Authentication(AuthError { error_type: AuthErrorType::InvalidApiKey, .. })
+        ));
+
+        // Bodies that are not OpenAI errors fall back to the status code
+        let err = api_error(502, "<html>Bad Gateway</html>");
+        assert!(matches!(err, ClientError::Api(ApiError { error_type: ApiErrorType::ServerError, .. })));
+        assert!(err.is_retryable());
+    }
+
+    #[test]
+    fn test_parse_choice_logprobs() {
+        let choice: Choice = serde_json::from_str(
+            r#"{
+                "index": 0,
+                "message": {"role": "assistant", "content": "Hello!"},
+                "logprobs": {
+                    "content": [
+                        {
+                            "token": "Hello",
+                            "logprob": -0.31725305,
+                            "bytes": [72, 101, 108, 108, 111],
+                            "top_logprobs": [
+                                {"token": "Hello", "logprob": -0.31725305, "bytes": [72, 101, 108, 108, 111]},
+                                {"token": "Hi", "logprob": -1.3190403, "bytes": [72, 105]}
+                            ]
+                        },
+                        {"token": "!", "logprob": -0.02380986, "bytes": [33], "top_logprobs": []}
+                    ],
+                    "refusal": null
+                },
+                "finish_reason": "stop"
+            }"#,
+        )
+        .unwrap();
+
+        let logprobs = choice.logprobs.and_then(|l| l.content).unwrap();
+        assert_eq!(
+            logprobs,
+            vec![
+                TokenLogprob { token: "Hello".to_string(), logprob: -0.31725305 },
+                TokenLogprob { token: "!".to_string(), logprob: -0.02380986 },
+            ]
+        );
+
+        // Without logprobs requested the field is absent or null
+        let choice: Choice =
+            serde_json::from_str(r#"{"message": {"content": "Hi"}, "logprobs": null}"#).unwrap();
+        assert!(choice.logprobs.is_none());
+    }
+
+    #[test]
+    fn test_message_content_string_or_parts() {
+        let message: RespMessage =
+            serde_json::from_str(r#"{"role": "assistant", "content": "Hello there"}"#).unwrap();
+        assert_eq!(message.content.text(), "Hello there");
+
+        let message: RespMessage = serde_json::from_str(
+            r#"{
+                "role": "assistant",
+                "content": [
+                    {"type": "text", "text": "Hello "},
+                    {"type": "image_url", "image_url": {"url": "https://example.com/cat.png"}},
+                    {"type": "text", "text": "there"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(message.content.text(), "Hello there");
+
+        let message: RespMessage =
+            serde_json::from_str(r#"{"role": "assistant", "content": null}"#).unwrap();
+        assert_eq!(message.content.text(), "");
+    }
+
+    #[cfg(feature = "orchestration")]
+    #[test]
+    fn test_parse_model_info_matches_whole_model_names() {
+        let window = |id: &str| {
+            parse_model_info(&serde_json::json!({"id": id}).to_string()).map(|info| info.max_context_length)
+        };
+        assert_eq!(window("gpt-4").unwrap(), 8192);
+        assert_eq!(window("gpt-4-0613").unwrap(), 8192);
+        assert_eq!(window("gpt-4-turbo-2024-04-09").unwrap(), 128000);
+        assert_eq!(window("gpt-4o-mini").unwrap(), 128000);
+        assert_eq!(window("gpt-3.5-turbo-instruct").unwrap(), 4096);
+
+        // Successors that only share a prefix are unknown, not gpt-4
+        for id in ["gpt-4.1", "gpt-4.5-preview", "gpt-40"] {
+            assert!(matches!(window(id), Err(ClientError::Parse(_))), "{id}");
+        }
+    }
+
+    #[test]
+    fn test_parse_moderation() {
+        // Recorded from the OpenAI API, trimmed to a few categories
+        let body = r#"{
+            "id": "modr-970d409ef3bef3b70c73d8232df86e7d",
+            "model": "omni-moderation-latest",
+            "results": [{
+                "flagged": true,
+                "categories": {
+                    "harassment": false,
+                    "violence": true,
+                    "violence/graphic": true,
+                    "self-harm": false
+                },
+                "category_scores": {
+                    "harassment": 0.0012,
+                    "violence": 0.8701,
+                    "violence/graphic": 0.6112,
+                    "self-harm": 0.0003
+                },
+                "category_applied_input_types": {"violence": ["text"]}
+            }]
+        }"#;
+
+        let result = parse_moderation(body).unwrap();
+        assert!(result.flagged);
+        assert_eq!(result.flagged_categories, ["violence", "violence/graphic"]);
+        assert_eq!(result.category_scores.len(), 4);
+        assert_eq!(result.category_scores["violence"], 0.8701);
+
+        assert!(matches!(
+            parse_moderation(r#"{"results": []}"#),
+            Err(ClientError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_embeddings() {
+        // Recorded from the OpenAI API, vectors truncated to three dimensions
+        let body = r#"{
+            "object": "list",
+            "data": [
+                {"object": "embedding", "index": 1, "embedding": [0.0123, -0.0456, 0.0789]},
+                {"object": "embedding", "index": 0, "embedding": [-0.0062, 0.0101, -0.0315]}
+            ],
+            "model": "text-embedding-3-small",
+            "usage": {"prompt_tokens": 9, "total_tokens": 9}
+        }"#;
+
+        let response = parse_embeddings(body).unwrap();
+        // Vectors are returned in input order regardless of response order
+        assert_eq!(
+            response.embeddings,
+            vec![vec![-0.0062, 0.0101, -0.0315], vec![0.0123, -0.0456, 0.0789]]
+        );
+        assert_eq!(response.metadata.model_used.as_deref(), Some("text-embedding-3-small"));
+        assert_eq!(response.metadata.prompt_tokens, Some(9));
+        assert_eq!(response.metadata.total_tokens, Some(9));
     }
 }
@@ -0,0 +1,115 @@
+//! Text embeddings
+//!
+//! Not every provider offers embeddings, so they live behind [`EmbeddingClient`]
+//! rather than [`AiClient`]. OpenAI and Gemini implement it;
+//! Claude has no embeddings endpoint and does not.
+
+use crate::{http_client_for, AiClient, ChatGpt, ClientConfig, ClientError, Gemini, ResponseMetadata};
+use async_trait::async_trait;
+
+/// Embedding vectors along with usage metadata
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct EmbeddingResponse {
+    /// One vector per input, in input order
+    pub embeddings: Vec<Vec<f32>>,
+    /// Model, token usage, and latency of the request
+    pub metadata: ResponseMetadata,
+}
+
+/// Common interface for providers that can embed text
+///
+/// Embedding clients are also [`AiClient`]s, which supplies their name and model.
+#[async_trait]
+pub trait EmbeddingClient: AiClient {
+    /// Embed each input, returning one vector per input in the same order
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>, ClientError> {
+        Ok(self.embed_with_metadata(inputs).await?.embeddings)
+    }
+
+    /// Embed each input, returning the vectors with usage metadata
+    async fn embed_with_metadata(&self, inputs: &[String]) -> Result<EmbeddingResponse, ClientError>;
+}
+
+/// Factory function to create embedding clients
+///
+/// # Arguments
+///
+/// * `provider` - The AI provider: "openai" or "google"/"gemini"
+/// * `api_key` - The API key for the provider
+/// * `model` - The embedding model (e.g., "text-embedding-3-small", "text-embedding-004")
+/// * `config` - Configuration for timeouts, retries, and rate limits
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use chatdelta::{create_embedding_client, ClientConfig};
+///
+/// # async fn example() -> Result<(), chatdelta::ClientError> {
+/// let client = create_embedding_client(
+///     "openai",
+///     "your-api-key",
+///     "text-embedding-3-small",
+///     ClientConfig::default(),
+/// )?;
+/// let vectors = client.embed(&["first chunk".to_string(), "second chunk".to_string()]).await?;
+/// assert_eq!(vectors.len(), 2);
+/// # Ok(())
+/// # }
+/// ```
+pub fn create_embedding_client(
+    provider: &str,
+    api_key: &str,
+    model: &str,
+    config: ClientConfig,
+) -> Result<Box<dyn EmbeddingClient>, ClientError> {
+    match provider.to_lowercase().as_str() {
+        "openai" | "gpt" | "chatgpt" => Ok(Box::new(ChatGpt::new(
+            http_client_for(provider, &config)?,
+            api_key.to_string(),
+            model.to_string(),
+            config,
+        ))),
+        "google" | "gemini" => Ok(Box::new(Gemini::new(
+            http_client_for(provider, &config)?,
+            api_key.to_string(),
+            model.to_string(),
+            config,
+        ))),
+        "anthropic" | "claude" => Err(ClientError::config(
+            "Anthropic does not offer an embeddings API",
+            Some("provider".to_string()),
+        )),
+        _ => Err(ClientError::config(
+            format!("Unknown provider: {provider}. Embedding providers: openai, google"),
+            Some("provider".to_string()),
+        )),
+    }
+}
+
+/// Rough token count of embedding inputs, at about four characters per token
+pub(crate) fn estimated_tokens(inputs: &[String]) -> usize {
+    inputs.iter().map(|input| input.len() / 4).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_embedding_client() {
+        let client = create_embedding_client(
+            "gemini",
+            "test-key",
+            "text-embedding-004",
+            ClientConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(client.name(), "Gemini");
+        assert_eq!(client.model(), "text-embedding-004");
+
+        let err = create_embedding_client("claude", "test-key", "any", ClientConfig::default())
+            .err()
+            .unwrap();
+        assert!(matches!(err, ClientError::Configuration(_)));
+    }
+}
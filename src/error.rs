@@ -1,9 +1,21 @@
 //! Error types for the ChatDelta AI client library
 
 use std::fmt;
+use std::sync::Arc;
+
+/// Default maximum number of bytes of a response body kept on a parse error
+pub const DEFAULT_RAW_CONTENT_LIMIT: usize = 4096;
+
+/// Number of bytes of the raw response shown when a parse error is displayed
+const RAW_CONTENT_SNIPPET_LEN: usize = 200;
+
+/// Underlying error preserved as the `source()` of a [`ClientError`]
+///
+/// Reference-counted so that `ClientError` stays cheap to clone.
+pub type ErrorSource = Arc<dyn std::error::Error + Send + Sync>;
 
 /// Errors that can occur when using AI clients
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ClientError {
     /// Network-related errors (timeouts, connection failures, etc.)
     Network(NetworkError),
@@ -20,30 +32,36 @@ pub enum ClientError {
 }
 
 /// Network-related error details
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct NetworkError {
     pub message: String,
     pub error_type: NetworkErrorType,
+    /// Underlying error that caused this one, if any
+    pub source: Option<ErrorSource>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum NetworkErrorType {
     Timeout,
     ConnectionFailed,
     ConnectionReset,
     DnsResolution,
+    /// Abandoned locally because the caller cancelled the request
+    Cancelled,
     Other,
 }
 
 /// API-related error details
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ApiError {
     pub message: String,
     pub status_code: Option<u16>,
     pub error_type: ApiErrorType,
+    /// Underlying error that caused this one, if any
+    pub source: Option<ErrorSource>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ApiErrorType {
     RateLimit,
     QuotaExceeded,
@@ -51,17 +69,23 @@ pub enum ApiErrorType {
     ContentFilter,
     ServerError,
     BadRequest,
+    /// Rejected locally because the provider's circuit breaker is open
+    CircuitOpen,
+    /// Rejected locally because the client's spending budget is exhausted
+    BudgetExceeded,
     Other,
 }
 
 /// Authentication error details
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct AuthError {
     pub message: String,
     pub error_type: AuthErrorType,
+    /// Underlying error that caused this one, if any
+    pub source: Option<ErrorSource>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum AuthErrorType {
     InvalidApiKey,
     MissingApiKey,
@@ -71,21 +95,23 @@ pub enum AuthErrorType {
 }
 
 /// Configuration error details
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ConfigError {
     pub message: String,
     pub parameter: Option<String>,
 }
 
 /// Parse error details
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ParseError {
     pub message: String,
     pub error_type: ParseErrorType,
     pub raw_content: Option<String>,
+    /// Underlying error that caused this one, if any
+    pub source: Option<ErrorSource>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ParseErrorType {
     JsonParsing,
     MissingField,
@@ -94,13 +120,13 @@ pub enum ParseErrorType {
 }
 
 /// Streaming error details
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct StreamError {
     pub message: String,
     pub error_type: StreamErrorType,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum StreamErrorType {
     ConnectionLost,
     InvalidChunk,
@@ -109,11 +135,103 @@ pub enum StreamErrorType {
 }
 
 impl ClientError {
+    /// Whether retrying the same request may succeed
+    ///
+    /// Network failures other than cancellation, rate limits, server errors
+    /// (HTTP 5xx), and lost stream connections are transient; everything else is not.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ClientError::Network(err) => !matches!(err.error_type, NetworkErrorType::Cancelled),
+            ClientError::Api(err) => match err.error_type {
+                ApiErrorType::RateLimit | ApiErrorType::ServerError => true,
+                // Providers report an exhausted quota with HTTP 429 too, but waiting won't help
+                ApiErrorType::QuotaExceeded => false,
+                _ => err.status_code.is_some_and(|status| status == 429 || status >= 500),
+            },
+            ClientError::Stream(err) => matches!(err.error_type, StreamErrorType::ConnectionLost),
+            _ => false,
+        }
+    }
+
+    /// HTTP status code returned by the provider, if any
+    pub fn status_code(&self) -> Option<u16> {
+        match self {
+            ClientError::Api(err) => err.status_code,
+            _ => None,
+        }
+    }
+
+    /// Whether the provider rejected the request for exceeding its rate limit
+    pub fn is_rate_limit(&self) -> bool {
+        match self {
+            ClientError::Api(err) => match err.error_type {
+                ApiErrorType::RateLimit => true,
+                ApiErrorType::QuotaExceeded => false,
+                _ => err.status_code == Some(429),
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether the provider rejected the request because the account's quota is used up
+    pub fn is_quota_exceeded(&self) -> bool {
+        matches!(
+            self,
+            ClientError::Api(ApiError { error_type: ApiErrorType::QuotaExceeded, .. })
+        )
+    }
+
+    /// Whether the caller cancelled the request
+    pub fn is_cancelled(&self) -> bool {
+        matches!(
+            self,
+            ClientError::Network(NetworkError { error_type: NetworkErrorType::Cancelled, .. })
+        )
+    }
+
+    /// Attach the raw response body to a parse error, truncated to `max_len` bytes
+    ///
+    /// Errors other than `ClientError::Parse` are returned unchanged.
+    pub fn with_raw_content(self, raw: &str, max_len: usize) -> Self {
+        match self {
+            ClientError::Parse(mut err) => {
+                err.raw_content = Some(truncate_to_char_boundary(raw, max_len).to_string());
+                ClientError::Parse(err)
+            }
+            other => other,
+        }
+    }
+
+    /// Attach the underlying error that caused this one, exposed through `source()`
+    ///
+    /// Configuration and stream errors carry no source and are returned unchanged.
+    fn with_source(mut self, source: impl std::error::Error + Send + Sync + 'static) -> Self {
+        let source: ErrorSource = Arc::new(source);
+        match &mut self {
+            ClientError::Network(err) => err.source = Some(source),
+            ClientError::Api(err) => err.source = Some(source),
+            ClientError::Authentication(err) => err.source = Some(source),
+            ClientError::Parse(err) => err.source = Some(source),
+            ClientError::Configuration(_) | ClientError::Stream(_) => {}
+        }
+        self
+    }
+
     /// Create a timeout network error
     pub fn timeout(message: impl Into<String>) -> Self {
         Self::Network(NetworkError {
             message: message.into(),
             error_type: NetworkErrorType::Timeout,
+            source: None,
+        })
+    }
+
+    /// Create a cancellation network error
+    pub fn cancelled(message: impl Into<String>) -> Self {
+        Self::Network(NetworkError {
+            message: message.into(),
+            error_type: NetworkErrorType::Cancelled,
+            source: None,
         })
     }
 
@@ -123,6 +241,7 @@ impl ClientError {
             message: message.into(),
             status_code: Some(429),
             error_type: ApiErrorType::RateLimit,
+            source: None,
         })
     }
 
@@ -131,6 +250,7 @@ impl ClientError {
         Self::Authentication(AuthError {
             message: message.into(),
             error_type: AuthErrorType::InvalidApiKey,
+            source: None,
         })
     }
 
@@ -148,6 +268,7 @@ impl ClientError {
             message: message.into(),
             error_type: ParseErrorType::JsonParsing,
             raw_content: None,
+            source: None,
         })
     }
 }
@@ -171,21 +292,41 @@ impl fmt::Display for ClientError {
                     write!(f, "Configuration error: {}", err.message)
                 }
             }
-            ClientError::Parse(err) => write!(f, "Parse error: {}", err.message),
+            ClientError::Parse(err) => {
+                write!(f, "Parse error: {}", err.message)?;
+                if let Some(raw) = &err.raw_content {
+                    let snippet = truncate_to_char_boundary(raw, RAW_CONTENT_SNIPPET_LEN);
+                    let ellipsis = if snippet.len() < raw.len() { "..." } else { "" };
+                    write!(f, " (response: {}{})", snippet, ellipsis)?;
+                }
+                Ok(())
+            }
             ClientError::Stream(err) => write!(f, "Stream error: {}", err.message),
         }
     }
 }
 
-impl std::error::Error for ClientError {}
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        let source = match self {
+            ClientError::Network(err) => err.source.as_ref(),
+            ClientError::Api(err) => err.source.as_ref(),
+            ClientError::Authentication(err) => err.source.as_ref(),
+            ClientError::Parse(err) => err.source.as_ref(),
+            ClientError::Configuration(_) | ClientError::Stream(_) => None,
+        };
+        source.map(|err| &**err as &(dyn std::error::Error + 'static))
+    }
+}
 
 impl From<reqwest::Error> for ClientError {
     fn from(err: reqwest::Error) -> Self {
-        if err.is_timeout() {
+        let error = if err.is_timeout() {
             let url = err.url().map(|u| u.as_str()).unwrap_or("unknown");
             ClientError::Network(NetworkError {
                 message: format!("Request timed out after attempting to reach {}. Consider increasing timeout or checking network connectivity.", url),
                 error_type: NetworkErrorType::Timeout,
+                source: None,
             })
         } else if err.is_connect() {
             let host = err.url()
@@ -194,6 +335,7 @@ impl From<reqwest::Error> for ClientError {
             ClientError::Network(NetworkError {
                 message: format!("Failed to connect to {}. Check internet connectivity and DNS resolution.", host),
                 error_type: NetworkErrorType::ConnectionFailed,
+                source: None,
             })
         } else if err.status().is_some() {
             let status = err.status().unwrap();
@@ -203,39 +345,78 @@ impl From<reqwest::Error> for ClientError {
                 ClientError::Authentication(AuthError {
                     message: "Invalid API key".to_string(),
                     error_type: AuthErrorType::InvalidApiKey,
+                    source: None,
                 })
             } else if status_code == 429 {
                 ClientError::Api(ApiError {
                     message: "Rate limit exceeded".to_string(),
                     status_code: Some(status_code),
                     error_type: ApiErrorType::RateLimit,
+                    source: None,
                 })
             } else if status_code >= 500 {
                 ClientError::Api(ApiError {
                     message: format!("Server error: {err}"),
                     status_code: Some(status_code),
                     error_type: ApiErrorType::ServerError,
+                    source: None,
                 })
             } else if status_code >= 400 {
                 ClientError::Api(ApiError {
                     message: format!("Bad request: {err}"),
                     status_code: Some(status_code),
                     error_type: ApiErrorType::BadRequest,
+                    source: None,
                 })
             } else {
                 ClientError::Api(ApiError {
                     message: format!("HTTP {status}: {err}"),
                     status_code: Some(status_code),
                     error_type: ApiErrorType::Other,
+                    source: None,
                 })
             }
+        } else if is_connection_reset(&err) {
+            ClientError::Network(NetworkError {
+                message: format!("Connection reset by peer: {err}"),
+                error_type: NetworkErrorType::ConnectionReset,
+                source: None,
+            })
         } else {
             ClientError::Network(NetworkError {
                 message: err.to_string(),
                 error_type: NetworkErrorType::Other,
+                source: None,
             })
+        };
+        error.with_source(err)
+    }
+}
+
+/// Shorten `s` to at most `max_len` bytes without splitting a UTF-8 character
+fn truncate_to_char_boundary(s: &str, max_len: usize) -> &str {
+    if s.len() <= max_len {
+        return s;
+    }
+    let mut end = max_len;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// Check whether an error, or any error in its source chain, is an I/O connection reset
+fn is_connection_reset(err: &(dyn std::error::Error + 'static)) -> bool {
+    let mut current = Some(err);
+    while let Some(err) = current {
+        if let Some(io_err) = err.downcast_ref::<std::io::Error>() {
+            if io_err.kind() == std::io::ErrorKind::ConnectionReset {
+                return true;
+            }
         }
+        current = err.source();
     }
+    false
 }
 
 impl From<serde_json::Error> for ClientError {
@@ -244,6 +425,157 @@ impl From<serde_json::Error> for ClientError {
             message: format!("JSON parsing failed: {err}"),
             error_type: ParseErrorType::JsonParsing,
             raw_content: None,
+            source: Some(Arc::new(err)),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Wrapper(std::io::Error);
+
+    impl fmt::Display for Wrapper {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "request failed")
+        }
+    }
+
+    impl std::error::Error for Wrapper {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    #[test]
+    fn test_connection_reset_detection() {
+        let reset = Wrapper(std::io::Error::from(std::io::ErrorKind::ConnectionReset));
+        assert!(is_connection_reset(&reset));
+
+        let refused = Wrapper(std::io::Error::from(std::io::ErrorKind::ConnectionRefused));
+        assert!(!is_connection_reset(&refused));
+    }
+
+    #[test]
+    fn test_error_classification() {
+        let rate_limited = ClientError::rate_limit("slow down");
+        assert!(rate_limited.is_retryable());
+        assert!(rate_limited.is_rate_limit());
+        assert_eq!(rate_limited.status_code(), Some(429));
+
+        let overloaded = ClientError::Api(ApiError {
+            message: "overloaded".to_string(),
+            status_code: Some(529),
+            error_type: ApiErrorType::Other,
+            source: None,
+        });
+        assert!(overloaded.is_retryable());
+        assert!(!overloaded.is_rate_limit());
+
+        let bad_request = ClientError::Api(ApiError {
+            message: "bad request".to_string(),
+            status_code: Some(400),
+            error_type: ApiErrorType::BadRequest,
+            source: None,
+        });
+        assert!(!bad_request.is_retryable());
+        assert_eq!(bad_request.status_code(), Some(400));
+
+        let reset = ClientError::Network(NetworkError {
+            message: "reset".to_string(),
+            error_type: NetworkErrorType::ConnectionReset,
+            source: None,
+        });
+        assert!(reset.is_retryable());
+        assert_eq!(reset.status_code(), None);
+
+        let lost = ClientError::Stream(StreamError {
+            message: "lost".to_string(),
+            error_type: StreamErrorType::ConnectionLost,
+        });
+        assert!(lost.is_retryable());
+
+        assert!(!ClientError::invalid_api_key("bad key").is_retryable());
+        assert!(!ClientError::config("bad value", None).is_retryable());
+        assert!(!ClientError::json_parse("bad json").is_retryable());
+    }
+
+    #[test]
+    fn test_parse_error_raw_content() {
+        let body = format!("{{\"unexpected\": \"{}\"}}", "é".repeat(600));
+        let err: ClientError = serde_json::from_str::<Vec<u8>>(&body).unwrap_err().into();
+        let err = err.with_raw_content(&body, 1001);
+
+        let ClientError::Parse(parse) = &err else {
+            panic!("expected a parse error");
+        };
+        // 1001 bytes would split a two-byte character, so one more byte is dropped
+        assert_eq!(parse.raw_content.as_deref().map(str::len), Some(1000));
+        assert!(body.starts_with(parse.raw_content.as_deref().unwrap()));
+        assert!(err.to_string().contains("(response: {\"unexpected\""));
+        assert!(err.to_string().ends_with("...)"));
+
+        let timeout = ClientError::timeout("slow").with_raw_content(&body, 100);
+        assert_eq!(timeout.to_string(), "Network error: slow");
+    }
+
+    #[test]
+    fn test_error_source_chain() {
+        use std::error::Error;
+
+        let json_err = serde_json::from_str::<Vec<u8>>("not json").unwrap_err();
+        let message = json_err.to_string();
+        let err: ClientError = json_err.into();
+
+        let source = err.source().expect("parse error should keep its source");
+        let json_source = source
+            .downcast_ref::<serde_json::Error>()
+            .expect("source should be the original serde_json error");
+        assert_eq!(json_source.to_string(), message);
+
+        assert!(ClientError::timeout("slow").source().is_none());
+        assert!(ClientError::config("bad", None).source().is_none());
+    }
+
+    #[test]
+    fn test_clone_each_variant() {
+        use std::error::Error;
+
+        let parse: ClientError = serde_json::from_str::<Vec<u8>>("not json")
+            .unwrap_err()
+            .into();
+        let errors = vec![
+            ClientError::timeout("slow"),
+            ClientError::rate_limit("too many requests"),
+            ClientError::invalid_api_key("bad key"),
+            ClientError::config("bad temperature", Some("temperature".to_string())),
+            parse.with_raw_content("not json", 100),
+            ClientError::Stream(StreamError {
+                message: "dropped".to_string(),
+                error_type: StreamErrorType::ConnectionLost,
+            }),
+        ];
+
+        for err in &errors {
+            let cloned = err.clone();
+            assert_eq!(cloned.to_string(), err.to_string());
+            assert_eq!(cloned.is_retryable(), err.is_retryable());
+            assert_eq!(cloned.source().is_some(), err.source().is_some());
+        }
+
+        // Clones share the same underlying source rather than copying it
+        let ClientError::Parse(original) = &errors[4] else {
+            panic!("expected a parse error");
+        };
+        let ClientError::Parse(cloned) = errors[4].clone() else {
+            panic!("expected a parse error");
+        };
+        assert!(Arc::ptr_eq(
+            original.source.as_ref().unwrap(),
+            cloned.source.as_ref().unwrap()
+        ));
+        assert_eq!(cloned.raw_content.as_deref(), Some("not json"));
+    }
+}
@@ -1,17 +1,36 @@
 //! Optimized HTTP client configuration for AI providers
 
-use reqwest::Client;
+use reqwest::{redirect, Client, ClientBuilder, NoProxy, Proxy};
 use std::sync::Arc;
 use std::time::Duration;
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
+use tracing::warn;
 
 /// Global shared HTTP client for all AI providers
+///
+/// # Panics
+///
+/// Panics on first use if the client cannot be built, for example when no TLS
+/// backend is available. Use [`try_shared_client`] to handle that instead.
 pub static SHARED_CLIENT: Lazy<Arc<Client>> = Lazy::new(|| {
-    Arc::new(create_optimized_client(Duration::from_secs(30))
-        .expect("Failed to create shared HTTP client"))
+    try_shared_client().expect("Failed to create shared HTTP client")
 });
 
+/// The global shared HTTP client, or the error that prevented building it
+///
+/// A failed build is retried on the next call. Returns the same client as
+/// [`SHARED_CLIENT`] once one has been built.
+pub fn try_shared_client() -> Result<Arc<Client>, reqwest::Error> {
+    static CLIENT: OnceCell<Arc<Client>> = OnceCell::new();
+    CLIENT
+        .get_or_try_init(|| create_optimized_client(Duration::from_secs(30)).map(Arc::new))
+        .cloned()
+}
+
 /// Create an optimized HTTP client with connection pooling and keepalive
+///
+/// Traffic goes through the proxies named by the standard `HTTP_PROXY`,
+/// `HTTPS_PROXY` and `NO_PROXY` environment variables, if any are set.
 pub fn create_optimized_client(timeout: Duration) -> Result<Client, reqwest::Error> {
     Client::builder()
         .timeout(timeout)
@@ -34,6 +53,54 @@ pub struct HttpConfig {
     pub pool_idle_timeout: Duration,
     pub tcp_keepalive: Option<Duration>,
     pub http2_adaptive_window: bool,
+    /// Proxy URL all requests are sent through, overriding the proxy environment variables
+    pub proxy: Option<String>,
+    /// Username and password for the proxy
+    pub proxy_auth: Option<(String, String)>,
+    /// Which redirects responses may send requests on to
+    pub redirect_policy: RedirectPolicy,
+}
+
+/// Which HTTP redirects a client follows
+///
+/// reqwest drops the `Authorization` header when a redirect leaves the original
+/// host, but not provider key headers such as `x-api-key` or `X-goog-api-key`, so
+/// a gateway redirecting elsewhere would receive the key.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RedirectPolicy {
+    /// Follow up to 10 redirects to any host, reqwest's default
+    #[default]
+    Follow,
+    /// Follow redirects only to the same host and port as the original request
+    SameHost,
+    /// Never follow redirects; the redirect response is returned as an error
+    None,
+}
+
+/// Most redirects followed for a single request
+const MAX_REDIRECTS: usize = 10;
+
+impl RedirectPolicy {
+    fn to_reqwest(self) -> redirect::Policy {
+        match self {
+            Self::Follow => redirect::Policy::limited(MAX_REDIRECTS),
+            Self::None => redirect::Policy::none(),
+            Self::SameHost => redirect::Policy::custom(|attempt| {
+                let origin = |url: &reqwest::Url| (url.host_str().map(str::to_owned), url.port_or_known_default());
+                let same_host = attempt
+                    .previous()
+                    .first()
+                    .is_some_and(|first| origin(first) == origin(attempt.url()));
+                if attempt.previous().len() > MAX_REDIRECTS {
+                    attempt.error("too many redirects")
+                } else if same_host {
+                    attempt.follow()
+                } else {
+                    attempt.stop()
+                }
+            }),
+        }
+    }
 }
 
 impl Default for HttpConfig {
@@ -45,6 +112,9 @@ impl Default for HttpConfig {
             pool_idle_timeout: Duration::from_secs(90),
             tcp_keepalive: Some(Duration::from_secs(60)),
             http2_adaptive_window: true,
+            proxy: None,
+            proxy_auth: None,
+            redirect_policy: RedirectPolicy::Follow,
         }
     }
 }
@@ -76,6 +146,16 @@ impl HttpConfig {
         }
     }
     
+    /// Create optimized config for `provider`, falling back to the defaults
+    pub fn for_provider(provider: &str) -> Self {
+        match provider.to_lowercase().as_str() {
+            "openai" | "gpt" | "chatgpt" => Self::for_openai(),
+            "claude" | "anthropic" => Self::for_claude(),
+            "gemini" | "google" => Self::for_gemini(),
+            _ => Self::default(),
+        }
+    }
+
     /// Build a client from this configuration
     pub fn build_client(&self) -> Result<Client, reqwest::Error> {
         let mut builder = Client::builder()
@@ -84,6 +164,7 @@ impl HttpConfig {
             .pool_idle_timeout(self.pool_idle_timeout)
             .pool_max_idle_per_host(self.pool_max_idle_per_host)
             .http2_adaptive_window(self.http2_adaptive_window)
+            .redirect(self.redirect_policy.to_reqwest())
             .use_rustls_tls()
             .user_agent(format!("chatdelta/{}", env!("CARGO_PKG_VERSION")));
             
@@ -91,35 +172,63 @@ impl HttpConfig {
             builder = builder.tcp_keepalive(keepalive);
         }
         
-        builder.build()
+        apply_proxy(builder, self.proxy.as_deref(), self.proxy_auth.as_ref())?.build()
     }
 }
 
+/// Route all requests through `proxy`, if set
+///
+/// Hosts listed in `NO_PROXY` still bypass an explicit proxy. Without one, reqwest
+/// falls back to the `HTTP_PROXY`/`HTTPS_PROXY` environment variables on its own.
+pub(crate) fn apply_proxy(
+    builder: ClientBuilder,
+    proxy: Option<&str>,
+    auth: Option<&(String, String)>,
+) -> Result<ClientBuilder, reqwest::Error> {
+    let Some(url) = proxy else {
+        return Ok(builder);
+    };
+    let mut proxy = Proxy::all(url)?.no_proxy(NoProxy::from_env());
+    if let Some((username, password)) = auth {
+        proxy = proxy.basic_auth(username, password);
+    }
+    Ok(builder.proxy(proxy))
+}
+
 /// Get or create a provider-specific HTTP client
+///
+/// Each provider has one process-wide client, so everything using it shares a
+/// connection pool.
+///
+/// # Panics
+///
+/// Panics if neither the provider's client nor the shared fallback can be built.
+/// Use [`try_provider_client`] to handle that instead.
 pub fn get_provider_client(provider: &str) -> Arc<Client> {
-    match provider.to_lowercase().as_str() {
-        "openai" | "gpt" | "chatgpt" => {
-            static OPENAI_CLIENT: Lazy<Arc<Client>> = Lazy::new(|| {
-                Arc::new(HttpConfig::for_openai().build_client()
-                    .expect("Failed to create OpenAI HTTP client"))
-            });
-            OPENAI_CLIENT.clone()
-        }
-        "claude" | "anthropic" => {
-            static CLAUDE_CLIENT: Lazy<Arc<Client>> = Lazy::new(|| {
-                Arc::new(HttpConfig::for_claude().build_client()
-                    .expect("Failed to create Claude HTTP client"))
-            });
-            CLAUDE_CLIENT.clone()
-        }
-        "gemini" | "google" => {
-            static GEMINI_CLIENT: Lazy<Arc<Client>> = Lazy::new(|| {
-                Arc::new(HttpConfig::for_gemini().build_client()
-                    .expect("Failed to create Gemini HTTP client"))
-            });
-            GEMINI_CLIENT.clone()
+    try_provider_client(provider).expect("Failed to create HTTP client")
+}
+
+/// Get or create a provider-specific HTTP client without panicking
+///
+/// If the provider's tuned client cannot be built, logs a warning and falls back
+/// to [`try_shared_client`]; the error is returned only if that fails too.
+pub fn try_provider_client(provider: &str) -> Result<Arc<Client>, reqwest::Error> {
+    static OPENAI_CLIENT: OnceCell<Arc<Client>> = OnceCell::new();
+    static CLAUDE_CLIENT: OnceCell<Arc<Client>> = OnceCell::new();
+    static GEMINI_CLIENT: OnceCell<Arc<Client>> = OnceCell::new();
+
+    let (cell, config): (&OnceCell<Arc<Client>>, fn() -> HttpConfig) = match provider.to_lowercase().as_str() {
+        "openai" | "gpt" | "chatgpt" => (&OPENAI_CLIENT, HttpConfig::for_openai),
+        "claude" | "anthropic" => (&CLAUDE_CLIENT, HttpConfig::for_claude),
+        "gemini" | "google" => (&GEMINI_CLIENT, HttpConfig::for_gemini),
+        _ => return try_shared_client(),
+    };
+    match cell.get_or_try_init(|| config().build_client().map(Arc::new)) {
+        Ok(client) => Ok(client.clone()),
+        Err(e) => {
+            warn!("Failed to create {provider} HTTP client, using the shared client: {e}");
+            try_shared_client()
         }
-        _ => SHARED_CLIENT.clone()
     }
 }
 
@@ -144,5 +253,35 @@ mod tests {
         
         let gemini = HttpConfig::for_gemini();
         assert_eq!(gemini.request_timeout, Duration::from_secs(25));
+
+        assert_eq!(HttpConfig::for_provider("Anthropic").request_timeout, Duration::from_secs(45));
+        assert_eq!(HttpConfig::for_provider("local").request_timeout, Duration::from_secs(30));
+    }
+    
+    #[test]
+    fn test_try_clients_are_pooled() {
+        let shared = try_shared_client().unwrap();
+        assert!(Arc::ptr_eq(&shared, &SHARED_CLIENT));
+        assert!(Arc::ptr_eq(&try_provider_client("local").unwrap(), &shared));
+
+        let openai = try_provider_client("openai").unwrap();
+        assert!(!Arc::ptr_eq(&openai, &shared));
+        assert!(Arc::ptr_eq(&openai, &get_provider_client("ChatGPT")));
+    }
+    
+    #[test]
+    fn test_proxy_config() {
+        let config = HttpConfig {
+            proxy: Some("http://proxy.internal:3128".to_string()),
+            proxy_auth: Some(("user".to_string(), "secret".to_string())),
+            ..Default::default()
+        };
+        assert!(config.build_client().is_ok());
+        
+        let invalid = HttpConfig {
+            proxy: Some("not a url".to_string()),
+            ..Default::default()
+        };
+        assert!(invalid.build_client().is_err());
     }
 }
\ No newline at end of file
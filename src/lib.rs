@@ -28,18 +28,30 @@
 
 use async_trait::async_trait;
 use futures::stream::BoxStream;
-use reqwest::Client;
+use reqwest::{Client, RequestBuilder};
+use sse::SseEvent;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::mpsc;
+use tracing::{debug, warn};
 
+pub mod blocking;
 pub mod clients;
+pub mod embeddings;
 pub mod error;
 pub mod http;
 pub mod metrics;
 pub mod middleware;
+pub mod moderation;
 pub mod observability;
+pub mod pricing;
+pub mod rate_limit;
+pub mod resilience;
 pub mod utils;
-mod sse;
+pub mod sse;
 
 #[cfg(feature = "orchestration")]
 pub mod orchestration;
@@ -47,20 +59,42 @@ pub mod orchestration;
 #[cfg(feature = "prompt-optimization")]
 pub mod prompt_optimizer;
 
+pub use blocking::BlockingClient;
 pub use clients::*;
+pub use embeddings::{create_embedding_client, EmbeddingClient, EmbeddingResponse};
 pub use error::*;
-pub use http::{HttpConfig, get_provider_client, SHARED_CLIENT};
-pub use metrics::{ClientMetrics, MetricsSnapshot, RequestTimer};
-pub use utils::{execute_with_retry, RetryStrategy};
+pub use http::{HttpConfig, RedirectPolicy, get_provider_client, try_provider_client, try_shared_client, SHARED_CLIENT};
+pub use metrics::{ClientMetrics, FailureBreakdown, MetricsSnapshot, ProviderMetrics, RequestTimer};
+pub use middleware::{clear_global_interceptors, set_global_interceptor, RequestMiddleware};
+pub use moderation::{create_moderation_client, ModerationClient, ModerationResult};
+pub use observability::{
+    init_tracing, MetricsExporter, ObservabilityContext, PrometheusTextExporter, TextExporter,
+    DEFAULT_LOG_TRUNCATE_CHARS,
+};
+#[cfg(feature = "metrics-export")]
+pub use observability::PrometheusExporter;
+pub use pricing::{ModelRates, Pricing};
+pub use rate_limit::{RateLimitStatus, RateLimiter};
+pub use resilience::{BudgetedClient, CircuitBreakerClient, CircuitBreakerConfig, CircuitState};
+pub use utils::{canonicalize_model, execute_with_retry, RetryStrategy};
+pub use futures::stream::AbortHandle;
+pub use tokio_util::sync::CancellationToken;
 
 #[cfg(feature = "orchestration")]
-pub use orchestration::{AiOrchestrator, FusedResponse, OrchestrationStrategy, ModelCapabilities};
+pub use orchestration::{
+    AiOrchestrator, BenchmarkResult, BenchmarkSort, Capability, CompactionConfig, ConfidenceTracker,
+    FusedResponse, ModelCapabilities, ModelFailure, OrchestrationReport, OrchestrationStrategy,
+};
 
 #[cfg(feature = "prompt-optimization")]
-pub use prompt_optimizer::{PromptOptimizer, OptimizedPrompt};
+pub use prompt_optimizer::{
+    OptimizationStrategy, OptimizedPrompt, PerformanceHistory, PromptOptimizer, TechniqueStats,
+};
 
 /// Configuration for AI clients
-#[derive(Debug, Clone)]
+///
+/// The `Debug` output masks the proxy password and the values of `extra_headers`.
+#[derive(Clone)]
 pub struct ClientConfig {
     /// Timeout for HTTP requests
     pub timeout: Duration,
@@ -82,6 +116,46 @@ pub struct ClientConfig {
     pub base_url: Option<String>,
     /// Retry strategy for failed requests
     pub retry_strategy: RetryStrategy,
+    /// Metrics collector that clients record request outcomes into
+    pub metrics: Option<ClientMetrics>,
+    /// Maximum number of bytes of a malformed response body kept on parse errors
+    pub raw_content_limit: usize,
+    /// Maximum requests per minute enforced by the client-side rate limiter
+    pub requests_per_minute: Option<u32>,
+    /// Maximum estimated prompt tokens per minute enforced by the client-side rate limiter
+    pub tokens_per_minute: Option<u32>,
+    /// Rate limiter awaited before each HTTP call, shared by every clone of this config
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// Proxy URL all requests are sent through, overriding the proxy environment variables
+    pub proxy: Option<String>,
+    /// Username and password for the proxy
+    pub proxy_auth: Option<(String, String)>,
+    /// Which HTTP redirects are followed
+    pub redirect_policy: RedirectPolicy,
+    /// Additional headers sent with every request, after the provider's own headers
+    pub extra_headers: HashMap<String, String>,
+    /// Cheaper model on the same provider to use when the primary model is rate limited
+    pub fallback_model: Option<String>,
+    /// Output format the model is asked to produce
+    pub response_format: Option<ResponseFormat>,
+    /// Return token log probabilities with this many top alternatives per token (OpenAI only)
+    pub logprobs: Option<u8>,
+    /// Bias added to the logits of these token ids, from -100 (ban) to 100 (force) (OpenAI only)
+    pub logit_bias: Option<HashMap<u32, f32>>,
+    /// Emit prompts and responses as debug events in the request span
+    pub log_bodies: bool,
+    /// Maximum number of characters of each prompt or response logged when `log_bodies` is set
+    pub log_truncate_chars: usize,
+    /// Strip control characters other than newlines and tabs from messages before sending
+    pub sanitize_prompts: bool,
+    /// Map model aliases such as `gpt4` to canonical names in [`create_client`]
+    pub canonicalize_models: bool,
+    /// Fail a stream when no event, including keep-alive pings, arrives within this window
+    pub stream_idle_timeout: Option<Duration>,
+    /// Number of alternative responses to generate per request (Gemini only)
+    pub candidate_count: Option<u32>,
+    /// Largest SSE event, in bytes, a stream buffers before failing
+    pub max_sse_event_size: usize,
 }
 
 impl Default for ClientConfig {
@@ -97,6 +171,26 @@ impl Default for ClientConfig {
             system_message: None,
             base_url: None,
             retry_strategy: RetryStrategy::default(),
+            metrics: None,
+            raw_content_limit: DEFAULT_RAW_CONTENT_LIMIT,
+            requests_per_minute: None,
+            tokens_per_minute: None,
+            rate_limiter: None,
+            proxy: None,
+            proxy_auth: None,
+            redirect_policy: RedirectPolicy::Follow,
+            extra_headers: HashMap::new(),
+            fallback_model: None,
+            response_format: None,
+            logprobs: None,
+            logit_bias: None,
+            log_bodies: false,
+            log_truncate_chars: DEFAULT_LOG_TRUNCATE_CHARS,
+            sanitize_prompts: false,
+            canonicalize_models: false,
+            stream_idle_timeout: None,
+            candidate_count: None,
+            max_sse_event_size: sse::DEFAULT_MAX_EVENT_SIZE,
         }
     }
 }
@@ -106,10 +200,129 @@ impl ClientConfig {
     pub fn builder() -> ClientConfigBuilder {
         ClientConfigBuilder::default()
     }
+
+    /// Check that sampling penalties are within the range providers accept
+    ///
+    /// [`create_client`] calls this, so out-of-range values fail up front rather
+    /// than as an error from the provider on the first request.
+    pub fn validate(&self) -> Result<(), ClientError> {
+        for (name, value) in [
+            ("frequency_penalty", self.frequency_penalty),
+            ("presence_penalty", self.presence_penalty),
+        ] {
+            if let Some(value) = value.filter(|v| !(-2.0..=2.0).contains(v)) {
+                return Err(ClientError::config(
+                    format!("{name} must be between -2.0 and 2.0, got {value}"),
+                    Some(name.to_string()),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Note at debug level that `provider` ignores the configured penalties
+    pub(crate) fn log_unsupported_penalties(&self, provider: &str) {
+        if self.frequency_penalty.is_some() || self.presence_penalty.is_some() {
+            debug!(
+                provider,
+                frequency_penalty = ?self.frequency_penalty,
+                presence_penalty = ?self.presence_penalty,
+                "Provider does not support frequency or presence penalties; ignoring them"
+            );
+        }
+    }
+
+    /// Wait for rate limiter capacity before sending `conversation`, recording the wait
+    pub(crate) async fn throttle(&self, conversation: &Conversation) {
+        self.throttle_tokens(conversation.estimated_tokens()).await;
+    }
+
+    /// Wait for rate limiter capacity for a request of `tokens` estimated tokens
+    pub(crate) async fn throttle_tokens(&self, tokens: usize) {
+        if let Some(limiter) = &self.rate_limiter {
+            let tokens = u32::try_from(tokens).unwrap_or(u32::MAX);
+            let waited = limiter.acquire(tokens).await;
+            if let Some(metrics) = &self.metrics {
+                metrics.record_rate_limit_wait(waited);
+            }
+        }
+    }
+
+    /// The conversation with control characters stripped, when `sanitize_prompts` is set
+    pub(crate) fn sanitize<'a>(&self, conversation: &'a Conversation) -> Cow<'a, Conversation> {
+        let is_stripped = |c: char| c.is_control() && !matches!(c, '\n' | '\r' | '\t');
+        if !self.sanitize_prompts
+            || !conversation.messages.iter().any(|m| m.content.contains(is_stripped))
+        {
+            return Cow::Borrowed(conversation);
+        }
+
+        let mut sanitized = conversation.clone();
+        for message in &mut sanitized.messages {
+            message.content.retain(|c| !is_stripped(c));
+        }
+        Cow::Owned(sanitized)
+    }
+
+    /// Run `send` against `model`, then against `fallback_model` if `model` is rate limited
+    pub(crate) async fn with_fallback_model<'a, F, Fut>(
+        &'a self,
+        model: &'a str,
+        send: F,
+    ) -> Result<AiResponse, ClientError>
+    where
+        F: Fn(&'a str) -> Fut,
+        Fut: Future<Output = Result<AiResponse, ClientError>>,
+    {
+        let error = match send(model).await {
+            Err(error) if error.is_rate_limit() || error.is_quota_exceeded() => error,
+            result => return result,
+        };
+        match self.fallback_model.as_deref() {
+            Some(fallback) if fallback != model => {
+                warn!(model, fallback, %error, "Primary model rate limited, using fallback model");
+                send(fallback).await
+            }
+            _ => Err(error),
+        }
+    }
+
+    /// Finish a request that already carries the provider's headers
+    ///
+    /// Sets the configured timeout, adds `extra_headers`, then runs the global
+    /// interceptors. The timeout overrides the HTTP client's own, since clients may
+    /// share a provider's pooled HTTP client with a different timeout.
+    pub(crate) fn prepare_request(&self, request: RequestBuilder) -> RequestBuilder {
+        middleware::apply_global_interceptors(self.apply_extra_headers(request.timeout(self.timeout)))
+    }
+
+    /// Add `extra_headers` to a request that already carries the provider's headers
+    ///
+    /// Headers the client has already set, such as authentication, are left
+    /// untouched and the conflicting extra header is skipped with a warning.
+    fn apply_extra_headers(&self, mut request: RequestBuilder) -> RequestBuilder {
+        if self.extra_headers.is_empty() {
+            return request;
+        }
+
+        let existing = request
+            .try_clone()
+            .and_then(|request| request.build().ok())
+            .map(|request| request.headers().clone())
+            .unwrap_or_default();
+        for (name, value) in &self.extra_headers {
+            if existing.contains_key(name.as_str()) {
+                warn!(header = %name, "Ignoring extra header that would override a client header");
+                continue;
+            }
+            request = request.header(name, value);
+        }
+        request
+    }
 }
 
 /// Builder for ClientConfig
-#[derive(Debug, Default)]
+#[derive(Default)]
 pub struct ClientConfigBuilder {
     timeout: Option<Duration>,
     retries: Option<u32>,
@@ -121,6 +334,26 @@ pub struct ClientConfigBuilder {
     system_message: Option<String>,
     base_url: Option<String>,
     retry_strategy: Option<RetryStrategy>,
+    metrics: Option<ClientMetrics>,
+    raw_content_limit: Option<usize>,
+    requests_per_minute: Option<u32>,
+    tokens_per_minute: Option<u32>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    proxy: Option<String>,
+    proxy_auth: Option<(String, String)>,
+    redirect_policy: RedirectPolicy,
+    extra_headers: HashMap<String, String>,
+    fallback_model: Option<String>,
+    response_format: Option<ResponseFormat>,
+    logprobs: Option<u8>,
+    logit_bias: Option<HashMap<u32, f32>>,
+    log_bodies: bool,
+    log_truncate_chars: Option<usize>,
+    sanitize_prompts: bool,
+    canonicalize_models: bool,
+    stream_idle_timeout: Option<Duration>,
+    candidate_count: Option<u32>,
+    max_sse_event_size: Option<usize>,
 }
 
 impl ClientConfigBuilder {
@@ -184,8 +417,173 @@ impl ClientConfigBuilder {
         self
     }
 
+    /// Record request outcomes, including failure categories, into `metrics`
+    pub fn metrics(mut self, metrics: ClientMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Set how many bytes of a malformed response body are kept on parse errors
+    pub fn raw_content_limit(mut self, limit: usize) -> Self {
+        self.raw_content_limit = Some(limit);
+        self
+    }
+
+    /// Limit how many requests per minute are sent
+    pub fn requests_per_minute(mut self, limit: u32) -> Self {
+        self.requests_per_minute = Some(limit);
+        self
+    }
+
+    /// Limit how many estimated prompt tokens per minute are sent
+    pub fn tokens_per_minute(mut self, limit: u32) -> Self {
+        self.tokens_per_minute = Some(limit);
+        self
+    }
+
+    /// Use an existing rate limiter, sharing its budget with other clients
+    ///
+    /// Takes precedence over `requests_per_minute` and `tokens_per_minute`.
+    pub fn rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    /// Send all requests through an HTTP(S) proxy, e.g. `http://proxy.internal:3128`
+    ///
+    /// Hosts listed in `NO_PROXY` still bypass it. Without an explicit proxy, the
+    /// `HTTP_PROXY`/`HTTPS_PROXY` environment variables are used.
+    pub fn proxy<S: Into<String>>(mut self, url: S) -> Self {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    /// Authenticate to the proxy with basic auth
+    pub fn proxy_auth<U: Into<String>, P: Into<String>>(mut self, username: U, password: P) -> Self {
+        self.proxy_auth = Some((username.into(), password.into()));
+        self
+    }
+
+    /// Restrict which HTTP redirects are followed, e.g. for gateways behind a custom `base_url`
+    ///
+    /// Provider key headers are sent on to wherever a redirect points, so use
+    /// [`RedirectPolicy::SameHost`] or [`RedirectPolicy::None`] unless every host a
+    /// redirect may lead to is trusted.
+    pub fn redirect_policy(mut self, policy: RedirectPolicy) -> Self {
+        self.redirect_policy = policy;
+        self
+    }
+
+    /// Send an additional header with every request, e.g. a routing key or `x-request-id`
+    ///
+    /// Headers are added after the provider-specific ones and cannot replace them:
+    /// a header the client already sets (authentication, API version, content
+    /// type) is skipped with a warning. Setting the same key twice keeps the last value.
+    pub fn header<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> Self {
+        self.extra_headers.insert(key.into(), value.into());
+        self
+    }
+
+    /// Fall back to `model` on the same provider when the primary model is rate limited
+    ///
+    /// The fallback is tried once, after the primary model's retries are exhausted
+    /// on a rate limit (HTTP 429) or quota error. Streaming requests do not fall back.
+    pub fn fallback_model<S: Into<String>>(mut self, model: S) -> Self {
+        self.fallback_model = Some(model.into());
+        self
+    }
+
+    /// Ask the model for output in `format`, e.g. [`ResponseFormat::JsonObject`]
+    pub fn response_format(mut self, format: ResponseFormat) -> Self {
+        self.response_format = Some(format);
+        self
+    }
+
+    /// Request token log probabilities, with up to `top` alternatives per token (0-20)
+    ///
+    /// Only the OpenAI client supports this; the probabilities of the chosen tokens
+    /// end up in [`ResponseMetadata::logprobs`].
+    pub fn logprobs(mut self, top: u8) -> Self {
+        self.logprobs = Some(top);
+        self
+    }
+
+    /// Suppress or boost specific tokens, mapping token ids to a bias from -100 to 100
+    ///
+    /// Only the OpenAI client (and OpenAI-compatible servers) supports this; token
+    /// ids are those of the model's tokenizer, and -100 bans a token outright.
+    pub fn logit_bias(mut self, bias: HashMap<u32, f32>) -> Self {
+        self.logit_bias = Some(bias);
+        self
+    }
+
+    /// Log prompts and responses as debug events
+    ///
+    /// Bodies may contain sensitive data, so this is off by default.
+    pub fn log_bodies(mut self, enabled: bool) -> Self {
+        self.log_bodies = enabled;
+        self
+    }
+
+    /// Set how many characters of each logged prompt or response are kept
+    pub fn log_truncate_chars(mut self, limit: usize) -> Self {
+        self.log_truncate_chars = Some(limit);
+        self
+    }
+
+    /// Strip control characters, such as null bytes from pasted content, from messages
+    ///
+    /// Providers may reject requests containing them. Newlines, carriage returns,
+    /// and tabs are kept.
+    pub fn sanitize_prompts(mut self, enabled: bool) -> Self {
+        self.sanitize_prompts = enabled;
+        self
+    }
+
+    /// Resolve model aliases with [`canonicalize_model`] when creating clients
+    ///
+    /// Lets users write `gpt4` or `GPT-4` where the provider expects `gpt-4`.
+    pub fn canonicalize_models(mut self, enabled: bool) -> Self {
+        self.canonicalize_models = enabled;
+        self
+    }
+
+    /// Fail streams that go quiet for longer than `timeout`
+    ///
+    /// The request timeout only covers receiving the response headers, so a provider
+    /// that stalls mid-stream would otherwise hang forever. Every received event,
+    /// including keep-alive pings, resets the clock.
+    pub fn stream_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.stream_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Generate `count` alternative responses per request, returned by
+    /// [`AiClient::send_prompt_multi`] (Gemini only)
+    ///
+    /// Other methods return the first candidate. Every candidate is billed as output.
+    pub fn candidate_count(mut self, count: u32) -> Self {
+        self.candidate_count = Some(count);
+        self
+    }
+
+    /// Fail streams whose next SSE event grows past `bytes` before it is complete
+    ///
+    /// Guards against a malformed or hostile endpoint that never ends an event,
+    /// which would otherwise buffer without bound. Defaults to 1 MiB.
+    pub fn max_sse_event_size(mut self, bytes: usize) -> Self {
+        self.max_sse_event_size = Some(bytes);
+        self
+    }
+
     /// Build the ClientConfig
     pub fn build(self) -> ClientConfig {
+        let rate_limiter = self.rate_limiter.or_else(|| {
+            (self.requests_per_minute.is_some() || self.tokens_per_minute.is_some()).then(|| {
+                Arc::new(RateLimiter::new(self.requests_per_minute, self.tokens_per_minute))
+            })
+        });
+
         ClientConfig {
             timeout: self.timeout.unwrap_or(Duration::from_secs(30)),
             retries: self.retries.unwrap_or(0),
@@ -197,6 +595,69 @@ impl ClientConfigBuilder {
             system_message: self.system_message,
             base_url: self.base_url,
             retry_strategy: self.retry_strategy.unwrap_or_default(),
+            metrics: self.metrics,
+            raw_content_limit: self.raw_content_limit.unwrap_or(DEFAULT_RAW_CONTENT_LIMIT),
+            requests_per_minute: self.requests_per_minute,
+            tokens_per_minute: self.tokens_per_minute,
+            rate_limiter,
+            proxy: self.proxy,
+            proxy_auth: self.proxy_auth,
+            redirect_policy: self.redirect_policy,
+            extra_headers: self.extra_headers,
+            fallback_model: self.fallback_model,
+            response_format: self.response_format,
+            logprobs: self.logprobs,
+            logit_bias: self.logit_bias,
+            log_bodies: self.log_bodies,
+            log_truncate_chars: self.log_truncate_chars.unwrap_or(DEFAULT_LOG_TRUNCATE_CHARS),
+            sanitize_prompts: self.sanitize_prompts,
+            canonicalize_models: self.canonicalize_models,
+            stream_idle_timeout: self.stream_idle_timeout,
+            candidate_count: self.candidate_count,
+            max_sse_event_size: self.max_sse_event_size.unwrap_or(sse::DEFAULT_MAX_EVENT_SIZE),
+        }
+    }
+}
+
+/// Output format requested from the model
+///
+/// OpenAI and Gemini enforce JSON output natively. Claude has no JSON mode, so the
+/// Claude client appends an instruction to the system prompt instead, which the
+/// model follows reliably but without a guarantee.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ResponseFormat {
+    /// Free-form text, the provider default
+    Text,
+    /// Any syntactically valid JSON object
+    JsonObject,
+    /// JSON matching the given JSON Schema
+    JsonSchema(serde_json::Value),
+}
+
+impl ResponseFormat {
+    /// OpenAI `response_format` request field
+    pub(crate) fn to_openai(&self) -> serde_json::Value {
+        match self {
+            ResponseFormat::Text => serde_json::json!({"type": "text"}),
+            ResponseFormat::JsonObject => serde_json::json!({"type": "json_object"}),
+            ResponseFormat::JsonSchema(schema) => serde_json::json!({
+                "type": "json_schema",
+                "json_schema": {"name": "response", "schema": schema},
+            }),
+        }
+    }
+
+    /// Instruction to add to the system prompt of providers without a JSON mode
+    pub(crate) fn instruction(&self) -> Option<String> {
+        match self {
+            ResponseFormat::Text => None,
+            ResponseFormat::JsonObject => Some(
+                "Respond only with a valid JSON object, without markdown code fences or any other text."
+                    .to_string(),
+            ),
+            ResponseFormat::JsonSchema(schema) => Some(format!(
+                "Respond only with a valid JSON object matching this JSON Schema, without markdown code fences or any other text:\n{schema}"
+            )),
         }
     }
 }
@@ -290,6 +751,197 @@ impl Conversation {
     pub fn is_empty(&self) -> bool {
         self.messages.is_empty()
     }
+
+    /// Rough token count of the conversation, at about four characters per token
+    pub fn estimated_tokens(&self) -> usize {
+        self.messages
+            .iter()
+            .map(|m| (m.role.len() + m.content.len()) / 4)
+            .sum()
+    }
+
+    /// Split the conversation into its system messages and all other messages
+    ///
+    /// Both halves keep their original relative order. Providers that take the system
+    /// prompt as a separate request field (Claude, Gemini) use this to pull it out.
+    pub fn split_system(&self) -> (Vec<Message>, Vec<Message>) {
+        self.messages
+            .iter()
+            .cloned()
+            .partition(|message| message.role == "system")
+    }
+
+    /// Export the conversation as an OpenAI-compatible `messages` array
+    ///
+    /// Each message becomes a `{"role": ..., "content": ...}` object, in order,
+    /// matching the shape the OpenAI chat completions API expects.
+    pub fn to_openai_messages(&self) -> Vec<serde_json::Value> {
+        self.messages
+            .iter()
+            .map(|message| {
+                serde_json::json!({
+                    "role": message.role,
+                    "content": message.content,
+                })
+            })
+            .collect()
+    }
+
+    /// Check that the conversation can be sent to `client` as-is
+    ///
+    /// Every client needs at least one message, each with a known role
+    /// ("system", "user", "assistant") and non-blank content. On top of that:
+    ///
+    /// - **ChatGPT** follows the [`ConversationBuilder`] ordering: an optional
+    ///   system message first, then user and assistant turns alternating from the user.
+    /// - **Claude** takes system messages separately, so they may appear anywhere,
+    ///   but the remaining turns must alternate starting with the user.
+    /// - **Gemini** currently sends only the latest user message, so it needs one.
+    ///
+    /// These provider checks come from [`AiClient::validate_conversation`], so they
+    /// also apply through wrappers such as [`CircuitBreakerClient`]. Other clients
+    /// only get the common checks.
+    pub fn validate_for(&self, client: &dyn AiClient) -> Result<(), ClientError> {
+        let invalid = |message: String| Err(ClientError::config(message, Some("messages".to_string())));
+
+        if self.messages.is_empty() {
+            return invalid("Conversation has no messages".to_string());
+        }
+        for (index, message) in self.messages.iter().enumerate() {
+            if !matches!(message.role.as_str(), "system" | "user" | "assistant") {
+                return invalid(format!("Unknown role {} at position {index}", message.role));
+            }
+            if message.content.trim().is_empty() {
+                return invalid(format!("Message at position {index} is empty"));
+            }
+        }
+
+        client.validate_conversation(self)
+    }
+
+    /// Create a builder that validates message ordering
+    pub fn builder() -> ConversationBuilder {
+        ConversationBuilder::default()
+    }
+
+    /// Compute a stable hash of the roles and contents of all messages
+    ///
+    /// Uses 64-bit FNV-1a with length-prefixed fields, so the value is identical
+    /// across runs, platforms, and Rust versions and suitable as a cache key.
+    pub fn content_hash(&self) -> u64 {
+        const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+        let mut hash = FNV_OFFSET_BASIS;
+        let mut write = |bytes: &[u8]| {
+            for byte in bytes {
+                hash ^= u64::from(*byte);
+                hash = hash.wrapping_mul(FNV_PRIME);
+            }
+        };
+
+        for message in &self.messages {
+            for field in [&message.role, &message.content] {
+                write(&(field.len() as u64).to_le_bytes());
+                write(field.as_bytes());
+            }
+        }
+        hash
+    }
+}
+
+/// Builder for a [`Conversation`] with validated message ordering
+///
+/// An optional system message may come first, followed by messages that strictly
+/// alternate between user and assistant, starting with the user.
+///
+/// # Example
+///
+/// ```
+/// use chatdelta::Conversation;
+///
+/// let conversation = Conversation::builder()
+///     .system("You are a helpful assistant.")
+///     .user("Hello")
+///     .assistant("Hi there!")
+///     .user("How are you?")
+///     .build()?;
+/// assert_eq!(conversation.len(), 4);
+/// # Ok::<(), chatdelta::ClientError>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ConversationBuilder {
+    messages: Vec<Message>,
+}
+
+impl ConversationBuilder {
+    /// Add the system message
+    pub fn system<S: Into<String>>(mut self, content: S) -> Self {
+        self.messages.push(Message::system(content));
+        self
+    }
+
+    /// Add a user message
+    pub fn user<S: Into<String>>(mut self, content: S) -> Self {
+        self.messages.push(Message::user(content));
+        self
+    }
+
+    /// Add an assistant message
+    pub fn assistant<S: Into<String>>(mut self, content: S) -> Self {
+        self.messages.push(Message::assistant(content));
+        self
+    }
+
+    /// Validate the message ordering and build the Conversation
+    pub fn build(self) -> Result<Conversation, ClientError> {
+        check_turn_order(&self.messages, false)?;
+
+        Ok(Conversation {
+            messages: self.messages,
+        })
+    }
+}
+
+/// Error unless `messages` include a user message, which `provider` requires
+pub(crate) fn require_user_message(messages: &[Message], provider: &str) -> Result<(), ClientError> {
+    if messages.iter().any(|message| message.role == "user") {
+        Ok(())
+    } else {
+        Err(ClientError::config(
+            format!("{provider} requires at least one user message"),
+            Some("messages".to_string()),
+        ))
+    }
+}
+
+/// Check that messages alternate between user and assistant, starting with the user
+///
+/// A system message is only accepted first unless `system_anywhere` is set, in which
+/// case system messages are skipped wherever they appear.
+pub(crate) fn check_turn_order(messages: &[Message], system_anywhere: bool) -> Result<(), ClientError> {
+    let mut expected = "user";
+    for (index, message) in messages.iter().enumerate() {
+        match message.role.as_str() {
+            "system" if index == 0 || system_anywhere => continue,
+            "system" => {
+                return Err(ClientError::config(
+                    format!("System message must be the first message, found at position {index}"),
+                    Some("messages".to_string()),
+                ));
+            }
+            role if role == expected => {
+                expected = if role == "user" { "assistant" } else { "user" };
+            }
+            role => {
+                return Err(ClientError::config(
+                    format!("Expected a {expected} message at position {index}, found {role}"),
+                    Some("messages".to_string()),
+                ));
+            }
+        }
+    }
+    Ok(())
 }
 
 /// Response metadata containing additional information from the AI provider
@@ -311,6 +963,139 @@ pub struct ResponseMetadata {
     pub request_id: Option<String>,
     /// Time taken to generate response in milliseconds
     pub latency_ms: Option<u64>,
+    /// Orchestration statistics for responses produced by an orchestrator
+    #[serde(default)]
+    pub orchestration: Option<serde_json::Value>,
+    /// Sources the response was grounded in (e.g. Gemini web search grounding)
+    #[serde(default)]
+    pub citations: Option<Vec<Citation>>,
+    /// Log probabilities of the generated tokens, when the provider returns them
+    #[serde(default)]
+    pub logprobs: Option<Vec<TokenLogprob>>,
+    /// Rate limit budget the provider reported alongside this response
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitStatus>,
+    /// Idempotency key sent with every attempt of this request
+    #[serde(default)]
+    pub idempotency_key: Option<String>,
+}
+
+/// Token counts accumulated over several requests
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TokenUsage {
+    /// Prompt tokens across all requests
+    pub prompt_tokens: u64,
+    /// Completion tokens across all requests
+    pub completion_tokens: u64,
+    /// Total tokens across all requests
+    pub total_tokens: u64,
+}
+
+impl TokenUsage {
+    /// Add the usage a provider reported for one request
+    ///
+    /// Providers that report no total count it as prompt plus completion tokens.
+    pub fn record(&mut self, metadata: &ResponseMetadata) {
+        let prompt = metadata.prompt_tokens.unwrap_or(0) as u64;
+        let completion = metadata.completion_tokens.unwrap_or(0) as u64;
+        self.prompt_tokens += prompt;
+        self.completion_tokens += completion;
+        self.total_tokens += metadata
+            .total_tokens
+            .map_or(prompt + completion, |total| total as u64);
+    }
+}
+
+/// Log probability of a single generated token
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TokenLogprob {
+    /// The generated token
+    pub token: String,
+    /// Natural log of the token's probability
+    pub logprob: f64,
+}
+
+/// A source cited by a grounded response
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Citation {
+    /// Address of the source
+    pub uri: String,
+    /// Title of the source, if provided
+    pub title: Option<String>,
+    /// Segments of the response text supported by this source
+    #[serde(default)]
+    pub supported_text: Vec<String>,
+}
+
+impl ResponseMetadata {
+    /// Estimated cost of this request, in US dollars, at the rates in `pricing`
+    ///
+    /// Prompt and completion tokens are priced separately at the rates for
+    /// `model_used`. Returns `None` when the model is unknown to `pricing` or the
+    /// provider reported no token counts. Sum the results to budget a session.
+    pub fn estimated_cost(&self, pricing: &pricing::Pricing) -> Option<f64> {
+        if self.prompt_tokens.is_none() && self.completion_tokens.is_none() {
+            return None;
+        }
+        pricing.cost(
+            self.model_used.as_deref()?,
+            self.prompt_tokens.unwrap_or(0),
+            self.completion_tokens.unwrap_or(0),
+        )
+    }
+
+    /// Get the provider's finish reason normalized into a common [`FinishReason`]
+    pub fn normalized_finish_reason(&self) -> Option<FinishReason> {
+        self.finish_reason.as_deref().map(FinishReason::from_raw)
+    }
+
+    /// Confidence (0.0 - 1.0) as the mean probability of the generated tokens
+    ///
+    /// Returns `None` unless token log probabilities were captured.
+    pub fn confidence_from_logprobs(&self) -> Option<f64> {
+        let logprobs = self.logprobs.as_ref().filter(|l| !l.is_empty())?;
+        let total: f64 = logprobs.iter().map(|t| t.logprob.exp()).sum();
+        Some(total / logprobs.len() as f64)
+    }
+
+    /// Requests left in the provider's current rate limit window, if reported
+    pub fn rate_limit_remaining(&self) -> Option<u32> {
+        self.rate_limit.as_ref()?.requests_remaining
+    }
+}
+
+/// Provider-independent reason a response finished
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum FinishReason {
+    /// The model finished naturally or hit a stop sequence
+    Stop,
+    /// The response was cut off by the token limit
+    Length,
+    /// The response was blocked or truncated by a safety filter
+    ContentFilter,
+    /// The model stopped to call a tool or function
+    ToolCalls,
+    /// A reason this library does not recognize, with the raw value
+    Other(String),
+}
+
+impl FinishReason {
+    /// Normalize a raw finish reason from OpenAI, Claude, or Gemini
+    pub fn from_raw(raw: &str) -> Self {
+        match raw {
+            // OpenAI: stop, Claude: end_turn/stop_sequence, Gemini: STOP
+            "stop" | "end_turn" | "stop_sequence" | "STOP" => FinishReason::Stop,
+            // OpenAI: length, Claude: max_tokens, Gemini: MAX_TOKENS
+            "length" | "max_tokens" | "MAX_TOKENS" => FinishReason::Length,
+            // OpenAI: content_filter, Gemini: SAFETY/RECITATION/BLOCKLIST/PROHIBITED_CONTENT
+            "content_filter" | "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" => {
+                FinishReason::ContentFilter
+            }
+            // OpenAI: tool_calls/function_call, Claude: tool_use
+            "tool_calls" | "function_call" | "tool_use" => FinishReason::ToolCalls,
+            other => FinishReason::Other(other.to_string()),
+        }
+    }
 }
 
 /// AI response with content and metadata
@@ -348,85 +1133,571 @@ pub struct StreamChunk {
     pub metadata: Option<ResponseMetadata>,
 }
 
-/// A session for managing multi-turn conversations with an AI client.
-/// 
-/// Automatically maintains conversation history and handles context management.
-/// 
+/// Stream-specific options for a single streaming request
+///
+/// These are kept apart from [`ClientConfig`], which applies to every request a
+/// client makes. Pass them to [`AiClient::stream_conversation_with`].
+///
 /// # Example
-/// 
-/// ```no_run
-/// # use chatdelta::{ChatSession, create_client, ClientConfig};
-/// # #[tokio::main]
-/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
-/// let client = create_client("openai", "key", "gpt-4", ClientConfig::default())?;
-/// let mut session = ChatSession::with_system_message(client, "You are a helpful assistant.");
-/// 
-/// let response1 = session.send("What is Rust?").await?;
-/// let response2 = session.send("What are its main features?").await?; // Remembers context
-/// # Ok(())
-/// # }
+///
 /// ```
-pub struct ChatSession {
-    /// The AI client to use for this session
-    client: Box<dyn AiClient>,
-    /// The conversation history
-    conversation: Conversation,
+/// use chatdelta::StreamRequest;
+/// use std::time::Duration;
+///
+/// let request = StreamRequest::builder()
+///     .include_usage(true)
+///     .idle_timeout(Duration::from_secs(10))
+///     .build();
+/// assert!(request.include_usage);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StreamRequest {
+    /// Ask the provider to report token usage in the final chunk (OpenAI only)
+    pub include_usage: bool,
+    /// End the stream with an error when no chunk arrives for this long
+    pub idle_timeout: Option<Duration>,
 }
 
-impl ChatSession {
-    /// Create a new chat session with the given client
-    pub fn new(client: Box<dyn AiClient>) -> Self {
-        Self {
-            client,
-            conversation: Conversation::new(),
-        }
+impl StreamRequest {
+    /// Create a new StreamRequest builder
+    pub fn builder() -> StreamRequestBuilder {
+        StreamRequestBuilder::default()
     }
 
-    /// Create a new chat session with a system message
-    pub fn with_system_message<S: Into<String>>(client: Box<dyn AiClient>, message: S) -> Self {
-        Self {
-            client,
-            conversation: Conversation::with_system(message),
+    /// OpenAI `stream_options` request field
+    pub(crate) fn to_openai(&self) -> Option<serde_json::Value> {
+        self.include_usage
+            .then(|| serde_json::json!({"include_usage": true}))
+    }
+}
+
+/// Builder for StreamRequest
+#[derive(Debug, Default)]
+pub struct StreamRequestBuilder {
+    include_usage: bool,
+    idle_timeout: Option<Duration>,
+}
+
+impl StreamRequestBuilder {
+    /// Report token usage in the final chunk
+    pub fn include_usage(mut self, include: bool) -> Self {
+        self.include_usage = include;
+        self
+    }
+
+    /// Set how long the stream may go without a chunk before it fails
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Build the StreamRequest
+    pub fn build(self) -> StreamRequest {
+        StreamRequest {
+            include_usage: self.include_usage,
+            idle_timeout: self.idle_timeout,
         }
     }
+}
 
-    /// Send a message and get a response
-    pub async fn send<S: Into<String>>(&mut self, message: S) -> Result<String, ClientError> {
-        let user_msg = message.into();
-        self.conversation.add_user(user_msg);
-        
-        let response = self.client.send_conversation(&self.conversation).await?;
-        self.conversation.add_assistant(&response);
-        
-        Ok(response)
+/// Await `request`, abandoning it with a cancellation error once `token` is cancelled
+async fn cancellable<T>(
+    token: &CancellationToken,
+    request: impl Future<Output = Result<T, ClientError>>,
+) -> Result<T, ClientError> {
+    tokio::select! {
+        biased;
+        _ = token.cancelled() => Err(ClientError::cancelled("Request cancelled")),
+        result = request => result,
     }
+}
 
-    /// Send a message and get a response with metadata
-    pub async fn send_with_metadata<S: Into<String>>(
+/// End `stream` with a cancellation error once `token` is cancelled, dropping the stream
+fn cancellable_stream(
+    stream: BoxStream<'_, Result<StreamChunk, ClientError>>,
+    token: CancellationToken,
+) -> BoxStream<'_, Result<StreamChunk, ClientError>> {
+    use futures::stream::{self, StreamExt};
+
+    Box::pin(stream::unfold(Some(stream), move |stream| {
+        let token = token.clone();
+        async move {
+            let mut stream = stream?;
+            tokio::select! {
+                biased;
+                _ = token.cancelled() => {
+                    Some((Err(ClientError::cancelled("Stream cancelled")), None))
+                }
+                chunk = stream.next() => chunk.map(|chunk| (chunk, Some(stream))),
+            }
+        }
+    }))
+}
+
+/// End `stream` with a connection-lost error once no item arrives within `idle_timeout`
+///
+/// The timeout is counted in the failure breakdown of `metrics`, if given.
+pub(crate) fn with_idle_timeout<'a, T: Send + 'a>(
+    stream: BoxStream<'a, Result<T, ClientError>>,
+    idle_timeout: Option<Duration>,
+    metrics: Option<ClientMetrics>,
+) -> BoxStream<'a, Result<T, ClientError>> {
+    use futures::stream::{self, StreamExt};
+
+    let Some(idle_timeout) = idle_timeout else {
+        return stream;
+    };
+    Box::pin(stream::unfold(Some((stream, metrics)), move |state| async move {
+        let (mut stream, metrics) = state?;
+        match tokio::time::timeout(idle_timeout, stream.next()).await {
+            Ok(Some(chunk)) => Some((chunk, Some((stream, metrics)))),
+            Ok(None) => None,
+            Err(_) => {
+                let error = ClientError::Stream(StreamError {
+                    message: format!("No stream data received for {idle_timeout:?}"),
+                    error_type: StreamErrorType::ConnectionLost,
+                });
+                if let Some(metrics) = &metrics {
+                    metrics.record_failure(&error);
+                }
+                Some((Err(error), None))
+            }
+        }
+    }))
+}
+
+/// A provider API key that never appears in `Debug` or `Display` output
+///
+/// Clients hold their key as an `ApiKey` so that logging a client, or an error
+/// that captured one, cannot leak the credential. Use [`ApiKey::expose`] where
+/// the raw value is genuinely needed, such as building the auth header.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ApiKey(String);
+
+impl ApiKey {
+    /// Wrap a raw API key
+    pub fn new<S: Into<String>>(key: S) -> Self {
+        Self(key.into())
+    }
+
+    /// The raw key, for authenticating requests
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for ApiKey {
+    fn from(key: String) -> Self {
+        Self(key)
+    }
+}
+
+impl From<&str> for ApiKey {
+    fn from(key: &str) -> Self {
+        Self(key.to_string())
+    }
+}
+
+impl std::fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("***redacted***")
+    }
+}
+
+impl std::fmt::Display for ApiKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("***redacted***")
+    }
+}
+
+/// Stands in for a secret in `Debug` output
+struct Redacted;
+
+impl std::fmt::Debug for Redacted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("***redacted***")
+    }
+}
+
+/// Proxy credentials for `Debug` output, keeping the username but not the password
+fn redacted_proxy_auth(auth: &Option<(String, String)>) -> Option<(&str, Redacted)> {
+    auth.as_ref().map(|(username, _)| (username.as_str(), Redacted))
+}
+
+/// Header names for `Debug` output, with every value masked
+fn redacted_headers(headers: &HashMap<String, String>) -> std::collections::BTreeMap<&str, Redacted> {
+    headers.keys().map(|name| (name.as_str(), Redacted)).collect()
+}
+
+// Written out by hand so that proxy passwords and extra header values, which often
+// carry gateway credentials, never appear in logs of a config or of a client.
+impl std::fmt::Debug for ClientConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientConfig")
+            .field("timeout", &self.timeout)
+            .field("retries", &self.retries)
+            .field("temperature", &self.temperature)
+            .field("max_tokens", &self.max_tokens)
+            .field("top_p", &self.top_p)
+            .field("frequency_penalty", &self.frequency_penalty)
+            .field("presence_penalty", &self.presence_penalty)
+            .field("system_message", &self.system_message)
+            .field("base_url", &self.base_url)
+            .field("retry_strategy", &self.retry_strategy)
+            .field("metrics", &self.metrics)
+            .field("raw_content_limit", &self.raw_content_limit)
+            .field("requests_per_minute", &self.requests_per_minute)
+            .field("tokens_per_minute", &self.tokens_per_minute)
+            .field("rate_limiter", &self.rate_limiter)
+            .field("proxy", &self.proxy)
+            .field("proxy_auth", &redacted_proxy_auth(&self.proxy_auth))
+            .field("redirect_policy", &self.redirect_policy)
+            .field("extra_headers", &redacted_headers(&self.extra_headers))
+            .field("fallback_model", &self.fallback_model)
+            .field("response_format", &self.response_format)
+            .field("logprobs", &self.logprobs)
+            .field("logit_bias", &self.logit_bias)
+            .field("log_bodies", &self.log_bodies)
+            .field("log_truncate_chars", &self.log_truncate_chars)
+            .field("sanitize_prompts", &self.sanitize_prompts)
+            .field("canonicalize_models", &self.canonicalize_models)
+            .field("stream_idle_timeout", &self.stream_idle_timeout)
+            .field("candidate_count", &self.candidate_count)
+            .field("max_sse_event_size", &self.max_sse_event_size)
+            .finish()
+    }
+}
+
+impl std::fmt::Debug for ClientConfigBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientConfigBuilder")
+            .field("timeout", &self.timeout)
+            .field("retries", &self.retries)
+            .field("temperature", &self.temperature)
+            .field("max_tokens", &self.max_tokens)
+            .field("top_p", &self.top_p)
+            .field("frequency_penalty", &self.frequency_penalty)
+            .field("presence_penalty", &self.presence_penalty)
+            .field("system_message", &self.system_message)
+            .field("base_url", &self.base_url)
+            .field("retry_strategy", &self.retry_strategy)
+            .field("metrics", &self.metrics)
+            .field("raw_content_limit", &self.raw_content_limit)
+            .field("requests_per_minute", &self.requests_per_minute)
+            .field("tokens_per_minute", &self.tokens_per_minute)
+            .field("rate_limiter", &self.rate_limiter)
+            .field("proxy", &self.proxy)
+            .field("proxy_auth", &redacted_proxy_auth(&self.proxy_auth))
+            .field("redirect_policy", &self.redirect_policy)
+            .field("extra_headers", &redacted_headers(&self.extra_headers))
+            .field("fallback_model", &self.fallback_model)
+            .field("response_format", &self.response_format)
+            .field("logprobs", &self.logprobs)
+            .field("logit_bias", &self.logit_bias)
+            .field("log_bodies", &self.log_bodies)
+            .field("log_truncate_chars", &self.log_truncate_chars)
+            .field("sanitize_prompts", &self.sanitize_prompts)
+            .field("canonicalize_models", &self.canonicalize_models)
+            .field("stream_idle_timeout", &self.stream_idle_timeout)
+            .field("candidate_count", &self.candidate_count)
+            .field("max_sse_event_size", &self.max_sse_event_size)
+            .finish()
+    }
+}
+
+/// Header names whose values are always masked in a [`RequestPreview`]
+const SENSITIVE_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "x-api-key",
+    "x-goog-api-key",
+    "cookie",
+];
+
+/// Placeholder for masked credentials in a [`RequestPreview`]
+const REDACTED: &str = "[REDACTED]";
+
+/// The HTTP request a client would send, with credentials masked
+///
+/// Built by [`AiClient::build_request_preview`] without contacting the provider, so
+/// request construction can be inspected in tests or logged while debugging.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RequestPreview {
+    /// Full request URL
+    pub url: String,
+    /// HTTP method, e.g. "POST"
+    pub method: String,
+    /// Request headers in send order, with credential values replaced by `[REDACTED]`
+    pub headers: Vec<(String, String)>,
+    /// Serialized JSON body, or `Null` when the request has none
+    pub body: serde_json::Value,
+}
+
+impl RequestPreview {
+    /// Preview a built request, masking credential headers and every occurrence of `secret`
+    pub(crate) fn from_request(
+        request: RequestBuilder,
+        secret: &str,
+    ) -> Result<Self, ClientError> {
+        let request = request.build()?;
+        let redact = |text: &str| {
+            if secret.is_empty() {
+                text.to_string()
+            } else {
+                text.replace(secret, REDACTED)
+            }
+        };
+
+        let headers = request
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                let value = if SENSITIVE_HEADERS.contains(&name.as_str()) {
+                    REDACTED.to_string()
+                } else {
+                    redact(&String::from_utf8_lossy(value.as_bytes()))
+                };
+                (name.to_string(), value)
+            })
+            .collect();
+        let body = match request.body().and_then(|body| body.as_bytes()) {
+            Some(bytes) => serde_json::from_slice(bytes)?,
+            None => serde_json::Value::Null,
+        };
+
+        Ok(Self {
+            url: redact(request.url().as_str()),
+            method: request.method().to_string(),
+            headers,
+            body,
+        })
+    }
+
+    /// Value of the header `name`, compared case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A session for managing multi-turn conversations with an AI client.
+/// 
+/// Automatically maintains conversation history and handles context management.
+/// 
+/// # Example
+/// 
+/// ```no_run
+/// # use chatdelta::{ChatSession, create_client, ClientConfig};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = create_client("openai", "key", "gpt-4", ClientConfig::default())?;
+/// let mut session = ChatSession::with_system_message(client, "You are a helpful assistant.");
+/// 
+/// let response1 = session.send("What is Rust?").await?;
+/// let response2 = session.send("What are its main features?").await?; // Remembers context
+/// # Ok(())
+/// # }
+/// ```
+pub struct ChatSession {
+    /// The AI client to use for this session
+    client: Box<dyn AiClient>,
+    /// The conversation history
+    conversation: Conversation,
+    /// Most messages sent per request, `None` for an unbounded history
+    max_messages: Option<usize>,
+    /// Tokens used by every request in the session so far
+    usage: TokenUsage,
+}
+
+impl ChatSession {
+    /// Create a new chat session with the given client
+    pub fn new(client: Box<dyn AiClient>) -> Self {
+        Self {
+            client,
+            conversation: Conversation::new(),
+            max_messages: None,
+            usage: TokenUsage::default(),
+        }
+    }
+
+    /// Create a chat session with a new client for `provider`
+    ///
+    /// Shorthand for [`create_client`] followed by [`ChatSession::new`]. Errors
+    /// building the client, such as an unknown provider, are returned.
+    pub fn from_provider(
+        provider: &str,
+        api_key: &str,
+        model: &str,
+        config: ClientConfig,
+    ) -> Result<Self, ClientError> {
+        create_client(provider, api_key, model, config).map(Self::new)
+    }
+
+    /// Create a new chat session with a system message
+    pub fn with_system_message<S: Into<String>>(client: Box<dyn AiClient>, message: S) -> Self {
+        Self {
+            client,
+            conversation: Conversation::with_system(message),
+            max_messages: None,
+            usage: TokenUsage::default(),
+        }
+    }
+
+    /// Keep the history to at most `max` messages as a sliding window
+    ///
+    /// Before each request, the oldest exchanges are dropped until the conversation
+    /// fits. System messages are always kept, and a user message is only dropped
+    /// together with the replies to it, so the history never starts mid-exchange.
+    /// The message being sent is kept even if it alone exceeds the limit.
+    pub fn with_max_messages(mut self, max: usize) -> Self {
+        self.max_messages = Some(max);
+        self
+    }
+
+    /// Drop the oldest exchanges exceeding the `with_max_messages` limit
+    ///
+    /// Called before every request; returns the dropped messages, oldest first.
+    pub fn trim_history(&mut self) -> Vec<Message> {
+        let Some(max) = self.max_messages else {
+            return Vec::new();
+        };
+        let messages = &mut self.conversation.messages;
+        let mut dropped = Vec::new();
+        while messages.len() > max {
+            // The first exchange: a user message (or stray reply) and the replies after it
+            let Some(start) = messages.iter().position(|m| m.role != "system") else {
+                break;
+            };
+            let end = messages[start + 1..]
+                .iter()
+                .position(|m| m.role == "user")
+                .map_or(messages.len(), |offset| start + 1 + offset);
+            if end == messages.len() {
+                break;
+            }
+            let mut index = start;
+            for _ in start..end {
+                if messages[index].role == "system" {
+                    index += 1;
+                } else {
+                    dropped.push(messages.remove(index));
+                }
+            }
+        }
+        dropped
+    }
+
+    /// Send a message and get a response
+    pub async fn send<S: Into<String>>(&mut self, message: S) -> Result<String, ClientError> {
+        Ok(self.send_with_metadata(message).await?.content)
+    }
+
+    /// Send a message and get a response with metadata
+    pub async fn send_with_metadata<S: Into<String>>(
         &mut self,
         message: S,
     ) -> Result<AiResponse, ClientError> {
         let user_msg = message.into();
         self.conversation.add_user(user_msg);
+        self.trim_history();
         
         let response = self
             .client
             .send_conversation_with_metadata(&self.conversation)
             .await?;
+        self.usage.record(&response.metadata);
         self.conversation.add_assistant(&response.content);
         
         Ok(response)
     }
 
     /// Stream a response for the given message
+    ///
+    /// The returned stream borrows the session and records the assistant's reply
+    /// in the history once it has yielded its last chunk. A stream that ends in an
+    /// error, or is dropped before it ends, leaves only the user message recorded.
+    /// Token usage from the last chunk carrying metadata is added when the stream ends.
     pub async fn stream<S: Into<String>>(
         &mut self,
         message: S,
     ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
+        use futures::stream::{self, StreamExt};
+
         let user_msg = message.into();
         self.conversation.add_user(user_msg);
+        self.trim_history();
+        let Self { client, conversation, usage, .. } = self;
+        
+        let stream = client.stream_conversation(conversation).await?;
+        let state = (stream, Some(conversation), usage, String::new(), None);
+        Ok(Box::pin(stream::unfold(
+            state,
+            |(mut stream, conversation, usage, mut reply, mut metadata)| async move {
+                match stream.next().await {
+                    Some(Ok(chunk)) => {
+                        reply.push_str(&chunk.content);
+                        if chunk.metadata.is_some() {
+                            metadata = chunk.metadata.clone();
+                        }
+                        Some((Ok(chunk), (stream, conversation, usage, reply, metadata)))
+                    }
+                    Some(Err(e)) => Some((Err(e), (stream, None, usage, reply, metadata))),
+                    None => {
+                        if let Some(metadata) = &metadata {
+                            usage.record(metadata);
+                        }
+                        if let Some(conversation) = conversation {
+                            conversation.add_assistant(reply);
+                        }
+                        None
+                    }
+                }
+            },
+        )))
+    }
+
+    /// Remove the last user message and the replies after it
+    ///
+    /// Returns the removed messages, oldest first. System messages are never
+    /// removed, even ones added after the last user message, so with no user turn
+    /// in the history nothing is.
+    pub fn undo_last_turn(&mut self) -> Vec<Message> {
+        let messages = &mut self.conversation.messages;
+        let Some(start) = messages.iter().rposition(|m| m.role == "user") else {
+            return Vec::new();
+        };
+        let (system, removed): (Vec<_>, Vec<_>) =
+            messages.drain(start..).partition(|m| m.role == "system");
+        messages.extend(system);
+        removed
+    }
+
+    /// Replace the last assistant reply with a newly generated one
+    ///
+    /// Errors without sending anything if the history does not end with an
+    /// assistant reply. If the request fails, the previous reply is kept.
+    pub async fn regenerate(&mut self) -> Result<String, ClientError> {
+        if self.conversation.last_message().map(|m| m.role.as_str()) != Some("assistant") {
+            return Err(ClientError::config(
+                "Cannot regenerate: the conversation does not end with an assistant reply",
+                None,
+            ));
+        }
+        let previous = self.conversation.messages.pop();
         
-        self.client.stream_conversation(&self.conversation).await
+        match self.client.send_conversation_with_metadata(&self.conversation).await {
+            Ok(response) => {
+                self.usage.record(&response.metadata);
+                self.conversation.add_assistant(&response.content);
+                Ok(response.content)
+            }
+            Err(e) => {
+                self.conversation.messages.extend(previous);
+                Err(e)
+            }
+        }
     }
 
     /// Add a message to the conversation without sending
@@ -444,14 +1715,21 @@ impl ChatSession {
         &mut self.conversation
     }
 
-    /// Clear the conversation history
+    /// Tokens used by every request in this session since it started or was last reset
+    pub fn token_usage(&self) -> TokenUsage {
+        self.usage
+    }
+
+    /// Clear the conversation history and token usage
     pub fn clear(&mut self) {
         self.conversation.clear();
+        self.usage = TokenUsage::default();
     }
 
-    /// Reset the session with a new system message
+    /// Reset the session with a new system message, clearing token usage
     pub fn reset_with_system<S: Into<String>>(&mut self, message: S) {
         self.conversation = Conversation::with_system(message);
+        self.usage = TokenUsage::default();
     }
 }
 
@@ -468,6 +1746,14 @@ pub trait AiClient: Send + Sync {
         Ok(AiResponse::new(content))
     }
 
+    /// Sends a prompt and returns every candidate response, in the provider's order
+    ///
+    /// Only Gemini generates several candidates, as set by `candidate_count` in
+    /// [`ClientConfig`]; the default implementation returns the single response.
+    async fn send_prompt_multi(&self, prompt: &str) -> Result<Vec<String>, ClientError> {
+        Ok(vec![self.send_prompt(prompt).await?])
+    }
+
     /// Sends a conversation and returns the textual response
     async fn send_conversation(&self, conversation: &Conversation) -> Result<String, ClientError> {
         // Default implementation converts conversation to a single prompt
@@ -547,6 +1833,149 @@ pub trait AiClient: Send + Sync {
         Ok(Box::pin(futures::stream::once(async { Ok(chunk) })))
     }
 
+    /// Sends a conversation and returns a stream of response chunks, applying `request`
+    ///
+    /// The default implementation applies the idle timeout to
+    /// [`stream_conversation`](Self::stream_conversation), without counting it in any
+    /// metrics; providers override it to record the timeout or to support further
+    /// stream options.
+    async fn stream_conversation_with(
+        &self,
+        conversation: &Conversation,
+        request: &StreamRequest,
+    ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
+        let stream = self.stream_conversation(conversation).await?;
+        Ok(with_idle_timeout(stream, request.idle_timeout, None))
+    }
+
+    /// Streams the raw server-sent events for a prompt, without parsing them
+    ///
+    /// Meant for debugging stream parsing: every event the provider sends, including
+    /// pings and the end-of-stream marker, is yielded as received. Clients that do
+    /// not stream over SSE return a configuration error.
+    async fn debug_stream(
+        &self,
+        _prompt: &str,
+    ) -> Result<BoxStream<'_, Result<SseEvent, ClientError>>, ClientError> {
+        Err(ClientError::config(
+            format!("{} does not stream server-sent events", self.name()),
+            None,
+        ))
+    }
+
+    /// Builds the request `send_conversation` would make, without sending it
+    ///
+    /// The preview holds the URL, method, headers, and JSON body. The API key is
+    /// always masked, so previews are safe to log. Clients that cannot describe their
+    /// requests return a configuration error.
+    fn build_request_preview(
+        &self,
+        _conversation: &Conversation,
+    ) -> Result<RequestPreview, ClientError> {
+        Err(ClientError::config(
+            format!("{} does not support request previews", self.name()),
+            None,
+        ))
+    }
+
+    /// Checks the provider's own constraints on a conversation's messages
+    ///
+    /// [`Conversation::validate_for`] calls this after the checks every client needs.
+    /// The default accepts any conversation; wrappers forward to the client they wrap.
+    fn validate_conversation(&self, _conversation: &Conversation) -> Result<(), ClientError> {
+        Ok(())
+    }
+
+    /// Sends a prompt, returning a cancellation error as soon as `token` is cancelled
+    ///
+    /// Cancelling drops the in-flight request, closing its connection. The provider
+    /// may still finish generating (and bill for) a response that is never read.
+    async fn send_prompt_cancellable(
+        &self,
+        prompt: &str,
+        token: CancellationToken,
+    ) -> Result<String, ClientError> {
+        cancellable(&token, self.send_prompt(prompt)).await
+    }
+
+    /// Sends a conversation, returning a cancellation error as soon as `token` is cancelled
+    ///
+    /// See [`send_prompt_cancellable`](Self::send_prompt_cancellable) for what
+    /// cancellation does and does not stop.
+    async fn send_conversation_cancellable(
+        &self,
+        conversation: &Conversation,
+        token: CancellationToken,
+    ) -> Result<String, ClientError> {
+        cancellable(&token, self.send_conversation(conversation)).await
+    }
+
+    /// Streams a prompt's response until `token` is cancelled
+    ///
+    /// On cancellation the stream yields a cancellation error and ends, dropping
+    /// the underlying response body so no more of it is read. The provider may
+    /// still finish generating server side.
+    async fn stream_prompt_cancellable(
+        &self,
+        prompt: &str,
+        token: CancellationToken,
+    ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
+        let stream = cancellable(&token, self.stream_prompt(prompt)).await?;
+        Ok(cancellable_stream(stream, token))
+    }
+
+    /// Streams a conversation's response until `token` is cancelled
+    ///
+    /// See [`stream_prompt_cancellable`](Self::stream_prompt_cancellable) for what
+    /// cancellation does and does not stop.
+    async fn stream_conversation_cancellable(
+        &self,
+        conversation: &Conversation,
+        token: CancellationToken,
+    ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
+        let stream = cancellable(&token, self.stream_conversation(conversation)).await?;
+        Ok(cancellable_stream(stream, token))
+    }
+
+    /// Streams a prompt's response along with a handle that aborts the stream
+    ///
+    /// After [`AbortHandle::abort`] the stream ends without yielding further
+    /// chunks, and the underlying response body is no longer read. Unlike
+    /// [`stream_prompt_cancellable`](Self::stream_prompt_cancellable), no error is yielded.
+    async fn stream_prompt_abortable(
+        &self,
+        prompt: &str,
+    ) -> Result<(BoxStream<'_, Result<StreamChunk, ClientError>>, AbortHandle), ClientError> {
+        let (stream, handle) = futures::stream::abortable(self.stream_prompt(prompt).await?);
+        Ok((Box::pin(stream), handle))
+    }
+
+    /// Streams a conversation's response along with a handle that aborts the stream
+    ///
+    /// See [`stream_prompt_abortable`](Self::stream_prompt_abortable).
+    async fn stream_conversation_abortable(
+        &self,
+        conversation: &Conversation,
+    ) -> Result<(BoxStream<'_, Result<StreamChunk, ClientError>>, AbortHandle), ClientError> {
+        let (stream, handle) = futures::stream::abortable(self.stream_conversation(conversation).await?);
+        Ok((Box::pin(stream), handle))
+    }
+
+    /// Checks that the provider is reachable and accepts this client's credentials
+    ///
+    /// The default implementation sends a minimal prompt, so it counts against usage.
+    async fn ping(&self) -> Result<(), ClientError> {
+        self.send_prompt("ping").await.map(|_| ())
+    }
+
+    /// Rate limit budget reported by the provider's most recent response
+    ///
+    /// Returns `None` before the first response and for providers that do not
+    /// report rate limits in response headers.
+    fn last_rate_limit(&self) -> Option<RateLimitStatus> {
+        None
+    }
+
     /// Returns whether this client supports streaming
     fn supports_streaming(&self) -> bool {
         false
@@ -573,6 +2002,15 @@ pub trait AiClient: Send + Sync {
 /// * `model` - The model name (e.g., "gpt-4", "claude-3-sonnet-20240229", "gemini-1.5-pro")
 /// * `config` - Configuration for timeouts, retries, and generation parameters
 ///
+/// # Connection reuse
+///
+/// Clients created with no base URL or proxy share the provider's pooled HTTP
+/// client from [`get_provider_client`], so creating a client per request keeps
+/// reusing warm connections. The configured timeout is applied to each request,
+/// so it holds whichever HTTP client is used. A base URL or proxy gets a
+/// dedicated client built from the provider's [`HttpConfig`] with those
+/// settings applied.
+///
 /// # Example
 ///
 /// ```rust,no_run
@@ -589,28 +2027,31 @@ pub fn create_client(
     model: &str,
     config: ClientConfig,
 ) -> Result<Box<dyn AiClient>, ClientError> {
-    let http_client = Client::builder()
-        .timeout(config.timeout)
-        .build()
-        .map_err(|e| ClientError::config(format!("Failed to create HTTP client: {e}"), None))?;
+    config.validate()?;
+    let http_client = http_client_for(provider, &config)?;
+    let model = if config.canonicalize_models {
+        canonicalize_model(model)
+    } else {
+        model.to_string()
+    };
 
     match provider.to_lowercase().as_str() {
         "openai" | "gpt" | "chatgpt" => Ok(Box::new(ChatGpt::new(
             http_client,
             api_key.to_string(),
-            model.to_string(),
+            model,
             config,
         ))),
         "google" | "gemini" => Ok(Box::new(Gemini::new(
             http_client,
             api_key.to_string(),
-            model.to_string(),
+            model,
             config,
         ))),
         "anthropic" | "claude" => Ok(Box::new(Claude::new(
             http_client,
             api_key.to_string(),
-            model.to_string(),
+            model,
             config,
         ))),
         _ => Err(ClientError::config(
@@ -620,6 +2061,30 @@ pub fn create_client(
     }
 }
 
+/// Pick the HTTP client for `provider`, sharing its pool unless `config` needs custom transport
+fn http_client_for(provider: &str, config: &ClientConfig) -> Result<Client, ClientError> {
+    let defaults = ClientConfig::default();
+    if config.base_url.is_none()
+        && config.proxy.is_none()
+        && config.redirect_policy == defaults.redirect_policy
+    {
+        // Cloning a reqwest client shares its connection pool
+        return try_provider_client(provider)
+            .map(|client| client.as_ref().clone())
+            .map_err(|e| ClientError::config(format!("Failed to create HTTP client: {e}"), None));
+    }
+
+    HttpConfig {
+        request_timeout: config.timeout,
+        proxy: config.proxy.clone(),
+        proxy_auth: config.proxy_auth.clone(),
+        redirect_policy: config.redirect_policy,
+        ..HttpConfig::for_provider(provider)
+    }
+    .build_client()
+    .map_err(|e| ClientError::config(format!("Failed to create HTTP client: {e}"), None))
+}
+
 /// Execute multiple AI clients in parallel and return all results
 ///
 /// This function runs all provided clients concurrently and returns the results
@@ -712,74 +2177,637 @@ pub async fn execute_parallel_conversation(
     future::join_all(futures).await
 }
 
-/// Generate a summary using one of the provided clients
+/// Run a conversation on every client twice, showing each its peers' answers in between
 ///
-/// Takes the responses from multiple AI models and uses another AI client
-/// to generate a summary highlighting key differences and commonalities.
+/// The first round is [`execute_parallel_conversation`]. In the second, each client
+/// gets its own first answer followed by a user message quoting every other client's
+/// successful first answer, and is asked for a final answer in light of them. Clients
+/// whose first round failed skip the second and report that error.
 ///
-/// # Arguments
+/// # Returns
 ///
-/// * `client` - The AI client to use for generating the summary
-/// * `responses` - Vector of tuples containing (model_name, response) pairs
+/// The second-round `(client_name, result)` pairs, in the same order as `clients`
+pub async fn execute_parallel_conversation_with_peers(
+    clients: Vec<Box<dyn AiClient>>,
+    conversation: &Conversation,
+) -> Vec<(String, Result<String, ClientError>)> {
+    use futures::future;
+
+    let first_round = future::join_all(clients.iter().map(|client| async move {
+        (client.name().to_string(), client.send_conversation(conversation).await)
+    }))
+    .await;
+
+    let futures = clients.iter().zip(&first_round).enumerate().map(|(index, (client, (name, first)))| {
+        let peers: Vec<(&str, &str)> = first_round
+            .iter()
+            .enumerate()
+            .filter(|&(other, _)| other != index)
+            .filter_map(|(_, (peer, result))| Some((peer.as_str(), result.as_deref().ok()?)))
+            .collect();
+        async move {
+            let own = match first {
+                Ok(own) => own,
+                Err(e) => return (name.clone(), Err(e.clone())),
+            };
+            let mut second = conversation.clone();
+            second.add_assistant(own.as_str());
+            second.add_user(peer_review_prompt(&peers));
+            (name.clone(), client.send_conversation(&second).await)
+        }
+    });
+
+    future::join_all(futures).await
+}
+
+/// Build the second-round message quoting each peer's first answer
+fn peer_review_prompt(peers: &[(&str, &str)]) -> String {
+    let mut prompt = String::from("Other AI models answered the same question:\n\n");
+    for (name, answer) in peers {
+        prompt.push_str(&format!("{name}:\n{answer}\n\n"));
+    }
+    prompt.push_str(
+        "Considering their answers, correct any mistakes in yours and give your final answer.",
+    );
+    prompt
+}
+
+/// Send many prompts through one client with at most `concurrency` requests in flight
+///
+/// Results line up with `prompts` by index, regardless of the order in which
+/// requests complete. A `concurrency` of 0 is treated as 1.
 ///
 /// # Example
 ///
 /// ```rust,no_run
-/// use chatdelta::{create_client, generate_summary, ClientConfig};
+/// use chatdelta::{create_client, execute_batch, ClientConfig};
 ///
 /// # #[tokio::main]
 /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
-/// let config = ClientConfig::default();
-/// let summarizer = create_client("openai", "your-key", "gpt-4", config)?;
+/// let client = create_client("openai", "key", "gpt-4o-mini", ClientConfig::default())?;
+/// let prompts: Vec<String> = (1..=100).map(|n| format!("Summarize chapter {n}")).collect();
 ///
-/// let responses = vec![
-///     ("GPT-4".to_string(), "Response from GPT-4...".to_string()),
-///     ("Claude".to_string(), "Response from Claude...".to_string()),
-/// ];
-///
-/// let summary = generate_summary(&*summarizer, &responses).await?;
-/// println!("Summary: {}", summary);
+/// let results = execute_batch(client.as_ref(), &prompts, 8).await;
+/// for (prompt, result) in prompts.iter().zip(results) {
+///     println!("{prompt}: {:?}", result);
+/// }
 /// # Ok(())
 /// # }
 /// ```
-pub async fn generate_summary(
+pub async fn execute_batch(
     client: &dyn AiClient,
-    responses: &[(String, String)],
-) -> Result<String, ClientError> {
-    let mut summary_prompt = "Given these AI model responses:\n".to_string();
-    for (name, response) in responses {
-        summary_prompt.push_str(&format!("{name}:\n{response}\n---\n"));
-    }
-    summary_prompt.push_str("Summarize the key differences and commonalities.");
-
-    client.send_prompt(&summary_prompt).await
+    prompts: &[String],
+    concurrency: usize,
+) -> Vec<Result<String, ClientError>> {
+    let requests = prompts.iter().map(|prompt| client.send_prompt(prompt));
+    buffered_in_order(requests, concurrency).await
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::collections::VecDeque;
-    use std::sync::{Arc, Mutex};
+/// Like [`execute_batch`], returning each response with its metadata
+pub async fn execute_batch_with_metadata(
+    client: &dyn AiClient,
+    prompts: &[String],
+    concurrency: usize,
+) -> Vec<Result<AiResponse, ClientError>> {
+    let requests = prompts.iter().map(|prompt| client.send_prompt_with_metadata(prompt));
+    buffered_in_order(requests, concurrency).await
+}
 
-    // Mock client for testing
-    pub struct MockClient {
-        pub name: String,
-        pub model: String,
-        pub responses: Arc<Mutex<VecDeque<Result<String, ClientError>>>>,
-    }
+/// Execute multiple AI clients in parallel, giving up on any still pending after `deadline`
+///
+/// Clients that miss the deadline are reported as timeout errors alongside the
+/// completed results, in client order. Their requests are dropped, cancelling them.
+pub async fn execute_parallel_with_deadline(
+    clients: Vec<Box<dyn AiClient>>,
+    prompt: &str,
+    deadline: Duration,
+) -> Vec<(String, Result<String, ClientError>)> {
+    let max_concurrent = clients.len();
+    execute_parallel_limited(clients, prompt, max_concurrent, Some(deadline)).await
+}
 
-    impl MockClient {
-        pub fn new(name: &str, responses: Vec<Result<String, ClientError>>) -> Self {
-            Self {
-                name: name.to_string(),
-                model: "mock-model".to_string(),
-                responses: Arc::new(Mutex::new(VecDeque::from(responses))),
-            }
-        }
-    }
+/// Execute multiple AI clients with at most `max_concurrent` requests in flight
+///
+/// Like [`execute_parallel`], results are returned in client order. With a
+/// `timeout`, every client still pending once it has elapsed, counted from the
+/// start of the whole batch, is reported as a timeout error instead of holding
+/// up the others. A `max_concurrent` of 0 is treated as 1.
+pub async fn execute_parallel_limited(
+    clients: Vec<Box<dyn AiClient>>,
+    prompt: &str,
+    max_concurrent: usize,
+    timeout: Option<Duration>,
+) -> Vec<(String, Result<String, ClientError>)> {
+    let deadline = timeout.map(|timeout| (tokio::time::Instant::now() + timeout, timeout));
+    let requests = clients.iter().map(|client| {
+        with_deadline(client.name(), client.send_prompt(prompt), deadline)
+    });
+    buffered_in_order(requests, max_concurrent).await
+}
 
-    #[async_trait]
-    impl AiClient for MockClient {
+/// Execute multiple AI clients on a conversation with at most `max_concurrent` requests in flight
+///
+/// See [`execute_parallel_limited`] for ordering and timeout behavior.
+pub async fn execute_parallel_conversation_limited(
+    clients: Vec<Box<dyn AiClient>>,
+    conversation: &Conversation,
+    max_concurrent: usize,
+    timeout: Option<Duration>,
+) -> Vec<(String, Result<String, ClientError>)> {
+    let deadline = timeout.map(|timeout| (tokio::time::Instant::now() + timeout, timeout));
+    let requests = clients.iter().map(|client| {
+        with_deadline(client.name(), client.send_conversation(conversation), deadline)
+    });
+    buffered_in_order(requests, max_concurrent).await
+}
+
+/// Await `request`, giving up with a timeout error at `deadline` if one is set
+async fn with_deadline<T>(
+    name: &str,
+    request: impl Future<Output = Result<T, ClientError>>,
+    deadline: Option<(tokio::time::Instant, Duration)>,
+) -> (String, Result<T, ClientError>) {
+    let result = match deadline {
+        Some((deadline, timeout)) => tokio::time::timeout_at(deadline, request)
+            .await
+            .unwrap_or_else(|_| {
+                Err(ClientError::timeout(format!("{name} did not respond within {timeout:?}")))
+            }),
+        None => request.await,
+    };
+    (name.to_string(), result)
+}
+
+/// Await `requests` with at most `concurrency` in flight, returning results in input order
+async fn buffered_in_order<T>(
+    requests: impl Iterator<Item = impl Future<Output = T>>,
+    concurrency: usize,
+) -> Vec<T> {
+    use futures::stream::{self, StreamExt};
+
+    let mut results: Vec<(usize, T)> = stream::iter(requests.enumerate())
+        .map(|(index, request)| async move { (index, request.await) })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+    results.sort_unstable_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Try clients in order until one succeeds, optionally falling back to a default
+///
+/// Each client is asked in turn and the first successful response is returned. If
+/// every client fails, `fallback_response` is returned when set; otherwise the last
+/// client's error is returned.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use chatdelta::{create_client, execute_fallback, ClientConfig};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let config = ClientConfig::default();
+/// let clients = vec![
+///     create_client("openai", "key1", "gpt-4", config.clone())?,
+///     create_client("anthropic", "key2", "claude-3-sonnet-20240229", config)?,
+/// ];
+///
+/// let fallback = Some("Sorry, I can't answer right now.".to_string());
+/// let response = execute_fallback(&clients, "Hello, world!", fallback).await?;
+/// println!("{}", response);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn execute_fallback(
+    clients: &[Box<dyn AiClient>],
+    prompt: &str,
+    fallback_response: Option<String>,
+) -> Result<String, ClientError> {
+    let mut last_error = None;
+    for client in clients {
+        match client.send_prompt(prompt).await {
+            Ok(response) => return Ok(response),
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    match (fallback_response, last_error) {
+        (Some(fallback), _) => Ok(fallback),
+        (None, Some(e)) => Err(e),
+        (None, None) => Err(ClientError::config("No clients provided", None)),
+    }
+}
+
+/// Ping every client concurrently and report each one's health
+///
+/// Returns `(client_name, result)` pairs in the same order as `clients`, where
+/// `Ok(())` means the provider answered.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use chatdelta::{create_client, health_check_all, ClientConfig};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let config = ClientConfig::default();
+/// let clients = vec![
+///     create_client("openai", "key1", "gpt-4", config.clone())?,
+///     create_client("anthropic", "key2", "claude-3-sonnet-20240229", config)?,
+/// ];
+///
+/// for (name, health) in health_check_all(&clients).await {
+///     match health {
+///         Ok(()) => println!("{}: healthy", name),
+///         Err(e) => println!("{}: unhealthy ({})", name, e),
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn health_check_all(clients: &[Box<dyn AiClient>]) -> Vec<(String, Result<(), ClientError>)> {
+    let futures = clients
+        .iter()
+        .map(|client| async move { (client.name().to_string(), client.ping().await) });
+
+    futures::future::join_all(futures).await
+}
+
+/// Check whether every client in a parallel execution failed
+///
+/// Returns `true` for an empty result set, since no client produced a response.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use chatdelta::{all_failed, create_client, execute_parallel, ClientConfig};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let clients = vec![create_client("openai", "key", "gpt-4", ClientConfig::default())?];
+/// let results = execute_parallel(clients, "Hello, world!").await;
+/// if all_failed(&results) {
+///     eprintln!("No provider returned a response");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn all_failed(results: &[(String, Result<String, ClientError>)]) -> bool {
+    results.iter().all(|(_, result)| result.is_err())
+}
+
+/// Collect the successful responses from a parallel execution
+///
+/// Returns `(client_name, response)` pairs in the same order as `results`,
+/// skipping any client that returned an error.
+pub fn successes(results: &[(String, Result<String, ClientError>)]) -> Vec<(String, String)> {
+    results
+        .iter()
+        .filter_map(|(name, result)| {
+            result
+                .as_ref()
+                .ok()
+                .map(|response| (name.clone(), response.clone()))
+        })
+        .collect()
+}
+
+/// Reassemble a streamed response into a complete [`AiResponse`]
+///
+/// Concatenates the content of every chunk and takes the metadata from the final
+/// chunk, or leaves it empty if the provider sent none. The first error in the
+/// stream is returned and the rest of the stream is not read.
+///
+/// # Example
+///
+/// ```no_run
+/// # use chatdelta::{collect_stream, create_client, ClientConfig};
+/// # async fn example() -> Result<(), chatdelta::ClientError> {
+/// let client = create_client("openai", "your-api-key", "gpt-4o", ClientConfig::default())?;
+/// let response = collect_stream(client.stream_prompt("Hello").await?).await?;
+/// println!("{} ({:?} tokens)", response.content, response.metadata.total_tokens);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn collect_stream<S>(stream: S) -> Result<AiResponse, ClientError>
+where
+    S: futures::Stream<Item = Result<StreamChunk, ClientError>>,
+{
+    use futures::stream::StreamExt;
+
+    let mut stream = std::pin::pin!(stream);
+    let mut content = String::new();
+    let mut metadata = None;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        content.push_str(&chunk.content);
+        if chunk.metadata.is_some() {
+            metadata = chunk.metadata;
+        }
+    }
+    Ok(AiResponse::with_metadata(content, metadata.unwrap_or_default()))
+}
+
+/// Stream a prompt from any client, buffering clients that cannot stream
+///
+/// Clients whose [`AiClient::supports_streaming`] is true are streamed as usual.
+/// Others are sent the prompt with [`AiClient::send_prompt_with_metadata`], and
+/// the reply arrives as a single finished chunk carrying the response metadata,
+/// so callers can consume every client the same way.
+///
+/// # Example
+///
+/// ```no_run
+/// # use chatdelta::{buffered_as_stream, create_client, ClientConfig};
+/// # use futures::StreamExt;
+/// # async fn example() -> Result<(), chatdelta::ClientError> {
+/// let client = create_client("gemini", "your-api-key", "gemini-1.5-pro", ClientConfig::default())?;
+/// let mut stream = buffered_as_stream(client.as_ref(), "Hello").await?;
+/// while let Some(chunk) = stream.next().await {
+///     print!("{}", chunk?.content);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn buffered_as_stream<'a>(
+    client: &'a dyn AiClient,
+    prompt: &str,
+) -> Result<BoxStream<'a, Result<StreamChunk, ClientError>>, ClientError> {
+    if client.supports_streaming() {
+        return client.stream_prompt(prompt).await;
+    }
+
+    let response = client.send_prompt_with_metadata(prompt).await?;
+    let chunk = StreamChunk {
+        content: response.content,
+        finished: true,
+        metadata: Some(response.metadata),
+    };
+    Ok(Box::pin(futures::stream::once(async { Ok(chunk) })))
+}
+
+/// Combinators for streams of [`StreamChunk`]s
+pub trait StreamChunkExt: futures::Stream<Item = Result<StreamChunk, ClientError>> + Send + Sized {
+    /// Re-chunk the stream so that each chunk is one complete sentence
+    ///
+    /// Useful for text-to-speech pipelines that speak a sentence at a time. A
+    /// sentence ends at `.`, `!`, or `?` (with any closing quotes or brackets)
+    /// followed by whitespace, which stays with the sentence, so the chunks still
+    /// concatenate to the original text. Abbreviations such as "Dr." are not
+    /// recognised. Whatever follows the last boundary arrives in the final chunk,
+    /// along with the metadata of the underlying stream. Errors pass through as-is.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use chatdelta::{create_client, ClientConfig, StreamChunkExt};
+    /// # use futures::StreamExt;
+    /// # async fn example() -> Result<(), chatdelta::ClientError> {
+    /// let client = create_client("openai", "your-api-key", "gpt-4o", ClientConfig::default())?;
+    /// let mut sentences = client.stream_prompt("Tell me a story").await?.by_sentence();
+    /// while let Some(sentence) = sentences.next().await {
+    ///     println!("speak: {}", sentence?.content.trim());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn by_sentence<'a>(self) -> BoxStream<'a, Result<StreamChunk, ClientError>>
+    where
+        Self: 'a,
+    {
+        by_sentence(Box::pin(self))
+    }
+}
+
+impl<S> StreamChunkExt for S where S: futures::Stream<Item = Result<StreamChunk, ClientError>> + Send {}
+
+/// Byte offset just past the first complete sentence in `text`, if any
+///
+/// Punctuation at the very end of `text` is not yet a boundary, since the next
+/// chunk may continue it (as in "3" "." "14").
+fn sentence_end(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        if !matches!(c, '.' | '!' | '?') {
+            continue;
+        }
+        while chars
+            .next_if(|&(_, c)| matches!(c, '.' | '!' | '?' | '"' | '\'' | ')' | ']' | '\u{201D}' | '\u{2019}'))
+            .is_some()
+        {}
+        let mut end = None;
+        while let Some((i, c)) = chars.next_if(|&(_, c)| c.is_whitespace()) {
+            end = Some(i + c.len_utf8());
+        }
+        if end.is_some() {
+            return end;
+        }
+    }
+    None
+}
+
+fn by_sentence(
+    stream: BoxStream<'_, Result<StreamChunk, ClientError>>,
+) -> BoxStream<'_, Result<StreamChunk, ClientError>> {
+    use futures::stream::{self, StreamExt};
+
+    struct State<'a> {
+        /// `None` once the underlying stream has finished
+        stream: Option<BoxStream<'a, Result<StreamChunk, ClientError>>>,
+        buffer: String,
+        metadata: Option<ResponseMetadata>,
+        finished: bool,
+        done: bool,
+    }
+
+    let state = State {
+        stream: Some(stream),
+        buffer: String::new(),
+        metadata: None,
+        finished: false,
+        done: false,
+    };
+    Box::pin(stream::unfold(state, |mut state| async move {
+        loop {
+            if state.done {
+                return None;
+            }
+            if let Some(end) = sentence_end(&state.buffer) {
+                let chunk = StreamChunk {
+                    content: state.buffer.drain(..end).collect(),
+                    finished: false,
+                    metadata: None,
+                };
+                return Some((Ok(chunk), state));
+            }
+
+            let Some(inner) = state.stream.as_mut() else {
+                state.done = true;
+                if state.buffer.is_empty() && !state.finished {
+                    return None;
+                }
+                let chunk = StreamChunk {
+                    content: std::mem::take(&mut state.buffer),
+                    finished: true,
+                    metadata: state.metadata.take(),
+                };
+                return Some((Ok(chunk), state));
+            };
+            match inner.next().await {
+                Some(Ok(chunk)) => {
+                    state.buffer.push_str(&chunk.content);
+                    if chunk.metadata.is_some() {
+                        state.metadata = chunk.metadata;
+                    }
+                    if chunk.finished {
+                        state.finished = true;
+                        state.stream = None;
+                    }
+                }
+                Some(Err(e)) => return Some((Err(e), state)),
+                None => state.stream = None,
+            }
+        }
+    }))
+}
+
+/// Re-send each user turn of a recorded conversation to `client`, in order
+///
+/// Useful for regression testing a model upgrade against recorded sessions. The
+/// client sees the recorded system and user messages, but its own earlier replies
+/// in place of the recorded assistant messages, just as in a live session. Returns
+/// one reply per user turn, stopping at the first error.
+pub async fn replay(conversation: &Conversation, client: &dyn AiClient) -> Result<Vec<String>, ClientError> {
+    let mut replayed = Conversation::new();
+    let mut replies = Vec::new();
+    for message in &conversation.messages {
+        match message.role.as_str() {
+            "assistant" => continue,
+            "user" => {
+                replayed.add_message(message.clone());
+                let reply = client.send_conversation(&replayed).await?;
+                replayed.add_assistant(reply.clone());
+                replies.push(reply);
+            }
+            _ => replayed.add_message(message.clone()),
+        }
+    }
+    Ok(replies)
+}
+
+/// Generate a summary using one of the provided clients
+///
+/// Takes the responses from multiple AI models and uses another AI client
+/// to generate a summary highlighting key differences and commonalities.
+///
+/// # Arguments
+///
+/// * `client` - The AI client to use for generating the summary
+/// * `responses` - Vector of tuples containing (model_name, response) pairs
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use chatdelta::{create_client, generate_summary, ClientConfig};
+///
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let config = ClientConfig::default();
+/// let summarizer = create_client("openai", "your-key", "gpt-4", config)?;
+///
+/// let responses = vec![
+///     ("GPT-4".to_string(), "Response from GPT-4...".to_string()),
+///     ("Claude".to_string(), "Response from Claude...".to_string()),
+/// ];
+///
+/// let summary = generate_summary(&*summarizer, &responses).await?;
+/// println!("Summary: {}", summary);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn generate_summary(
+    client: &dyn AiClient,
+    responses: &[(String, String)],
+) -> Result<String, ClientError> {
+    generate_summary_with_instruction(client, responses, DEFAULT_SUMMARY_INSTRUCTION)
+        .await
+        .map(|response| response.content)
+}
+
+/// Instruction [`generate_summary`] gives the summarizing model
+pub const DEFAULT_SUMMARY_INSTRUCTION: &str = "Summarize the key differences and commonalities \
+between these responses. Point out where the models agree, where they disagree or contradict \
+each other, and anything only one of them mentions.";
+
+/// Generate a summary of multiple models' responses following a custom instruction
+///
+/// Each response is numbered and wrapped in its own fenced block, so responses
+/// containing separators or code fences cannot blur into one another. Returns
+/// the summarizer's full [`AiResponse`], including the tokens the summary used.
+///
+/// # Arguments
+///
+/// * `client` - The AI client to use for generating the summary
+/// * `responses` - Vector of tuples containing (model_name, response) pairs
+/// * `instruction` - What to do with the responses, placed after them in the prompt
+pub async fn generate_summary_with_instruction(
+    client: &dyn AiClient,
+    responses: &[(String, String)],
+    instruction: &str,
+) -> Result<AiResponse, ClientError> {
+    client
+        .send_prompt_with_metadata(&summary_prompt(responses, instruction))
+        .await
+}
+
+/// Build the summarization prompt, fencing each response with more backticks than it contains
+fn summary_prompt(responses: &[(String, String)], instruction: &str) -> String {
+    let mut prompt = format!(
+        "Below are {} responses from different AI models, each numbered and enclosed in a fenced block.\n\n",
+        responses.len()
+    );
+    for (index, (name, response)) in responses.iter().enumerate() {
+        let longest_run = response
+            .split(|c| c != '`')
+            .map(str::len)
+            .max()
+            .unwrap_or(0);
+        let fence = "`".repeat(longest_run.max(2) + 1);
+        prompt.push_str(&format!(
+            "Response {} ({name}):\n{fence}\n{response}\n{fence}\n\n",
+            index + 1
+        ));
+    }
+    prompt.push_str(instruction);
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::sync::{Arc, Mutex};
+
+    // Mock client for testing
+    pub struct MockClient {
+        pub name: String,
+        pub model: String,
+        pub responses: Arc<Mutex<VecDeque<Result<String, ClientError>>>>,
+    }
+
+    impl MockClient {
+        pub fn new(name: &str, responses: Vec<Result<String, ClientError>>) -> Self {
+            Self {
+                name: name.to_string(),
+                model: "mock-model".to_string(),
+                responses: Arc::new(Mutex::new(VecDeque::from(responses))),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl AiClient for MockClient {
         async fn send_prompt(&self, _prompt: &str) -> Result<String, ClientError> {
             self.responses
                 .lock()
@@ -825,6 +2853,183 @@ mod tests {
         assert_eq!(config.max_tokens, Some(1024));
     }
 
+    #[test]
+    fn test_penalty_ranges_are_validated() {
+        let config = ClientConfig::builder()
+            .frequency_penalty(-2.0)
+            .presence_penalty(2.0)
+            .build();
+        assert!(config.validate().is_ok());
+
+        let config = ClientConfig::builder().presence_penalty(2.5).build();
+        let err = create_client("openai", "key", "gpt-4o", config).err().unwrap();
+        assert!(
+            matches!(&err, ClientError::Configuration(e) if e.parameter.as_deref() == Some("presence_penalty")),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_create_client_canonicalizes_models() {
+        let client = create_client("openai", "key", "GPT4o", ClientConfig::default()).unwrap();
+        assert_eq!(client.model(), "GPT4o");
+
+        let config = ClientConfig::builder().canonicalize_models(true).build();
+        let client = create_client("openai", "key", "GPT4o", config).unwrap();
+        assert_eq!(client.model(), "gpt-4o");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limiter_config() {
+        assert!(ClientConfig::default().rate_limiter.is_none());
+
+        let metrics = ClientMetrics::new();
+        let config = ClientConfig::builder()
+            .requests_per_minute(6000)
+            .tokens_per_minute(1_000_000)
+            .metrics(metrics.clone())
+            .build();
+        assert_eq!(config.requests_per_minute, Some(6000));
+
+        // Clones of the config, e.g. one per client, share a single budget
+        let other = config.clone();
+        assert!(Arc::ptr_eq(
+            config.rate_limiter.as_ref().unwrap(),
+            other.rate_limiter.as_ref().unwrap()
+        ));
+
+        // The second request in the same instant waits for the bucket to refill
+        let conversation = Conversation::with_system("Be brief.");
+        for _ in 0..6001 {
+            config.throttle(&conversation).await;
+        }
+        assert_eq!(metrics.get_stats().rate_limit_wait_ms, 10);
+
+        let shared = Arc::new(RateLimiter::new(Some(10), None));
+        let config = ClientConfig::builder()
+            .requests_per_minute(5)
+            .rate_limiter(shared.clone())
+            .build();
+        assert!(Arc::ptr_eq(config.rate_limiter.as_ref().unwrap(), &shared));
+    }
+
+    #[test]
+    fn test_sanitize_prompts() {
+        let mut conversation = Conversation::with_system("Be\u{7} brief");
+        conversation.add_user("pasted\0 text\u{1b}[0m\nline two\ttabbed\r\n");
+
+        let sanitized = ClientConfig::builder().sanitize_prompts(true).build().sanitize(&conversation);
+        assert_eq!(sanitized.messages[0].content, "Be brief");
+        assert_eq!(sanitized.messages[1].content, "pasted text[0m\nline two\ttabbed\r\n");
+
+        let untouched = ClientConfig::default().sanitize(&conversation);
+        assert!(matches!(untouched, Cow::Borrowed(_)));
+        assert_eq!(untouched.messages[1].content, conversation.messages[1].content);
+    }
+
+    #[test]
+    fn test_extra_headers() {
+        let config = ClientConfig::builder()
+            .header("x-request-id", "req-42")
+            .header("x-ms-region", "westeurope")
+            .header("Authorization", "Bearer stolen")
+            .build();
+
+        let request = Client::new()
+            .post("https://api.openai.com/v1/chat/completions")
+            .bearer_auth("test-key")
+            .json(&serde_json::json!({}));
+        let request = config.apply_extra_headers(request).build().unwrap();
+        let headers = request.headers();
+
+        assert_eq!(headers["x-request-id"], "req-42");
+        assert_eq!(headers["x-ms-region"], "westeurope");
+        // Headers set by the client itself cannot be overridden
+        let auth: Vec<_> = headers.get_all("authorization").iter().collect();
+        assert_eq!(auth, ["Bearer test-key"]);
+    }
+
+    #[test]
+    fn test_build_request_preview() {
+        let key = "sk-secret-123";
+        let config = ClientConfig::builder()
+            .temperature(0.5)
+            .header("x-trace", format!("trace-{key}"))
+            .build();
+        let mut conversation = Conversation::with_system("Be brief");
+        conversation.add_user("Hi");
+
+        let openai = create_client("openai", key, "gpt-4o", config.clone()).unwrap();
+        let preview = openai.build_request_preview(&conversation).unwrap();
+        assert_eq!(preview.method, "POST");
+        assert_eq!(preview.url, "https://api.openai.com/v1/chat/completions");
+        assert_eq!(preview.header("authorization"), Some("[REDACTED]"));
+        assert_eq!(preview.header("x-trace"), Some("trace-[REDACTED]"));
+        assert_eq!(preview.body["model"], "gpt-4o");
+        assert_eq!(preview.body["temperature"], 0.5);
+        assert_eq!(preview.body["messages"][1]["content"], "Hi");
+        assert!(!format!("{preview:?}").contains(key));
+
+        let claude = create_client("claude", key, "claude-3-5-haiku", config.clone()).unwrap();
+        let preview = claude.build_request_preview(&conversation).unwrap();
+        assert_eq!(preview.header("x-api-key"), Some("[REDACTED]"));
+        assert_eq!(preview.header("anthropic-version"), Some("2023-06-01"));
+        assert_eq!(preview.body["system"], "Be brief");
+        assert!(!format!("{preview:?}").contains(key));
+
+        let gemini = create_client("gemini", key, "gemini-1.5-flash", config).unwrap();
+        let preview = gemini.build_request_preview(&conversation).unwrap();
+        assert_eq!(preview.header("x-goog-api-key"), Some("[REDACTED]"));
+        assert!(preview.url.ends_with("/models/gemini-1.5-flash:generateContent"));
+        assert!(!format!("{preview:?}").contains(key));
+    }
+
+    #[test]
+    fn test_debug_output_masks_api_key() {
+        let key = "sk-live-abc123";
+        let api_key = ApiKey::new(key);
+        assert_eq!(api_key.expose(), key);
+        assert_eq!(format!("{api_key:?}"), "***redacted***");
+        assert_eq!(api_key.to_string(), "***redacted***");
+
+        let http = Client::new();
+        let config = ClientConfig::builder()
+            .proxy_auth("proxy-user", "proxy-pass")
+            .header("Authorization", "Bearer gateway-token")
+            .build();
+        let clients: Vec<Box<dyn std::fmt::Debug>> = vec![
+            Box::new(ChatGpt::new(http.clone(), key.into(), "gpt-4o".into(), config.clone())),
+            Box::new(Claude::new(http.clone(), key.into(), "claude-3-5-haiku".into(), config.clone())),
+            Box::new(Gemini::new(http, key.into(), "gemini-1.5-flash".into(), config)),
+        ];
+        for client in clients {
+            let debug = format!("{client:?}");
+            assert!(debug.contains("***redacted***"), "{debug}");
+            for secret in [key, "proxy-pass", "gateway-token"] {
+                assert!(!debug.contains(secret), "{debug}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_config_debug_masks_proxy_password_and_extra_headers() {
+        let config = ClientConfig::builder()
+            .proxy_auth("proxy-user", "proxy-pass")
+            .header("Authorization", "Bearer gateway-token")
+            .build();
+        let debug = format!("{config:?}");
+        assert!(debug.contains("proxy-user"), "{debug}");
+        assert!(debug.contains("Authorization"), "{debug}");
+        assert!(!debug.contains("proxy-pass"), "{debug}");
+        assert!(!debug.contains("gateway-token"), "{debug}");
+
+        let builder = ClientConfig::builder()
+            .proxy_auth("proxy-user", "proxy-pass")
+            .header("Authorization", "Bearer gateway-token");
+        let debug = format!("{builder:?}");
+        assert!(!debug.contains("proxy-pass") && !debug.contains("gateway-token"), "{debug}");
+    }
+
     #[tokio::test]
     async fn test_execute_parallel() {
         let clients: Vec<Box<dyn AiClient>> = vec![
@@ -859,6 +3064,174 @@ mod tests {
         assert_eq!(summary.unwrap(), "summary response");
     }
 
+    #[test]
+    fn test_summary_prompt_fences_responses() {
+        let responses = vec![
+            ("AI1".to_string(), "first\n---\nsecond".to_string()),
+            ("AI2".to_string(), "```rust\nfn main() {}\n```".to_string()),
+        ];
+        let prompt = summary_prompt(&responses, "Compare them.");
+
+        assert!(prompt.contains("Response 1 (AI1):\n```\nfirst\n---\nsecond\n```\n"));
+        assert!(prompt.contains("Response 2 (AI2):\n````\n```rust\nfn main() {}\n```\n````\n"));
+        assert!(prompt.ends_with("\n\nCompare them."));
+    }
+
+    #[tokio::test]
+    async fn test_generate_summary_with_instruction() {
+        let client = MockClient::new("summarizer", vec![Ok("summary response".to_string())]);
+        let responses = vec![("AI1".to_string(), "response1".to_string())];
+
+        let summary = generate_summary_with_instruction(&client, &responses, "List the facts.")
+            .await
+            .unwrap();
+        assert_eq!(summary.content, "summary response");
+    }
+
+    #[tokio::test]
+    async fn test_buffered_as_stream() {
+        use futures::StreamExt;
+
+        let client = MockClient::new("buffered", vec![Ok("whole reply".to_string())]);
+        let chunks: Vec<_> = buffered_as_stream(&client, "Hi").await.unwrap().collect().await;
+        assert_eq!(chunks.len(), 1);
+        let chunk = chunks.into_iter().next().unwrap().unwrap();
+        assert_eq!(chunk.content, "whole reply");
+        assert!(chunk.finished);
+        assert!(chunk.metadata.is_some());
+
+        let client = MockClient::new("failing", vec![Err(ClientError::timeout("timed out"))]);
+        assert!(buffered_as_stream(&client, "Hi").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_by_sentence_aligns_chunks_to_sentences() {
+        use futures::StreamExt;
+
+        let tokens = ["Hel", "lo world", ". How", " are you", "? Pi is 3", ".", "14", ". I'm", " \"fine", ".\"", ""];
+        let chunks = tokens.iter().enumerate().map(|(i, token)| {
+            Ok(StreamChunk {
+                content: token.to_string(),
+                finished: i == tokens.len() - 1,
+                metadata: (i == tokens.len() - 1).then(|| ResponseMetadata {
+                    total_tokens: Some(21),
+                    ..Default::default()
+                }),
+            })
+        });
+
+        let sentences: Vec<StreamChunk> = futures::stream::iter(chunks)
+            .by_sentence()
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let contents: Vec<&str> = sentences.iter().map(|chunk| chunk.content.as_str()).collect();
+        assert_eq!(contents, ["Hello world. ", "How are you? ", "Pi is 3.14. ", "I'm \"fine.\""]);
+        assert!(sentences[..3].iter().all(|chunk| !chunk.finished && chunk.metadata.is_none()));
+        let last = sentences.last().unwrap();
+        assert!(last.finished);
+        assert_eq!(last.metadata.as_ref().unwrap().total_tokens, Some(21));
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        /// Replies with the number of messages it was sent
+        struct CountingClient;
+
+        #[async_trait]
+        impl AiClient for CountingClient {
+            async fn send_prompt(&self, _prompt: &str) -> Result<String, ClientError> {
+                unreachable!("replay sends conversations")
+            }
+
+            async fn send_conversation(&self, conversation: &Conversation) -> Result<String, ClientError> {
+                let last = conversation.messages.last().unwrap();
+                Ok(format!("{} messages, last: {}", conversation.messages.len(), last.content))
+            }
+
+            fn name(&self) -> &str {
+                "Counting"
+            }
+
+            fn model(&self) -> &str {
+                "count-1"
+            }
+        }
+
+        let mut conversation = Conversation::with_system("Be brief.");
+        conversation.add_user("Hello");
+        conversation.add_assistant("Recorded reply");
+        conversation.add_user("How are you?");
+        conversation.add_assistant("Another recorded reply");
+
+        // One reply per user turn, each built on the client's own earlier replies
+        let replies = replay(&conversation, &CountingClient).await.unwrap();
+        assert_eq!(replies, vec!["2 messages, last: Hello", "4 messages, last: How are you?"]);
+    }
+
+    #[tokio::test]
+    async fn test_execute_parallel_conversation_with_peers() {
+        /// Answers with a fixed reply and records the last message of each request
+        struct PeerClient {
+            name: &'static str,
+            answer: Result<&'static str, &'static str>,
+            seen: Arc<Mutex<Vec<String>>>,
+        }
+
+        #[async_trait]
+        impl AiClient for PeerClient {
+            async fn send_prompt(&self, _prompt: &str) -> Result<String, ClientError> {
+                unreachable!("only conversations are sent")
+            }
+
+            async fn send_conversation(&self, conversation: &Conversation) -> Result<String, ClientError> {
+                let last = conversation.last_message().unwrap().content.clone();
+                self.seen.lock().unwrap().push(last);
+                self.answer
+                    .map(|answer| format!("{answer} ({} messages)", conversation.len()))
+                    .map_err(|e| ClientError::config(e, None))
+            }
+
+            fn name(&self) -> &str {
+                self.name
+            }
+
+            fn model(&self) -> &str {
+                "peer-1"
+            }
+        }
+
+        let seen: Vec<_> = (0..3).map(|_| Arc::new(Mutex::new(Vec::new()))).collect();
+        let answers = [Ok("Paris"), Ok("Lyon"), Err("unavailable")];
+        let clients: Vec<Box<dyn AiClient>> = ["A", "B", "C"]
+            .into_iter()
+            .zip(answers)
+            .zip(&seen)
+            .map(|((name, answer), seen)| {
+                Box::new(PeerClient { name, answer, seen: seen.clone() }) as Box<dyn AiClient>
+            })
+            .collect();
+
+        let mut conversation = Conversation::new();
+        conversation.add_user("What is the capital of France?");
+        let results = execute_parallel_conversation_with_peers(clients, &conversation).await;
+
+        assert_eq!(results[0].1.as_deref().unwrap(), "Paris (3 messages)");
+        assert_eq!(results[1].1.as_deref().unwrap(), "Lyon (3 messages)");
+        assert!(results[2].1.is_err());
+
+        // Each second-round prompt quotes the peers' answers but not the client's own
+        let second_a = &seen[0].lock().unwrap()[1];
+        assert!(second_a.contains("B:\nLyon (1 messages)"));
+        assert!(!second_a.contains("Paris"));
+        assert!(!second_a.contains("C:"));
+        let second_b = &seen[1].lock().unwrap()[1];
+        assert!(second_b.contains("A:\nParis (1 messages)"));
+        assert_eq!(seen[2].lock().unwrap().len(), 1);
+    }
+
     #[tokio::test]
     async fn test_execute_parallel_conversation() {
         let clients: Vec<Box<dyn AiClient>> = vec![
@@ -885,6 +3258,535 @@ mod tests {
         assert!(results[1].1.is_ok());
     }
 
+    #[test]
+    fn test_conversation_content_hash() {
+        let mut first = Conversation::with_system("Be brief");
+        first.add_user("Hello");
+        first.add_assistant("Hi!");
+        let second = first.clone();
+        assert_eq!(first.content_hash(), second.content_hash());
+
+        let mut reordered = Conversation::with_system("Be brief");
+        reordered.add_assistant("Hi!");
+        reordered.add_user("Hello");
+        assert_ne!(first.content_hash(), reordered.content_hash());
+
+        // Role and content boundaries are part of the hash
+        let mut shifted = Conversation::new();
+        shifted.add_message(Message { role: "user".to_string(), content: "ab".to_string() });
+        let mut unshifted = Conversation::new();
+        unshifted.add_message(Message { role: "usera".to_string(), content: "b".to_string() });
+        assert_ne!(shifted.content_hash(), unshifted.content_hash());
+    }
+
+    #[test]
+    fn test_conversation_builder_valid() {
+        let conversation = Conversation::builder()
+            .system("Be brief")
+            .user("Hello")
+            .assistant("Hi!")
+            .user("Bye")
+            .build()
+            .unwrap();
+        let roles: Vec<&str> = conversation.messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, vec!["system", "user", "assistant", "user"]);
+    }
+
+    #[test]
+    fn test_split_system() {
+        let mut conversation = Conversation::with_system("Be brief.");
+        conversation.add_user("Hello");
+        conversation.add_message(Message::system("Answer in French."));
+        conversation.add_assistant("Bonjour !");
+        conversation.add_message(Message::system("Stay polite."));
+        conversation.add_user("Merci");
+
+        let (system, rest) = conversation.split_system();
+        let system: Vec<&str> = system.iter().map(|m| m.content.as_str()).collect();
+        let rest: Vec<&str> = rest.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(system, vec!["Be brief.", "Answer in French.", "Stay polite."]);
+        assert_eq!(rest, vec!["Hello", "Bonjour !", "Merci"]);
+
+        let (system, rest) = Conversation::new().split_system();
+        assert!(system.is_empty() && rest.is_empty());
+    }
+
+    #[test]
+    fn test_to_openai_messages() {
+        let mut conversation = Conversation::with_system("You are a helpful assistant.");
+        conversation.add_user("What is the capital of France?");
+        conversation.add_assistant("Paris.");
+        conversation.add_user("And of Italy?");
+
+        let expected: serde_json::Value = serde_json::from_str(
+            r#"[
+                {"role": "system", "content": "You are a helpful assistant."},
+                {"role": "user", "content": "What is the capital of France?"},
+                {"role": "assistant", "content": "Paris."},
+                {"role": "user", "content": "And of Italy?"}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(serde_json::Value::Array(conversation.to_openai_messages()), expected);
+        assert!(Conversation::new().to_openai_messages().is_empty());
+    }
+
+    #[test]
+    fn test_conversation_builder_rejects_invalid_order() {
+        let double_user = Conversation::builder().user("Hello").user("Again").build();
+        assert!(matches!(double_user, Err(ClientError::Configuration(_))));
+
+        let assistant_first = Conversation::builder().assistant("Hi!").build();
+        assert!(assistant_first.is_err());
+
+        let late_system = Conversation::builder().user("Hello").system("Be brief").build();
+        assert!(late_system.is_err());
+    }
+
+    #[test]
+    fn test_validate_for_openai() {
+        let client = create_client("openai", "test-key", "gpt-4o", ClientConfig::default()).unwrap();
+
+        let valid = Conversation::builder().system("Be brief").user("Hi").assistant("Hello").user("Bye");
+        assert!(valid.build().unwrap().validate_for(&*client).is_ok());
+
+        let mut double_user = Conversation::new();
+        double_user.add_user("Hi");
+        double_user.add_user("Again");
+        assert!(matches!(double_user.validate_for(&*client), Err(ClientError::Configuration(_))));
+
+        assert!(Conversation::new().validate_for(&*client).is_err());
+
+        let mut bad_role = Conversation::new();
+        bad_role.add_message(Message { role: "tool".to_string(), content: "{}".to_string() });
+        assert!(bad_role.validate_for(&*client).is_err());
+    }
+
+    #[test]
+    fn test_validate_for_claude() {
+        let client = create_client("claude", "test-key", "claude-3-5-sonnet", ClientConfig::default()).unwrap();
+
+        let mut late_system = Conversation::new();
+        late_system.add_user("Hi");
+        late_system.add_message(Message::system("Answer in French"));
+        late_system.add_assistant("Bonjour");
+        late_system.add_user("Merci");
+        assert!(late_system.validate_for(&*client).is_ok());
+
+        let system_only = Conversation::with_system("Be brief");
+        assert!(system_only.validate_for(&*client).is_err());
+
+        let mut assistant_first = Conversation::with_system("Be brief");
+        assistant_first.add_assistant("Hello");
+        assistant_first.add_user("Hi");
+        assert!(assistant_first.validate_for(&*client).is_err());
+
+        let mut blank = Conversation::new();
+        blank.add_user("   ");
+        assert!(blank.validate_for(&*client).is_err());
+    }
+
+    #[test]
+    fn test_validate_for_gemini() {
+        let client = create_client("gemini", "test-key", "gemini-1.5-pro", ClientConfig::default()).unwrap();
+
+        let mut conversation = Conversation::with_system("Be brief");
+        conversation.add_user("Hi");
+        conversation.add_user("Still there?");
+        assert!(conversation.validate_for(&*client).is_ok());
+
+        let mut no_user = Conversation::with_system("Be brief");
+        no_user.add_assistant("Hello");
+        assert!(no_user.validate_for(&*client).is_err());
+    }
+
+    #[test]
+    fn test_confidence_from_logprobs() {
+        let mut metadata = ResponseMetadata::default();
+        assert_eq!(metadata.confidence_from_logprobs(), None);
+
+        // Probabilities 0.9, 0.8 and 0.4
+        metadata.logprobs = Some(
+            [("The", -0.105_360_5), (" sky", -0.223_143_6), (" is", -0.916_290_7)]
+                .into_iter()
+                .map(|(token, logprob)| TokenLogprob {
+                    token: token.to_string(),
+                    logprob,
+                })
+                .collect(),
+        );
+        let confidence = metadata.confidence_from_logprobs().unwrap();
+        assert!((confidence - 0.7).abs() < 1e-6, "confidence was {confidence}");
+    }
+
+    #[test]
+    fn test_finish_reason_normalization() {
+        for raw in ["length", "max_tokens", "MAX_TOKENS"] {
+            assert_eq!(FinishReason::from_raw(raw), FinishReason::Length);
+        }
+        for raw in ["stop", "end_turn", "STOP"] {
+            assert_eq!(FinishReason::from_raw(raw), FinishReason::Stop);
+        }
+        assert_eq!(
+            FinishReason::from_raw("something_new"),
+            FinishReason::Other("something_new".to_string())
+        );
+
+        let metadata = ResponseMetadata {
+            finish_reason: Some("max_tokens".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(metadata.finish_reason.as_deref(), Some("max_tokens"));
+        assert_eq!(metadata.normalized_finish_reason(), Some(FinishReason::Length));
+        assert_eq!(ResponseMetadata::default().normalized_finish_reason(), None);
+    }
+
+    #[test]
+    fn test_all_failed_and_successes() {
+        let mixed = vec![
+            ("client1".to_string(), Ok("response1".to_string())),
+            ("client2".to_string(), Err(ClientError::timeout("timed out"))),
+            ("client3".to_string(), Ok("response3".to_string())),
+        ];
+        assert!(!all_failed(&mixed));
+        assert_eq!(
+            successes(&mixed),
+            vec![
+                ("client1".to_string(), "response1".to_string()),
+                ("client3".to_string(), "response3".to_string()),
+            ]
+        );
+
+        let failed = vec![
+            ("client1".to_string(), Err(ClientError::rate_limit("slow down"))),
+            ("client2".to_string(), Err(ClientError::invalid_api_key("bad key"))),
+        ];
+        assert!(all_failed(&failed));
+        assert!(successes(&failed).is_empty());
+
+        assert!(all_failed(&[]));
+        assert!(successes(&[]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_execute_fallback() {
+        let clients: Vec<Box<dyn AiClient>> = vec![
+            Box::new(MockClient::new("client1", vec![Err(ClientError::timeout("timed out"))])),
+            Box::new(MockClient::new("client2", vec![Ok("second".to_string())])),
+        ];
+        let response = execute_fallback(&clients, "test", Some("default".to_string())).await;
+        assert_eq!(response.unwrap(), "second");
+
+        let failing = || -> Vec<Box<dyn AiClient>> {
+            vec![
+                Box::new(MockClient::new("client1", vec![Err(ClientError::timeout("timed out"))])),
+                Box::new(MockClient::new("client2", vec![Err(ClientError::rate_limit("slow down"))])),
+            ]
+        };
+        let response = execute_fallback(&failing(), "test", Some("default".to_string())).await;
+        assert_eq!(response.unwrap(), "default");
+
+        let response = execute_fallback(&failing(), "test", None).await;
+        assert!(matches!(response, Err(ClientError::Api(_))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_execute_batch_preserves_order() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        /// Echoes the prompt after a delay that shrinks with the prompt number
+        struct EchoClient {
+            in_flight: AtomicUsize,
+            max_in_flight: AtomicUsize,
+        }
+
+        #[async_trait]
+        impl AiClient for EchoClient {
+            async fn send_prompt(&self, prompt: &str) -> Result<String, ClientError> {
+                let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                self.max_in_flight.fetch_max(now, Ordering::SeqCst);
+                let n: u64 = prompt.trim_start_matches("prompt ").parse().unwrap();
+                tokio::time::sleep(Duration::from_millis(100 - n * 10)).await;
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+                if n == 4 {
+                    return Err(ClientError::config("bad prompt", None));
+                }
+                Ok(format!("echo {prompt}"))
+            }
+
+            fn name(&self) -> &str {
+                "Echo"
+            }
+
+            fn model(&self) -> &str {
+                "echo-1"
+            }
+        }
+
+        let client = EchoClient {
+            in_flight: AtomicUsize::new(0),
+            max_in_flight: AtomicUsize::new(0),
+        };
+        let prompts: Vec<String> = (0..10).map(|n| format!("prompt {n}")).collect();
+
+        let results = execute_batch(&client, &prompts, 3).await;
+        assert_eq!(results.len(), 10);
+        for (n, result) in results.iter().enumerate() {
+            match result {
+                Ok(response) => assert_eq!(response, &format!("echo prompt {n}")),
+                Err(_) => assert_eq!(n, 4),
+            }
+        }
+        assert!(results[4].is_err());
+        assert_eq!(client.max_in_flight.load(Ordering::SeqCst), 3);
+
+        let results = execute_batch_with_metadata(&client, &prompts[..2], 0).await;
+        assert_eq!(results[1].as_ref().unwrap().content, "echo prompt 1");
+        assert_eq!(client.max_in_flight.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_cancellable_and_abortable_requests() {
+        use futures::StreamExt;
+
+        /// Takes a minute to answer, or streams one chunk per second forever
+        struct SlowClient;
+
+        #[async_trait]
+        impl AiClient for SlowClient {
+            async fn send_prompt(&self, _prompt: &str) -> Result<String, ClientError> {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+                Ok("too late".to_string())
+            }
+
+            async fn stream_prompt(
+                &self,
+                _prompt: &str,
+            ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
+                let chunks = futures::stream::iter(1..).then(|second| async move {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                    Ok(StreamChunk {
+                        content: format!("chunk {second}"),
+                        finished: false,
+                        metadata: None,
+                    })
+                });
+                Ok(Box::pin(chunks))
+            }
+
+            fn name(&self) -> &str {
+                "Slow"
+            }
+
+            fn model(&self) -> &str {
+                "slow-1"
+            }
+        }
+
+        let cancel_after = |delay: Duration| {
+            let token = CancellationToken::new();
+            let trigger = token.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                trigger.cancel();
+            });
+            token
+        };
+
+        let start = tokio::time::Instant::now();
+        let err = SlowClient.send_prompt_cancellable("Hi", cancel_after(Duration::from_secs(1))).await.unwrap_err();
+        assert!(err.is_cancelled());
+        assert!(!err.is_retryable());
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+
+        let results: Vec<_> = SlowClient
+            .stream_prompt_cancellable("Hi", cancel_after(Duration::from_millis(2500)))
+            .await
+            .unwrap()
+            .collect()
+            .await;
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().content, "chunk 1");
+        assert_eq!(results[1].as_ref().unwrap().content, "chunk 2");
+        assert!(results[2].as_ref().unwrap_err().is_cancelled());
+
+        // An already cancelled token stops the request before it is sent
+        let token = CancellationToken::new();
+        token.cancel();
+        let mut conversation = Conversation::new();
+        conversation.add_user("Hi");
+        let err = SlowClient.send_conversation_cancellable(&conversation, token).await.unwrap_err();
+        assert!(err.is_cancelled());
+
+        // Aborting through the handle ends the stream without further chunks
+        let (mut stream, handle) = SlowClient.stream_prompt_abortable("Hi").await.unwrap();
+        assert_eq!(stream.next().await.unwrap().unwrap().content, "chunk 1");
+        handle.abort();
+        assert!(stream.next().await.is_none());
+        assert!(handle.is_aborted());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_execute_parallel_with_deadline() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        /// Answers after `delay`, recording whether it finished or was dropped mid-request
+        struct DelayedClient {
+            name: &'static str,
+            delay: Duration,
+            finished: Arc<AtomicBool>,
+            dropped: Arc<AtomicBool>,
+        }
+
+        struct DropFlag(Arc<AtomicBool>);
+
+        impl Drop for DropFlag {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+
+        #[async_trait]
+        impl AiClient for DelayedClient {
+            async fn send_prompt(&self, _prompt: &str) -> Result<String, ClientError> {
+                let flag = DropFlag(self.dropped.clone());
+                tokio::time::sleep(self.delay).await;
+                self.finished.store(true, Ordering::SeqCst);
+                std::mem::forget(flag);
+                Ok(format!("{} answer", self.name))
+            }
+
+            fn name(&self) -> &str {
+                self.name
+            }
+
+            fn model(&self) -> &str {
+                "delayed-1"
+            }
+        }
+
+        let slow_finished = Arc::new(AtomicBool::new(false));
+        let slow_dropped = Arc::new(AtomicBool::new(false));
+        let clients: Vec<Box<dyn AiClient>> = vec![
+            Box::new(DelayedClient {
+                name: "slow",
+                delay: Duration::from_secs(30),
+                finished: slow_finished.clone(),
+                dropped: slow_dropped.clone(),
+            }),
+            Box::new(DelayedClient {
+                name: "fast",
+                delay: Duration::from_millis(100),
+                finished: Arc::default(),
+                dropped: Arc::default(),
+            }),
+        ];
+
+        let start = tokio::time::Instant::now();
+        let results = execute_parallel_with_deadline(clients, "Hi", Duration::from_secs(2)).await;
+        assert_eq!(start.elapsed(), Duration::from_secs(2));
+
+        assert_eq!(results[0].0, "slow");
+        assert!(matches!(
+            &results[0].1,
+            Err(ClientError::Network(NetworkError { error_type: NetworkErrorType::Timeout, .. }))
+        ));
+        assert_eq!(results[1].0, "fast");
+        assert_eq!(results[1].1.as_deref().unwrap(), "fast answer");
+
+        // The slow request was cancelled rather than left running in the background
+        assert!(slow_dropped.load(Ordering::SeqCst));
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        assert!(!slow_finished.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_execute_parallel_limited() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        /// Answers after `delay`, tracking how many clients are in flight at once
+        struct SlowClient {
+            name: String,
+            delay: Duration,
+            in_flight: Arc<AtomicUsize>,
+            max_in_flight: Arc<AtomicUsize>,
+        }
+
+        #[async_trait]
+        impl AiClient for SlowClient {
+            async fn send_prompt(&self, _prompt: &str) -> Result<String, ClientError> {
+                let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                self.max_in_flight.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(self.delay).await;
+                self.in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok(format!("{} done", self.name))
+            }
+
+            fn name(&self) -> &str {
+                &self.name
+            }
+
+            fn model(&self) -> &str {
+                "slow-1"
+            }
+        }
+
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let clients = |delays: &[u64]| -> Vec<Box<dyn AiClient>> {
+            delays
+                .iter()
+                .enumerate()
+                .map(|(i, &delay)| {
+                    Box::new(SlowClient {
+                        name: format!("client-{i}"),
+                        delay: Duration::from_millis(delay),
+                        in_flight: in_flight.clone(),
+                        max_in_flight: max_in_flight.clone(),
+                    }) as Box<dyn AiClient>
+                })
+                .collect()
+        };
+
+        let results = execute_parallel_limited(clients(&[50, 10, 40, 20, 30, 60]), "Hi", 2, None).await;
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 2);
+        let names: Vec<&str> = results.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["client-0", "client-1", "client-2", "client-3", "client-4", "client-5"]);
+        assert!(results.iter().all(|(_, result)| result.is_ok()));
+
+        // Clients still pending at the deadline time out without holding up the rest
+        let results = execute_parallel_limited(
+            clients(&[100, 5_000, 200]),
+            "Hi",
+            3,
+            Some(Duration::from_secs(1)),
+        )
+        .await;
+        assert_eq!(results[0].1.as_deref().unwrap(), "client-0 done");
+        assert!(matches!(
+            &results[1].1,
+            Err(ClientError::Network(NetworkError { error_type: NetworkErrorType::Timeout, .. }))
+        ));
+        assert_eq!(results[2].1.as_deref().unwrap(), "client-2 done");
+    }
+
+    #[tokio::test]
+    async fn test_health_check_all() {
+        let clients: Vec<Box<dyn AiClient>> = vec![
+            Box::new(MockClient::new("healthy", vec![Ok("pong".to_string())])),
+            Box::new(MockClient::new("unhealthy", vec![Err(ClientError::invalid_api_key("bad key"))])),
+        ];
+
+        let health = health_check_all(&clients).await;
+        assert_eq!(health.len(), 2);
+        assert_eq!(health[0].0, "healthy");
+        assert!(health[0].1.is_ok());
+        assert_eq!(health[1].0, "unhealthy");
+        assert!(matches!(health[1].1, Err(ClientError::Authentication(_))));
+
+        assert!(health_check_all(&[]).await.is_empty());
+    }
+
     #[tokio::test]
     async fn test_mock_client_conversation_support() {
         let client = MockClient::new("test", vec![Ok("conversation test".to_string())]);
@@ -1,8 +1,14 @@
 //! Performance metrics collection for ChatDelta clients
 
+use crate::{AiResponse, ApiErrorType, ClientError};
+
+use std::collections::{HashMap, VecDeque};
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
-use std::time::Instant;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// Number of most recent request latencies kept for percentile calculations
+const LATENCY_WINDOW: usize = 1024;
 
 /// Metrics collector for AI client performance
 #[derive(Debug, Clone)]
@@ -14,6 +20,50 @@ pub struct ClientMetrics {
     pub total_tokens_used: Arc<AtomicU64>,
     pub cache_hits: Arc<AtomicU64>,
     pub cache_misses: Arc<AtomicU64>,
+    /// Total time spent waiting on the client-side rate limiter
+    pub rate_limit_wait_ms: Arc<AtomicU64>,
+    /// Sliding window of the most recent request latencies
+    latency_window: Arc<Mutex<VecDeque<u64>>>,
+    /// Failed requests by error category
+    failures: Arc<FailureCounters>,
+}
+
+/// Failure counters, one per error category
+#[derive(Debug, Default)]
+struct FailureCounters {
+    network: AtomicU64,
+    api_rate_limit: AtomicU64,
+    api_server: AtomicU64,
+    auth: AtomicU64,
+    parse: AtomicU64,
+    stream: AtomicU64,
+    other: AtomicU64,
+}
+
+impl FailureCounters {
+    fn counters(&self) -> [&AtomicU64; 7] {
+        [
+            &self.network,
+            &self.api_rate_limit,
+            &self.api_server,
+            &self.auth,
+            &self.parse,
+            &self.stream,
+            &self.other,
+        ]
+    }
+
+    fn breakdown(&self) -> FailureBreakdown {
+        FailureBreakdown {
+            network: self.network.load(Ordering::Relaxed),
+            api_rate_limit: self.api_rate_limit.load(Ordering::Relaxed),
+            api_server: self.api_server.load(Ordering::Relaxed),
+            auth: self.auth.load(Ordering::Relaxed),
+            parse: self.parse.load(Ordering::Relaxed),
+            stream: self.stream.load(Ordering::Relaxed),
+            other: self.other.load(Ordering::Relaxed),
+        }
+    }
 }
 
 impl Default for ClientMetrics {
@@ -33,6 +83,9 @@ impl ClientMetrics {
             total_tokens_used: Arc::new(AtomicU64::new(0)),
             cache_hits: Arc::new(AtomicU64::new(0)),
             cache_misses: Arc::new(AtomicU64::new(0)),
+            rate_limit_wait_ms: Arc::new(AtomicU64::new(0)),
+            latency_window: Arc::new(Mutex::new(VecDeque::with_capacity(LATENCY_WINDOW))),
+            failures: Arc::new(FailureCounters::default()),
         }
     }
     
@@ -41,6 +94,14 @@ impl ClientMetrics {
         self.requests_total.fetch_add(1, Ordering::Relaxed);
         self.total_latency_ms.fetch_add(latency_ms, Ordering::Relaxed);
         
+        {
+            let mut window = self.latency_window.lock().unwrap();
+            if window.len() == LATENCY_WINDOW {
+                window.pop_front();
+            }
+            window.push_back(latency_ms);
+        }
+        
         if success {
             self.requests_successful.fetch_add(1, Ordering::Relaxed);
         } else {
@@ -52,6 +113,41 @@ impl ClientMetrics {
         }
     }
     
+    /// Count a failed request under its error category
+    ///
+    /// This only updates the per-category breakdown; the request itself is still
+    /// recorded with `record_request`.
+    pub fn record_failure(&self, error: &ClientError) {
+        let failures = &self.failures;
+        let counter = match error {
+            ClientError::Network(_) => &failures.network,
+            ClientError::Api(_) if error.is_rate_limit() => &failures.api_rate_limit,
+            ClientError::Api(e) if matches!(e.error_type, ApiErrorType::ServerError) => {
+                &failures.api_server
+            }
+            ClientError::Api(_) if error.status_code().is_some_and(|status| status >= 500) => {
+                &failures.api_server
+            }
+            ClientError::Api(_) => &failures.other,
+            ClientError::Authentication(_) => &failures.auth,
+            ClientError::Parse(_) => &failures.parse,
+            ClientError::Stream(_) => &failures.stream,
+            ClientError::Configuration(_) => &failures.other,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Record the final outcome of a request, including its failure category
+    pub fn record_response(&self, result: &Result<AiResponse, ClientError>, latency_ms: u64) {
+        match result {
+            Ok(response) => self.record_request(true, latency_ms, response.metadata.total_tokens),
+            Err(error) => {
+                self.record_request(false, latency_ms, None);
+                self.record_failure(error);
+            }
+        }
+    }
+    
     /// Record a cache hit
     pub fn record_cache_hit(&self) {
         self.cache_hits.fetch_add(1, Ordering::Relaxed);
@@ -62,9 +158,24 @@ impl ClientMetrics {
         self.cache_misses.fetch_add(1, Ordering::Relaxed);
     }
     
+    /// Record time spent waiting on the client-side rate limiter
+    pub fn record_rate_limit_wait(&self, waited: Duration) {
+        self.rate_limit_wait_ms.fetch_add(waited.as_millis() as u64, Ordering::Relaxed);
+    }
+    
+    /// Latency percentile (0.0 - 100.0) over the most recent requests
+    ///
+    /// Uses the nearest-rank method over a window of the last 1024 latencies, so the
+    /// result is always an observed value. Returns `None` if nothing was recorded yet.
+    pub fn percentile(&self, p: f64) -> Option<u64> {
+        let mut samples: Vec<u64> = self.latency_window.lock().unwrap().iter().copied().collect();
+        nearest_rank(&mut samples, p)
+    }
+    
     /// Get a snapshot of current metrics
     pub fn get_stats(&self) -> MetricsSnapshot {
         let total = self.requests_total.load(Ordering::Relaxed);
+        let mut samples: Vec<u64> = self.latency_window.lock().unwrap().iter().copied().collect();
         let cache_total = self.cache_hits.load(Ordering::Relaxed) + self.cache_misses.load(Ordering::Relaxed);
 
         MetricsSnapshot {
@@ -77,9 +188,14 @@ impl ClientMetrics {
             average_latency_ms: if total > 0 {
                 Some(self.total_latency_ms.load(Ordering::Relaxed) as f64 / total as f64)
             } else { None },
+            p50_latency_ms: nearest_rank(&mut samples, 50.0),
+            p95_latency_ms: nearest_rank(&mut samples, 95.0),
+            p99_latency_ms: nearest_rank(&mut samples, 99.0),
             total_tokens_used: self.total_tokens_used.load(Ordering::Relaxed),
             cache_hits: self.cache_hits.load(Ordering::Relaxed),
             cache_misses: self.cache_misses.load(Ordering::Relaxed),
+            rate_limit_wait_ms: self.rate_limit_wait_ms.load(Ordering::Relaxed),
+            failures: self.failures.breakdown(),
             cache_hit_rate: if cache_total > 0 {
                 self.cache_hits.load(Ordering::Relaxed) as f64 / cache_total as f64
             } else { 0.0 },
@@ -100,6 +216,96 @@ impl ClientMetrics {
         self.total_tokens_used.store(0, Ordering::Relaxed);
         self.cache_hits.store(0, Ordering::Relaxed);
         self.cache_misses.store(0, Ordering::Relaxed);
+        self.rate_limit_wait_ms.store(0, Ordering::Relaxed);
+        self.latency_window.lock().unwrap().clear();
+        for counter in self.failures.counters() {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Nearest-rank percentile of `samples`, sorting them in place
+fn nearest_rank(samples: &mut [u64], p: f64) -> Option<u64> {
+    if samples.is_empty() {
+        return None;
+    }
+    samples.sort_unstable();
+    let rank = (p.clamp(0.0, 100.0) / 100.0 * samples.len() as f64).ceil() as usize;
+    Some(samples[rank.max(1) - 1])
+}
+
+/// Metrics collector that tracks each provider separately
+///
+/// Every request is recorded both under its provider key (e.g. `"Claude"` or
+/// `"openai/gpt-4o"`) and in a global collector holding the sum across providers.
+#[derive(Debug, Clone, Default)]
+pub struct ProviderMetrics {
+    global: ClientMetrics,
+    providers: Arc<RwLock<HashMap<String, ClientMetrics>>>,
+}
+
+impl ProviderMetrics {
+    /// Create a new per-provider metrics collector
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a request and its outcome for the given provider
+    pub fn record_request(
+        &self,
+        provider: &str,
+        success: bool,
+        latency_ms: u64,
+        tokens: Option<u32>,
+    ) {
+        self.provider_metrics(provider).record_request(success, latency_ms, tokens);
+        self.global.record_request(success, latency_ms, tokens);
+    }
+
+    /// Count a failed request for the given provider under its error category
+    pub fn record_failure(&self, provider: &str, error: &ClientError) {
+        self.provider_metrics(provider).record_failure(error);
+        self.global.record_failure(error);
+    }
+
+    /// Get the metrics collector for a provider, creating it on first use
+    pub fn provider_metrics(&self, provider: &str) -> ClientMetrics {
+        // Fast path: the provider is almost always already registered
+        if let Some(metrics) = self.providers.read().unwrap().get(provider) {
+            return metrics.clone();
+        }
+        self.providers
+            .write()
+            .unwrap()
+            .entry(provider.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Get the metrics summed across all providers
+    pub fn global(&self) -> &ClientMetrics {
+        &self.global
+    }
+
+    /// Get a snapshot of the metrics summed across all providers
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.global.get_stats()
+    }
+
+    /// Get a snapshot of the metrics for each provider
+    pub fn snapshot_by_provider(&self) -> HashMap<String, MetricsSnapshot> {
+        self.providers
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(provider, metrics)| (provider.clone(), metrics.get_stats()))
+            .collect()
+    }
+
+    /// Reset the global and per-provider metrics
+    pub fn reset(&self) {
+        self.global.reset();
+        self.providers.write().unwrap().clear();
     }
 }
 
@@ -111,12 +317,58 @@ pub struct MetricsSnapshot {
     pub requests_failed: u64,
     pub success_rate: f64,
     pub average_latency_ms: Option<f64>,
+    #[serde(default)]
+    pub p50_latency_ms: Option<u64>,
+    #[serde(default)]
+    pub p95_latency_ms: Option<u64>,
+    #[serde(default)]
+    pub p99_latency_ms: Option<u64>,
     pub total_tokens_used: u64,
     pub cache_hits: u64,
     pub cache_misses: u64,
+    /// Total time spent waiting on the client-side rate limiter
+    #[serde(default)]
+    pub rate_limit_wait_ms: u64,
+    /// Failed requests broken down by error category
+    #[serde(default)]
+    pub failures: FailureBreakdown,
     pub cache_hit_rate: f64,
 }
 
+/// Failed requests broken down by error category
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct FailureBreakdown {
+    /// Timeouts, connection and DNS failures
+    pub network: u64,
+    /// Rate limit responses (HTTP 429)
+    pub api_rate_limit: u64,
+    /// Provider-side server errors (HTTP 5xx)
+    pub api_server: u64,
+    /// Invalid, missing, or expired credentials
+    pub auth: u64,
+    /// Responses that could not be parsed
+    pub parse: u64,
+    /// Errors while streaming a response
+    pub stream: u64,
+    /// Configuration and remaining API errors
+    pub other: u64,
+}
+
+impl FailureBreakdown {
+    /// Category labels paired with their counts, in a stable order
+    pub fn categories(&self) -> [(&'static str, u64); 7] {
+        [
+            ("network", self.network),
+            ("api-rate-limit", self.api_rate_limit),
+            ("api-server", self.api_server),
+            ("auth", self.auth),
+            ("parse", self.parse),
+            ("stream", self.stream),
+            ("other", self.other),
+        ]
+    }
+}
+
 impl MetricsSnapshot {
     /// Get a human-readable summary of the metrics
     pub fn summary(&self) -> String {
@@ -181,6 +433,109 @@ mod tests {
         assert!(stats.success_rate > 0.66 && stats.success_rate < 0.67); // 2/3 = 0.666...
     }
     
+    #[test]
+    fn test_latency_percentiles() {
+        let metrics = ClientMetrics::new();
+        assert_eq!(metrics.percentile(50.0), None);
+        
+        // Latencies 1..=100ms, recorded out of order
+        for latency in (1..=100).rev() {
+            metrics.record_request(true, latency, None);
+        }
+        
+        assert_eq!(metrics.percentile(0.0), Some(1));
+        assert_eq!(metrics.percentile(50.0), Some(50));
+        assert_eq!(metrics.percentile(100.0), Some(100));
+        
+        let stats = metrics.get_stats();
+        assert_eq!(stats.p50_latency_ms, Some(50));
+        assert_eq!(stats.p95_latency_ms, Some(95));
+        assert_eq!(stats.p99_latency_ms, Some(99));
+        
+        // Only the most recent window is kept
+        for _ in 0..LATENCY_WINDOW {
+            metrics.record_request(true, 500, None);
+        }
+        assert_eq!(metrics.percentile(1.0), Some(500));
+        
+        metrics.reset();
+        assert_eq!(metrics.get_stats().p99_latency_ms, None);
+    }
+    
+    #[test]
+    fn test_failure_breakdown() {
+        use crate::{ApiError, AuthError, AuthErrorType, NetworkError, NetworkErrorType};
+        
+        let metrics = ClientMetrics::new();
+        metrics.record_failure(&ClientError::Network(NetworkError {
+            message: "timed out".to_string(),
+            error_type: NetworkErrorType::Timeout,
+            source: None,
+        }));
+        metrics.record_failure(&ClientError::Api(ApiError {
+            message: "slow down".to_string(),
+            status_code: Some(429),
+            error_type: ApiErrorType::Other,
+            source: None,
+        }));
+        metrics.record_failure(&ClientError::Api(ApiError {
+            message: "overloaded".to_string(),
+            status_code: Some(529),
+            error_type: ApiErrorType::Other,
+            source: None,
+        }));
+        metrics.record_failure(&ClientError::Authentication(AuthError {
+            message: "bad key".to_string(),
+            error_type: AuthErrorType::InvalidApiKey,
+            source: None,
+        }));
+        metrics.record_response(&Err(ClientError::config("bad temperature", None)), 10);
+        metrics.record_response(&Ok(AiResponse::new("ok".to_string())), 20);
+        
+        let stats = metrics.get_stats();
+        assert_eq!(stats.requests_total, 2);
+        assert_eq!(stats.requests_failed, 1);
+        assert_eq!(
+            stats.failures,
+            FailureBreakdown {
+                network: 1,
+                api_rate_limit: 1,
+                api_server: 1,
+                auth: 1,
+                other: 1,
+                ..Default::default()
+            }
+        );
+        
+        metrics.reset();
+        assert_eq!(metrics.get_stats().failures, FailureBreakdown::default());
+    }
+    
+    #[test]
+    fn test_provider_metrics() {
+        let metrics = ProviderMetrics::new();
+
+        metrics.record_request("Claude", true, 100, Some(10));
+        metrics.record_request("Claude", false, 300, None);
+        metrics.record_request("Gemini", true, 50, Some(20));
+
+        let by_provider = metrics.snapshot_by_provider();
+        assert_eq!(by_provider.len(), 2);
+        assert_eq!(by_provider["Claude"].requests_total, 2);
+        assert_eq!(by_provider["Claude"].requests_failed, 1);
+        assert_eq!(by_provider["Claude"].average_latency_ms, Some(200.0));
+        assert_eq!(by_provider["Gemini"].total_tokens_used, 20);
+
+        let global = metrics.snapshot();
+        assert_eq!(global.requests_total, 3);
+        assert_eq!(global.requests_successful, 2);
+        assert_eq!(global.total_tokens_used, 30);
+
+        metrics.reset();
+        assert!(metrics.snapshot_by_provider().is_empty());
+        assert_eq!(metrics.snapshot().requests_total, 0);
+    }
+
     #[test]
     fn test_cache_metrics() {
         let metrics = ClientMetrics::new();
@@ -5,7 +5,9 @@
 
 use crate::{ClientError, ClientConfig};
 use async_trait::async_trait;
+use once_cell::sync::Lazy;
 use reqwest::{Client, Response, RequestBuilder};
+use std::sync::{Arc, RwLock};
 use std::time::Duration;
 use tokio::time::sleep;
 use tracing::{debug, warn, instrument};
@@ -24,6 +26,51 @@ pub trait RequestMiddleware: Send + Sync {
     }
 }
 
+/// Any `Fn(RequestBuilder) -> RequestBuilder` closure can be used as request middleware
+impl<F> RequestMiddleware for F
+where
+    F: Fn(RequestBuilder) -> RequestBuilder + Send + Sync,
+{
+    fn process_request(&self, request: RequestBuilder) -> RequestBuilder {
+        self(request)
+    }
+}
+
+/// Interceptors applied by every client in the process, in registration order
+static GLOBAL_INTERCEPTORS: Lazy<RwLock<Vec<Arc<dyn RequestMiddleware>>>> =
+    Lazy::new(|| RwLock::new(Vec::new()));
+
+/// Register an interceptor that every client runs on each outgoing request
+///
+/// The interceptor's `process_request` sees the request after the provider's
+/// own headers and `ClientConfig::extra_headers` have been added, so unlike extra
+/// headers it can replace anything, including authentication. Interceptors
+/// accumulate; call [`clear_global_interceptors`] to remove them.
+///
+/// ```
+/// chatdelta::set_global_interceptor(|request: reqwest::RequestBuilder| {
+///     request.header("x-trace-id", "4bf92f3577b34da6")
+/// });
+/// # chatdelta::clear_global_interceptors();
+/// ```
+pub fn set_global_interceptor<M: RequestMiddleware + 'static>(interceptor: M) {
+    GLOBAL_INTERCEPTORS.write().unwrap().push(Arc::new(interceptor));
+}
+
+/// Remove every interceptor registered with [`set_global_interceptor`]
+pub fn clear_global_interceptors() {
+    GLOBAL_INTERCEPTORS.write().unwrap().clear();
+}
+
+/// Run `request` through the global interceptors
+pub(crate) fn apply_global_interceptors(request: RequestBuilder) -> RequestBuilder {
+    // Clone the list so an interceptor can register another without deadlocking
+    let interceptors = GLOBAL_INTERCEPTORS.read().unwrap().clone();
+    interceptors
+        .iter()
+        .fold(request, |request, interceptor| interceptor.process_request(request))
+}
+
 /// Base HTTP client with common retry and timeout logic
 pub struct MiddlewareClient {
     client: Client,
@@ -64,7 +111,7 @@ impl MiddlewareClient {
                     warn!("All retry attempts exhausted: {}", err);
                     return Err(err);
                 }
-                Err(err) if self.should_retry(&err) => {
+                Err(err) if err.is_retryable() => {
                     let delay = self.get_retry_delay(attempts);
                     warn!("Request failed (attempt {}), retrying in {:?}: {}", attempts, delay, err);
                     sleep(delay).await;
@@ -77,28 +124,6 @@ impl MiddlewareClient {
         }
     }
 
-    /// Determine if an error is retryable
-    fn should_retry(&self, error: &ClientError) -> bool {
-        match error {
-            ClientError::Network(net_err) => {
-                matches!(
-                    net_err.error_type,
-                    crate::NetworkErrorType::Timeout |
-                    crate::NetworkErrorType::ConnectionFailed |
-                    crate::NetworkErrorType::ConnectionReset
-                )
-            }
-            ClientError::Api(api_err) => {
-                matches!(
-                    api_err.error_type,
-                    crate::ApiErrorType::RateLimit |
-                    crate::ApiErrorType::ServerError
-                )
-            }
-            _ => false,
-        }
-    }
-
     /// Calculate retry delay based on strategy
     fn get_retry_delay(&self, attempt: u32) -> Duration {
         self.config.retry_strategy.delay(attempt - 1)
@@ -119,8 +144,12 @@ impl MiddlewareClient {
 pub mod streaming {
     use crate::{StreamChunk, ClientError};
     use futures::stream::{Stream, StreamExt};
-    
+    use pin_project_lite::pin_project;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
     use tokio::sync::mpsc;
+    use tokio::time::Instant;
     use tracing::error;
 
     /// Convert a stream to channel-based interface
@@ -157,6 +186,89 @@ pub mod streaming {
         }
         Ok(())
     }
+
+    /// Distribution of the gaps between consecutive stream chunks
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct InterTokenLatency {
+        /// Number of gaps measured (one less than the number of chunks)
+        pub gaps: usize,
+        pub min: Duration,
+        pub mean: Duration,
+        pub max: Duration,
+    }
+
+    /// Wrap `stream` to measure the time between consecutive items
+    ///
+    /// When the stream ends, `on_complete` is called once with the latency
+    /// distribution, or `None` if fewer than two items arrived. Items are passed
+    /// through unchanged, errors included.
+    pub fn measure_inter_token_latency<S, F>(stream: S, on_complete: F) -> InterTokenLatencyStream<S, F>
+    where
+        S: Stream,
+        F: FnOnce(Option<InterTokenLatency>),
+    {
+        InterTokenLatencyStream {
+            inner: stream,
+            last_item: None,
+            count: 0,
+            min: Duration::MAX,
+            max: Duration::ZERO,
+            total: Duration::ZERO,
+            on_complete: Some(on_complete),
+        }
+    }
+
+    pin_project! {
+        /// Stream returned by [`measure_inter_token_latency`]
+        pub struct InterTokenLatencyStream<S, F> {
+            #[pin]
+            inner: S,
+            last_item: Option<Instant>,
+            count: usize,
+            min: Duration,
+            max: Duration,
+            total: Duration,
+            on_complete: Option<F>,
+        }
+    }
+
+    impl<S, F> Stream for InterTokenLatencyStream<S, F>
+    where
+        S: Stream,
+        F: FnOnce(Option<InterTokenLatency>),
+    {
+        type Item = S::Item;
+
+        fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            let this = self.project();
+            match this.inner.poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    let now = Instant::now();
+                    if let Some(last) = this.last_item.replace(now) {
+                        let gap = now - last;
+                        *this.count += 1;
+                        *this.min = (*this.min).min(gap);
+                        *this.max = (*this.max).max(gap);
+                        *this.total += gap;
+                    }
+                    Poll::Ready(Some(item))
+                }
+                Poll::Ready(None) => {
+                    if let Some(on_complete) = this.on_complete.take() {
+                        let count = *this.count;
+                        on_complete((count > 0).then(|| InterTokenLatency {
+                            gaps: count,
+                            min: *this.min,
+                            mean: *this.total / count as u32,
+                            max: *this.max,
+                        }));
+                    }
+                    Poll::Ready(None)
+                }
+                Poll::Pending => Poll::Pending,
+            }
+        }
+    }
 }
 
 /// Response validation utilities
@@ -164,6 +276,7 @@ pub mod validation {
     use crate::ClientError;
     use serde::de::DeserializeOwned;
     use serde_json::Value;
+    use std::sync::Arc;
 
     /// Validate JSON response structure
     pub fn validate_json_response<T: DeserializeOwned>(
@@ -177,6 +290,7 @@ pub mod validation {
                     message: format!("Missing required field: {}", field),
                     error_type: crate::ParseErrorType::MissingField,
                     raw_content: Some(json.to_string()),
+                    source: None,
                 }));
             }
         }
@@ -187,6 +301,7 @@ pub mod validation {
                 message: format!("Failed to deserialize response: {}", e),
                 error_type: crate::ParseErrorType::JsonParsing,
                 raw_content: Some(json.to_string()),
+                source: Some(Arc::new(e)),
             })
         })
     }
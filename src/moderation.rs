@@ -0,0 +1,79 @@
+//! Content moderation
+//!
+//! Screening text with a moderation endpoint is far cheaper than sending it to a
+//! chat model, so applications can reject disallowed input up front and log why.
+//! OpenAI implements [`ModerationClient`] through `/v1/moderations`. Gemini only
+//! rates content as part of a generation, via `safety_ratings` in
+//! [`ResponseMetadata`](crate::ResponseMetadata), and has no standalone endpoint.
+
+use crate::{http_client_for, AiClient, ChatGpt, ClientConfig, ClientError};
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+/// Outcome of screening one piece of text
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ModerationResult {
+    /// Whether the text violates any category
+    pub flagged: bool,
+    /// Categories the text was flagged for, sorted by name
+    pub flagged_categories: Vec<String>,
+    /// Score (0.0-1.0) for every category the provider checked
+    pub category_scores: HashMap<String, f64>,
+}
+
+/// Common interface for providers that can screen text for policy violations
+///
+/// Moderation clients are also [`AiClient`]s, which supplies their name and model.
+#[async_trait]
+pub trait ModerationClient: AiClient {
+    /// Classify `text` against the provider's content policy
+    async fn moderate(&self, text: &str) -> Result<ModerationResult, ClientError>;
+}
+
+/// Factory function to create moderation clients
+///
+/// # Arguments
+///
+/// * `provider` - The AI provider: "openai"
+/// * `api_key` - The API key for the provider
+/// * `model` - The moderation model (e.g., "omni-moderation-latest")
+/// * `config` - Configuration for timeouts, retries, and rate limits
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use chatdelta::{create_moderation_client, ClientConfig};
+///
+/// # async fn example() -> Result<(), chatdelta::ClientError> {
+/// let client = create_moderation_client(
+///     "openai",
+///     "your-api-key",
+///     "omni-moderation-latest",
+///     ClientConfig::default(),
+/// )?;
+/// let result = client.moderate("user input").await?;
+/// if result.flagged {
+///     println!("Rejected: {:?}", result.flagged_categories);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn create_moderation_client(
+    provider: &str,
+    api_key: &str,
+    model: &str,
+    config: ClientConfig,
+) -> Result<Box<dyn ModerationClient>, ClientError> {
+    match provider.to_lowercase().as_str() {
+        "openai" | "gpt" | "chatgpt" => Ok(Box::new(ChatGpt::new(
+            http_client_for(provider, &config)?,
+            api_key.to_string(),
+            model.to_string(),
+            config,
+        ))),
+        _ => Err(ClientError::config(
+            format!("Unknown provider: {provider}. Moderation providers: openai"),
+            Some("provider".to_string()),
+        )),
+    }
+}
@@ -1,18 +1,18 @@
 //! Observability pipeline for metrics export and structured logging
 
-use crate::ClientMetrics;
+use crate::{AiResponse, ClientConfig, ClientError, ClientMetrics, Conversation};
+use std::borrow::Cow;
+use std::future::Future;
 use std::sync::Arc;
-use tracing::{info, Level};
+use std::time::Instant;
+use tracing::{debug, info, warn, Instrument, Level};
 use tracing_subscriber::{EnvFilter, fmt, prelude::*};
 
-#[cfg(feature = "metrics-export")]
-use prometheus::{Encoder, TextEncoder, Registry, Counter, Histogram, HistogramOpts, CounterOpts};
+/// Default number of characters of a prompt or response kept when bodies are logged
+pub const DEFAULT_LOG_TRUNCATE_CHARS: usize = 500;
 
 #[cfg(feature = "metrics-export")]
-use opentelemetry::{
-    metrics::{Meter, Counter as OtelCounter, Histogram as OtelHistogram},
-    KeyValue,
-};
+use prometheus::{Encoder, TextEncoder, Registry, Counter, CounterVec, Histogram, HistogramOpts, Opts};
 
 /// Initialize tracing with structured logging
 pub fn init_tracing() {
@@ -52,6 +52,8 @@ pub struct PrometheusExporter {
     tokens_used: Counter,
     cache_hits: Counter,
     cache_misses: Counter,
+    rate_limit_wait: Counter,
+    request_failures: CounterVec,
 }
 
 #[cfg(feature = "metrics-export")]
@@ -60,15 +62,15 @@ impl PrometheusExporter {
         let registry = Registry::new();
 
         let requests_total = Counter::with_opts(
-            CounterOpts::new("chatdelta_requests_total", "Total number of API requests")
+            Opts::new("chatdelta_requests_total", "Total number of API requests")
         )?;
 
         let requests_successful = Counter::with_opts(
-            CounterOpts::new("chatdelta_requests_successful", "Number of successful API requests")
+            Opts::new("chatdelta_requests_successful", "Number of successful API requests")
         )?;
 
         let requests_failed = Counter::with_opts(
-            CounterOpts::new("chatdelta_requests_failed", "Number of failed API requests")
+            Opts::new("chatdelta_requests_failed", "Number of failed API requests")
         )?;
 
         let request_duration = Histogram::with_opts(
@@ -77,15 +79,27 @@ impl PrometheusExporter {
         )?;
 
         let tokens_used = Counter::with_opts(
-            CounterOpts::new("chatdelta_tokens_used_total", "Total tokens consumed")
+            Opts::new("chatdelta_tokens_used_total", "Total tokens consumed")
         )?;
 
         let cache_hits = Counter::with_opts(
-            CounterOpts::new("chatdelta_cache_hits_total", "Total cache hits")
+            Opts::new("chatdelta_cache_hits_total", "Total cache hits")
         )?;
 
         let cache_misses = Counter::with_opts(
-            CounterOpts::new("chatdelta_cache_misses_total", "Total cache misses")
+            Opts::new("chatdelta_cache_misses_total", "Total cache misses")
+        )?;
+
+        let rate_limit_wait = Counter::with_opts(
+            Opts::new(
+                "chatdelta_rate_limit_wait_ms_total",
+                "Total time spent waiting on the client-side rate limiter in milliseconds",
+            )
+        )?;
+
+        let request_failures = CounterVec::new(
+            Opts::new("chatdelta_request_failures_total", "Failed API requests by error category"),
+            &["category"],
         )?;
 
         registry.register(Box::new(requests_total.clone()))?;
@@ -95,6 +109,8 @@ impl PrometheusExporter {
         registry.register(Box::new(tokens_used.clone()))?;
         registry.register(Box::new(cache_hits.clone()))?;
         registry.register(Box::new(cache_misses.clone()))?;
+        registry.register(Box::new(rate_limit_wait.clone()))?;
+        registry.register(Box::new(request_failures.clone()))?;
 
         Ok(Self {
             registry,
@@ -105,13 +121,13 @@ impl PrometheusExporter {
             tokens_used,
             cache_hits,
             cache_misses,
+            rate_limit_wait,
+            request_failures,
         })
     }
 
     /// Update Prometheus metrics from ClientMetrics
     pub fn update(&self, metrics: &ClientMetrics) {
-        use std::sync::atomic::Ordering;
-
         let snapshot = metrics.snapshot();
 
         // Set counters to current values
@@ -133,6 +149,13 @@ impl PrometheusExporter {
         self.cache_misses.inc_by(
             snapshot.cache_misses as f64 - self.cache_misses.get()
         );
+        self.rate_limit_wait.inc_by(
+            snapshot.rate_limit_wait_ms as f64 - self.rate_limit_wait.get()
+        );
+        for (category, count) in snapshot.failures.categories() {
+            let counter = self.request_failures.with_label_values(&[category]);
+            counter.inc_by(count as f64 - counter.get());
+        }
 
         // Update histogram with average latency if we have requests
         if snapshot.requests_total > 0 {
@@ -165,7 +188,7 @@ pub struct TextExporter;
 impl MetricsExporter for TextExporter {
     fn export(&self, metrics: &ClientMetrics) -> String {
         let snapshot = metrics.snapshot();
-        format!(
+        let mut output = format!(
             "ChatDelta Metrics:\n\
             Requests Total: {}\n\
             Requests Successful: {}\n\
@@ -174,7 +197,9 @@ impl MetricsExporter for TextExporter {
             Total Tokens Used: {}\n\
             Cache Hits: {}\n\
             Cache Misses: {}\n\
-            Success Rate: {:.2}%",
+            Rate Limit Wait: {}ms\n\
+            Success Rate: {:.2}%\n\
+            Failures by Category:",
             snapshot.requests_total,
             snapshot.requests_successful,
             snapshot.requests_failed,
@@ -182,8 +207,13 @@ impl MetricsExporter for TextExporter {
             snapshot.total_tokens_used,
             snapshot.cache_hits,
             snapshot.cache_misses,
+            snapshot.rate_limit_wait_ms,
             snapshot.success_rate * 100.0
-        )
+        );
+        for (category, count) in snapshot.failures.categories() {
+            output.push_str(&format!("\n  {}: {}", category, count));
+        }
+        output
     }
 
     fn name(&self) -> &str {
@@ -191,6 +221,49 @@ impl MetricsExporter for TextExporter {
     }
 }
 
+/// Prometheus text exposition format exporter (always available)
+///
+/// Produces `# HELP`/`# TYPE` annotated samples using the same metric names as
+/// `PrometheusExporter`, without depending on the prometheus crate.
+pub struct PrometheusTextExporter;
+
+impl MetricsExporter for PrometheusTextExporter {
+    fn export(&self, metrics: &ClientMetrics) -> String {
+        let snapshot = metrics.snapshot();
+        let samples = [
+            ("chatdelta_requests_total", "Total number of API requests", "counter", snapshot.requests_total as f64),
+            ("chatdelta_requests_successful", "Number of successful API requests", "counter", snapshot.requests_successful as f64),
+            ("chatdelta_requests_failed", "Number of failed API requests", "counter", snapshot.requests_failed as f64),
+            ("chatdelta_request_duration_ms_average", "Average request duration in milliseconds", "gauge", snapshot.average_latency_ms.unwrap_or(0.0)),
+            ("chatdelta_tokens_used_total", "Total tokens consumed", "counter", snapshot.total_tokens_used as f64),
+            ("chatdelta_cache_hits_total", "Total cache hits", "counter", snapshot.cache_hits as f64),
+            ("chatdelta_cache_misses_total", "Total cache misses", "counter", snapshot.cache_misses as f64),
+            ("chatdelta_rate_limit_wait_ms_total", "Total time spent waiting on the client-side rate limiter in milliseconds", "counter", snapshot.rate_limit_wait_ms as f64),
+        ];
+
+        let mut output = String::new();
+        for (name, help, kind, value) in samples {
+            output.push_str(&format!("# HELP {} {}\n", name, help));
+            output.push_str(&format!("# TYPE {} {}\n", name, kind));
+            output.push_str(&format!("{} {}\n", name, value));
+        }
+
+        output.push_str("# HELP chatdelta_request_failures_total Failed API requests by error category\n");
+        output.push_str("# TYPE chatdelta_request_failures_total counter\n");
+        for (category, count) in snapshot.failures.categories() {
+            output.push_str(&format!(
+                "chatdelta_request_failures_total{{category=\"{}\"}} {}\n",
+                category, count
+            ));
+        }
+        output
+    }
+
+    fn name(&self) -> &str {
+        "prometheus-text"
+    }
+}
+
 /// Observability context for request tracing
 pub struct ObservabilityContext {
     pub request_id: String,
@@ -222,6 +295,77 @@ impl ObservabilityContext {
             model = %self.model
         )
     }
+
+    /// Run a request inside this context's span and record its outcome in the metrics
+    pub async fn track<F>(&self, request: F) -> Result<AiResponse, ClientError>
+    where
+        F: Future<Output = Result<AiResponse, ClientError>>,
+    {
+        self.track_in(self.span(), request).await
+    }
+
+    /// Run a request like [`track`](Self::track), logging its bodies when `config.log_bodies` is set
+    ///
+    /// Each message of `conversation` and the response content are emitted as
+    /// debug events in the request span, truncated to `config.log_truncate_chars`.
+    pub async fn track_conversation<F>(
+        &self,
+        config: &ClientConfig,
+        conversation: &Conversation,
+        request: F,
+    ) -> Result<AiResponse, ClientError>
+    where
+        F: Future<Output = Result<AiResponse, ClientError>>,
+    {
+        let span = self.span();
+        if !config.log_bodies {
+            return self.track_in(span, request).await;
+        }
+
+        let limit = config.log_truncate_chars;
+        for message in &conversation.messages {
+            debug!(
+                parent: &span,
+                role = %message.role,
+                content = %truncate_for_log(&message.content, limit),
+                "request message"
+            );
+        }
+        let result = self.track_in(span.clone(), request).await;
+        if let Ok(response) = &result {
+            debug!(
+                parent: &span,
+                content = %truncate_for_log(&response.content, limit),
+                "response body"
+            );
+        }
+        result
+    }
+
+    async fn track_in<F>(&self, span: tracing::Span, request: F) -> Result<AiResponse, ClientError>
+    where
+        F: Future<Output = Result<AiResponse, ClientError>>,
+    {
+        let start = Instant::now();
+        let result = request.instrument(span.clone()).await;
+        let latency_ms = start.elapsed().as_millis() as u64;
+
+        self.metrics.record_response(&result, latency_ms);
+        match &result {
+            Ok(_) => debug!(parent: &span, latency_ms, "request completed"),
+            Err(error) => warn!(parent: &span, latency_ms, %error, "request failed"),
+        }
+
+        result
+    }
+}
+
+/// Cut `text` to at most `max_chars` characters, marking the cut with an ellipsis
+fn truncate_for_log(text: &str, max_chars: usize) -> Cow<'_, str> {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => Cow::Owned(format!("{}…", &text[..end])),
+        None => Cow::Borrowed(text),
+    }
 }
 
 #[cfg(test)]
@@ -244,5 +388,111 @@ mod tests {
         assert!(output.contains("Requests Failed: 1"));
         assert!(output.contains("Cache Hits: 1"));
         assert!(output.contains("Cache Misses: 1"));
+        assert!(output.contains("Failures by Category:"));
+    }
+
+    #[tokio::test]
+    async fn test_logged_prompt_is_truncated() {
+        use std::sync::Mutex;
+
+        #[derive(Clone, Default)]
+        struct Captured(Arc<Mutex<Vec<u8>>>);
+
+        impl std::io::Write for Captured {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let captured = Captured::default();
+        let writer = captured.clone();
+        let subscriber = fmt()
+            .with_max_level(Level::DEBUG)
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let config = ClientConfig::builder().log_bodies(true).log_truncate_chars(10).build();
+        let mut conversation = Conversation::new();
+        conversation.add_user("x".repeat(50));
+        let context = ObservabilityContext::new("Test".into(), "test-1".into(), Arc::default());
+        let response = context
+            .track_conversation(&config, &conversation, async { Ok(AiResponse::new("short".into())) })
+            .await
+            .unwrap();
+        assert_eq!(response.content, "short");
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        let logged = output.lines().find(|line| line.contains("request message")).unwrap();
+        assert!(logged.contains(&format!("content={}…", "x".repeat(10))), "{logged}");
+        assert!(!logged.contains(&"x".repeat(11)));
+        assert!(output.lines().any(|line| line.contains("response body") && line.contains("content=short")));
+    }
+
+    #[test]
+    fn test_truncate_for_log() {
+        assert_eq!(truncate_for_log("hello", 5), "hello");
+        assert_eq!(truncate_for_log("héllo wörld", 4), "héll…");
+        assert_eq!(truncate_for_log("", 0), "");
+    }
+
+    #[test]
+    fn test_prometheus_text_exporter_format() {
+        let metrics = ClientMetrics::new();
+        metrics.record_request(true, 100, Some(50));
+        metrics.record_request(false, 300, None);
+        metrics.record_failure(&crate::ClientError::Api(crate::ApiError {
+            message: "Too many requests".to_string(),
+            status_code: Some(429),
+            error_type: crate::ApiErrorType::RateLimit,
+            source: None,
+        }));
+        metrics.record_cache_hit();
+
+        let output = PrometheusTextExporter.export(&metrics);
+
+        // Every sample must be preceded by HELP and TYPE lines for the same metric
+        let mut described = std::collections::HashSet::new();
+        let mut typed = std::collections::HashSet::new();
+        let mut samples = std::collections::HashMap::new();
+        for line in output.lines() {
+            if let Some(rest) = line.strip_prefix("# HELP ") {
+                let (name, help) = rest.split_once(' ').expect("HELP needs a description");
+                assert!(!help.is_empty());
+                described.insert(name.to_string());
+            } else if let Some(rest) = line.strip_prefix("# TYPE ") {
+                let (name, kind) = rest.split_once(' ').expect("TYPE needs a kind");
+                assert!(["counter", "gauge", "histogram", "summary", "untyped"].contains(&kind));
+                typed.insert(name.to_string());
+            } else {
+                let (series, value) = line.rsplit_once(' ').expect("sample needs a value");
+                let name = match series.split_once('{') {
+                    Some((name, labels)) => {
+                        assert!(labels.ends_with('}'), "unterminated labels in {series}");
+                        name
+                    }
+                    None => series,
+                };
+                assert!(name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':'));
+                assert!(!name.starts_with(|c: char| c.is_ascii_digit()));
+                assert!(described.contains(name) && typed.contains(name), "{name} missing HELP/TYPE");
+                samples.insert(series.to_string(), value.parse::<f64>().expect("value must be numeric"));
+            }
+        }
+
+        assert!(output.ends_with('\n'));
+        assert_eq!(samples["chatdelta_requests_total"], 2.0);
+        assert_eq!(samples["chatdelta_requests_failed"], 1.0);
+        assert_eq!(samples["chatdelta_request_duration_ms_average"], 200.0);
+        assert_eq!(samples["chatdelta_tokens_used_total"], 50.0);
+        assert_eq!(samples["chatdelta_cache_hits_total"], 1.0);
+        assert_eq!(samples["chatdelta_request_failures_total{category=\"api-rate-limit\"}"], 1.0);
+        assert_eq!(samples["chatdelta_request_failures_total{category=\"network\"}"], 0.0);
     }
 }
\ No newline at end of file
@@ -6,11 +6,46 @@
 //! - Model specialization routing
 //! - Consensus building algorithms
 
-use crate::{AiClient, ClientError, ClientMetrics};
-use futures::future::join_all;
+use crate::{
+    buffered_as_stream, AiClient, ClientError, ClientMetrics, Conversation, Message, ModelRates, Pricing, ResponseMetadata,
+    StreamChunk,
+};
+use futures::future::{join_all, ready, BoxFuture, Future, FutureExt};
+use futures::stream::{self, BoxStream, FuturesUnordered, StreamExt};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Fallback price used for models missing from both the pricing and capability tables
+pub(crate) const DEFAULT_COST_PER_1K_TOKENS: f32 = 0.01;
+
+/// Default maximum number of cached orchestration results
+const DEFAULT_CACHE_CAPACITY: u64 = 1000;
+
+/// Default time a cached orchestration result stays valid
+const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// Default similarity two responses need to be counted as the same answer
+const DEFAULT_CONSENSUS_THRESHOLD: f64 = 0.5;
+
+/// Similarity above which two sentences are treated as the same point when merging
+const SENTENCE_DEDUP_THRESHOLD: f64 = 0.8;
+
+/// Words two streamed responses must have in common before early consensus compares them
+const CONSENSUS_PREFIX_WORDS: usize = 8;
+
+/// One model's reply to a prompt: model name, result, and latency in milliseconds
+type ModelResult = (String, Result<String, ClientError>, u64);
+
+/// A client's stream after yielding its next chunk, tagged with the client's index
+type StreamStep<'a> = (
+    usize,
+    Option<Result<StreamChunk, ClientError>>,
+    BoxStream<'a, Result<StreamChunk, ClientError>>,
+);
 
 /// Orchestrator for coordinating multiple AI models
 pub struct AiOrchestrator {
@@ -22,8 +57,18 @@ pub struct AiOrchestrator {
     strategy: OrchestrationStrategy,
     /// Performance metrics
     metrics: ClientMetrics,
-    /// Response cache
-    cache: ResponseCache,
+    /// Response cache, `None` when caching is disabled
+    cache: Option<ResponseCache>,
+    /// Minimum Jaccard similarity for two responses to agree in consensus voting
+    consensus_threshold: f64,
+    /// Auto-compaction settings for `query_conversation`, `None` when disabled
+    compaction: Option<CompactionConfig>,
+    /// Capabilities a client must have to be dispatched to
+    required_capabilities: Vec<Capability>,
+    /// Pin the clock and dispatch order so results are reproducible in tests
+    deterministic: bool,
+    /// Token rates used to estimate the cost of each response
+    pricing: Pricing,
 }
 
 /// Model capabilities and specialization areas
@@ -37,6 +82,32 @@ pub struct ModelCapabilities {
     pub supports_streaming: bool,
     pub supports_vision: bool,
     pub supports_function_calling: bool,
+    /// Relative quality multiplier applied when weighting this model's responses (1.0 = neutral)
+    #[serde(default = "default_quality_factor")]
+    pub quality_factor: f64,
+}
+
+fn default_quality_factor() -> f64 {
+    1.0
+}
+
+impl ModelCapabilities {
+    /// Whether the model has `capability`
+    pub fn supports(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Streaming => self.supports_streaming,
+            Capability::Vision => self.supports_vision,
+            Capability::FunctionCalling => self.supports_function_calling,
+        }
+    }
+}
+
+/// Feature a query can require of the models it is sent to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Capability {
+    Streaming,
+    Vision,
+    FunctionCalling,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -52,7 +123,7 @@ pub enum Strength {
 }
 
 /// Orchestration strategies for multi-model coordination
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum OrchestrationStrategy {
     /// All models process in parallel, then merge
     Parallel,
@@ -68,6 +139,18 @@ pub enum OrchestrationStrategy {
     Tournament,
     /// Adaptive strategy based on query analysis
     Adaptive,
+    /// Return the successful response with the lowest estimated cost
+    CheapestSuccessful,
+    /// Return the first successful response, recording every model's latency
+    ///
+    /// The query still waits for the slower models to finish so that their latencies
+    /// and outcomes appear in `contributions`.
+    FastestFirst,
+    /// Stream every model at once and settle as soon as two agree on the emerging answer
+    ///
+    /// The remaining streams are cancelled. If no two ever agree, falls back to
+    /// consensus voting over the complete responses.
+    StreamingConsensus,
 }
 
 /// Advanced response fusion result
@@ -118,16 +201,158 @@ pub struct OrchestrationMetrics {
     pub cost_estimate: f32,
 }
 
+/// Audit record of one orchestrated query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrchestrationReport {
+    /// When the query was made, in seconds since the Unix epoch
+    pub timestamp: u64,
+    /// The prompt sent to every model
+    pub prompt: String,
+    /// Strategy that fused the responses, after resolving `Adaptive`
+    pub strategy: OrchestrationStrategy,
+    /// The fused response, including successful contributions and metrics
+    pub response: FusedResponse,
+    /// Models that failed to answer, which have no contribution
+    pub failures: Vec<ModelFailure>,
+}
+
+/// A model that failed to answer during an orchestrated query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelFailure {
+    pub model: String,
+    pub error: String,
+}
+
+/// One model's latency, token usage, and estimated cost for a benchmarked prompt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkResult {
+    pub provider: String,
+    pub model: String,
+    pub latency_ms: u64,
+    /// Tokens reported by the provider, or estimated from the text when it reports none
+    pub total_tokens: u32,
+    /// Estimated cost in dollars, from the model's `cost_per_1k_tokens`
+    pub estimated_cost: f32,
+    /// Why the model failed to answer; failed models are sorted last
+    pub error: Option<String>,
+}
+
+/// Key `AiOrchestrator::benchmark` sorts its results by, ascending
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BenchmarkSort {
+    #[default]
+    Latency,
+    Tokens,
+    Cost,
+}
+
+/// Settings for summarizing old turns before a conversation outgrows the context window
+#[derive(Debug, Clone)]
+pub struct CompactionConfig {
+    /// Fraction (0.0 - 1.0) of the smallest participating context window at which
+    /// compaction kicks in
+    pub threshold: f64,
+    /// Number of most recent messages that are always kept verbatim
+    pub keep_recent: usize,
+}
+
+impl Default for CompactionConfig {
+    fn default() -> Self {
+        Self {
+            threshold: 0.8,
+            keep_recent: 4,
+        }
+    }
+}
+
+/// Running statistics over the confidence of a series of fused responses
+///
+/// Record each [`FusedResponse`] as it arrives to follow orchestration quality over
+/// time, for example to alert when confidence starts to slide.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConfidenceTracker {
+    confidences: Vec<f64>,
+}
+
+impl ConfidenceTracker {
+    /// Create an empty tracker
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the confidence of `response`
+    pub fn record(&mut self, response: &FusedResponse) {
+        self.record_confidence(response.confidence);
+    }
+
+    /// Record a confidence score directly
+    pub fn record_confidence(&mut self, confidence: f64) {
+        self.confidences.push(confidence);
+    }
+
+    /// Number of confidences recorded
+    pub fn count(&self) -> usize {
+        self.confidences.len()
+    }
+
+    /// Mean confidence so far, `None` before the first record
+    pub fn mean(&self) -> Option<f64> {
+        if self.confidences.is_empty() {
+            return None;
+        }
+        Some(self.confidences.iter().sum::<f64>() / self.confidences.len() as f64)
+    }
+
+    /// Change in confidence per response, as the least-squares slope over the records
+    ///
+    /// Positive when confidence is improving. `None` until two responses are recorded.
+    pub fn trend(&self) -> Option<f64> {
+        let n = self.confidences.len();
+        if n < 2 {
+            return None;
+        }
+        let mean_x = (n - 1) as f64 / 2.0;
+        let mean_y = self.mean()?;
+        let (covariance, variance) = self.confidences.iter().enumerate().fold(
+            (0.0, 0.0),
+            |(covariance, variance), (x, y)| {
+                let dx = x as f64 - mean_x;
+                (covariance + dx * (y - mean_y), variance + dx * dx)
+            },
+        );
+        Some(covariance / variance)
+    }
+}
+
 impl AiOrchestrator {
     /// Create a new orchestrator with default strategy
     pub fn new(clients: Vec<Box<dyn AiClient>>) -> Self {
-        let clients = clients.into_iter().map(|c| Arc::new(c)).collect();
+        let clients = clients.into_iter().map(Arc::new).collect();
         Self {
             clients,
             capabilities: Self::detect_capabilities(),
             strategy: OrchestrationStrategy::Adaptive,
             metrics: ClientMetrics::new(),
-            cache: ResponseCache::new(1000),
+            cache: Some(ResponseCache::new(DEFAULT_CACHE_CAPACITY, DEFAULT_CACHE_TTL)),
+            consensus_threshold: DEFAULT_CONSENSUS_THRESHOLD,
+            compaction: None,
+            required_capabilities: Vec::new(),
+            deterministic: false,
+            pricing: Pricing::current(),
+        }
+    }
+
+    /// Create an orchestrator whose results depend only on the clients' replies
+    ///
+    /// Meant for testing strategies against mock clients. Caching is disabled, every
+    /// latency is reported as 0 ms and report timestamps as 0, responses are gathered
+    /// in client order (so `FastestFirst` picks the first client that succeeds), and
+    /// prompts are not analyzed, so every prompt is treated as a general task.
+    pub fn for_testing(clients: Vec<Box<dyn AiClient>>) -> Self {
+        Self {
+            cache: None,
+            deterministic: true,
+            ..Self::new(clients)
         }
     }
     
@@ -137,78 +362,708 @@ impl AiOrchestrator {
         self
     }
     
+    /// Set the similarity (0.0 - 1.0) two responses need to vote for the same answer
+    pub fn with_consensus_threshold(mut self, threshold: f64) -> Self {
+        self.consensus_threshold = threshold.clamp(0.0, 1.0);
+        self
+    }
+    
+    /// Cache up to `capacity` results, each valid for `ttl`
+    pub fn with_cache(mut self, capacity: u64, ttl: Duration) -> Self {
+        self.cache = Some(ResponseCache::new(capacity, ttl));
+        self
+    }
+    
+    /// Disable response caching entirely
+    pub fn without_cache(mut self) -> Self {
+        self.cache = None;
+        self
+    }
+    
+    /// Estimate costs with `pricing` instead of [`Pricing::current`]
+    ///
+    /// Models missing from `pricing` fall back to the flat `cost_per_1k_tokens` of
+    /// their registered capabilities.
+    pub fn with_pricing(mut self, pricing: Pricing) -> Self {
+        self.pricing = pricing;
+        self
+    }
+    
+    /// Summarize old turns in `query_conversation` when nearing the context limit
+    pub fn with_auto_compaction(mut self, config: CompactionConfig) -> Self {
+        self.compaction = Some(config);
+        self
+    }
+    
+    /// Only dispatch to clients whose model supports `capability`
+    ///
+    /// Support is looked up in the capability table, so clients whose model is not
+    /// registered are skipped, except that a client reporting `supports_streaming`
+    /// satisfies [`Capability::Streaming`]. Can be called repeatedly to require
+    /// several capabilities.
+    pub fn require_capability(mut self, capability: Capability) -> Self {
+        if !self.required_capabilities.contains(&capability) {
+            self.required_capabilities.push(capability);
+        }
+        self
+    }
+    
+    /// Get the orchestrator's performance metrics
+    pub fn metrics(&self) -> &ClientMetrics {
+        &self.metrics
+    }
+    
+    /// Remove all cached results
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.clear();
+        }
+    }
+    
+    /// Serialize the cached results to JSON, as an object mapping prompts to responses
+    ///
+    /// Pass the output to [`import_cache`](Self::import_cache), possibly in another
+    /// process, for a warm start. With caching disabled this is an empty object.
+    pub fn export_cache(&self) -> Result<String, ClientError> {
+        let entries = self.cache.as_ref().map(ResponseCache::entries).unwrap_or_default();
+        Ok(serde_json::to_string(&entries)?)
+    }
+    
+    /// Load results produced by [`export_cache`](Self::export_cache) into the cache
+    ///
+    /// Imported entries get a fresh time to live and replace cached results for the
+    /// same prompts. Returns how many entries were imported, which is zero with
+    /// caching disabled.
+    pub async fn import_cache(&self, json: &str) -> Result<usize, ClientError> {
+        let entries: HashMap<String, FusedResponse> = serde_json::from_str(json)?;
+        let Some(cache) = &self.cache else {
+            return Ok(0);
+        };
+        let count = entries.len();
+        for (prompt, response) in entries {
+            cache.set(&prompt, response).await;
+        }
+        Ok(count)
+    }
+    
+    /// Replace the built-in capability table, keyed by model identifier
+    pub fn with_capabilities(mut self, capabilities: HashMap<String, ModelCapabilities>) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+    
+    /// Register or overwrite the capabilities of a single model
+    pub fn register_capability<S: Into<String>>(&mut self, model: S, capabilities: ModelCapabilities) {
+        self.capabilities.insert(model.into(), capabilities);
+    }
+    
+    /// Get the capabilities registered for a model, if any
+    pub fn capability(&self, model: &str) -> Option<&ModelCapabilities> {
+        self.capabilities.get(model)
+    }
+    
     /// Execute orchestrated query across models
     pub async fn query(&self, prompt: &str) -> Result<FusedResponse, ClientError> {
-        let start = std::time::Instant::now();
+        let Some(cache) = &self.cache else {
+            return self.query_uncached(prompt).await;
+        };
         
         // Check cache first
-        if let Some(cached) = self.cache.get(prompt).await {
+        if let Some(mut cached) = cache.get(prompt).await {
+            self.metrics.record_cache_hit();
+            self.metrics.record_request(true, 0, None);
+            cached.metrics.cache_hit = true;
             return Ok(cached);
         }
+        self.metrics.record_cache_miss();
+        
+        let response = self.query_uncached(prompt).await?;
+        
+        // Cache the response
+        cache.set(prompt, response.clone()).await;
+        
+        Ok(response)
+    }
+    
+    /// Execute orchestrated query without reading or populating the response cache
+    pub async fn query_uncached(&self, prompt: &str) -> Result<FusedResponse, ClientError> {
+        let (response, _) = self.query_verbose(prompt).await?;
+        Ok(response)
+    }
+    
+    /// Execute orchestrated query and also return every model's raw result
+    ///
+    /// The raw results are `(model, result)` pairs, including failures, in the order the
+    /// strategy received them. Like `query_uncached`, this bypasses the response cache.
+    pub async fn query_verbose(
+        &self,
+        prompt: &str,
+    ) -> Result<(FusedResponse, Vec<(String, Result<String, ClientError>)>), ClientError> {
+        let (response, results, _) = self.query_with_results(prompt).await?;
+        let raw = results
+            .into_iter()
+            .map(|(model, result, _)| (model, result))
+            .collect();
+        Ok((response, raw))
+    }
+    
+    /// Execute orchestrated query and return an audit report of it
+    ///
+    /// Like `query_uncached`, this bypasses the response cache.
+    pub async fn query_report(&self, prompt: &str) -> Result<OrchestrationReport, ClientError> {
+        let timestamp = if self.deterministic {
+            0
+        } else {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs())
+        };
+        let (response, results, strategy) = self.query_with_results(prompt).await?;
+        let failures = results
+            .into_iter()
+            .filter_map(|(model, result, _)| {
+                let error = result.err()?;
+                Some(ModelFailure { model, error: error.to_string() })
+            })
+            .collect();
+        
+        Ok(OrchestrationReport {
+            timestamp,
+            prompt: prompt.to_string(),
+            strategy,
+            response,
+            failures,
+        })
+    }
+    
+    /// Send `prompt` to every model once and compare latency, tokens, and cost
+    ///
+    /// Models are queried concurrently, bypassing the cache and strategy, and the
+    /// results are sorted by `sort_by` with failed models last.
+    pub async fn benchmark(&self, prompt: &str, sort_by: BenchmarkSort) -> Vec<BenchmarkResult> {
+        let futures = self.eligible_clients().map(|client| async move {
+            let start = Instant::now();
+            let result = client.send_prompt_with_metadata(prompt).await;
+            let latency_ms = self.elapsed_ms(start);
+            
+            let (total_tokens, estimated_cost, error) = match result {
+                Ok(response) => {
+                    let metadata = &response.metadata;
+                    let prompt_tokens = metadata.prompt_tokens.unwrap_or(estimate_tokens(prompt));
+                    let completion_tokens =
+                        metadata.completion_tokens.unwrap_or(estimate_tokens(&response.content));
+                    let total_tokens = metadata.total_tokens.unwrap_or(prompt_tokens + completion_tokens);
+                    let cost = self.rates(client.model()).cost(prompt_tokens, completion_tokens);
+                    (total_tokens, cost as f32, None)
+                }
+                Err(error) => (0, 0.0, Some(error.to_string())),
+            };
+            BenchmarkResult {
+                provider: client.name().to_string(),
+                model: client.model().to_string(),
+                latency_ms,
+                total_tokens,
+                estimated_cost,
+                error,
+            }
+        });
+        let mut results = join_all(futures).await;
+        
+        results.sort_by(|a, b| {
+            let by_key = match sort_by {
+                BenchmarkSort::Latency => a.latency_ms.cmp(&b.latency_ms),
+                BenchmarkSort::Tokens => a.total_tokens.cmp(&b.total_tokens),
+                BenchmarkSort::Cost => a.estimated_cost.total_cmp(&b.estimated_cost),
+            };
+            a.error.is_some().cmp(&b.error.is_some()).then(by_key)
+        });
+        results
+    }
+    
+    /// Query every model and fuse the results, returning the raw results and the
+    /// strategy that was used
+    async fn query_with_results(
+        &self,
+        prompt: &str,
+    ) -> Result<(FusedResponse, Vec<ModelResult>, OrchestrationStrategy), ClientError> {
+        let start = Instant::now();
         
-        // Analyze prompt to determine best strategy
-        let task_type = self.analyze_prompt(prompt);
-        let selected_strategy = self.select_strategy(&task_type);
+        // Analyze prompt to determine best strategy, unless one was explicitly configured
+        let task_type = self.task_type(prompt);
+        let selected_strategy = self.resolve_strategy(&task_type);
+        
+        let results = match selected_strategy {
+            OrchestrationStrategy::FastestFirst => self.race_responses(prompt).await,
+            OrchestrationStrategy::StreamingConsensus => {
+                let (response, results) = self.execute_streaming_consensus(prompt).await?;
+                let latency = self.elapsed_ms(start);
+                self.metrics.record_request(true, latency, Some(response.metrics.tokens_saved));
+                return Ok((response, results, selected_strategy));
+            }
+            _ => self.gather_responses(prompt).await,
+        };
         
-        // Execute based on strategy
+        // Fuse based on strategy
         let response = match selected_strategy {
             OrchestrationStrategy::Parallel => {
-                self.execute_parallel(prompt).await?
+                self.execute_parallel(prompt, &results)?
             }
             OrchestrationStrategy::Sequential => {
-                self.execute_sequential(prompt).await?
+                self.execute_sequential(prompt, &results)?
             }
             OrchestrationStrategy::Specialized => {
-                self.execute_specialized(prompt, &task_type).await?
+                self.execute_specialized(prompt, &task_type, &results)?
             }
             OrchestrationStrategy::Consensus => {
-                self.execute_consensus(prompt).await?
+                self.execute_consensus(prompt, &results)?
             }
             OrchestrationStrategy::WeightedFusion => {
-                self.execute_weighted_fusion(prompt).await?
+                self.execute_weighted_fusion(prompt, &results)?
             }
             OrchestrationStrategy::Tournament => {
-                self.execute_tournament(prompt).await?
+                self.execute_tournament(prompt, &results)?
             }
             OrchestrationStrategy::Adaptive => {
-                self.execute_adaptive(prompt, &task_type).await?
+                self.execute_adaptive(prompt, &task_type, &results)?
+            }
+            OrchestrationStrategy::CheapestSuccessful => {
+                self.execute_cheapest_successful(prompt, &results)?
+            }
+            OrchestrationStrategy::FastestFirst => {
+                self.execute_fastest_first(prompt, &results)?
+            }
+            OrchestrationStrategy::StreamingConsensus => {
+                unreachable!("streaming consensus returns before results are gathered")
             }
         };
         
         // Record metrics
-        let latency = start.elapsed().as_millis() as u64;
+        let latency = self.elapsed_ms(start);
         self.metrics.record_request(true, latency, Some(response.metrics.tokens_saved));
         
-        // Cache the response
-        self.cache.set(prompt, response.clone()).await;
+        Ok((response, results, selected_strategy))
+    }
+    
+    /// Send a conversation to every model and fuse the replies
+    ///
+    /// With auto-compaction enabled, the conversation is first checked against the
+    /// smallest context window among the participating models (as registered in the
+    /// capability table). Once its estimated size crosses the configured threshold, the
+    /// older turns are summarized by the first client and replaced, in place, by that
+    /// summary appended to the system message. The most recent messages are kept as-is.
+    pub async fn query_conversation(
+        &self,
+        conversation: &mut Conversation,
+    ) -> Result<FusedResponse, ClientError> {
+        let start = Instant::now();
+        
+        if let Some(config) = &self.compaction {
+            self.compact_conversation(conversation, config).await?;
+        }
+        
+        let futures = self.eligible_clients().map(|client| {
+            let client = client.clone();
+            let conversation = &*conversation;
+            async move {
+                let start = Instant::now();
+                let result = client.send_conversation(conversation).await;
+                let latency = self.elapsed_ms(start);
+                (client.model().to_string(), result, latency)
+            }
+        });
+        let results = join_all(futures).await;
+        let prompt = conversation
+            .messages
+            .iter()
+            .rev()
+            .find(|message| message.role == "user")
+            .map_or("", |message| message.content.as_str());
+        let response = self.fuse_responses(prompt, &results)?;
+        
+        let latency = self.elapsed_ms(start);
+        self.metrics.record_request(true, latency, None);
         
         Ok(response)
     }
     
-    /// Execute parallel strategy
-    async fn execute_parallel(&self, prompt: &str) -> Result<FusedResponse, ClientError> {
-        let futures = self.clients.iter().map(|client| {
-            let client = client.clone();
+    /// Execute orchestrated query and stream the result
+    ///
+    /// Only the `Parallel` and `StreamingConsensus` strategies stream for real: every
+    /// client is asked to stream, and `Parallel` forwards the first one to produce a
+    /// chunk live, while `StreamingConsensus` forwards the first one to agree with
+    /// another model, starting with the text it had so far. All other strategies need every
+    /// response before they can fuse or pick a winner, so they are buffered: an empty
+    /// unfinished chunk is sent immediately to signal work in progress, followed by the
+    /// complete result as a single finished chunk.
+    ///
+    /// `Tournament` is one of the buffered strategies. Responses are scored only once
+    /// they are complete, so every model's full response is held in memory until the
+    /// winner is chosen, and by then there is nothing left to stream.
+    ///
+    /// The final chunk's `metadata.orchestration` carries the strategy, the models whose
+    /// streams were used, and `OrchestrationMetrics`.
+    pub async fn query_streaming(
+        &self,
+        prompt: &str,
+    ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
+        let task_type = self.task_type(prompt);
+        match self.resolve_strategy(&task_type) {
+            OrchestrationStrategy::Parallel => self.stream_first_responder(prompt).await,
+            OrchestrationStrategy::StreamingConsensus => self.stream_consensus(prompt).await,
+            strategy => {
+                let prompt = prompt.to_string();
+                let placeholder = StreamChunk {
+                    content: String::new(),
+                    finished: false,
+                    metadata: None,
+                };
+                let fused = stream::once(async move {
+                    let response = self.query(&prompt).await?;
+                    let models: Vec<&str> = response
+                        .contributions
+                        .iter()
+                        .map(|c| c.model.as_str())
+                        .collect();
+                    let metadata = orchestration_metadata(&strategy, &models, &response.metrics);
+                    Ok(StreamChunk {
+                        content: response.content,
+                        finished: true,
+                        metadata: Some(metadata),
+                    })
+                });
+                Ok(Box::pin(stream::once(ready(Ok(placeholder))).chain(fused)))
+            }
+        }
+    }
+    
+    /// Stream from whichever client produces its first chunk soonest
+    async fn stream_first_responder(
+        &self,
+        prompt: &str,
+    ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
+        let start = Instant::now();
+        let mut pending: FuturesUnordered<_> = self
+            .eligible_clients()
+            .map(|client| async move {
+                let mut stream = buffered_as_stream(client.as_ref().as_ref(), prompt).await?;
+                match stream.next().await {
+                    Some(Ok(first)) => Ok((client.model().to_string(), first, stream)),
+                    Some(Err(e)) => Err(e),
+                    None => Err(ClientError::config("Stream ended without a response", None)),
+                }
+            })
+            .collect();
+        
+        let mut last_error = None;
+        let mut winner = None;
+        while let Some(result) = pending.next().await {
+            match result {
+                Ok(responder) => {
+                    winner = Some(responder);
+                    break;
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+        let Some((model, first, rest)) = winner else {
+            return Err(last_error.unwrap_or_else(|| ClientError::config("No successful responses", None)));
+        };
+        
+        let models = vec![model];
+        Ok(self.forward_with_stats(OrchestrationStrategy::Parallel, prompt, models, first, rest, start))
+    }
+    
+    /// Stream the answer of the first model to agree with another, cancelling the rest
+    async fn stream_consensus(
+        &self,
+        prompt: &str,
+    ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
+        let start = Instant::now();
+        match self.race_to_agreement(prompt).await {
+            ConsensusRace::Agreed { leader, partner, leader_text, rest, .. } => {
+                let first = StreamChunk {
+                    content: leader_text,
+                    finished: false,
+                    metadata: None,
+                };
+                let models = vec![leader, partner];
+                Ok(self.forward_with_stats(OrchestrationStrategy::StreamingConsensus, prompt, models, first, rest, start))
+            }
+            ConsensusRace::Exhausted(results) => {
+                let response = self.execute_consensus(prompt, &results)?;
+                let models: Vec<&str> = response
+                    .contributions
+                    .iter()
+                    .map(|c| c.model.as_str())
+                    .collect();
+                let metadata = orchestration_metadata(
+                    &OrchestrationStrategy::StreamingConsensus,
+                    &models,
+                    &response.metrics,
+                );
+                let chunk = StreamChunk {
+                    content: response.content,
+                    finished: true,
+                    metadata: Some(metadata),
+                };
+                Ok(Box::pin(stream::once(ready(Ok(chunk)))))
+            }
+        }
+    }
+    
+    /// Forward the stream of `models[0]`, attaching orchestration stats to its final chunk
+    ///
+    /// `models` lists every model whose stream was consumed to produce the response,
+    /// starting with the one being forwarded.
+    fn forward_with_stats<'a>(
+        &'a self,
+        strategy: OrchestrationStrategy,
+        prompt: &str,
+        models: Vec<String>,
+        first: StreamChunk,
+        rest: BoxStream<'a, Result<StreamChunk, ClientError>>,
+        start: Instant,
+    ) -> BoxStream<'a, Result<StreamChunk, ClientError>> {
+        let content = Arc::new(Mutex::new(String::new()));
+        let saw_final = Arc::new(AtomicBool::new(false));
+        let finish = {
+            let content = Arc::clone(&content);
             let prompt = prompt.to_string();
-            async move {
-                let start = std::time::Instant::now();
-                let result = client.send_prompt(&prompt).await;
-                let latency = start.elapsed().as_millis() as u64;
-                (client.name().to_string(), result, latency)
+            move || {
+                let content = content.lock().unwrap().clone();
+                let latency = self.elapsed_ms(start);
+                let metrics = OrchestrationMetrics {
+                    total_latency_ms: latency,
+                    models_used: models.len(),
+                    cache_hit: false,
+                    tokens_saved: 0,
+                    cost_estimate: self.estimate_cost(&prompt, &[(models[0].clone(), Ok(content), latency)]),
+                };
+                let models: Vec<&str> = models.iter().map(String::as_str).collect();
+                orchestration_metadata(&strategy, &models, &metrics)
+            }
+        };
+        
+        let forwarded = stream::once(ready(Ok(first))).chain(rest).map({
+            let finish = finish.clone();
+            let saw_final = Arc::clone(&saw_final);
+            move |result| {
+                result.map(|mut chunk| {
+                    content.lock().unwrap().push_str(&chunk.content);
+                    if chunk.finished {
+                        saw_final.store(true, Ordering::Relaxed);
+                        let orchestration = finish().orchestration;
+                        chunk.metadata.get_or_insert_with(ResponseMetadata::default).orchestration = orchestration;
+                    }
+                    chunk
+                })
             }
         });
         
-        let results = join_all(futures).await;
-        self.fuse_responses(results)
+        // Close the stream with the orchestration stats if the client never sent a final chunk
+        let tail = stream::once(async move {
+            if saw_final.load(Ordering::Relaxed) {
+                None
+            } else {
+                Some(Ok(StreamChunk {
+                    content: String::new(),
+                    finished: true,
+                    metadata: Some(finish()),
+                }))
+            }
+        })
+        .filter_map(ready);
+        
+        Box::pin(forwarded.chain(tail))
     }
     
-    /// Execute weighted fusion strategy with confidence scoring
-    async fn execute_weighted_fusion(&self, prompt: &str) -> Result<FusedResponse, ClientError> {
-        let results = self.gather_responses(prompt).await;
+    /// Stream from every client until two responses agree on their common prefix
+    ///
+    /// Once they do, every other stream is dropped, cancelling its request, and the
+    /// stream whose chunk settled the agreement is handed back to be read to the end.
+    async fn race_to_agreement(&self, prompt: &str) -> ConsensusRace<'_> {
+        let start = Instant::now();
+        let clients: Vec<_> = self.eligible_clients().collect();
+        let models: Vec<String> = clients.iter().map(|client| client.model().to_string()).collect();
+        
+        let mut pending: FuturesUnordered<BoxFuture<'_, StreamStep<'_>>> = clients
+            .iter()
+            .enumerate()
+            .map(|(index, &client)| {
+                let client = client.as_ref().as_ref();
+                async move {
+                    match buffered_as_stream(client, prompt).await {
+                        Ok(stream) => {
+                            let (chunk, rest) = stream.into_future().await;
+                            (index, chunk, rest)
+                        }
+                        Err(e) => (index, Some(Err(e)), stream::empty().boxed()),
+                    }
+                }
+                .boxed()
+            })
+            .collect();
+        
+        let mut texts = vec![String::new(); clients.len()];
+        let mut results: Vec<Option<ModelResult>> = clients.iter().map(|_| None).collect();
+        while let Some((index, chunk, rest)) = pending.next().await {
+            let rest = match chunk {
+                Some(Ok(chunk)) => {
+                    texts[index].push_str(&chunk.content);
+                    (!chunk.finished).then_some(rest)
+                }
+                Some(Err(e)) => {
+                    results[index] = Some((models[index].clone(), Err(e), self.elapsed_ms(start)));
+                    continue;
+                }
+                None => None,
+            };
+            if rest.is_none() {
+                results[index] = Some((models[index].clone(), Ok(texts[index].clone()), self.elapsed_ms(start)));
+            }
+            
+            let complete = |i: usize| matches!(results[i], Some((_, Ok(_), _)));
+            let agreement = (0..clients.len())
+                .filter(|&other| other != index && !matches!(results[other], Some((_, Err(_), _))))
+                .find_map(|other| {
+                    self.prefix_agreement(&texts[index], complete(index), &texts[other], complete(other))
+                        .map(|similarity| (other, similarity))
+                });
+            if let Some((partner, similarity)) = agreement {
+                return ConsensusRace::Agreed {
+                    leader: models[index].clone(),
+                    partner: models[partner].clone(),
+                    similarity,
+                    leader_text: std::mem::take(&mut texts[index]),
+                    partner_text: std::mem::take(&mut texts[partner]),
+                    rest: rest.unwrap_or_else(|| stream::empty().boxed()),
+                    agreed_after_ms: self.elapsed_ms(start),
+                };
+            }
+            
+            if let Some(rest) = rest {
+                pending.push(
+                    async move {
+                        let (chunk, rest) = rest.into_future().await;
+                        (index, chunk, rest)
+                    }
+                    .boxed(),
+                );
+            }
+        }
+        
+        ConsensusRace::Exhausted(results.into_iter().flatten().collect())
+    }
+    
+    /// Similarity of two partial responses over their common prefix, if they agree
+    ///
+    /// Responses are compared by the Jaccard similarity of the words in their common
+    /// prefix once it reaches `CONSENSUS_PREFIX_WORDS` words, or sooner if the shorter
+    /// response is complete. The last word of an unfinished response may be cut off,
+    /// so it is left out.
+    fn prefix_agreement(&self, a: &str, a_complete: bool, b: &str, b_complete: bool) -> Option<f64> {
+        let words = |text: &str, complete: bool| {
+            let mut words: Vec<String> = text
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .map(str::to_lowercase)
+                .collect();
+            if !complete && text.ends_with(char::is_alphanumeric) {
+                words.pop();
+            }
+            words
+        };
+        let (a_words, b_words) = (words(a, a_complete), words(b, b_complete));
+        let len = a_words.len().min(b_words.len());
+        let shorter_complete = (a_complete && a_words.len() == len) || (b_complete && b_words.len() == len);
+        if len == 0 || (len < CONSENSUS_PREFIX_WORDS && !shorter_complete) {
+            return None;
+        }
+        
+        let prefix = |words: &[String]| words[..len].iter().cloned().collect::<HashSet<_>>();
+        let similarity = jaccard_similarity(&prefix(&a_words), &prefix(&b_words));
+        (similarity >= self.consensus_threshold).then_some(similarity)
+    }
+    
+    /// Run the streaming consensus race to completion, returning the fused response and raw results
+    async fn execute_streaming_consensus(
+        &self,
+        prompt: &str,
+    ) -> Result<(FusedResponse, Vec<ModelResult>), ClientError> {
+        let start = Instant::now();
+        let (leader, partner, similarity, mut content, partner_text, mut rest, agreed_after_ms) =
+            match self.race_to_agreement(prompt).await {
+                ConsensusRace::Agreed {
+                    leader,
+                    partner,
+                    similarity,
+                    leader_text,
+                    partner_text,
+                    rest,
+                    agreed_after_ms,
+                } => (leader, partner, similarity, leader_text, partner_text, rest, agreed_after_ms),
+                ConsensusRace::Exhausted(results) => {
+                    let response = self.execute_consensus(prompt, &results)?;
+                    return Ok((response, results));
+                }
+            };
+        while let Some(chunk) = rest.next().await {
+            content.push_str(&chunk?.content);
+        }
+        let latency = self.elapsed_ms(start);
+        
+        let results: Vec<ModelResult> = vec![
+            (leader.clone(), Ok(content.clone()), latency),
+            (partner.clone(), Ok(partner_text), agreed_after_ms),
+        ];
+        let contributions: Vec<ModelContribution> = results
+            .iter()
+            .filter_map(|(model, response, latency)| {
+                let response = response.as_ref().ok()?;
+                Some(ModelContribution {
+                    model: model.clone(),
+                    response: response.clone(),
+                    confidence: self.calculate_confidence(response, prompt),
+                    weight: 0.5,
+                    latency_ms: *latency,
+                })
+            })
+            .collect();
         
+        let response = FusedResponse {
+            content,
+            confidence: similarity,
+            consensus: ConsensusAnalysis {
+                agreement_score: similarity,
+                key_points: vec![format!(
+                    "{} and {} agreed after {}ms; the other models were cancelled",
+                    leader, partner, agreed_after_ms
+                )],
+                disagreements: vec![],
+                fact_verification: vec![],
+            },
+            metrics: OrchestrationMetrics {
+                total_latency_ms: latency,
+                models_used: contributions.len(),
+                cache_hit: false,
+                tokens_saved: 0,
+                cost_estimate: self.estimate_cost(prompt, &results),
+            },
+            contributions,
+        };
+        Ok((response, results))
+    }
+    
+    /// Execute parallel strategy
+    fn execute_parallel(&self, prompt: &str, results: &[ModelResult]) -> Result<FusedResponse, ClientError> {
+        self.fuse_responses(prompt, results)
+    }
+    
+    /// Execute weighted fusion strategy with confidence scoring
+    fn execute_weighted_fusion(&self, prompt: &str, results: &[ModelResult]) -> Result<FusedResponse, ClientError> {
         // Calculate confidence scores for each response
         let mut contributions = Vec::new();
-        for (model, response, latency) in &results {
+        for (model, response, latency) in results {
             if let Ok(content) = response {
                 let confidence = self.calculate_confidence(content, prompt);
                 let weight = self.calculate_weight(model, confidence, *latency);
@@ -238,18 +1093,16 @@ impl AiOrchestrator {
                 models_used: results.len(),
                 cache_hit: false,
                 tokens_saved: 0,
-                cost_estimate: self.estimate_cost(&results),
+                cost_estimate: self.estimate_cost(prompt, results),
             },
         })
     }
     
     /// Tournament-style selection of best response
-    async fn execute_tournament(&self, prompt: &str) -> Result<FusedResponse, ClientError> {
-        let results = self.gather_responses(prompt).await;
-        
+    fn execute_tournament(&self, prompt: &str, results: &[ModelResult]) -> Result<FusedResponse, ClientError> {
         // Score each response
         let mut scored_responses = Vec::new();
-        for (model, response, latency) in &results {
+        for (model, response, latency) in results {
             if let Ok(content) = response {
                 let score = self.score_response(content, prompt);
                 scored_responses.push((model.clone(), content.clone(), score, *latency));
@@ -283,7 +1136,7 @@ impl AiOrchestrator {
                     models_used: results.len(),
                     cache_hit: false,
                     tokens_saved: 0,
-                    cost_estimate: self.estimate_cost(&results),
+                    cost_estimate: self.estimate_cost(prompt, results),
                 },
             })
         } else {
@@ -291,33 +1144,157 @@ impl AiOrchestrator {
         }
     }
     
-    // Helper methods
-    
-    fn analyze_prompt(&self, prompt: &str) -> TaskType {
-        // Analyze prompt to determine task type
-        let prompt_lower = prompt.to_lowercase();
+    /// Select the successful response that cost the least, priced by `rates`
+    fn execute_cheapest_successful(&self, prompt: &str, results: &[ModelResult]) -> Result<FusedResponse, ClientError> {
+        let cheapest = results
+            .iter()
+            .filter_map(|(model, response, latency)| {
+                response.as_ref().ok().map(|content| (model, content, *latency))
+            })
+            .min_by(|a, b| {
+                self.response_cost(a.0, prompt, a.1).total_cmp(&self.response_cost(b.0, prompt, b.1))
+            });
         
-        if prompt_lower.contains("code") || prompt_lower.contains("function") || prompt_lower.contains("implement") {
-            TaskType::Code
-        } else if prompt_lower.contains("creative") || prompt_lower.contains("story") || prompt_lower.contains("poem") {
-            TaskType::Creative
-        } else if prompt_lower.contains("analyze") || prompt_lower.contains("explain") {
-            TaskType::Analysis
-        } else if prompt_lower.contains("math") || prompt_lower.contains("calculate") {
-            TaskType::Mathematics
-        } else {
-            TaskType::General
-        }
-    }
-    
-    fn calculate_confidence(&self, response: &str, prompt: &str) -> f64 {
-        // Sophisticated confidence calculation
-        let mut confidence: f64 = 0.5;
+        let Some((winner_model, winner_content, _)) = cheapest else {
+            return Err(ClientError::config("No successful responses", None));
+        };
         
-        // Check response length appropriateness
-        let expected_length = prompt.len() * 10;
-        let actual_length = response.len();
-        if actual_length > expected_length / 2 && actual_length < expected_length * 3 {
+        let contributions = results
+            .iter()
+            .filter_map(|(model, response, latency)| {
+                response.as_ref().ok().map(|content| ModelContribution {
+                    model: model.clone(),
+                    response: content.clone(),
+                    confidence: self.calculate_confidence(content, prompt),
+                    weight: if model == winner_model { 1.0 } else { 0.0 },
+                    latency_ms: *latency,
+                })
+            })
+            .collect::<Vec<_>>();
+        
+        Ok(FusedResponse {
+            content: winner_content.clone(),
+            confidence: self.calculate_confidence(winner_content, prompt),
+            contributions,
+            consensus: ConsensusAnalysis {
+                agreement_score: 0.0,
+                key_points: vec![format!("Cheapest successful model: {}", winner_model)],
+                disagreements: vec![],
+                fact_verification: vec![],
+            },
+            metrics: OrchestrationMetrics {
+                total_latency_ms: results.iter().map(|(_, _, l)| l).max().copied().unwrap_or(0),
+                models_used: results.len(),
+                cache_hit: false,
+                tokens_saved: 0,
+                cost_estimate: self.estimate_cost(prompt, results),
+            },
+        })
+    }
+    
+    /// Return the first successful response among results raced by `race_responses`
+    ///
+    /// Every model appears in `contributions` in the order it finished, with its
+    /// latency, so slower and failed models can be compared with the winner; only the
+    /// winner has a non-zero weight and failed models have an empty response.
+    /// `total_latency_ms` reports the winner's time to respond.
+    fn execute_fastest_first(&self, prompt: &str, results: &[ModelResult]) -> Result<FusedResponse, ClientError> {
+        let Some((winner_model, winner_content, winner_latency)) = results
+            .iter()
+            .find_map(|(model, result, latency)| result.as_ref().ok().map(|content| (model, content, *latency)))
+        else {
+            return Err(ClientError::config("No successful responses", None));
+        };
+        
+        let contributions = results
+            .iter()
+            .map(|(model, result, latency)| {
+                let (response, confidence) = match result {
+                    Ok(content) => (content.clone(), self.calculate_confidence(content, prompt)),
+                    Err(_) => (String::new(), 0.0),
+                };
+                let weight = if model == winner_model && result.is_ok() { 1.0 } else { 0.0 };
+                ModelContribution {
+                    model: model.clone(),
+                    response,
+                    confidence,
+                    weight,
+                    latency_ms: *latency,
+                }
+            })
+            .collect();
+        
+        Ok(FusedResponse {
+            confidence: self.calculate_confidence(winner_content, prompt),
+            content: winner_content.clone(),
+            contributions,
+            consensus: ConsensusAnalysis {
+                agreement_score: 0.0,
+                key_points: vec![format!("Fastest model: {}", winner_model)],
+                disagreements: vec![],
+                fact_verification: vec![],
+            },
+            metrics: OrchestrationMetrics {
+                total_latency_ms: winner_latency,
+                models_used: results.len(),
+                cache_hit: false,
+                tokens_saved: 0,
+                cost_estimate: self.estimate_cost(prompt, results),
+            },
+        })
+    }
+    
+    // Helper methods
+    
+    fn cost_per_1k_tokens(&self, model: &str) -> f32 {
+        self.capability(model)
+            .map(|caps| caps.cost_per_1k_tokens)
+            .unwrap_or(DEFAULT_COST_PER_1K_TOKENS)
+    }
+    
+    /// Milliseconds since `start`, or 0 for a deterministic orchestrator
+    fn elapsed_ms(&self, start: Instant) -> u64 {
+        if self.deterministic {
+            0
+        } else {
+            start.elapsed().as_millis() as u64
+        }
+    }
+    
+    /// Task type of `prompt`, always general for a deterministic orchestrator
+    fn task_type(&self, prompt: &str) -> TaskType {
+        if self.deterministic {
+            TaskType::General
+        } else {
+            self.analyze_prompt(prompt)
+        }
+    }
+    
+    fn analyze_prompt(&self, prompt: &str) -> TaskType {
+        // Analyze prompt to determine task type
+        let prompt_lower = prompt.to_lowercase();
+        
+        if prompt_lower.contains("code") || prompt_lower.contains("function") || prompt_lower.contains("implement") {
+            TaskType::Code
+        } else if prompt_lower.contains("creative") || prompt_lower.contains("story") || prompt_lower.contains("poem") {
+            TaskType::Creative
+        } else if prompt_lower.contains("analyze") || prompt_lower.contains("explain") {
+            TaskType::Analysis
+        } else if prompt_lower.contains("math") || prompt_lower.contains("calculate") {
+            TaskType::Mathematics
+        } else {
+            TaskType::General
+        }
+    }
+    
+    fn calculate_confidence(&self, response: &str, prompt: &str) -> f64 {
+        // Sophisticated confidence calculation
+        let mut confidence: f64 = 0.5;
+        
+        // Check response length appropriateness
+        let expected_length = prompt.len() * 10;
+        let actual_length = response.len();
+        if actual_length > expected_length / 2 && actual_length < expected_length * 3 {
             confidence += 0.1;
         }
         
@@ -347,49 +1324,82 @@ impl AiOrchestrator {
         let latency_factor = 1.0 / (1.0 + (latency as f64 / 1000.0));
         
         // Adjust for model capabilities
-        let capability_factor = match model {
-            "gpt-4" => 1.2,
-            "claude-3-opus" => 1.15,
-            "gemini-1.5-pro" => 1.1,
-            _ => 1.0,
-        };
+        let capability_factor = self
+            .capability(model)
+            .map(|caps| caps.quality_factor)
+            .unwrap_or(1.0);
         
         (base_weight * latency_factor * capability_factor).min(1.0)
     }
     
-    async fn gather_responses(&self, prompt: &str) -> Vec<(String, Result<String, ClientError>, u64)> {
-        let futures = self.clients.iter().map(|client| {
-            let client = client.clone();
-            let prompt = prompt.to_string();
-            async move {
-                let start = std::time::Instant::now();
-                let result = client.send_prompt(&prompt).await;
-                let latency = start.elapsed().as_millis() as u64;
-                (client.name().to_string(), result, latency)
-            }
-        });
-        
-        join_all(futures).await
+    /// Send the prompt to every model, returning results in client order
+    async fn gather_responses(&self, prompt: &str) -> Vec<ModelResult> {
+        join_all(self.timed_prompts(prompt)).await
+    }
+    
+    /// Send the prompt to every model, returning results in the order they complete
+    ///
+    /// Deterministic orchestrators return them in client order instead.
+    async fn race_responses(&self, prompt: &str) -> Vec<ModelResult> {
+        if self.deterministic {
+            return self.gather_responses(prompt).await;
+        }
+        self.timed_prompts(prompt).collect::<FuturesUnordered<_>>().collect().await
+    }
+    
+    /// One future per client that sends the prompt and measures its latency
+    fn timed_prompts<'a>(
+        &'a self,
+        prompt: &'a str,
+    ) -> impl Iterator<Item = impl Future<Output = ModelResult> + 'a> + 'a {
+        self.eligible_clients().map(move |client| async move {
+            let start = Instant::now();
+            let result = client.send_prompt(prompt).await;
+            let latency = self.elapsed_ms(start);
+            (client.model().to_string(), result, latency)
+        })
     }
     
+    /// Merge contributions into a sentence-level union of their points
+    ///
+    /// This is a lexical heuristic rather than semantic NLP: responses are split into
+    /// sentences, sentences whose word-token Jaccard similarity reaches
+    /// `SENTENCE_DEDUP_THRESHOLD` are collapsed into one point (keeping the first
+    /// wording seen), and the unique points are joined in order of the summed weight
+    /// of the models that made them.
     fn weighted_merge(&self, contributions: &[ModelContribution]) -> String {
-        // For now, return the highest weighted response
-        // In a real implementation, this would intelligently merge content
-        contributions
-            .iter()
-            .max_by(|a, b| a.weight.partial_cmp(&b.weight).unwrap())
-            .map(|c| c.response.clone())
-            .unwrap_or_default()
+        merge_points(contributions)
+            .into_iter()
+            .map(|point| point.text)
+            .collect::<Vec<_>>()
+            .join(" ")
     }
     
     fn analyze_consensus(&self, contributions: &[ModelContribution]) -> ConsensusAnalysis {
         // Analyze agreement between models
         let avg_confidence: f64 = contributions.iter().map(|c| c.confidence).sum::<f64>() / contributions.len() as f64;
         
+        // Points every model made are key points; points only one model made are disagreements
+        let points = merge_points(contributions);
+        let key_points = points
+            .iter()
+            .filter(|point| point.models.len() == contributions.len())
+            .map(|point| point.text.clone())
+            .collect();
+        let disagreements = if contributions.len() > 1 {
+            points
+                .iter()
+                .filter(|point| point.models.len() == 1)
+                .map(|point| format!("{}: {}", point.models[0], point.text))
+                .collect()
+        } else {
+            vec![]
+        };
+        
         ConsensusAnalysis {
             agreement_score: avg_confidence,
-            key_points: vec!["Models processed query successfully".to_string()],
-            disagreements: vec![],
+            key_points,
+            disagreements,
             fact_verification: vec![],
         }
     }
@@ -435,26 +1445,114 @@ impl AiOrchestrator {
         score.min(100.0)
     }
     
-    fn estimate_cost(&self, results: &[(String, Result<String, ClientError>, u64)]) -> f32 {
-        // Estimate cost based on tokens and model pricing
-        let mut total_cost = 0.0;
+    /// Estimated cost of the successful results, at about four characters per token
+    ///
+    /// Each response is priced as completion tokens and the prompt, once per response,
+    /// as prompt tokens, at the model's rates in the pricing table.
+    fn estimate_cost(&self, prompt: &str, results: &[ModelResult]) -> f32 {
+        results
+            .iter()
+            .filter_map(|(model, result, _)| {
+                let response = result.as_ref().ok()?;
+                Some(self.response_cost(model, prompt, response))
+            })
+            .sum::<f64>() as f32
+    }
+    
+    /// Estimated cost of `model` answering `prompt` with `response`
+    fn response_cost(&self, model: &str, prompt: &str, response: &str) -> f64 {
+        self.rates(model).cost(estimate_tokens(prompt), estimate_tokens(response))
+    }
+    
+    /// Rates for `model` from the pricing table, else a flat rate from its capabilities
+    fn rates(&self, model: &str) -> ModelRates {
+        self.pricing.rates(model).unwrap_or_else(|| {
+            let rate = f64::from(self.cost_per_1k_tokens(model));
+            ModelRates::new(rate, rate)
+        })
+    }
+    
+    /// Clients that have every required capability, in registration order
+    fn eligible_clients(&self) -> impl Iterator<Item = &Arc<Box<dyn AiClient>>> + '_ {
+        self.clients.iter().filter(|client| {
+            self.required_capabilities.iter().all(|&capability| {
+                match self.capabilities.get(client.model()) {
+                    Some(caps) => caps.supports(capability),
+                    None => capability == Capability::Streaming && client.supports_streaming(),
+                }
+            })
+        })
+    }
+    
+    /// Smallest context window among clients with registered capabilities
+    fn smallest_context_window(&self) -> Option<usize> {
+        self.eligible_clients()
+            .filter_map(|client| self.capabilities.get(client.model()))
+            .map(|caps| caps.max_context_length)
+            .min()
+    }
+    
+    /// Summarize old turns if the conversation nears the smallest context window
+    ///
+    /// Returns whether the conversation was compacted.
+    async fn compact_conversation(
+        &self,
+        conversation: &mut Conversation,
+        config: &CompactionConfig,
+    ) -> Result<bool, ClientError> {
+        let Some(window) = self.smallest_context_window() else {
+            return Ok(false);
+        };
+        let tokens = conversation.estimated_tokens();
+        if (tokens as f64) < window as f64 * config.threshold {
+            return Ok(false);
+        }
         
-        for (model, result, _) in results {
-            if let Ok(response) = result {
-                let tokens = (response.len() / 4) as f32; // Rough estimate
-                let rate = match model.as_str() {
-                    "gpt-4" => 0.03,
-                    "claude-3-opus" => 0.025,
-                    "gemini-1.5-pro" => 0.02,
-                    _ => 0.01,
-                };
-                total_cost += (tokens / 1000.0) * rate;
-            }
+        // Keep the system message and a recent tail that starts on a user turn
+        let first = usize::from(conversation.messages.first().is_some_and(|m| m.role == "system"));
+        let mut split = conversation
+            .messages
+            .len()
+            .saturating_sub(config.keep_recent.max(1))
+            .max(first);
+        while split > first && conversation.messages[split].role != "user" {
+            split -= 1;
+        }
+        if split <= first {
+            return Ok(false);
+        }
+        
+        let client = self
+            .eligible_clients()
+            .next()
+            .ok_or_else(|| ClientError::config("No clients available for compaction", None))?;
+        let transcript = conversation.messages[first..split]
+            .iter()
+            .map(|m| format!("{}: {}", m.role, m.content))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let summary = client
+            .send_prompt(&format!(
+                "Summarize the following conversation, keeping every fact, decision and open \
+                 question needed to continue it:\n\n{}",
+                transcript
+            ))
+            .await?;
+        
+        let note = format!("Summary of the earlier conversation:\n{}", summary.trim());
+        conversation.messages.drain(first..split);
+        if first == 1 {
+            let system = &mut conversation.messages[0].content;
+            system.push_str("\n\n");
+            system.push_str(&note);
+        } else {
+            conversation.messages.insert(0, Message::system(note));
         }
         
-        total_cost
+        Ok(true)
     }
     
+    /// Built-in capability table, used until overridden via `with_capabilities`
     fn detect_capabilities() -> HashMap<String, ModelCapabilities> {
         let mut caps = HashMap::new();
         
@@ -467,6 +1565,7 @@ impl AiOrchestrator {
             supports_streaming: true,
             supports_vision: true,
             supports_function_calling: true,
+            quality_factor: 1.2,
         });
         
         caps.insert("claude-3-opus".to_string(), ModelCapabilities {
@@ -478,6 +1577,7 @@ impl AiOrchestrator {
             supports_streaming: true,
             supports_vision: true,
             supports_function_calling: false,
+            quality_factor: 1.15,
         });
         
         caps.insert("gemini-1.5-pro".to_string(), ModelCapabilities {
@@ -489,30 +1589,136 @@ impl AiOrchestrator {
             supports_streaming: false,
             supports_vision: true,
             supports_function_calling: true,
+            quality_factor: 1.1,
         });
         
         caps
     }
     
     // Stub implementations for other strategies
-    async fn execute_sequential(&self, prompt: &str) -> Result<FusedResponse, ClientError> {
-        self.execute_parallel(prompt).await
+    fn execute_sequential(&self, prompt: &str, results: &[ModelResult]) -> Result<FusedResponse, ClientError> {
+        self.execute_parallel(prompt, results)
     }
     
-    async fn execute_specialized(&self, prompt: &str, _task_type: &TaskType) -> Result<FusedResponse, ClientError> {
+    fn execute_specialized(&self, prompt: &str, _task_type: &TaskType, results: &[ModelResult]) -> Result<FusedResponse, ClientError> {
         // TODO: Route to specialized models based on task type
-        self.execute_parallel(prompt).await
+        self.execute_parallel(prompt, results)
     }
     
-    async fn execute_consensus(&self, prompt: &str) -> Result<FusedResponse, ClientError> {
-        self.execute_weighted_fusion(prompt).await
+    /// Majority voting: cluster responses by token similarity and return the largest cluster
+    ///
+    /// Responses are compared with Jaccard similarity over normalized word tokens. Each
+    /// response joins the first cluster whose founding response meets the consensus
+    /// threshold, otherwise it starts a new cluster. The representative answer is the
+    /// member most similar to the rest of the winning cluster.
+    fn execute_consensus(&self, prompt: &str, results: &[ModelResult]) -> Result<FusedResponse, ClientError> {
+        let successful: Vec<(&String, &String, u64)> = results
+            .iter()
+            .filter_map(|(model, response, latency)| {
+                response.as_ref().ok().map(|content| (model, content, *latency))
+            })
+            .collect();
+        
+        if successful.is_empty() {
+            return Err(ClientError::config("No successful responses", None));
+        }
+        
+        let tokens: Vec<HashSet<String>> = successful
+            .iter()
+            .map(|(_, content, _)| normalized_tokens(content))
+            .collect();
+        
+        let mut clusters: Vec<Vec<usize>> = Vec::new();
+        for i in 0..successful.len() {
+            match clusters
+                .iter_mut()
+                .find(|cluster| jaccard_similarity(&tokens[cluster[0]], &tokens[i]) >= self.consensus_threshold)
+            {
+                Some(cluster) => cluster.push(i),
+                None => clusters.push(vec![i]),
+            }
+        }
+        
+        // Earliest cluster wins ties so results stay deterministic
+        let winning = clusters
+            .iter()
+            .rev()
+            .max_by_key(|cluster| cluster.len())
+            .cloned()
+            .unwrap_or_default();
+        
+        let representative = winning
+            .iter()
+            .copied()
+            .max_by(|&a, &b| {
+                let score = |i: usize| -> f64 {
+                    winning.iter().map(|&j| jaccard_similarity(&tokens[i], &tokens[j])).sum()
+                };
+                score(a).total_cmp(&score(b)).then(b.cmp(&a))
+            })
+            .unwrap_or(0);
+        
+        let agreement_score = winning.len() as f64 / successful.len() as f64;
+        
+        let contributions = successful
+            .iter()
+            .enumerate()
+            .map(|(i, (model, content, latency))| ModelContribution {
+                model: model.to_string(),
+                response: content.to_string(),
+                confidence: self.calculate_confidence(content, prompt),
+                weight: if winning.contains(&i) { 1.0 / winning.len() as f64 } else { 0.0 },
+                latency_ms: *latency,
+            })
+            .collect();
+        
+        let disagreements = successful
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !winning.contains(i))
+            .map(|(_, (model, content, _))| format!("{}: {}", model, content))
+            .collect();
+        
+        let (winner_model, winner_content, _) = successful[representative];
+        
+        Ok(FusedResponse {
+            content: winner_content.clone(),
+            confidence: agreement_score,
+            contributions,
+            consensus: ConsensusAnalysis {
+                agreement_score,
+                key_points: vec![format!(
+                    "{} of {} models agreed (representative: {})",
+                    winning.len(),
+                    successful.len(),
+                    winner_model
+                )],
+                disagreements,
+                fact_verification: vec![],
+            },
+            metrics: OrchestrationMetrics {
+                total_latency_ms: results.iter().map(|(_, _, l)| l).max().copied().unwrap_or(0),
+                models_used: results.len(),
+                cache_hit: false,
+                tokens_saved: 0,
+                cost_estimate: self.estimate_cost(prompt, results),
+            },
+        })
     }
     
-    async fn execute_adaptive(&self, prompt: &str, task_type: &TaskType) -> Result<FusedResponse, ClientError> {
+    fn execute_adaptive(&self, prompt: &str, task_type: &TaskType, results: &[ModelResult]) -> Result<FusedResponse, ClientError> {
         match task_type {
-            TaskType::Code => self.execute_specialized(prompt, task_type).await,
-            TaskType::Creative => self.execute_tournament(prompt).await,
-            _ => self.execute_weighted_fusion(prompt).await,
+            TaskType::Code => self.execute_specialized(prompt, task_type, results),
+            TaskType::Creative => self.execute_tournament(prompt, results),
+            _ => self.execute_weighted_fusion(prompt, results),
+        }
+    }
+    
+    /// The configured strategy, or the one chosen from the task type when adaptive
+    fn resolve_strategy(&self, task_type: &TaskType) -> OrchestrationStrategy {
+        match &self.strategy {
+            OrchestrationStrategy::Adaptive => self.select_strategy(task_type),
+            strategy => strategy.clone(),
         }
     }
     
@@ -526,48 +1732,178 @@ impl AiOrchestrator {
         }
     }
     
-    fn fuse_responses(&self, results: Vec<(String, Result<String, ClientError>, u64)>) -> Result<FusedResponse, ClientError> {
-        let mut contributions = Vec::new();
-        
-        for (model, result, latency) in results {
-            if let Ok(response) = result {
-                contributions.push(ModelContribution {
-                    model: model.clone(),
-                    response: response.clone(),
-                    confidence: 0.8,
-                    weight: 1.0 / 3.0,
-                    latency_ms: latency,
-                });
-            }
-        }
+    /// Return the first successful response, weighting every successful model equally
+    ///
+    /// Latency is the slowest model's, since every response was awaited, and
+    /// confidence is the mean of the per-model confidence scores.
+    fn fuse_responses(&self, prompt: &str, results: &[ModelResult]) -> Result<FusedResponse, ClientError> {
+        let successful: Vec<(&String, &String, u64)> = results
+            .iter()
+            .filter_map(|(model, response, latency)| {
+                response.as_ref().ok().map(|content| (model, content, *latency))
+            })
+            .collect();
         
-        if contributions.is_empty() {
+        if successful.is_empty() {
             return Err(ClientError::config("No successful responses", None));
         }
         
-        let content = contributions[0].response.clone();
+        let weight = 1.0 / successful.len() as f64;
+        let contributions: Vec<ModelContribution> = successful
+            .iter()
+            .map(|(model, content, latency)| ModelContribution {
+                model: model.to_string(),
+                response: content.to_string(),
+                confidence: self.calculate_confidence(content, prompt),
+                weight,
+                latency_ms: *latency,
+            })
+            .collect();
         
         Ok(FusedResponse {
-            content,
-            confidence: 0.85,
-            contributions,
-            consensus: ConsensusAnalysis {
-                agreement_score: 0.8,
-                key_points: vec![],
-                disagreements: vec![],
-                fact_verification: vec![],
-            },
+            content: contributions[0].response.clone(),
+            confidence: self.calculate_total_confidence(&contributions),
+            consensus: self.analyze_consensus(&contributions),
             metrics: OrchestrationMetrics {
-                total_latency_ms: 2000,
-                models_used: 3,
+                total_latency_ms: results.iter().map(|(_, _, l)| l).max().copied().unwrap_or(0),
+                models_used: contributions.len(),
                 cache_hit: false,
                 tokens_saved: 0,
-                cost_estimate: 0.05,
+                cost_estimate: self.estimate_cost(prompt, results),
             },
+            contributions,
         })
     }
 }
 
+/// Response metadata describing how an orchestrated response was produced
+fn orchestration_metadata(
+    strategy: &OrchestrationStrategy,
+    models: &[&str],
+    metrics: &OrchestrationMetrics,
+) -> ResponseMetadata {
+    ResponseMetadata {
+        model_used: Some(models.join(", ")),
+        latency_ms: Some(metrics.total_latency_ms),
+        orchestration: Some(serde_json::json!({
+            "strategy": format!("{:?}", strategy),
+            "models": models,
+            "metrics": metrics,
+        })),
+        ..Default::default()
+    }
+}
+
+/// Outcome of racing every model's stream toward agreement
+enum ConsensusRace<'a> {
+    /// Two models agreed; the rest of the leader's stream is still to be read
+    Agreed {
+        leader: String,
+        partner: String,
+        similarity: f64,
+        /// The leader's response so far
+        leader_text: String,
+        /// The partner's response when they agreed
+        partner_text: String,
+        rest: BoxStream<'a, Result<StreamChunk, ClientError>>,
+        agreed_after_ms: u64,
+    },
+    /// Every stream ended without two agreeing
+    Exhausted(Vec<ModelResult>),
+}
+
+/// A deduplicated point gathered from one or more model responses
+struct MergedPoint {
+    text: String,
+    tokens: HashSet<String>,
+    models: Vec<String>,
+    weight: f64,
+}
+
+/// Collect the distinct sentences across contributions, ordered by aggregate weight
+fn merge_points(contributions: &[ModelContribution]) -> Vec<MergedPoint> {
+    let mut points: Vec<MergedPoint> = Vec::new();
+    
+    for contribution in contributions {
+        for sentence in split_sentences(&contribution.response) {
+            let tokens = normalized_tokens(&sentence);
+            if tokens.is_empty() {
+                continue;
+            }
+            
+            match points
+                .iter_mut()
+                .find(|point| jaccard_similarity(&point.tokens, &tokens) >= SENTENCE_DEDUP_THRESHOLD)
+            {
+                Some(point) => {
+                    if !point.models.contains(&contribution.model) {
+                        point.models.push(contribution.model.clone());
+                        point.weight += contribution.weight;
+                    }
+                }
+                None => points.push(MergedPoint {
+                    text: sentence,
+                    tokens,
+                    models: vec![contribution.model.clone()],
+                    weight: contribution.weight,
+                }),
+            }
+        }
+    }
+    
+    // Stable sort keeps first-seen order for equally weighted points
+    points.sort_by(|a, b| b.weight.total_cmp(&a.weight));
+    points
+}
+
+/// Split text into trimmed sentences on terminal punctuation and line breaks
+fn split_sentences(text: &str) -> Vec<String> {
+    let mut sentences = Vec::new();
+    let mut current = String::new();
+    
+    for c in text.chars() {
+        if c != '\n' {
+            current.push(c);
+        }
+        if matches!(c, '.' | '!' | '?' | '\n') {
+            let sentence = current.trim();
+            if !sentence.is_empty() {
+                sentences.push(sentence.to_string());
+            }
+            current.clear();
+        }
+    }
+    
+    let sentence = current.trim();
+    if !sentence.is_empty() {
+        sentences.push(sentence.to_string());
+    }
+    sentences
+}
+
+/// Rough token count of `text`, at about four characters per token
+fn estimate_tokens(text: &str) -> u32 {
+    (text.len() / 4) as u32
+}
+
+/// Lowercased alphanumeric word tokens used for similarity comparisons
+fn normalized_tokens(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_lowercase())
+        .collect()
+}
+
+/// Jaccard similarity (intersection over union) of two token sets
+fn jaccard_similarity(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let intersection = a.intersection(b).count();
+    let union = a.union(b).count();
+    intersection as f64 / union as f64
+}
+
 #[derive(Debug, Clone)]
 enum TaskType {
     Code,
@@ -583,11 +1919,11 @@ struct ResponseCache {
 }
 
 impl ResponseCache {
-    fn new(capacity: u64) -> Self {
+    fn new(capacity: u64, ttl: Duration) -> Self {
         Self {
             cache: moka::future::Cache::builder()
                 .max_capacity(capacity)
-                .time_to_live(std::time::Duration::from_secs(3600))
+                .time_to_live(ttl)
                 .build(),
         }
     }
@@ -599,4 +1935,667 @@ impl ResponseCache {
     async fn set(&self, key: &str, value: FusedResponse) {
         self.cache.insert(key.to_string(), value).await;
     }
-}
\ No newline at end of file
+    
+    fn clear(&self) {
+        self.cache.invalidate_all();
+    }
+    
+    /// Snapshot of every live entry, keyed by prompt
+    fn entries(&self) -> HashMap<String, FusedResponse> {
+        self.cache
+            .iter()
+            .map(|(prompt, response)| (prompt.as_ref().clone(), response))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    struct MockClient {
+        model: String,
+        response: Result<String, String>,
+        delay: std::time::Duration,
+    }
+
+    impl MockClient {
+        fn new(model: &str, response: Result<&str, &str>) -> Self {
+            Self {
+                model: model.to_string(),
+                response: response.map(String::from).map_err(String::from),
+                delay: std::time::Duration::ZERO,
+            }
+        }
+
+        fn with_delay(mut self, millis: u64) -> Self {
+            self.delay = std::time::Duration::from_millis(millis);
+            self
+        }
+    }
+
+    #[async_trait]
+    impl AiClient for MockClient {
+        async fn send_prompt(&self, _prompt: &str) -> Result<String, ClientError> {
+            tokio::time::sleep(self.delay).await;
+            self.response
+                .clone()
+                .map_err(|e| ClientError::config(e, None))
+        }
+
+        fn name(&self) -> &str {
+            "Mock"
+        }
+
+        fn model(&self) -> &str {
+            &self.model
+        }
+    }
+
+    /// Streams its response a word at a time, pausing before each word
+    struct StreamingMock {
+        model: String,
+        response: String,
+        delay: std::time::Duration,
+    }
+
+    impl StreamingMock {
+        fn new(model: &str, response: &str, millis: u64) -> Self {
+            Self {
+                model: model.to_string(),
+                response: response.to_string(),
+                delay: std::time::Duration::from_millis(millis),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl AiClient for StreamingMock {
+        async fn send_prompt(&self, _prompt: &str) -> Result<String, ClientError> {
+            Ok(self.response.clone())
+        }
+
+        async fn stream_prompt(
+            &self,
+            _prompt: &str,
+        ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
+            let words: Vec<String> = self.response.split_inclusive(' ').map(String::from).collect();
+            let delay = self.delay;
+            let chunks = stream::iter(words).then(move |word| async move {
+                tokio::time::sleep(delay).await;
+                Ok(StreamChunk {
+                    content: word,
+                    finished: false,
+                    metadata: None,
+                })
+            });
+            let done = stream::once(ready(Ok(StreamChunk {
+                content: String::new(),
+                finished: true,
+                metadata: None,
+            })));
+            Ok(Box::pin(chunks.chain(done)))
+        }
+
+        fn supports_streaming(&self) -> bool {
+            true
+        }
+
+        fn name(&self) -> &str {
+            "StreamingMock"
+        }
+
+        fn model(&self) -> &str {
+            &self.model
+        }
+    }
+
+    fn capabilities(cost_per_1k_tokens: f32, quality_factor: f64) -> ModelCapabilities {
+        ModelCapabilities {
+            name: "Test Model".to_string(),
+            strengths: vec![],
+            avg_latency_ms: 1000,
+            cost_per_1k_tokens,
+            max_context_length: 8192,
+            supports_streaming: false,
+            supports_vision: false,
+            supports_function_calling: false,
+            quality_factor,
+        }
+    }
+
+    #[test]
+    fn test_registered_capabilities_drive_cost_and_weight() {
+        let mut orchestrator = AiOrchestrator::new(vec![Box::new(MockClient::new("my-model", Ok("ok")))])
+            .with_capabilities(HashMap::new());
+        assert!(orchestrator.capability("gpt-4").is_none());
+
+        orchestrator.register_capability("my-model", capabilities(2.0, 0.5));
+
+        // 4000 chars ~ 1000 tokens at $2.00 per 1k tokens
+        let results = vec![("my-model".to_string(), Ok("x".repeat(4000)), 0)];
+        assert!((orchestrator.estimate_cost("", &results) - 2.0).abs() < f32::EPSILON);
+
+        let unknown = vec![("other-model".to_string(), Ok("x".repeat(4000)), 0)];
+        assert!((orchestrator.estimate_cost("", &unknown) - DEFAULT_COST_PER_1K_TOKENS).abs() < f32::EPSILON);
+
+        assert!((orchestrator.calculate_weight("my-model", 0.8, 0) - 0.4).abs() < 1e-9);
+        assert!((orchestrator.calculate_weight("other-model", 0.8, 0) - 0.8).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_estimated_cost_uses_pricing_rates() {
+        let orchestrator = AiOrchestrator::new(vec![]);
+        // 1000 prompt tokens at $0.03 and 1000 completion tokens at $0.06 per 1k
+        let prompt = "x".repeat(4000);
+        let results = vec![("gpt-4-0613".to_string(), Ok("y".repeat(4000)), 0)];
+        assert!((orchestrator.estimate_cost(&prompt, &results) - 0.09).abs() < 1e-6);
+
+        let orchestrator = orchestrator.with_pricing(Pricing::new().with_rates("gpt-4", ModelRates::new(1.0, 2.0)));
+        assert!((orchestrator.estimate_cost(&prompt, &results) - 3.0).abs() < 1e-6);
+    }
+
+    #[tokio::test]
+    async fn test_required_capability_skips_incompatible_clients() {
+        let clients: Vec<Box<dyn AiClient>> = vec![
+            Box::new(MockClient::new("text-only", Ok("I cannot see images"))),
+            Box::new(MockClient::new("vision", Ok("A cat on a mat"))),
+            Box::new(MockClient::new("unregistered", Ok("Unknown"))),
+        ];
+        let mut orchestrator = AiOrchestrator::new(clients)
+            .with_capabilities(HashMap::new())
+            .with_strategy(OrchestrationStrategy::Parallel)
+            .require_capability(Capability::Vision);
+        orchestrator.register_capability("text-only", capabilities(0.01, 1.0));
+        orchestrator.register_capability(
+            "vision",
+            ModelCapabilities { supports_vision: true, ..capabilities(0.01, 1.0) },
+        );
+
+        let (response, raw) = orchestrator.query_verbose("Describe this image").await.unwrap();
+        let models: Vec<&str> = raw.iter().map(|(model, _)| model.as_str()).collect();
+        assert_eq!(models, vec!["vision"]);
+        assert_eq!(response.content, "A cat on a mat");
+    }
+
+    #[test]
+    fn test_confidence_tracker() {
+        let mut tracker = ConfidenceTracker::new();
+        assert_eq!(tracker.mean(), None);
+        assert_eq!(tracker.trend(), None);
+
+        for confidence in [0.5, 0.6, 0.7, 0.8] {
+            tracker.record_confidence(confidence);
+        }
+        assert_eq!(tracker.count(), 4);
+        assert!((tracker.mean().unwrap() - 0.65).abs() < 1e-9);
+        assert!((tracker.trend().unwrap() - 0.1).abs() < 1e-9);
+
+        tracker.record_confidence(0.1);
+        assert!((tracker.mean().unwrap() - 0.54).abs() < 1e-9);
+        assert!(tracker.trend().unwrap() < 0.1);
+    }
+
+    #[tokio::test]
+    async fn test_cheapest_successful_strategy() {
+        let clients: Vec<Box<dyn AiClient>> = vec![
+            Box::new(MockClient::new("expensive", Ok("expensive answer"))),
+            Box::new(MockClient::new("cheapest", Err("unavailable"))),
+            Box::new(MockClient::new("budget", Ok("budget answer"))),
+        ];
+        let mut orchestrator = AiOrchestrator::new(clients)
+            .with_capabilities(HashMap::new())
+            .with_strategy(OrchestrationStrategy::CheapestSuccessful);
+        orchestrator.register_capability("expensive", capabilities(0.03, 1.0));
+        orchestrator.register_capability("cheapest", capabilities(0.001, 1.0));
+        orchestrator.register_capability("budget", capabilities(0.01, 1.0));
+
+        let response = orchestrator.query("What is Rust?").await.unwrap();
+        assert_eq!(response.content, "budget answer");
+        assert_eq!(response.contributions.len(), 2);
+        let winner = response.contributions.iter().find(|c| c.weight == 1.0).unwrap();
+        assert_eq!(winner.model, "budget");
+    }
+
+    #[tokio::test]
+    async fn test_query_verbose_returns_raw_responses() {
+        let clients: Vec<Box<dyn AiClient>> = vec![
+            Box::new(MockClient::new("model-a", Ok("Rust is a systems language."))),
+            Box::new(MockClient::new("model-b", Err("unavailable"))),
+            Box::new(MockClient::new("model-c", Ok("Rust is memory safe."))),
+        ];
+        let orchestrator = AiOrchestrator::new(clients)
+            .with_strategy(OrchestrationStrategy::WeightedFusion);
+
+        let (response, raw) = orchestrator.query_verbose("What is Rust?").await.unwrap();
+        let models: Vec<&str> = raw.iter().map(|(model, _)| model.as_str()).collect();
+        assert_eq!(models, vec!["model-a", "model-b", "model-c"]);
+        assert!(matches!(raw[1].1, Err(ClientError::Configuration(_))));
+
+        // Every successful raw response appears as a contribution, unchanged
+        let successful: Vec<(&str, &str)> = raw
+            .iter()
+            .filter_map(|(model, result)| Some((model.as_str(), result.as_deref().ok()?)))
+            .collect();
+        let contributions: Vec<(&str, &str)> = response
+            .contributions
+            .iter()
+            .map(|c| (c.model.as_str(), c.response.as_str()))
+            .collect();
+        assert_eq!(contributions, successful);
+    }
+
+    #[tokio::test]
+    async fn test_query_report_round_trip() {
+        let clients: Vec<Box<dyn AiClient>> = vec![
+            Box::new(MockClient::new("model-a", Ok("Rust is a systems language."))),
+            Box::new(MockClient::new("model-b", Err("unavailable"))),
+        ];
+        let orchestrator = AiOrchestrator::new(clients)
+            .with_strategy(OrchestrationStrategy::WeightedFusion);
+
+        let report = orchestrator.query_report("What is Rust?").await.unwrap();
+        assert!(report.timestamp > 0);
+        assert_eq!(report.prompt, "What is Rust?");
+        assert!(matches!(report.strategy, OrchestrationStrategy::WeightedFusion));
+        assert_eq!(report.response.contributions.len(), 1);
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(report.failures[0].model, "model-b");
+
+        let json = serde_json::to_value(&report).unwrap();
+        for field in ["timestamp", "prompt", "strategy", "response", "failures"] {
+            assert!(json.get(field).is_some(), "missing {field}");
+        }
+        let decoded: OrchestrationReport = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&decoded).unwrap(), json);
+    }
+
+    #[tokio::test]
+    async fn test_consensus_picks_largest_cluster() {
+        let clients: Vec<Box<dyn AiClient>> = vec![
+            Box::new(MockClient::new("model-a", Ok("The capital of France is Paris."))),
+            Box::new(MockClient::new("model-b", Ok("London is the capital of England."))),
+            Box::new(MockClient::new("model-c", Ok("Paris is the capital of France."))),
+        ];
+        let orchestrator = AiOrchestrator::new(clients)
+            .with_strategy(OrchestrationStrategy::Consensus)
+            .with_consensus_threshold(0.8);
+
+        let response = orchestrator.query("What is the capital of France?").await.unwrap();
+        assert!(response.content.contains("Paris"));
+        assert!((response.consensus.agreement_score - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(response.consensus.disagreements.len(), 1);
+        assert!(response.consensus.disagreements[0].starts_with("model-b"));
+    }
+
+    #[test]
+    fn test_jaccard_similarity() {
+        let a = normalized_tokens("Paris is the capital of France.");
+        let b = normalized_tokens("the CAPITAL of france is paris");
+        let c = normalized_tokens("Rust has no garbage collector");
+        assert!((jaccard_similarity(&a, &b) - 1.0).abs() < 1e-9);
+        assert_eq!(jaccard_similarity(&a, &c), 0.0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_benchmark_sorts_results() {
+        let mut orchestrator = AiOrchestrator::new(vec![
+            Box::new(MockClient::new("slow", Ok("slow answer")).with_delay(120)),
+            Box::new(MockClient::new("broken", Err("boom"))),
+            Box::new(MockClient::new("fast", Ok("fast answer")).with_delay(10)),
+            Box::new(MockClient::new("medium", Ok("medium answer")).with_delay(60)),
+        ])
+        .with_capabilities(HashMap::new());
+        orchestrator.register_capability("slow", capabilities(0.5, 1.0));
+        orchestrator.register_capability("fast", capabilities(4.0, 1.0));
+        orchestrator.register_capability("medium", capabilities(2.0, 1.0));
+        let orchestrator = orchestrator.with_pricing(Pricing::new().with_rates("fast", ModelRates::new(4.0, 8.0)));
+        
+        let prompt = "Compare these models on a reasonably long prompt";
+        let results = orchestrator.benchmark(prompt, BenchmarkSort::Latency).await;
+        let models: Vec<&str> = results.iter().map(|r| r.model.as_str()).collect();
+        assert_eq!(models, ["fast", "medium", "slow", "broken"]);
+        let latencies: Vec<u64> = results.iter().map(|r| r.latency_ms).collect();
+        assert_eq!(latencies, [10, 60, 120, 0]);
+        assert_eq!(results[3].error.as_deref(), Some("Configuration error: boom"));
+        
+        // Prompt and completion tokens are priced separately, as in `estimate_cost`
+        let (prompt_tokens, completion_tokens) = (prompt.len() / 4, "fast answer".len() / 4);
+        let fast = &results[0];
+        assert_eq!(fast.provider, "Mock");
+        assert_eq!(fast.total_tokens, (prompt_tokens + completion_tokens) as u32);
+        let expected = (prompt_tokens as f64 * 4.0 + completion_tokens as f64 * 8.0) / 1000.0;
+        assert!((fast.estimated_cost - expected as f32).abs() < 1e-6);
+        let fast_result = vec![("fast".to_string(), Ok("fast answer".to_string()), 0)];
+        assert_eq!(fast.estimated_cost, orchestrator.estimate_cost(prompt, &fast_result));
+        
+        let results = orchestrator.benchmark(prompt, BenchmarkSort::Cost).await;
+        let models: Vec<&str> = results.iter().map(|r| r.model.as_str()).collect();
+        assert_eq!(models, ["slow", "medium", "fast", "broken"]);
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_fastest_first_records_every_latency() {
+        let clients: Vec<Box<dyn AiClient>> = vec![
+            Box::new(MockClient::new("slow", Ok("slow answer")).with_delay(2_000)),
+            Box::new(MockClient::new("fast", Ok("fast answer")).with_delay(60)),
+            Box::new(MockClient::new("broken", Err("boom")).with_delay(10)),
+        ];
+        let orchestrator = AiOrchestrator::new(clients)
+            .with_strategy(OrchestrationStrategy::FastestFirst);
+
+        let response = orchestrator.query("Who is fastest?").await.unwrap();
+        assert_eq!(response.content, "fast answer");
+        assert_eq!(response.metrics.total_latency_ms, 60);
+        assert_eq!(response.metrics.models_used, 3);
+
+        // Every model is recorded in the order it finished; only the winner has weight
+        let recorded: Vec<(&str, u64, f64)> = response
+            .contributions
+            .iter()
+            .map(|c| (c.model.as_str(), c.latency_ms, c.weight))
+            .collect();
+        assert_eq!(recorded, [("broken", 10, 0.0), ("fast", 60, 1.0), ("slow", 2_000, 0.0)]);
+        assert!(response.contributions[0].response.is_empty());
+        assert_eq!(response.contributions[2].response, "slow answer");
+    }
+
+    #[test]
+    fn test_weighted_merge_dedupes_overlapping_sentences() {
+        let orchestrator = AiOrchestrator::new(vec![]);
+        let contribution = |model: &str, response: &str, weight: f64| ModelContribution {
+            model: model.to_string(),
+            response: response.to_string(),
+            confidence: 0.8,
+            weight,
+            latency_ms: 0,
+        };
+        let contributions = vec![
+            contribution("model-a", "Rust is memory safe. Rust is fast.", 0.6),
+            contribution("model-b", "Rust is memory safe! Rust has great tooling.", 0.4),
+        ];
+
+        let merged = orchestrator.weighted_merge(&contributions);
+        assert_eq!(merged, "Rust is memory safe. Rust is fast. Rust has great tooling.");
+
+        let consensus = orchestrator.analyze_consensus(&contributions);
+        assert_eq!(consensus.key_points, vec!["Rust is memory safe.".to_string()]);
+        assert_eq!(
+            consensus.disagreements,
+            vec![
+                "model-a: Rust is fast.".to_string(),
+                "model-b: Rust has great tooling.".to_string(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_query_streaming_parallel_forwards_first_responder() {
+        let clients: Vec<Box<dyn AiClient>> = vec![
+            Box::new(MockClient::new("slow", Ok("slow answer")).with_delay(100)),
+            Box::new(MockClient::new("fast", Ok("fast answer")).with_delay(5)),
+        ];
+        let orchestrator = AiOrchestrator::new(clients)
+            .with_strategy(OrchestrationStrategy::Parallel);
+
+        let chunks: Vec<StreamChunk> = orchestrator
+            .query_streaming("Hello")
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+
+        let content: String = chunks.iter().map(|c| c.content.as_str()).collect();
+        assert_eq!(content, "fast answer");
+        let last = chunks.last().unwrap();
+        assert!(last.finished);
+        let stats = last.metadata.as_ref().unwrap().orchestration.as_ref().unwrap();
+        assert_eq!(stats["strategy"], "Parallel");
+        // The slow model's stream was cancelled, so only the winner counts
+        assert_eq!(stats["models"], serde_json::json!(["fast"]));
+        assert_eq!(stats["metrics"]["models_used"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_streaming_consensus_completes_early() {
+        let answer_a = "The capital of France is Paris, which sits on the Seine in the north of the country.";
+        let answer_b = "The capital of France is Paris, a city on the Seine known for its museums.";
+        let clients = || -> Vec<Box<dyn AiClient>> {
+            vec![
+                Box::new(StreamingMock::new("model-a", answer_a, 2)),
+                Box::new(StreamingMock::new("model-b", answer_b, 3)),
+                Box::new(StreamingMock::new("stalled", answer_a, 60_000)),
+            ]
+        };
+        let timeout = std::time::Duration::from_secs(5);
+
+        let orchestrator = AiOrchestrator::for_testing(clients())
+            .with_strategy(OrchestrationStrategy::StreamingConsensus);
+        let response = tokio::time::timeout(timeout, orchestrator.query("Capital of France?"))
+            .await
+            .expect("consensus should not wait for the stalled model")
+            .unwrap();
+        assert!(response.content == answer_a || response.content == answer_b);
+        let mut models: Vec<&str> = response.contributions.iter().map(|c| c.model.as_str()).collect();
+        models.sort();
+        assert_eq!(models, ["model-a", "model-b"]);
+        assert!(response.consensus.agreement_score >= DEFAULT_CONSENSUS_THRESHOLD);
+        assert_eq!(response.metrics.models_used, 2);
+
+        let orchestrator = AiOrchestrator::for_testing(clients())
+            .with_strategy(OrchestrationStrategy::StreamingConsensus);
+        let chunks: Vec<StreamChunk> = tokio::time::timeout(timeout, async {
+            orchestrator
+                .query_streaming("Capital of France?")
+                .await
+                .unwrap()
+                .map(|chunk| chunk.unwrap())
+                .collect()
+                .await
+        })
+        .await
+        .expect("consensus should not wait for the stalled model");
+        let content: String = chunks.iter().map(|c| c.content.as_str()).collect();
+        assert!(content == answer_a || content == answer_b);
+        let stats = chunks.last().unwrap().metadata.as_ref().unwrap().orchestration.as_ref().unwrap();
+        assert_eq!(stats["strategy"], "StreamingConsensus");
+        assert_eq!(stats["metrics"]["models_used"], 2);
+        assert_eq!(stats["models"].as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_parallel_metrics_reflect_responses() {
+        let clients: Vec<Box<dyn AiClient>> = vec![
+            Box::new(MockClient::new("model-a", Ok("Paris is the capital of France."))),
+            Box::new(MockClient::new("model-b", Ok("The capital is Paris"))),
+            Box::new(MockClient::new("model-c", Err("unavailable"))),
+        ];
+        let orchestrator = AiOrchestrator::for_testing(clients)
+            .with_strategy(OrchestrationStrategy::Parallel);
+
+        let response = orchestrator.query("Capital of France?").await.unwrap();
+        assert_eq!(response.metrics.models_used, 2);
+        assert_eq!(response.contributions.len(), 2);
+        assert!(response.contributions.iter().all(|c| c.weight == 0.5));
+        // Only the first response ends with a full stop, which raises its confidence
+        let confidences: Vec<f64> = response.contributions.iter().map(|c| c.confidence).collect();
+        assert!(confidences[0] > confidences[1]);
+        let mean = confidences.iter().sum::<f64>() / 2.0;
+        assert!((response.confidence - mean).abs() < 1e-9);
+        assert_eq!(response.metrics.total_latency_ms, 0);
+    }
+
+    #[tokio::test]
+    async fn test_query_streaming_buffers_tournament() {
+        let clients: Vec<Box<dyn AiClient>> = vec![
+            Box::new(MockClient::new("model-a", Ok("A short answer."))),
+            Box::new(MockClient::new("model-b", Err("unavailable"))),
+        ];
+        let orchestrator = AiOrchestrator::new(clients)
+            .with_strategy(OrchestrationStrategy::Tournament);
+
+        let chunks: Vec<StreamChunk> = orchestrator
+            .query_streaming("Give me an answer")
+            .await
+            .unwrap()
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(chunks.len(), 2);
+        assert!(!chunks[0].finished);
+        assert!(chunks[0].content.is_empty());
+        assert!(chunks[1].finished);
+        assert_eq!(chunks[1].content, "A short answer.");
+        let stats = chunks[1].metadata.as_ref().unwrap().orchestration.as_ref().unwrap();
+        assert_eq!(stats["strategy"], "Tournament");
+    }
+
+    #[tokio::test]
+    async fn test_query_conversation_auto_compaction() {
+        let clients: Vec<Box<dyn AiClient>> = vec![
+            Box::new(MockClient::new("small", Ok("Short summary."))),
+            Box::new(MockClient::new("large", Ok("Short summary."))),
+        ];
+        let mut small = capabilities(0.01, 1.0);
+        small.max_context_length = 40;
+        let mut orchestrator = AiOrchestrator::new(clients)
+            .with_capabilities(HashMap::new())
+            .with_auto_compaction(CompactionConfig { threshold: 0.5, keep_recent: 2 });
+        orchestrator.register_capability("small", small);
+        orchestrator.register_capability("large", capabilities(0.01, 1.0));
+        
+        // Well below the window: left untouched
+        let mut conversation = Conversation::with_system("Be brief.");
+        conversation.add_user("Hi");
+        orchestrator.query_conversation(&mut conversation).await.unwrap();
+        assert_eq!(conversation.len(), 2);
+        
+        let mut conversation = Conversation::with_system("Be brief.");
+        conversation.add_user("Tell me about the history of the Roman Empire.");
+        conversation.add_assistant("It began in 27 BC when Augustus became emperor.");
+        conversation.add_user("And when did the Western Empire fall?");
+        conversation.add_assistant("The Western Roman Empire fell in 476 AD.");
+        conversation.add_user("What about the East?");
+        
+        let response = orchestrator.query_conversation(&mut conversation).await.unwrap();
+        assert_eq!(response.content, "Short summary.");
+        
+        // System message now carries the summary; the kept tail starts on a user turn
+        assert_eq!(conversation.len(), 4);
+        assert_eq!(conversation.messages[0].role, "system");
+        assert!(conversation.messages[0].content.starts_with("Be brief."));
+        assert!(conversation.messages[0].content.contains("Short summary."));
+        assert_eq!(conversation.messages[1].content, "And when did the Western Empire fall?");
+        assert_eq!(conversation.messages[3].content, "What about the East?");
+    }
+    
+    #[tokio::test]
+    async fn test_cache_configuration() {
+        let orchestrator = AiOrchestrator::new(vec![Box::new(MockClient::new("model-a", Ok("answer")))])
+            .with_strategy(OrchestrationStrategy::Parallel)
+            .with_cache(10, Duration::from_secs(60));
+
+        assert!(!orchestrator.query("prompt").await.unwrap().metrics.cache_hit);
+        assert!(orchestrator.query("prompt").await.unwrap().metrics.cache_hit);
+        assert!(!orchestrator.query_uncached("prompt").await.unwrap().metrics.cache_hit);
+
+        orchestrator.clear_cache();
+        assert!(!orchestrator.query("prompt").await.unwrap().metrics.cache_hit);
+
+        let stats = orchestrator.metrics().get_stats();
+        assert_eq!(stats.cache_hits, 1);
+        assert_eq!(stats.cache_misses, 2);
+        assert_eq!(stats.requests_total, 4);
+
+        let uncached = AiOrchestrator::new(vec![Box::new(MockClient::new("model-a", Ok("answer")))])
+            .with_strategy(OrchestrationStrategy::Parallel)
+            .without_cache();
+        assert!(!uncached.query("prompt").await.unwrap().metrics.cache_hit);
+        assert!(!uncached.query("prompt").await.unwrap().metrics.cache_hit);
+    }
+
+    #[tokio::test]
+    async fn test_cache_export_import_round_trip() {
+        let orchestrator = AiOrchestrator::new(vec![Box::new(MockClient::new("model-a", Ok("answer")))])
+            .with_strategy(OrchestrationStrategy::Parallel);
+        orchestrator.query("first").await.unwrap();
+        orchestrator.query("second").await.unwrap();
+        let exported = orchestrator.export_cache().unwrap();
+
+        // A fresh orchestrator whose client would fail serves both prompts from the import
+        let restored = AiOrchestrator::new(vec![Box::new(MockClient::new("model-a", Err("offline")))])
+            .with_strategy(OrchestrationStrategy::Parallel);
+        assert_eq!(restored.import_cache(&exported).await.unwrap(), 2);
+        for prompt in ["first", "second"] {
+            let response = restored.query(prompt).await.unwrap();
+            assert!(response.metrics.cache_hit);
+            assert_eq!(response.content, "answer");
+        }
+        assert_eq!(restored.export_cache().unwrap().len(), exported.len());
+
+        let uncached = AiOrchestrator::new(Vec::new()).without_cache();
+        assert_eq!(uncached.export_cache().unwrap(), "{}");
+        assert_eq!(uncached.import_cache(&exported).await.unwrap(), 0);
+    }
+
+    /// Three clients with pinned replies; alpha is the slowest to answer
+    fn pinned_clients() -> Vec<Box<dyn AiClient>> {
+        vec![
+            Box::new(MockClient::new("alpha", Ok("Paris is the capital of France.")).with_delay(50)),
+            Box::new(MockClient::new("beta", Ok("The capital of France is Paris."))),
+            Box::new(MockClient::new("gamma", Ok("Lyon is a large city in France."))),
+        ]
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_for_testing_strategies_are_deterministic() {
+        let prompt = "What is the capital of France?";
+        let expected = [
+            (OrchestrationStrategy::Parallel, "Paris is the capital of France."),
+            (OrchestrationStrategy::Sequential, "Paris is the capital of France."),
+            (OrchestrationStrategy::Specialized, "Paris is the capital of France."),
+            (OrchestrationStrategy::Consensus, "Paris is the capital of France."),
+            (OrchestrationStrategy::Tournament, "Paris is the capital of France."),
+            (OrchestrationStrategy::CheapestSuccessful, "Lyon is a large city in France."),
+            // Client order, not completion order, decides the winner
+            (OrchestrationStrategy::FastestFirst, "Paris is the capital of France."),
+        ];
+        for (strategy, content) in expected {
+            let mut orchestrator = AiOrchestrator::for_testing(pinned_clients())
+                .with_capabilities(HashMap::new())
+                .with_strategy(strategy.clone());
+            orchestrator.register_capability("gamma", capabilities(0.001, 1.0));
+
+            let first = orchestrator.query(prompt).await.unwrap();
+            assert_eq!(first.content, content, "{strategy:?}");
+            assert!(first.contributions.iter().all(|c| c.latency_ms == 0), "{strategy:?}");
+            assert!(!first.metrics.cache_hit, "{strategy:?}");
+
+            let second = orchestrator.query(prompt).await.unwrap();
+            assert_eq!(
+                serde_json::to_value(&first).unwrap(),
+                serde_json::to_value(&second).unwrap(),
+                "{strategy:?}"
+            );
+        }
+
+        // Weighted fusion and adaptive (a general prompt) merge every reply identically
+        let mut fused = Vec::new();
+        for strategy in [OrchestrationStrategy::WeightedFusion, OrchestrationStrategy::Adaptive] {
+            let orchestrator = AiOrchestrator::for_testing(pinned_clients())
+                .with_capabilities(HashMap::new())
+                .with_strategy(strategy);
+            let response = orchestrator.query(prompt).await.unwrap();
+            assert!(response.content.contains("Lyon is a large city in France."));
+            assert_eq!(response.contributions.len(), 3);
+            fused.push(serde_json::to_value(&response).unwrap());
+        }
+        assert_eq!(fused[0], fused[1]);
+
+        let report = AiOrchestrator::for_testing(pinned_clients()).query_report(prompt).await.unwrap();
+        assert_eq!(report.timestamp, 0);
+    }
+}
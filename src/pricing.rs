@@ -0,0 +1,156 @@
+//! Token pricing and cost estimation
+//!
+//! Providers bill prompt (input) and completion (output) tokens at different
+//! rates. [`Pricing`] maps models to their [`ModelRates`], and
+//! [`ResponseMetadata::estimated_cost`](crate::ResponseMetadata::estimated_cost)
+//! applies them to the token counts a provider reported. Prices change often, so
+//! [`Pricing::current`] is only a starting point; override rates as needed.
+
+use std::collections::HashMap;
+
+/// Price of a model's tokens, in US dollars per 1,000 tokens
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ModelRates {
+    /// Price per 1,000 prompt tokens
+    pub input_per_1k: f64,
+    /// Price per 1,000 completion tokens
+    pub output_per_1k: f64,
+}
+
+impl ModelRates {
+    /// Rates from per-1K-token input and output prices
+    pub fn new(input_per_1k: f64, output_per_1k: f64) -> Self {
+        Self {
+            input_per_1k,
+            output_per_1k,
+        }
+    }
+
+    /// Cost of `prompt_tokens` input and `completion_tokens` output tokens
+    pub fn cost(&self, prompt_tokens: u32, completion_tokens: u32) -> f64 {
+        prompt_tokens as f64 / 1000.0 * self.input_per_1k
+            + completion_tokens as f64 / 1000.0 * self.output_per_1k
+    }
+}
+
+/// Published list prices at the time of writing, per 1,000 tokens
+const CURRENT_RATES: &[(&str, f64, f64)] = &[
+    ("gpt-4", 0.03, 0.06),
+    ("gpt-4-turbo", 0.01, 0.03),
+    ("gpt-4o", 0.0025, 0.01),
+    ("gpt-4o-mini", 0.00015, 0.0006),
+    ("gpt-3.5-turbo", 0.0005, 0.0015),
+    ("claude-3-opus", 0.015, 0.075),
+    ("claude-3-sonnet", 0.003, 0.015),
+    ("claude-3-5-sonnet", 0.003, 0.015),
+    ("claude-3-haiku", 0.00025, 0.00125),
+    ("claude-3-5-haiku", 0.0008, 0.004),
+    ("gemini-1.5-pro", 0.00125, 0.005),
+    ("gemini-1.5-flash", 0.000075, 0.0003),
+];
+
+/// Table of per-model token rates
+///
+/// Models are matched exactly first, then by the longest known prefix, so dated
+/// versions such as `gpt-4o-2024-08-06` are priced as `gpt-4o`.
+///
+/// # Example
+///
+/// ```
+/// use chatdelta::{ModelRates, Pricing};
+///
+/// let pricing = Pricing::current().with_rates("my-fine-tune", ModelRates::new(0.002, 0.004));
+/// assert_eq!(pricing.cost("gpt-4-0613", 1000, 500), Some(0.06));
+/// assert!(pricing.rates("my-fine-tune").is_some());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Pricing {
+    rates: HashMap<String, ModelRates>,
+}
+
+impl Pricing {
+    /// An empty table that prices no models
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Known public list prices for OpenAI, Anthropic, and Google models
+    pub fn current() -> Self {
+        CURRENT_RATES
+            .iter()
+            .fold(Self::new(), |pricing, &(model, input, output)| {
+                pricing.with_rates(model, ModelRates::new(input, output))
+            })
+    }
+
+    /// Set the rates for `model`, replacing any existing entry
+    pub fn with_rates(mut self, model: impl Into<String>, rates: ModelRates) -> Self {
+        self.set_rates(model, rates);
+        self
+    }
+
+    /// Set the rates for `model` in place, replacing any existing entry
+    pub fn set_rates(&mut self, model: impl Into<String>, rates: ModelRates) {
+        self.rates.insert(model.into(), rates);
+    }
+
+    /// Rates for `model`, by exact name or else the longest matching prefix
+    pub fn rates(&self, model: &str) -> Option<ModelRates> {
+        self.rates.get(model).copied().or_else(|| {
+            self.rates
+                .iter()
+                .filter(|(known, _)| model.starts_with(known.as_str()))
+                .max_by_key(|(known, _)| known.len())
+                .map(|(_, rates)| *rates)
+        })
+    }
+
+    /// Cost of a request to `model`, or `None` if the model has no rates
+    pub fn cost(&self, model: &str, prompt_tokens: u32, completion_tokens: u32) -> Option<f64> {
+        self.rates(model)
+            .map(|rates| rates.cost(prompt_tokens, completion_tokens))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ResponseMetadata;
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.expect("expected a cost");
+        assert!((actual - expected).abs() < 1e-9, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn test_gpt4_cost_uses_separate_input_and_output_rates() {
+        let metadata = ResponseMetadata {
+            model_used: Some("gpt-4-0613".to_string()),
+            prompt_tokens: Some(1500),
+            completion_tokens: Some(500),
+            total_tokens: Some(2000),
+            ..Default::default()
+        };
+        // 1.5K prompt tokens at $0.03 plus 0.5K completion tokens at $0.06
+        assert_close(metadata.estimated_cost(&Pricing::current()), 0.045 + 0.03);
+
+        // Overrides replace the built-in rates
+        let pricing = Pricing::current().with_rates("gpt-4", ModelRates::new(0.01, 0.01));
+        assert_close(metadata.estimated_cost(&pricing), 0.02);
+
+        assert_eq!(metadata.estimated_cost(&Pricing::new()), None);
+        let unknown = ResponseMetadata {
+            model_used: None,
+            ..metadata
+        };
+        assert_eq!(unknown.estimated_cost(&Pricing::current()), None);
+    }
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        let pricing = Pricing::current();
+        assert_eq!(pricing.rates("gpt-4o-mini-2024-07-18"), pricing.rates("gpt-4o-mini"));
+        assert_eq!(pricing.rates("gpt-4o-2024-08-06"), pricing.rates("gpt-4o"));
+        assert_eq!(pricing.rates("llama-3"), None);
+    }
+}
@@ -15,9 +15,14 @@ pub struct PromptOptimizer {
     history: PerformanceHistory,
 }
 
-/// Optimization strategies
-trait OptimizationStrategy: Send + Sync {
+/// A prompt transformation applied by [`PromptOptimizer`]
+///
+/// Strategies run in the order they were added, each receiving the previous
+/// one's output, and are listed by name in `OptimizedPrompt::techniques_applied`.
+pub trait OptimizationStrategy: Send + Sync {
+    /// Rewrite `prompt`, using the detected context to decide how
     fn optimize(&self, prompt: &str, context: &OptimizationContext) -> String;
+    /// Name recorded when the strategy is applied
     fn name(&self) -> &str;
 }
 
@@ -31,7 +36,7 @@ pub struct OptimizationContext {
     pub expertise_level: ExpertiseLevel,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum TaskCategory {
     Analysis,
     Generation,
@@ -61,15 +66,89 @@ pub enum ExpertiseLevel {
     Expert,
 }
 
+impl Default for PromptOptimizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PromptOptimizer {
+    /// Create an optimizer with the built-in strategies and templates
     pub fn new() -> Self {
         Self {
             strategies: Self::default_strategies(),
+            ..Self::empty()
+        }
+    }
+    
+    /// Create an optimizer without any strategies
+    ///
+    /// Only strategies added with [`add_strategy`](Self::add_strategy) run. The
+    /// template library is still applied.
+    pub fn empty() -> Self {
+        Self {
+            strategies: Vec::new(),
             templates: TemplateLibrary::default(),
             history: PerformanceHistory::new(),
         }
     }
     
+    /// Append a strategy to the pipeline, after the existing ones
+    pub fn with_strategy(mut self, strategy: Box<dyn OptimizationStrategy>) -> Self {
+        self.add_strategy(strategy);
+        self
+    }
+    
+    /// Append a strategy to the pipeline, after the existing ones
+    pub fn add_strategy(&mut self, strategy: Box<dyn OptimizationStrategy>) {
+        self.strategies.push(strategy);
+    }
+    
+    /// Register a template for a task category, replacing any existing one
+    ///
+    /// `{PROMPT}` in the pattern is replaced by the prompt being optimized; a
+    /// pattern without the placeholder gets the prompt appended after a blank line.
+    pub fn add_template<S: Into<String>>(&mut self, category: TaskCategory, pattern: S) {
+        let mut pattern = pattern.into();
+        if !pattern.contains(PROMPT_PLACEHOLDER) {
+            pattern.push_str("\n\n");
+            pattern.push_str(PROMPT_PLACEHOLDER);
+        }
+        let name = format!("Custom {category:?}");
+        self.templates.templates.insert(category, PromptTemplate { name, pattern });
+    }
+    
+    /// Record how well an optimized prompt performed, from 0.0 (useless) to 1.0 (ideal)
+    ///
+    /// Recorded scores feed back into later optimizations: confidence becomes the
+    /// average score of the techniques applied, and a strategy that averages below
+    /// 0.4 over at least five uses is no longer applied.
+    pub fn record_outcome(&mut self, prompt: &OptimizedPrompt, score: f64) {
+        self.history.record(prompt, score.clamp(0.0, 1.0));
+    }
+    
+    /// Score statistics for every technique with recorded outcomes, best first
+    pub fn history_stats(&self) -> Vec<TechniqueStats> {
+        let mut stats: Vec<TechniqueStats> = self.history.technique_stats().into_values().collect();
+        stats.sort_by(|a, b| {
+            b.average_score
+                .total_cmp(&a.average_score)
+                .then_with(|| a.technique.cmp(&b.technique))
+        });
+        stats
+    }
+    
+    /// Recorded outcomes, e.g. to persist with serde and restore via [`with_history`](Self::with_history)
+    pub fn history(&self) -> &PerformanceHistory {
+        &self.history
+    }
+    
+    /// Replace the recorded outcomes, e.g. with history saved by a previous run
+    pub fn with_history(mut self, history: PerformanceHistory) -> Self {
+        self.history = history;
+        self
+    }
+    
     /// Optimize a prompt for better AI response
     pub fn optimize(&self, prompt: &str) -> OptimizedPrompt {
         let context = self.analyze_context(prompt);
@@ -79,7 +158,7 @@ impl PromptOptimizer {
         let mut techniques_applied = Vec::new();
         
         for strategy in &self.strategies {
-            if self.should_apply_strategy(&strategy, &context) {
+            if self.should_apply_strategy(strategy.as_ref(), &context) {
                 optimized = strategy.optimize(&optimized, &context);
                 techniques_applied.push(strategy.name().to_string());
             }
@@ -175,9 +254,11 @@ impl PromptOptimizer {
         }
     }
     
-    fn should_apply_strategy(&self, _strategy: &Box<dyn OptimizationStrategy>, _context: &OptimizationContext) -> bool {
-        // Decide whether to apply a strategy based on context
-        true // Simplified for now
+    fn should_apply_strategy(&self, strategy: &dyn OptimizationStrategy, _context: &OptimizationContext) -> bool {
+        match self.history.technique_stats().get(strategy.name()) {
+            Some(stats) if stats.uses >= MIN_SAMPLES_TO_SKIP => stats.average_score >= POOR_SCORE_THRESHOLD,
+            _ => true,
+        }
     }
     
     fn generate_variations(&self, optimized: &str, _context: &OptimizationContext) -> Vec<PromptVariation> {
@@ -208,6 +289,17 @@ impl PromptOptimizer {
     }
     
     fn calculate_confidence(&self, techniques: &[String]) -> f64 {
+        // Prefer how the applied techniques actually scored, when any have been recorded
+        let stats = self.history.technique_stats();
+        let scores: Vec<f64> = techniques
+            .iter()
+            .filter_map(|technique| stats.get(technique.as_str()))
+            .map(|stats| stats.average_score)
+            .collect();
+        if !scores.is_empty() {
+            return scores.iter().sum::<f64>() / scores.len() as f64;
+        }
+        
         // Base confidence
         let mut confidence = 0.7;
         
@@ -336,10 +428,60 @@ impl OptimizationStrategy for RoleSpecification {
     }
 }
 
-/// Template library for common patterns
-#[derive(Default)]
+/// Recorded uses a strategy needs before a poor average score can disable it
+const MIN_SAMPLES_TO_SKIP: usize = 5;
+
+/// Average score below which a well-sampled strategy is no longer applied
+const POOR_SCORE_THRESHOLD: f64 = 0.4;
+
+/// Placeholder in a template pattern that is replaced by the prompt
+const PROMPT_PLACEHOLDER: &str = "{PROMPT}";
+
+/// Template library for common patterns, at most one template per task category
 struct TemplateLibrary {
-    templates: HashMap<String, PromptTemplate>,
+    templates: HashMap<TaskCategory, PromptTemplate>,
+}
+
+impl Default for TemplateLibrary {
+    fn default() -> Self {
+        let builtin = [
+            (
+                TaskCategory::Analysis,
+                "Structured Analysis",
+                "{PROMPT}\n\nStructure your analysis as follows:\n\
+                 1. Overview: the subject and the question being answered\n\
+                 2. Key factors: the main components, causes, or considerations\n\
+                 3. Evaluation: the evidence for and against each factor\n\
+                 4. Conclusion: your overall assessment and any open questions",
+            ),
+            (
+                TaskCategory::Reasoning,
+                "Step-by-Step Reasoning",
+                "{PROMPT}\n\nSolve this step by step. First list what is given and what is asked. \
+                 Then work through each step, stating the reasoning behind it. Check the result \
+                 against the original question, and finish with the final answer on its own line.",
+            ),
+            (
+                TaskCategory::Creative,
+                "Creative Writing Scaffold",
+                "{PROMPT}\n\nBefore writing, settle on the audience, tone, and point of view. \
+                 Give the piece a clear opening that draws the reader in, a middle that develops \
+                 the central idea with concrete detail, and an ending that resolves or reframes it.",
+            ),
+        ];
+        
+        let templates = builtin
+            .into_iter()
+            .map(|(category, name, pattern)| {
+                let template = PromptTemplate {
+                    name: name.to_string(),
+                    pattern: pattern.to_string(),
+                };
+                (category, template)
+            })
+            .collect();
+        Self { templates }
+    }
 }
 
 struct PromptTemplate {
@@ -349,39 +491,169 @@ struct PromptTemplate {
 
 impl PromptTemplate {
     fn apply(&self, prompt: &str) -> String {
-        self.pattern.replace("{PROMPT}", prompt)
+        self.pattern.replace(PROMPT_PLACEHOLDER, prompt)
     }
 }
 
 impl TemplateLibrary {
-    fn find_best_template(&self, _context: &OptimizationContext) -> Option<&PromptTemplate> {
-        None // Simplified for now
+    fn find_best_template(&self, context: &OptimizationContext) -> Option<&PromptTemplate> {
+        self.templates.get(&context.task_type)
     }
 }
 
-/// Performance history for learning
-struct PerformanceHistory {
+/// Recorded outcomes of optimized prompts, used to learn which techniques help
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PerformanceHistory {
     history: Vec<HistoryEntry>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 struct HistoryEntry {
     prompt: String,
     optimized: String,
+    techniques: Vec<String>,
     performance_score: f64,
 }
 
+/// How the prompts a technique was applied to scored
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TechniqueStats {
+    /// Strategy name, or `"Template: <name>"` for templates
+    pub technique: String,
+    /// Number of recorded outcomes the technique was applied in
+    pub uses: usize,
+    /// Mean score of those outcomes (0.0 - 1.0)
+    pub average_score: f64,
+}
+
 impl PerformanceHistory {
     fn new() -> Self {
-        Self {
-            history: Vec::new(),
-        }
+        Self::default()
+    }
+    
+    /// Number of recorded outcomes
+    pub fn len(&self) -> usize {
+        self.history.len()
     }
     
-    fn record(&mut self, prompt: String, optimized: String, score: f64) {
+    /// Whether no outcomes have been recorded
+    pub fn is_empty(&self) -> bool {
+        self.history.is_empty()
+    }
+    
+    fn record(&mut self, prompt: &OptimizedPrompt, score: f64) {
         self.history.push(HistoryEntry {
-            prompt,
-            optimized,
+            prompt: prompt.original.clone(),
+            optimized: prompt.optimized.clone(),
+            techniques: prompt.techniques_applied.clone(),
             performance_score: score,
         });
     }
-}
\ No newline at end of file
+    
+    fn technique_stats(&self) -> HashMap<&str, TechniqueStats> {
+        let mut stats: HashMap<&str, TechniqueStats> = HashMap::new();
+        for entry in &self.history {
+            for technique in &entry.techniques {
+                let stats = stats.entry(technique).or_insert_with(|| TechniqueStats {
+                    technique: technique.clone(),
+                    uses: 0,
+                    average_score: 0.0,
+                });
+                stats.uses += 1;
+                stats.average_score += (entry.performance_score - stats.average_score) / stats.uses as f64;
+            }
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analysis_prompt_uses_analysis_template() {
+        let optimizer = PromptOptimizer::new();
+        let result = optimizer.optimize("Analyze the causes of the 2008 financial crisis.");
+
+        assert!(matches!(result.context.task_type, TaskCategory::Analysis));
+        assert!(result.techniques_applied.contains(&"Template: Structured Analysis".to_string()));
+        assert!(result.optimized.contains("the causes of the 2008 financial crisis"));
+        assert!(result.optimized.ends_with("4. Conclusion: your overall assessment and any open questions"));
+        assert!(!result.optimized.contains(PROMPT_PLACEHOLDER));
+    }
+
+    #[test]
+    fn test_custom_strategy() {
+        struct Uppercase;
+        impl OptimizationStrategy for Uppercase {
+            fn optimize(&self, prompt: &str, _context: &OptimizationContext) -> String {
+                prompt.to_uppercase()
+            }
+
+            fn name(&self) -> &str {
+                "Uppercase"
+            }
+        }
+
+        let optimizer = PromptOptimizer::empty().with_strategy(Box::new(Uppercase));
+        let result = optimizer.optimize("translate hello into French");
+        assert_eq!(result.optimized, "TRANSLATE HELLO INTO FRENCH");
+        assert_eq!(result.techniques_applied, vec!["Uppercase".to_string()]);
+
+        // Added strategies run after the built-in ones
+        let mut optimizer = PromptOptimizer::new();
+        optimizer.add_strategy(Box::new(Uppercase));
+        let result = optimizer.optimize("translate hello into French");
+        assert_eq!(result.techniques_applied.last().map(String::as_str), Some("Uppercase"));
+        assert_eq!(result.optimized, result.optimized.to_uppercase());
+    }
+
+    #[test]
+    fn test_recorded_outcomes_drive_confidence_and_selection() {
+        let mut optimizer = PromptOptimizer::new();
+        let prompt = "Translate good morning into Spanish";
+        let first = optimizer.optimize(prompt);
+        assert!(first.techniques_applied.contains(&"Clarity Enhancement".to_string()));
+        assert!(optimizer.history_stats().is_empty());
+
+        for _ in 0..MIN_SAMPLES_TO_SKIP {
+            optimizer.record_outcome(&first, 0.1);
+        }
+        let stats = optimizer.history_stats();
+        let clarity = stats.iter().find(|s| s.technique == "Clarity Enhancement").unwrap();
+        assert_eq!(clarity.uses, MIN_SAMPLES_TO_SKIP);
+        assert!((clarity.average_score - 0.1).abs() < 1e-9);
+
+        // Every technique scored poorly, so they are all dropped and confidence falls back
+        let second = optimizer.optimize(prompt);
+        assert!(second.techniques_applied.is_empty());
+        assert_eq!(second.optimized, prompt);
+
+        // A strong result for a new technique lifts confidence above the heuristic
+        let mut optimizer = PromptOptimizer::new();
+        let first = optimizer.optimize(prompt);
+        optimizer.record_outcome(&first, 1.0);
+        assert_eq!(optimizer.optimize(prompt).confidence, 1.0);
+
+        // History survives a serde round trip
+        let saved = serde_json::to_string(optimizer.history()).unwrap();
+        let restored = PromptOptimizer::new().with_history(serde_json::from_str(&saved).unwrap());
+        assert_eq!(restored.history().len(), 1);
+        assert_eq!(restored.history_stats(), optimizer.history_stats());
+    }
+
+    #[test]
+    fn test_custom_template() {
+        let mut optimizer = PromptOptimizer::new();
+        let result = optimizer.optimize("Translate 'good morning' into Spanish.");
+        assert!(!result.techniques_applied.iter().any(|t| t.starts_with("Template:")));
+
+        optimizer.add_template(TaskCategory::Translation, "Translate faithfully, keeping the register.");
+        let result = optimizer.optimize("Translate 'good morning' into Spanish.");
+        assert!(result.techniques_applied.contains(&"Template: Custom Translation".to_string()));
+        assert!(result
+            .optimized
+            .starts_with("Translate faithfully, keeping the register.\n\nTranslate 'good morning'"));
+    }
+}
@@ -0,0 +1,224 @@
+//! Client-side rate limiting
+//!
+//! A [`RateLimiter`] holds up to two token buckets, one counting requests and one
+//! counting estimated prompt tokens, each refilled continuously over a minute.
+//! Clients await capacity before every HTTP call, so bursts are smoothed locally
+//! instead of being rejected by the provider with HTTP 429.
+//!
+//! Share one limiter between clients by cloning the `ClientConfig` it was built
+//! into, or by passing the same `Arc<RateLimiter>` to several configs.
+//!
+//! [`RateLimitStatus`] is the provider's side of the story: the limits and
+//! remaining budget it reports in response headers.
+
+use reqwest::header::HeaderMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Token-bucket rate limiter for requests and prompt tokens per minute
+#[derive(Debug)]
+pub struct RateLimiter {
+    requests: Option<Mutex<TokenBucket>>,
+    tokens: Option<Mutex<TokenBucket>>,
+}
+
+impl RateLimiter {
+    /// Create a limiter; a `None` limit leaves that dimension unrestricted
+    ///
+    /// Both buckets start full, so a burst of up to the per-minute limit is allowed
+    /// before throttling kicks in.
+    pub fn new(requests_per_minute: Option<u32>, tokens_per_minute: Option<u32>) -> Self {
+        Self {
+            requests: requests_per_minute.map(|limit| Mutex::new(TokenBucket::per_minute(limit))),
+            tokens: tokens_per_minute.map(|limit| Mutex::new(TokenBucket::per_minute(limit))),
+        }
+    }
+
+    /// Wait until one request with `tokens` estimated prompt tokens fits the budget
+    ///
+    /// Returns how long the caller was held back. A request estimated above the
+    /// per-minute token limit waits for a full bucket rather than forever.
+    pub async fn acquire(&self, tokens: u32) -> Duration {
+        let mut waited = Duration::ZERO;
+        if let Some(bucket) = &self.requests {
+            waited += take(bucket, 1.0).await;
+        }
+        if let Some(bucket) = &self.tokens {
+            waited += take(bucket, f64::from(tokens)).await;
+        }
+        waited
+    }
+}
+
+/// Remove `amount` from the bucket, sleeping until it has refilled enough
+async fn take(bucket: &Mutex<TokenBucket>, amount: f64) -> Duration {
+    let mut waited = Duration::ZERO;
+    loop {
+        // Never hold the lock across the sleep
+        let wait = bucket.lock().unwrap().try_take(amount, Instant::now());
+        match wait {
+            None => return waited,
+            Some(wait) => {
+                tokio::time::sleep(wait).await;
+                waited += wait;
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    available: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn per_minute(limit: u32) -> Self {
+        let capacity = f64::from(limit.max(1));
+        Self {
+            capacity,
+            available: capacity,
+            refill_per_sec: capacity / 60.0,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Take `amount` if available, otherwise return how long until it will be
+    fn try_take(&mut self, amount: f64, now: Instant) -> Option<Duration> {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.available = (self.available + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+
+        let amount = amount.min(self.capacity);
+        if self.available >= amount {
+            self.available -= amount;
+            None
+        } else {
+            Some(Duration::from_secs_f64(
+                (amount - self.available) / self.refill_per_sec,
+            ))
+        }
+    }
+}
+
+/// Rate limit budget reported by the provider in response headers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RateLimitStatus {
+    /// Requests allowed in the current window
+    pub requests_limit: Option<u32>,
+    /// Requests left in the current window
+    pub requests_remaining: Option<u32>,
+    /// Tokens allowed in the current window
+    pub tokens_limit: Option<u32>,
+    /// Tokens left in the current window
+    pub tokens_remaining: Option<u32>,
+}
+
+impl RateLimitStatus {
+    /// Read OpenAI (`x-ratelimit-*`) or Anthropic (`anthropic-ratelimit-*`) headers
+    ///
+    /// Returns `None` if the response carries none of them.
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let read = |openai: &str, anthropic: &str| {
+            headers
+                .get(openai)
+                .or_else(|| headers.get(anthropic))
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse().ok())
+        };
+
+        let status = Self {
+            requests_limit: read("x-ratelimit-limit-requests", "anthropic-ratelimit-requests-limit"),
+            requests_remaining: read(
+                "x-ratelimit-remaining-requests",
+                "anthropic-ratelimit-requests-remaining",
+            ),
+            tokens_limit: read("x-ratelimit-limit-tokens", "anthropic-ratelimit-tokens-limit"),
+            tokens_remaining: read(
+                "x-ratelimit-remaining-tokens",
+                "anthropic-ratelimit-tokens-remaining",
+            ),
+        };
+        (status != Self::default()).then_some(status)
+    }
+}
+
+/// Latest [`RateLimitStatus`] seen by a client
+#[derive(Debug, Default)]
+pub(crate) struct RateLimitTracker(Mutex<Option<RateLimitStatus>>);
+
+impl RateLimitTracker {
+    /// Remember the status reported by `headers`, if any, and return it
+    pub(crate) fn record(&self, headers: &HeaderMap) -> Option<RateLimitStatus> {
+        let status = RateLimitStatus::from_headers(headers);
+        if status.is_some() {
+            *self.0.lock().unwrap() = status;
+        }
+        status
+    }
+
+    /// The most recently recorded status
+    pub(crate) fn last(&self) -> Option<RateLimitStatus> {
+        *self.0.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_bucket_refill() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::per_minute(60);
+        bucket.last_refill = start;
+
+        assert_eq!(bucket.try_take(60.0, start), None);
+        // Empty bucket refills at one token per second
+        assert_eq!(bucket.try_take(2.0, start), Some(Duration::from_secs(2)));
+        assert_eq!(bucket.try_take(2.0, start + Duration::from_secs(2)), None);
+        // Oversized requests are capped at the bucket capacity
+        assert_eq!(
+            bucket.try_take(1000.0, start + Duration::from_secs(2)),
+            Some(Duration::from_secs(60))
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_acquire_waits_for_capacity() {
+        let limiter = RateLimiter::new(Some(60), Some(600));
+        for _ in 0..60 {
+            assert_eq!(limiter.acquire(5).await, Duration::ZERO);
+        }
+
+        // Requests refill at one per second, tokens at ten per second
+        assert_eq!(limiter.acquire(5).await, Duration::from_secs(1));
+        // One second for the request, by which point 315 of the 400 tokens are back
+        assert_eq!(limiter.acquire(400).await, Duration::from_millis(9500));
+
+        let unlimited = RateLimiter::new(None, None);
+        assert_eq!(unlimited.acquire(u32::MAX).await, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_rate_limit_status_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(RateLimitStatus::from_headers(&headers), None);
+
+        headers.insert("x-ratelimit-limit-requests", "500".parse().unwrap());
+        headers.insert("x-ratelimit-remaining-requests", "499".parse().unwrap());
+        headers.insert("anthropic-ratelimit-tokens-remaining", "39000".parse().unwrap());
+        assert_eq!(
+            RateLimitStatus::from_headers(&headers),
+            Some(RateLimitStatus {
+                requests_limit: Some(500),
+                requests_remaining: Some(499),
+                tokens_limit: None,
+                tokens_remaining: Some(39000),
+            })
+        );
+    }
+}
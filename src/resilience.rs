@@ -0,0 +1,765 @@
+//! Resilience wrappers for AI clients
+//!
+//! [`CircuitBreakerClient`] stops sending requests to a provider that keeps failing.
+//! After a run of consecutive failures the circuit opens and every call fails fast
+//! for a cooldown window. The first call after the cooldown half-opens the circuit
+//! to probe the provider; enough consecutive successes close it again, while any
+//! failure reopens it.
+//!
+//! [`BudgetedClient`] caps spending: it adds up the estimated cost of each request
+//! and refuses further requests once a budget is used up.
+
+use crate::{
+    AiClient, AiResponse, ApiError, ApiErrorType, ClientError, Conversation, ModelRates, Pricing,
+    RateLimitStatus, RequestPreview, SseEvent, StreamChunk, StreamRequest,
+};
+use async_trait::async_trait;
+use futures::stream::{BoxStream, StreamExt};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+/// Settings for when a circuit breaker opens and closes
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive failures that open the circuit
+    pub failure_threshold: u32,
+    /// How long the circuit stays open before a probe request is let through
+    pub cooldown: Duration,
+    /// Consecutive successes while half-open that close the circuit
+    pub success_threshold: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(30),
+            success_threshold: 2,
+        }
+    }
+}
+
+/// State of a circuit breaker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum CircuitState {
+    /// Requests flow normally
+    Closed,
+    /// Requests fail fast without reaching the provider
+    Open,
+    /// Requests are let through to test whether the provider has recovered
+    HalfOpen,
+}
+
+#[derive(Debug)]
+struct Breaker {
+    state: CircuitState,
+    consecutive_failures: u32,
+    consecutive_successes: u32,
+    opened_at: Instant,
+}
+
+/// Client decorator that short-circuits requests to a failing provider
+///
+/// Only transient errors (see [`ClientError::is_retryable`]) such as network
+/// failures, rate limits, and 5xx responses count as failures. Any other
+/// outcome means the provider answered, and counts as a success.
+pub struct CircuitBreakerClient {
+    inner: Box<dyn AiClient>,
+    config: CircuitBreakerConfig,
+    breaker: Mutex<Breaker>,
+}
+
+impl CircuitBreakerClient {
+    /// Wrap `inner` with a circuit breaker
+    pub fn new(inner: Box<dyn AiClient>, config: CircuitBreakerConfig) -> Self {
+        Self {
+            inner,
+            config,
+            breaker: Mutex::new(Breaker {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                consecutive_successes: 0,
+                opened_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Current state of the circuit
+    ///
+    /// An open circuit whose cooldown has elapsed reports `HalfOpen`, since the
+    /// next request will be let through.
+    pub fn state(&self) -> CircuitState {
+        let breaker = self.breaker.lock().unwrap();
+        match breaker.state {
+            CircuitState::Open if breaker.opened_at.elapsed() >= self.config.cooldown => {
+                CircuitState::HalfOpen
+            }
+            state => state,
+        }
+    }
+
+    /// Number of failures in a row since the last success
+    pub fn consecutive_failures(&self) -> u32 {
+        self.breaker.lock().unwrap().consecutive_failures
+    }
+
+    /// The wrapped client
+    pub fn inner(&self) -> &dyn AiClient {
+        self.inner.as_ref()
+    }
+
+    /// Run `request` unless the circuit is open, then record its outcome
+    async fn call<T>(
+        &self,
+        request: impl Future<Output = Result<T, ClientError>>,
+    ) -> Result<T, ClientError> {
+        self.before_request()?;
+        let result = request.await;
+        self.after_request(result.as_ref().err());
+        result
+    }
+
+    fn before_request(&self) -> Result<(), ClientError> {
+        let mut breaker = self.breaker.lock().unwrap();
+        if breaker.state != CircuitState::Open {
+            return Ok(());
+        }
+
+        let elapsed = breaker.opened_at.elapsed();
+        if elapsed >= self.config.cooldown {
+            info!(provider = self.inner.name(), "Circuit half-open, probing provider");
+            breaker.state = CircuitState::HalfOpen;
+            breaker.consecutive_successes = 0;
+            return Ok(());
+        }
+
+        Err(ClientError::Api(ApiError {
+            message: format!(
+                "Circuit breaker open for {} after {} consecutive failures, retry in {:?}",
+                self.inner.name(),
+                breaker.consecutive_failures,
+                self.config.cooldown - elapsed
+            ),
+            status_code: None,
+            error_type: ApiErrorType::CircuitOpen,
+            source: None,
+        }))
+    }
+
+    fn after_request(&self, error: Option<&ClientError>) {
+        let mut breaker = self.breaker.lock().unwrap();
+        let failed = error.is_some_and(ClientError::is_retryable);
+
+        if failed {
+            breaker.consecutive_failures += 1;
+            breaker.consecutive_successes = 0;
+            let trips = match breaker.state {
+                CircuitState::Closed => breaker.consecutive_failures >= self.config.failure_threshold,
+                CircuitState::HalfOpen => true,
+                // Opened by a concurrent request while this one was in flight
+                CircuitState::Open => false,
+            };
+            if trips {
+                warn!(
+                    provider = self.inner.name(),
+                    failures = breaker.consecutive_failures,
+                    "Circuit opened"
+                );
+                breaker.state = CircuitState::Open;
+                breaker.opened_at = Instant::now();
+            }
+        } else {
+            breaker.consecutive_failures = 0;
+            breaker.consecutive_successes += 1;
+            if breaker.state == CircuitState::HalfOpen
+                && breaker.consecutive_successes >= self.config.success_threshold
+            {
+                info!(provider = self.inner.name(), "Circuit closed");
+                breaker.state = CircuitState::Closed;
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl AiClient for CircuitBreakerClient {
+    async fn send_prompt(&self, prompt: &str) -> Result<String, ClientError> {
+        self.call(self.inner.send_prompt(prompt)).await
+    }
+
+    async fn send_prompt_with_metadata(&self, prompt: &str) -> Result<AiResponse, ClientError> {
+        self.call(self.inner.send_prompt_with_metadata(prompt)).await
+    }
+
+    async fn send_prompt_multi(&self, prompt: &str) -> Result<Vec<String>, ClientError> {
+        self.call(self.inner.send_prompt_multi(prompt)).await
+    }
+
+    async fn send_conversation(&self, conversation: &Conversation) -> Result<String, ClientError> {
+        self.call(self.inner.send_conversation(conversation)).await
+    }
+
+    async fn send_prompt_streaming(
+        &self,
+        prompt: &str,
+        tx: mpsc::UnboundedSender<StreamChunk>,
+    ) -> Result<(), ClientError> {
+        self.call(self.inner.send_prompt_streaming(prompt, tx)).await
+    }
+
+    async fn send_conversation_with_metadata(
+        &self,
+        conversation: &Conversation,
+    ) -> Result<AiResponse, ClientError> {
+        self.call(self.inner.send_conversation_with_metadata(conversation)).await
+    }
+
+    async fn stream_prompt(
+        &self,
+        prompt: &str,
+    ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
+        self.call(self.inner.stream_prompt(prompt)).await
+    }
+
+    async fn stream_conversation(
+        &self,
+        conversation: &Conversation,
+    ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
+        self.call(self.inner.stream_conversation(conversation)).await
+    }
+
+    async fn stream_conversation_with(
+        &self,
+        conversation: &Conversation,
+        request: &StreamRequest,
+    ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
+        self.call(self.inner.stream_conversation_with(conversation, request)).await
+    }
+
+    fn build_request_preview(
+        &self,
+        conversation: &Conversation,
+    ) -> Result<RequestPreview, ClientError> {
+        self.inner.build_request_preview(conversation)
+    }
+
+    fn validate_conversation(&self, conversation: &Conversation) -> Result<(), ClientError> {
+        self.inner.validate_conversation(conversation)
+    }
+
+    async fn debug_stream(
+        &self,
+        prompt: &str,
+    ) -> Result<BoxStream<'_, Result<SseEvent, ClientError>>, ClientError> {
+        self.call(self.inner.debug_stream(prompt)).await
+    }
+
+    async fn ping(&self) -> Result<(), ClientError> {
+        self.call(self.inner.ping()).await
+    }
+
+    fn last_rate_limit(&self) -> Option<RateLimitStatus> {
+        self.inner.last_rate_limit()
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.inner.supports_streaming()
+    }
+
+    fn supports_conversations(&self) -> bool {
+        self.inner.supports_conversations()
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+}
+
+/// Client decorator that refuses requests once a spending budget is exhausted
+///
+/// The cost of each request is estimated with a [`Pricing`] table from the prompt
+/// and completion tokens the provider reports, or from the text length (about four
+/// characters per token) when it reports none. A provider that reports only a total
+/// is charged for it at the completion rate. Streamed responses are charged chunk by
+/// chunk. Requests already in flight when the budget runs out still complete, so
+/// spending can overshoot the budget by up to that many requests.
+pub struct BudgetedClient {
+    inner: Box<dyn AiClient>,
+    budget: f64,
+    pricing: Pricing,
+    rates: ModelRates,
+    spent: Mutex<f64>,
+}
+
+impl BudgetedClient {
+    /// Wrap `inner`, allowing it to spend `budget` at the rates in `pricing`
+    ///
+    /// Fails with a configuration error when `pricing` has no rates for the model of
+    /// `inner`, since its spending could not be tracked.
+    pub fn new(inner: Box<dyn AiClient>, budget: f64, pricing: Pricing) -> Result<Self, ClientError> {
+        let Some(rates) = pricing.rates(inner.model()) else {
+            return Err(ClientError::config(
+                format!("No pricing for model {}", inner.model()),
+                Some("model".to_string()),
+            ));
+        };
+        Ok(Self {
+            inner,
+            budget,
+            pricing,
+            rates,
+            spent: Mutex::new(0.0),
+        })
+    }
+
+    /// Estimated cost of all requests so far
+    pub fn spent(&self) -> f64 {
+        *self.spent.lock().unwrap()
+    }
+
+    /// Budget left before requests are refused, never negative
+    pub fn remaining(&self) -> f64 {
+        (self.budget - self.spent()).max(0.0)
+    }
+
+    /// The wrapped client
+    pub fn inner(&self) -> &dyn AiClient {
+        self.inner.as_ref()
+    }
+
+    fn check_budget(&self) -> Result<(), ClientError> {
+        let spent = self.spent();
+        if spent < self.budget {
+            return Ok(());
+        }
+        Err(ClientError::Api(ApiError {
+            message: format!(
+                "Budget of {:.4} exhausted for {}, {:.4} spent",
+                self.budget,
+                self.inner.name(),
+                spent
+            ),
+            status_code: None,
+            error_type: ApiErrorType::BudgetExceeded,
+            source: None,
+        }))
+    }
+
+    fn add_cost(&self, cost: f64) {
+        *self.spent.lock().unwrap() += cost;
+    }
+
+    /// Charge for text of `prompt_chars` and `completion_chars` at the wrapped model's rates
+    fn charge_chars(&self, prompt_chars: usize, completion_chars: usize) {
+        let prompt_tokens = prompt_chars as f64 / 4.0;
+        let completion_tokens = completion_chars as f64 / 4.0;
+        self.add_cost(
+            (prompt_tokens * self.rates.input_per_1k + completion_tokens * self.rates.output_per_1k) / 1000.0,
+        );
+    }
+
+    /// Charge for a completed response, preferring the token counts the provider reported
+    fn charge_response(&self, prompt_chars: usize, response: &AiResponse) {
+        let metadata = &response.metadata;
+        // A fallback model may have answered, so price the model that was actually used
+        if let Some(cost) = metadata.estimated_cost(&self.pricing) {
+            self.add_cost(cost);
+            return;
+        }
+        match (metadata.prompt_tokens, metadata.completion_tokens, metadata.total_tokens) {
+            (None, None, None) => self.charge_chars(prompt_chars, response.content.len()),
+            (None, None, Some(total)) => self.add_cost(self.rates.cost(0, total)),
+            (prompt, completion, _) => {
+                self.add_cost(self.rates.cost(prompt.unwrap_or(0), completion.unwrap_or(0)))
+            }
+        }
+    }
+
+    /// Charge for `prompt_chars` up front and for each chunk as it arrives
+    fn charge_stream<'a>(
+        &'a self,
+        prompt_chars: usize,
+        stream: BoxStream<'a, Result<StreamChunk, ClientError>>,
+    ) -> BoxStream<'a, Result<StreamChunk, ClientError>> {
+        self.charge_chars(prompt_chars, 0);
+        Box::pin(stream.inspect(move |chunk| {
+            if let Ok(chunk) = chunk {
+                self.charge_chars(0, chunk.content.len());
+            }
+        }))
+    }
+}
+
+/// Total characters of the messages in `conversation`
+fn conversation_chars(conversation: &Conversation) -> usize {
+    conversation.messages.iter().map(|m| m.content.len()).sum()
+}
+
+#[async_trait]
+impl AiClient for BudgetedClient {
+    async fn send_prompt(&self, prompt: &str) -> Result<String, ClientError> {
+        Ok(self.send_prompt_with_metadata(prompt).await?.content)
+    }
+
+    async fn send_prompt_with_metadata(&self, prompt: &str) -> Result<AiResponse, ClientError> {
+        self.check_budget()?;
+        let response = self.inner.send_prompt_with_metadata(prompt).await?;
+        self.charge_response(prompt.len(), &response);
+        Ok(response)
+    }
+
+    async fn send_prompt_multi(&self, prompt: &str) -> Result<Vec<String>, ClientError> {
+        self.check_budget()?;
+        let responses = self.inner.send_prompt_multi(prompt).await?;
+        self.charge_chars(prompt.len(), responses.iter().map(String::len).sum());
+        Ok(responses)
+    }
+
+    async fn send_conversation(&self, conversation: &Conversation) -> Result<String, ClientError> {
+        Ok(self.send_conversation_with_metadata(conversation).await?.content)
+    }
+
+    async fn send_prompt_streaming(
+        &self,
+        prompt: &str,
+        tx: mpsc::UnboundedSender<StreamChunk>,
+    ) -> Result<(), ClientError> {
+        let mut stream = self.stream_prompt(prompt).await?;
+        while let Some(chunk) = stream.next().await {
+            if tx.send(chunk?).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    async fn send_conversation_with_metadata(
+        &self,
+        conversation: &Conversation,
+    ) -> Result<AiResponse, ClientError> {
+        self.check_budget()?;
+        let response = self.inner.send_conversation_with_metadata(conversation).await?;
+        self.charge_response(conversation_chars(conversation), &response);
+        Ok(response)
+    }
+
+    async fn stream_prompt(
+        &self,
+        prompt: &str,
+    ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
+        self.check_budget()?;
+        let stream = self.inner.stream_prompt(prompt).await?;
+        Ok(self.charge_stream(prompt.len(), stream))
+    }
+
+    async fn stream_conversation(
+        &self,
+        conversation: &Conversation,
+    ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
+        self.check_budget()?;
+        let stream = self.inner.stream_conversation(conversation).await?;
+        Ok(self.charge_stream(conversation_chars(conversation), stream))
+    }
+
+    async fn stream_conversation_with(
+        &self,
+        conversation: &Conversation,
+        request: &StreamRequest,
+    ) -> Result<BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
+        self.check_budget()?;
+        let stream = self.inner.stream_conversation_with(conversation, request).await?;
+        Ok(self.charge_stream(conversation_chars(conversation), stream))
+    }
+
+    fn build_request_preview(
+        &self,
+        conversation: &Conversation,
+    ) -> Result<RequestPreview, ClientError> {
+        self.inner.build_request_preview(conversation)
+    }
+
+    fn validate_conversation(&self, conversation: &Conversation) -> Result<(), ClientError> {
+        self.inner.validate_conversation(conversation)
+    }
+
+    /// Refused once the budget is spent, but raw events are not charged for
+    async fn debug_stream(
+        &self,
+        prompt: &str,
+    ) -> Result<BoxStream<'_, Result<SseEvent, ClientError>>, ClientError> {
+        self.check_budget()?;
+        self.inner.debug_stream(prompt).await
+    }
+
+    async fn ping(&self) -> Result<(), ClientError> {
+        self.check_budget()?;
+        self.inner.ping().await
+    }
+
+    fn last_rate_limit(&self) -> Option<RateLimitStatus> {
+        self.inner.last_rate_limit()
+    }
+
+    fn supports_streaming(&self) -> bool {
+        self.inner.supports_streaming()
+    }
+
+    fn supports_conversations(&self) -> bool {
+        self.inner.supports_conversations()
+    }
+
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    /// Mock that fails with a server error while `failing` is set
+    struct FlakyClient {
+        failing: Arc<AtomicBool>,
+        calls: Arc<AtomicU32>,
+    }
+
+    #[async_trait]
+    impl AiClient for FlakyClient {
+        async fn send_prompt(&self, _prompt: &str) -> Result<String, ClientError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.failing.load(Ordering::SeqCst) {
+                Err(ClientError::Api(ApiError {
+                    message: "Service unavailable".to_string(),
+                    status_code: Some(503),
+                    error_type: ApiErrorType::ServerError,
+                    source: None,
+                }))
+            } else {
+                Ok("ok".to_string())
+            }
+        }
+
+        fn name(&self) -> &str {
+            "Flaky"
+        }
+
+        fn model(&self) -> &str {
+            "flaky-1"
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_circuit_breaker_state_machine() {
+        let failing = Arc::new(AtomicBool::new(true));
+        let calls = Arc::new(AtomicU32::new(0));
+        let client = CircuitBreakerClient::new(
+            Box::new(FlakyClient {
+                failing: failing.clone(),
+                calls: calls.clone(),
+            }),
+            CircuitBreakerConfig {
+                failure_threshold: 3,
+                cooldown: Duration::from_secs(10),
+                success_threshold: 2,
+            },
+        );
+
+        // Consecutive failures open the circuit
+        for _ in 0..3 {
+            assert!(client.send_prompt("hi").await.is_err());
+        }
+        assert_eq!(client.state(), CircuitState::Open);
+        assert_eq!(client.consecutive_failures(), 3);
+
+        // While open, requests fail fast without reaching the provider
+        let err = client.send_prompt("hi").await.unwrap_err();
+        assert!(matches!(
+            err,
+            ClientError::Api(ApiError { error_type: ApiErrorType::CircuitOpen, .. })
+        ));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // After the cooldown a failing probe reopens the circuit
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert_eq!(client.state(), CircuitState::HalfOpen);
+        assert!(client.send_prompt("hi").await.is_err());
+        assert_eq!(client.state(), CircuitState::Open);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        // Once the provider recovers, consecutive successes close it
+        failing.store(false, Ordering::SeqCst);
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert_eq!(client.send_prompt("hi").await.unwrap(), "ok");
+        assert_eq!(client.state(), CircuitState::HalfOpen);
+        assert_eq!(client.send_prompt("hi").await.unwrap(), "ok");
+        assert_eq!(client.state(), CircuitState::Closed);
+        assert_eq!(client.consecutive_failures(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_non_transient_errors_do_not_trip() {
+        struct BadRequestClient;
+
+        #[async_trait]
+        impl AiClient for BadRequestClient {
+            async fn send_prompt(&self, _prompt: &str) -> Result<String, ClientError> {
+                Err(ClientError::config("Empty prompt", None))
+            }
+
+            fn name(&self) -> &str {
+                "BadRequest"
+            }
+
+            fn model(&self) -> &str {
+                "bad-1"
+            }
+        }
+
+        let client = CircuitBreakerClient::new(
+            Box::new(BadRequestClient),
+            CircuitBreakerConfig {
+                failure_threshold: 1,
+                ..CircuitBreakerConfig::default()
+            },
+        );
+        for _ in 0..3 {
+            assert!(matches!(
+                client.send_prompt("").await,
+                Err(ClientError::Configuration(_))
+            ));
+        }
+        assert_eq!(client.state(), CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_budgeted_client_refuses_once_exhausted() {
+        /// Mock reporting 1000 tokens per response
+        struct MeteredClient {
+            calls: Arc<AtomicU32>,
+        }
+
+        #[async_trait]
+        impl AiClient for MeteredClient {
+            async fn send_prompt(&self, _prompt: &str) -> Result<String, ClientError> {
+                unreachable!("BudgetedClient asks for metadata")
+            }
+
+            async fn send_prompt_with_metadata(&self, _prompt: &str) -> Result<AiResponse, ClientError> {
+                self.calls.fetch_add(1, Ordering::SeqCst);
+                let mut response = AiResponse::new("ok".to_string());
+                response.metadata.total_tokens = Some(1000);
+                Ok(response)
+            }
+
+            fn name(&self) -> &str {
+                "Metered"
+            }
+
+            fn model(&self) -> &str {
+                "metered-1"
+            }
+        }
+
+        let calls = Arc::new(AtomicU32::new(0));
+        let pricing = Pricing::new().with_rates("metered", ModelRates::new(0.005, 0.01));
+        let client = BudgetedClient::new(Box::new(MeteredClient { calls: calls.clone() }), 0.025, pricing).unwrap();
+
+        // Each request costs 0.01; the third starts under budget and overshoots it
+        for _ in 0..3 {
+            assert_eq!(client.send_prompt("Hi").await.unwrap(), "ok");
+        }
+        assert!((client.spent() - 0.03).abs() < 1e-9);
+        assert_eq!(client.remaining(), 0.0);
+
+        let err = client.send_prompt("Hi").await.unwrap_err();
+        assert!(matches!(
+            err,
+            ClientError::Api(ApiError { error_type: ApiErrorType::BudgetExceeded, .. })
+        ));
+        assert!(!err.is_retryable());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let unpriced = BudgetedClient::new(Box::new(MeteredClient { calls }), 1.0, Pricing::new());
+        assert!(matches!(unpriced, Err(ClientError::Configuration(_))));
+    }
+
+    #[tokio::test]
+    async fn test_budgeted_client_prices_the_model_used() {
+        /// Mock whose responses come from a cheaper fallback model
+        struct FallbackClient;
+
+        #[async_trait]
+        impl AiClient for FallbackClient {
+            async fn send_prompt(&self, _prompt: &str) -> Result<String, ClientError> {
+                unreachable!("BudgetedClient asks for metadata")
+            }
+
+            async fn send_prompt_with_metadata(&self, _prompt: &str) -> Result<AiResponse, ClientError> {
+                let mut response = AiResponse::new("ok".to_string());
+                response.metadata.model_used = Some("gpt-4o-mini".to_string());
+                response.metadata.prompt_tokens = Some(1000);
+                response.metadata.completion_tokens = Some(2000);
+                Ok(response)
+            }
+
+            fn name(&self) -> &str {
+                "Fallback"
+            }
+
+            fn model(&self) -> &str {
+                "gpt-4o"
+            }
+        }
+
+        let client = BudgetedClient::new(Box::new(FallbackClient), 1.0, Pricing::current()).unwrap();
+        client.send_prompt("Hi").await.unwrap();
+        // gpt-4o-mini input and output rates, not gpt-4o's
+        assert!((client.spent() - (0.00015 + 2.0 * 0.0006)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_wrappers_forward_conversation_validation() {
+        use crate::{create_client, ClientConfig};
+
+        let claude = || create_client("claude", "test-key", "claude-3-5-sonnet", ClientConfig::default()).unwrap();
+        let openai = create_client("openai", "test-key", "gpt-4o", ClientConfig::default()).unwrap();
+        let wrapped: Vec<Box<dyn AiClient>> = vec![
+            Box::new(CircuitBreakerClient::new(claude(), CircuitBreakerConfig::default())),
+            Box::new(BudgetedClient::new(openai, 1.0, Pricing::current()).unwrap()),
+            // Wrappers nest, and the innermost provider's rules still apply
+            Box::new(CircuitBreakerClient::new(
+                Box::new(BudgetedClient::new(claude(), 1.0, Pricing::current()).unwrap()),
+                CircuitBreakerConfig::default(),
+            )),
+        ];
+
+        // Both providers reject an assistant turn before any user turn
+        let mut assistant_first = Conversation::with_system("Be brief");
+        assistant_first.add_assistant("Hello");
+        assistant_first.add_user("Hi");
+        for client in &wrapped {
+            let err = assistant_first.validate_for(client.as_ref()).unwrap_err();
+            assert!(matches!(err, ClientError::Configuration(_)), "{}: {err}", client.name());
+        }
+
+        let mut valid = Conversation::with_system("Be brief");
+        valid.add_user("Hi");
+        for client in &wrapped {
+            assert!(valid.validate_for(client.as_ref()).is_ok(), "{}", client.name());
+        }
+    }
+}
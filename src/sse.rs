@@ -1,11 +1,29 @@
 //! Server-Sent Events (SSE) parsing for streaming responses
+//!
+//! Providers differ in how they label events:
+//!
+//! - **OpenAI** sends data-only events (no `event:` line) carrying JSON chunks and ends
+//!   the stream with `data: [DONE]`. Every event has the default `message` type.
+//! - **Anthropic** sends an `event:` line on every event (`message_start`,
+//!   `content_block_delta`, `message_delta`, `message_stop`, `ping`, `error`) that
+//!   mirrors the `type` field of the JSON payload. The Claude client dispatches on the
+//!   JSON `type`; [`SseDispatcher`] can dispatch on the `event:` line instead.
+//! - **Gemini** (`streamGenerateContent?alt=sse`) sends data-only events.
 
+use crate::{with_idle_timeout, ClientConfig, ClientError, StreamError, StreamErrorType};
 use bytes::{Bytes, BytesMut};
-use futures::stream::Stream;
+use futures::stream::{BoxStream, Stream, StreamExt};
 use pin_project_lite::pin_project;
+use std::collections::HashMap;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
+/// Event type the SSE spec assigns to events without an `event:` field
+pub const DEFAULT_EVENT_TYPE: &str = "message";
+
+/// Default limit on the bytes buffered for a single incomplete event (1 MiB)
+pub const DEFAULT_MAX_EVENT_SIZE: usize = 1024 * 1024;
+
 /// Represents a single SSE event
 #[derive(Debug, Clone)]
 pub struct SseEvent {
@@ -15,12 +33,96 @@ pub struct SseEvent {
     pub retry: Option<u64>,
 }
 
+impl SseEvent {
+    /// The `event:` type, or `"message"` when the server did not send one
+    pub fn event_type(&self) -> &str {
+        self.event.as_deref().unwrap_or(DEFAULT_EVENT_TYPE)
+    }
+}
+
+type EventHandler<T> = Box<dyn Fn(&SseEvent) -> Option<T> + Send + Sync>;
+
+/// Routes SSE events to handlers registered for their `event:` type
+///
+/// Events without an `event:` field are routed as `"message"`. Events whose type has
+/// no handler go to the fallback, if one is set, and are dropped otherwise.
+///
+/// # Example
+///
+/// ```
+/// use chatdelta::sse::{SseDispatcher, SseEvent};
+///
+/// let dispatcher = SseDispatcher::new()
+///     .on("content_block_delta", |event| Some(event.data.clone()))
+///     .on("ping", |_| None);
+///
+/// let event = SseEvent {
+///     event: Some("content_block_delta".to_string()),
+///     data: "Hello".to_string(),
+///     id: None,
+///     retry: None,
+/// };
+/// assert_eq!(dispatcher.dispatch(&event), Some("Hello".to_string()));
+/// ```
+pub struct SseDispatcher<T> {
+    handlers: HashMap<String, EventHandler<T>>,
+    fallback: Option<EventHandler<T>>,
+}
+
+impl<T> Default for SseDispatcher<T> {
+    fn default() -> Self {
+        Self {
+            handlers: HashMap::new(),
+            fallback: None,
+        }
+    }
+}
+
+impl<T> SseDispatcher<T> {
+    /// Create a dispatcher with no handlers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle events of the given type, replacing any previous handler for it
+    pub fn on<F>(mut self, event_type: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(&SseEvent) -> Option<T> + Send + Sync + 'static,
+    {
+        self.handlers.insert(event_type.into(), Box::new(handler));
+        self
+    }
+
+    /// Handle events whose type has no registered handler
+    pub fn fallback<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&SseEvent) -> Option<T> + Send + Sync + 'static,
+    {
+        self.fallback = Some(Box::new(handler));
+        self
+    }
+
+    /// Run the handler for the event's type and return its output
+    pub fn dispatch(&self, event: &SseEvent) -> Option<T> {
+        self.handlers
+            .get(event.event_type())
+            .or(self.fallback.as_ref())
+            .and_then(|handler| handler(event))
+    }
+}
+
 pin_project! {
     /// A stream that parses SSE events from a byte stream
+    ///
+    /// Transport errors become [`StreamErrorType::Other`]. If an event grows past the
+    /// maximum size without being terminated, the stream yields one
+    /// [`StreamErrorType::InvalidChunk`] error and ends.
     pub struct SseStream<S> {
         #[pin]
         inner: S,
         buffer: BytesMut,
+        max_event_size: usize,
+        overflowed: bool,
     }
 }
 
@@ -32,32 +134,69 @@ where
         Self {
             inner: stream,
             buffer: BytesMut::new(),
+            max_event_size: DEFAULT_MAX_EVENT_SIZE,
+            overflowed: false,
         }
     }
 
+    /// Fail once an incomplete event exceeds `bytes`, instead of [`DEFAULT_MAX_EVENT_SIZE`]
+    pub fn with_max_event_size(mut self, bytes: usize) -> Self {
+        self.max_event_size = bytes;
+        self
+    }
+
+    /// Byte offset just past the blank line ending the first event in `buffer`
+    ///
+    /// Lines may end in `\r\n`, `\n`, or `\r`, so events are separated by
+    /// `\r\n\r\n`, `\n\n`, `\r\r`, or any mix of them.
+    fn event_end(buffer: &[u8]) -> Option<usize> {
+        let mut line_start = true;
+        let mut i = 0;
+        while i < buffer.len() {
+            let terminator = match (buffer[i], buffer.get(i + 1)) {
+                (b'\r', Some(b'\n')) => 2,
+                (b'\n', _) | (b'\r', Some(_)) => 1,
+                // A trailing `\r` may be the first half of `\r\n`, but either way it
+                // ends a blank line
+                (b'\r', None) if line_start => 1,
+                (b'\r', None) => return None,
+                _ => {
+                    line_start = false;
+                    i += 1;
+                    continue;
+                }
+            };
+            i += terminator;
+            if line_start {
+                return Some(i);
+            }
+            line_start = true;
+        }
+        None
+    }
+
     fn parse_event(data: &str) -> Option<SseEvent> {
         let mut event = None;
         let mut event_data = Vec::new();
         let mut id = None;
         let mut retry = None;
 
-        for line in data.lines() {
-            if line.is_empty() {
+        for line in data.split(['\r', '\n']) {
+            // Empty lines come from `\r\n` endings; lines starting with ':' are comments
+            if line.is_empty() || line.starts_with(':') {
                 continue;
             }
 
-            if let Some((key, value)) = line.split_once(':') {
-                let value = value.trim_start();
-                match key {
-                    "event" => event = Some(value.to_string()),
-                    "data" => event_data.push(value.to_string()),
-                    "id" => id = Some(value.to_string()),
-                    "retry" => retry = value.parse().ok(),
-                    _ => {}
-                }
-            } else if line.starts_with(':') {
-                // Comment, ignore
-                continue;
+            // A line without a colon is a field with an empty value. Only a single
+            // space after the colon is stripped; the rest belongs to the value.
+            let (key, value) = line.split_once(':').unwrap_or((line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match key {
+                "event" => event = Some(value.to_string()),
+                "data" => event_data.push(value.to_string()),
+                "id" => id = Some(value.to_string()),
+                "retry" => retry = value.parse().ok(),
+                _ => {}
             }
         }
 
@@ -78,20 +217,34 @@ impl<S> Stream for SseStream<S>
 where
     S: Stream<Item = Result<Bytes, reqwest::Error>>,
 {
-    type Item = Result<SseEvent, reqwest::Error>;
+    type Item = Result<SseEvent, ClientError>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let mut this = self.project();
+        if *this.overflowed {
+            return Poll::Ready(None);
+        }
 
         loop {
             // Try to parse an event from the buffer
-            if let Some(pos) = this.buffer.windows(2).position(|w| w == b"\n\n") {
-                let event_data = this.buffer.split_to(pos + 2);
+            if let Some(end) = Self::event_end(this.buffer) {
+                let event_data = this.buffer.split_to(end);
                 let event_str = String::from_utf8_lossy(&event_data);
-                
+
                 if let Some(event) = Self::parse_event(&event_str) {
                     return Poll::Ready(Some(Ok(event)));
                 }
+                // Comments and events without data; later events may already be buffered
+                continue;
+            }
+
+            if this.buffer.len() > *this.max_event_size {
+                *this.overflowed = true;
+                this.buffer.clear();
+                return Poll::Ready(Some(Err(ClientError::Stream(StreamError {
+                    message: format!("SSE event exceeded {} bytes without a delimiter", this.max_event_size),
+                    error_type: StreamErrorType::InvalidChunk,
+                }))));
             }
 
             // Read more data from the stream
@@ -99,7 +252,12 @@ where
                 Poll::Ready(Some(Ok(bytes))) => {
                     this.buffer.extend_from_slice(&bytes);
                 }
-                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e))),
+                Poll::Ready(Some(Err(e))) => {
+                    return Poll::Ready(Some(Err(ClientError::Stream(StreamError {
+                        message: format!("SSE stream error: {}", e),
+                        error_type: StreamErrorType::Other,
+                    }))))
+                }
                 Poll::Ready(None) => {
                     // Stream ended, try to parse any remaining data
                     if !this.buffer.is_empty() {
@@ -119,6 +277,151 @@ where
 }
 
 /// Helper function to create an SSE stream from a response
-pub fn sse_stream(response: reqwest::Response) -> impl Stream<Item = Result<SseEvent, reqwest::Error>> {
+pub fn sse_stream(response: reqwest::Response) -> impl Stream<Item = Result<SseEvent, ClientError>> {
     SseStream::new(response.bytes_stream())
-}
\ No newline at end of file
+}
+
+/// SSE events from a provider response, failing once the stream goes idle
+///
+/// Silence longer than the config's `stream_idle_timeout` becomes
+/// [`StreamErrorType::ConnectionLost`]. Every event counts as activity, so pings keep
+/// a slow generation alive. Events are limited to the config's `max_sse_event_size`.
+/// Errors, including the idle timeout, are counted in the config's metrics.
+pub(crate) fn client_sse_stream(
+    response: reqwest::Response,
+    config: &ClientConfig,
+) -> BoxStream<'static, Result<SseEvent, ClientError>> {
+    let metrics = config.metrics.clone();
+    let events = SseStream::new(response.bytes_stream())
+        .with_max_event_size(config.max_sse_event_size)
+        .inspect(move |event| {
+            if let (Err(error), Some(metrics)) = (event, &metrics) {
+                metrics.record_failure(error);
+            }
+        });
+    with_idle_timeout(Box::pin(events), config.stream_idle_timeout, config.metrics.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream::{self, StreamExt};
+
+    #[derive(Debug, PartialEq)]
+    enum Parsed {
+        Text(String),
+        Done,
+        Unknown(String),
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_on_event_type() {
+        let body = concat!(
+            "event: message_start\ndata: {\"type\":\"message_start\"}\n\n",
+            "event: content_block_delta\ndata: Hello\n\n",
+            "event: ping\ndata: {}\n\n",
+            "event: content_block_delta\ndata: world\n\n",
+            "event: error\ndata: overloaded\n\n",
+            "data: untyped\n\n",
+            "event: message_stop\ndata: {}\n\n",
+        );
+        // Split mid-event to exercise buffering across chunks
+        let (first, second) = body.split_at(40);
+        let bytes = stream::iter(vec![
+            Ok::<_, reqwest::Error>(Bytes::from(first)),
+            Ok(Bytes::from(second)),
+        ]);
+        let events: Vec<SseEvent> = SseStream::new(bytes)
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+
+        let types: Vec<&str> = events.iter().map(SseEvent::event_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                "message_start",
+                "content_block_delta",
+                "ping",
+                "content_block_delta",
+                "error",
+                "message",
+                "message_stop",
+            ]
+        );
+
+        let dispatcher = SseDispatcher::new()
+            .on("content_block_delta", |event| Some(Parsed::Text(event.data.clone())))
+            .on("message_stop", |_| Some(Parsed::Done))
+            .on("ping", |_| None)
+            .on("message_start", |_| None)
+            .fallback(|event| Some(Parsed::Unknown(event.event_type().to_string())));
+        let parsed: Vec<Parsed> = events.iter().filter_map(|e| dispatcher.dispatch(e)).collect();
+        assert_eq!(
+            parsed,
+            vec![
+                Parsed::Text("Hello".to_string()),
+                Parsed::Text("world".to_string()),
+                Parsed::Unknown("error".to_string()),
+                Parsed::Unknown("message".to_string()),
+                Parsed::Done,
+            ]
+        );
+    }
+
+    async fn parse_chunks(chunks: &[&'static str]) -> Vec<SseEvent> {
+        let bytes = stream::iter(
+            chunks
+                .iter()
+                .map(|chunk| Ok::<_, reqwest::Error>(Bytes::from(*chunk)))
+                .collect::<Vec<_>>(),
+        );
+        SseStream::new(bytes).map(|event| event.unwrap()).collect().await
+    }
+
+    #[tokio::test]
+    async fn test_crlf_and_cr_line_endings() {
+        // The first event's separator is split between its `\r` and `\n`
+        let events = parse_chunks(&[
+            "event: delta\r\ndata: one\r\n\r",
+            "\n: keep-alive\r\n\r\ndata: two\r\ndata: lines\r\n\r\n",
+            "data: three\r\rdata: four\n\n",
+        ])
+        .await;
+
+        let data: Vec<&str> = events.iter().map(|event| event.data.as_str()).collect();
+        assert_eq!(data, vec!["one", "two\nlines", "three", "four"]);
+        assert_eq!(events[0].event.as_deref(), Some("delta"));
+        assert_eq!(events[1].event, None);
+    }
+
+    #[tokio::test]
+    async fn test_field_values_keep_colons_and_extra_spaces() {
+        let events = parse_chunks(&[
+            "id: 2024-05-01T12:00:00Z\ndata: {\"time\": \"12:00\"}\n\n",
+            "data:  indented\ndata:tight\ndata\n\n",
+        ])
+        .await;
+
+        assert_eq!(events[0].id.as_deref(), Some("2024-05-01T12:00:00Z"));
+        assert_eq!(events[0].data, r#"{"time": "12:00"}"#);
+        // Only the one space after the colon is stripped; bare `data` is an empty line
+        assert_eq!(events[1].data, " indented\ntight\n");
+    }
+
+    #[tokio::test]
+    async fn test_undelimited_event_fails_at_size_limit() {
+        // An endless event that never sends a blank line
+        let bytes = stream::iter(std::iter::repeat_with(|| {
+            Ok::<_, reqwest::Error>(Bytes::from_static(b"data: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\n"))
+        }));
+        let mut events = SseStream::new(bytes).with_max_event_size(4096);
+
+        let error = events.next().await.unwrap().unwrap_err();
+        assert!(
+            matches!(&error, ClientError::Stream(e) if matches!(e.error_type, StreamErrorType::InvalidChunk)),
+            "{error}"
+        );
+        assert!(events.next().await.is_none());
+    }
+}
@@ -1,9 +1,10 @@
 use crate::ClientError;
 use std::future::Future;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Strategy for retrying failed requests
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum RetryStrategy {
     /// Fixed delay between retries
     Fixed(Duration),
@@ -13,6 +14,34 @@ pub enum RetryStrategy {
     Exponential(Duration),
     /// Exponential backoff with jitter (randomized delay)
     ExponentialWithJitter(Duration),
+    /// Exponential backoff (base * 2^attempt) that never waits longer than `max`
+    ExponentialCapped { base: Duration, max: Duration },
+    /// Exponential backoff lengthened by up to `jitter` (a fraction, e.g. 0.3 for
+    /// 30%) of itself at random, never waiting longer than `max`
+    ExponentialWithJitterCapped {
+        base: Duration,
+        jitter: f64,
+        max: Duration,
+    },
+    /// Explicit delay per retry; attempts past the end reuse the last entry
+    ///
+    /// The delays are shared, so cloning a strategy stays cheap. Build one from a
+    /// `Vec` with `RetryStrategy::Schedule(delays.into())`.
+    Schedule(Arc<[Duration]>),
+    /// "Full jitter": a uniformly random delay between zero and base * 2^attempt
+    ///
+    /// Spreads retries from many clients far more evenly than adding a little
+    /// jitter to the exponential delay, at the cost of sometimes retrying almost
+    /// immediately. A good default when many clients share one provider's limits.
+    FullJitter(Duration),
+    /// "Decorrelated jitter": each delay is random between `base` and three times
+    /// the previous delay, never more than `cap`
+    ///
+    /// Spreads retries about as well as full jitter while keeping delays from
+    /// collapsing toward zero, and grows more gently than pure exponential backoff.
+    /// Each delay depends on the one before, so use [`RetryStrategy::next_delay`]
+    /// to feed it back; [`RetryStrategy::delay`] simulates the chain from scratch.
+    DecorrelatedJitter { base: Duration, cap: Duration },
 }
 
 impl Default for RetryStrategy {
@@ -37,6 +66,40 @@ impl RetryStrategy {
                 let multiplier = base_delay as f64 * (1.0 + jitter);
                 base.mul_f64(multiplier)
             }
+            RetryStrategy::ExponentialCapped { base, max } => {
+                base.saturating_mul(2_u32.saturating_pow(attempt)).min(*max)
+            }
+            RetryStrategy::ExponentialWithJitterCapped { base, jitter, max } => {
+                let jitter = rand::random::<f64>() * jitter.max(0.0);
+                let secs = base.as_secs_f64() * 2_f64.powi(attempt.min(1024) as i32) * (1.0 + jitter);
+                Duration::try_from_secs_f64(secs).map_or(*max, |delay| delay.min(*max))
+            }
+            RetryStrategy::Schedule(delays) => {
+                let index = (attempt as usize).min(delays.len().saturating_sub(1));
+                delays.get(index).copied().unwrap_or_default()
+            }
+            RetryStrategy::FullJitter(base) => base
+                .saturating_mul(2_u32.saturating_pow(attempt))
+                .mul_f64(rand::random::<f64>()),
+            RetryStrategy::DecorrelatedJitter { .. } => {
+                (0..=attempt).fold(Duration::ZERO, |prev, attempt| self.next_delay(attempt, prev))
+            }
+        }
+    }
+
+    /// Calculate the delay for `attempt` given the delay used before it
+    ///
+    /// Only [`RetryStrategy::DecorrelatedJitter`] uses `prev` (pass
+    /// `Duration::ZERO` before the first retry); every other strategy returns
+    /// [`RetryStrategy::delay`] for `attempt`.
+    pub fn next_delay(&self, attempt: u32, prev: Duration) -> Duration {
+        match self {
+            RetryStrategy::DecorrelatedJitter { base, cap } => {
+                let upper = prev.max(*base).saturating_mul(3).min(*cap);
+                let spread = upper.saturating_sub(*base);
+                (*base + spread.mul_f64(rand::random::<f64>())).min(*cap)
+            }
+            _ => self.delay(attempt),
         }
     }
 }
@@ -80,12 +143,13 @@ where
     Fut: Future<Output = Result<T, ClientError>>,
 {
     let mut last_error = None;
+    let mut delay = Duration::ZERO;
     for attempt in 0..=retries {
         match op().await {
             Ok(value) => return Ok(value),
             Err(e) => {
                 // Check if error is retryable
-                if !is_retryable_error(&e) {
+                if !e.is_retryable() {
                     return Err(e);
                 }
                 last_error = Some(e);
@@ -93,7 +157,7 @@ where
         }
 
         if attempt < retries {
-            let delay = strategy.delay(attempt);
+            delay = strategy.next_delay(attempt, delay);
             tokio::time::sleep(delay).await;
         }
     }
@@ -102,22 +166,178 @@ where
     }))
 }
 
-/// Check if an error should trigger a retry
-fn is_retryable_error(error: &ClientError) -> bool {
-    match error {
-        ClientError::Network(_) => true,
-        ClientError::Api(api_error) => {
-            matches!(
-                api_error.error_type,
-                crate::ApiErrorType::RateLimit | crate::ApiErrorType::ServerError
-            )
+/// Header carrying the key that lets providers deduplicate retried requests
+pub(crate) const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// Generate a random idempotency key for one logical request
+///
+/// The key is a version 4 UUID. Callers attach the same key to every retry of a
+/// request so the provider can recognise repeats of work it already did.
+pub(crate) fn idempotency_key() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+/// Common aliases and the canonical model name each refers to
+const MODEL_ALIASES: &[(&str, &str)] = &[
+    ("gpt-4", "gpt-4"),
+    ("gpt-4-turbo", "gpt-4-turbo"),
+    ("gpt-4o", "gpt-4o"),
+    ("gpt-4o-mini", "gpt-4o-mini"),
+    ("gpt-3.5", "gpt-3.5-turbo"),
+    ("gpt-3.5-turbo", "gpt-3.5-turbo"),
+    ("gpt-35-turbo", "gpt-3.5-turbo"),
+    ("chatgpt", "gpt-4o"),
+    ("claude-opus", "claude-3-opus-latest"),
+    ("claude-sonnet", "claude-3-5-sonnet-latest"),
+    ("claude-haiku", "claude-3-5-haiku-latest"),
+    ("opus", "claude-3-opus-latest"),
+    ("sonnet", "claude-3-5-sonnet-latest"),
+    ("haiku", "claude-3-5-haiku-latest"),
+    ("gemini-pro", "gemini-1.5-pro"),
+    ("gemini-flash", "gemini-1.5-flash"),
+];
+
+/// Map a loosely written model name to the provider's canonical name
+///
+/// Matching ignores case and surrounding whitespace, treats spaces and underscores
+/// as hyphens, and accepts a missing hyphen after `gpt` (`gpt4o` is `gpt-4o`).
+/// Names that are not a known alias are returned trimmed but otherwise unchanged,
+/// so dated model versions pass through untouched.
+///
+/// # Example
+///
+/// ```
+/// use chatdelta::canonicalize_model;
+///
+/// assert_eq!(canonicalize_model("GPT4"), "gpt-4");
+/// assert_eq!(canonicalize_model("claude sonnet"), "claude-3-5-sonnet-latest");
+/// assert_eq!(canonicalize_model("gpt-4-0613"), "gpt-4-0613");
+/// ```
+pub fn canonicalize_model(model: &str) -> String {
+    let model = model.trim();
+    let mut key = model.to_lowercase().replace([' ', '_'], "-");
+    if key.starts_with("gpt") && key[3..].starts_with(|c: char| c.is_ascii_digit()) {
+        key.insert(3, '-');
+    }
+
+    MODEL_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == key)
+        .map_or_else(|| model.to_string(), |(_, canonical)| canonical.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule_retry_strategy() {
+        let strategy = RetryStrategy::Schedule(
+            vec![Duration::from_secs(1), Duration::from_secs(5), Duration::from_secs(30)].into(),
+        );
+        let delays: Vec<Duration> = (0..3).map(|attempt| strategy.delay(attempt)).collect();
+        assert_eq!(
+            delays,
+            vec![Duration::from_secs(1), Duration::from_secs(5), Duration::from_secs(30)]
+        );
+
+        // Extra attempts clamp to the last entry
+        assert_eq!(strategy.delay(7), Duration::from_secs(30));
+
+        assert_eq!(RetryStrategy::Schedule(Arc::from([])).delay(0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_capped_exponential_never_exceeds_max() {
+        let max = Duration::from_secs(30);
+        let capped = RetryStrategy::ExponentialCapped { base: Duration::from_secs(1), max };
+        assert_eq!(capped.delay(0), Duration::from_secs(1));
+        assert_eq!(capped.delay(3), Duration::from_secs(8));
+        assert_eq!(capped.delay(20), max);
+        assert_eq!(capped.delay(u32::MAX), max);
+
+        let jittered = RetryStrategy::ExponentialWithJitterCapped {
+            base: Duration::from_secs(1),
+            jitter: 0.5,
+            max,
+        };
+        for attempt in 0..=20 {
+            let delay = jittered.delay(attempt);
+            assert!(delay <= max, "attempt {attempt}: {delay:?}");
         }
-        ClientError::Stream(stream_error) => {
-            matches!(
-                stream_error.error_type,
-                crate::StreamErrorType::ConnectionLost
-            )
+        let first = jittered.delay(0);
+        assert!(first >= Duration::from_secs(1) && first <= Duration::from_millis(1500));
+
+        // Without jitter the delay is exact
+        let exact = RetryStrategy::ExponentialWithJitterCapped {
+            base: Duration::from_secs(1),
+            jitter: 0.0,
+            max,
+        };
+        assert_eq!(exact.delay(2), Duration::from_secs(4));
+        assert_eq!(exact.delay(u32::MAX), max);
+    }
+
+    #[test]
+    fn test_full_jitter_stays_within_exponential_bound() {
+        let strategy = RetryStrategy::FullJitter(Duration::from_millis(100));
+        for attempt in 0..10 {
+            let bound = Duration::from_millis(100) * 2_u32.pow(attempt);
+            for _ in 0..20 {
+                assert!(strategy.delay(attempt) <= bound, "attempt {attempt}");
+            }
         }
-        _ => false,
+    }
+
+    #[test]
+    fn test_decorrelated_jitter_bounds() {
+        let base = Duration::from_millis(100);
+        let cap = Duration::from_secs(5);
+        let strategy = RetryStrategy::DecorrelatedJitter { base, cap };
+
+        let mut prev = Duration::ZERO;
+        for attempt in 0..30 {
+            let delay = strategy.next_delay(attempt, prev);
+            assert!(delay >= base && delay <= cap, "attempt {attempt}: {delay:?}");
+            assert!(delay <= prev.max(base) * 3, "attempt {attempt}: {delay:?}");
+            prev = delay;
+        }
+        for attempt in [0, 5, 50] {
+            let delay = strategy.delay(attempt);
+            assert!(delay >= base && delay <= cap, "attempt {attempt}: {delay:?}");
+        }
+
+        // Other strategies ignore the previous delay
+        let fixed = RetryStrategy::Fixed(Duration::from_secs(2));
+        assert_eq!(fixed.next_delay(3, Duration::from_secs(60)), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_idempotency_key_is_uuid_v4() {
+        let key = idempotency_key();
+        let groups: Vec<usize> = key.split('-').map(str::len).collect();
+        assert_eq!(groups, [8, 4, 4, 4, 12]);
+        assert!(key.chars().all(|c| c == '-' || c.is_ascii_hexdigit()));
+        assert_eq!(&key[14..15], "4");
+        assert_ne!(key, idempotency_key());
+    }
+
+    #[test]
+    fn test_canonicalize_model() {
+        for alias in ["gpt4", "gpt-4", "GPT-4", " Gpt4 ", "gpt_4"] {
+            assert_eq!(canonicalize_model(alias), "gpt-4", "alias {alias:?}");
+        }
+        assert_eq!(canonicalize_model("GPT4o-Mini"), "gpt-4o-mini");
+        assert_eq!(canonicalize_model("gpt 3.5"), "gpt-3.5-turbo");
+        assert_eq!(canonicalize_model("Sonnet"), "claude-3-5-sonnet-latest");
+        assert_eq!(canonicalize_model("gemini flash"), "gemini-1.5-flash");
+
+        // Unknown names are only trimmed
+        assert_eq!(canonicalize_model(" claude-3-opus-20240229 "), "claude-3-opus-20240229");
+        assert_eq!(canonicalize_model("My-Fine-Tune"), "My-Fine-Tune");
     }
 }
@@ -0,0 +1,148 @@
+//! Mock HTTP server shared by the integration tests
+//!
+//! Each test binary uses a different subset of these helpers.
+#![allow(dead_code)]
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+
+/// A request received by the mock server
+pub struct Request {
+    /// Request line and headers, without the blank line that ends them
+    pub head: String,
+    /// Body, read up to the request's `Content-Length`
+    pub body: String,
+}
+
+impl Request {
+    /// The request target, such as `/v1/chat/completions`
+    pub fn path(&self) -> &str {
+        self.head.split_whitespace().nth(1).unwrap_or_default()
+    }
+
+    /// Value of the header `name`, matched case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.head.lines().skip(1).find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+
+    /// The body parsed as JSON
+    pub fn json(&self) -> serde_json::Value {
+        serde_json::from_str(&self.body).unwrap()
+    }
+}
+
+/// Read a request head and then its body, as long as `Content-Length` says
+pub async fn read_request(socket: &mut TcpStream) -> Request {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        let n = socket.read(&mut buf).await.unwrap();
+        request.extend_from_slice(&buf[..n]);
+        let text = String::from_utf8_lossy(&request);
+        if let Some(head_end) = text.find("\r\n\r\n") {
+            let head = text[..head_end].to_string();
+            let content_length = head
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("content-length")
+                        .then(|| value.trim().parse::<usize>().ok())
+                        .flatten()
+                })
+                .unwrap_or(0);
+            if n == 0 || request.len() >= head_end + 4 + content_length {
+                let body = text[head_end + 4..].to_string();
+                return Request { head, body };
+            }
+        } else if n == 0 {
+            return Request {
+                head: text.into_owned(),
+                body: String::new(),
+            };
+        }
+    }
+}
+
+/// A complete response that closes the connection once `body` is sent
+pub fn http_response(status: &str, headers: &[(&str, &str)], body: &str) -> String {
+    let headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{name}: {value}\r\n"))
+        .collect();
+    format!(
+        "HTTP/1.1 {status}\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// A complete JSON response
+pub fn json_response(status: &str, body: &str) -> String {
+    http_response(status, &[("Content-Type", "application/json")], body)
+}
+
+/// Listen on a free local port, returning the listener and its `http://` address
+pub async fn bind() -> (TcpListener, String) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = format!("http://{}", listener.local_addr().unwrap());
+    (listener, addr)
+}
+
+/// Answer every request with the response `handler` builds for it
+///
+/// Returns the server's `http://` address.
+pub async fn serve<F>(mut handler: F) -> String
+where
+    F: FnMut(Request) -> String + Send + 'static,
+{
+    let (listener, addr) = bind().await;
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let request = read_request(&mut socket).await;
+            let response = handler(request);
+            socket.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+    addr
+}
+
+/// Answer every request with `body` as a successful JSON response
+///
+/// Returns the server's `http://` address and a receiver for the first request.
+pub async fn serve_capturing_first(body: String) -> (String, oneshot::Receiver<Request>) {
+    let (tx, rx) = oneshot::channel();
+    let mut tx = Some(tx);
+    let addr = serve(move |request| {
+        if let Some(tx) = tx.take() {
+            let _ = tx.send(request);
+        }
+        json_response("200 OK", &body)
+    })
+    .await;
+    (addr, rx)
+}
+
+/// An OpenAI chat completion body from `gpt-4o` that says `content`
+///
+/// `usage` is the prompt and completion token counts to report, if any.
+pub fn openai_completion(content: &str, usage: Option<(u32, u32)>) -> serde_json::Value {
+    let mut completion = serde_json::json!({
+        "model": "gpt-4o",
+        "choices": [{
+            "message": {"role": "assistant", "content": content},
+            "finish_reason": "stop"
+        }]
+    });
+    if let Some((prompt_tokens, completion_tokens)) = usage {
+        completion["usage"] = serde_json::json!({
+            "prompt_tokens": prompt_tokens,
+            "completion_tokens": completion_tokens,
+            "total_tokens": prompt_tokens + completion_tokens
+        });
+    }
+    completion
+}
@@ -0,0 +1,78 @@
+//! Integration tests for falling back to a cheaper model when rate limited
+
+mod common;
+
+use chatdelta::{create_client, ClientConfig};
+use common::{json_response, openai_completion, serve};
+use std::sync::{Arc, Mutex};
+
+/// Serve OpenAI chat completions, rejecting `limited_model` with HTTP 429
+///
+/// Returns the server's base URL and the models requested, in order.
+async fn serve_rate_limited(limited_model: &'static str) -> (String, Arc<Mutex<Vec<String>>>) {
+    let requested = Arc::new(Mutex::new(Vec::new()));
+    let log = requested.clone();
+
+    let addr = serve(move |request| {
+        let model = request.json()["model"].as_str().unwrap().to_string();
+        log.lock().unwrap().push(model.clone());
+
+        if model == limited_model {
+            let error = r#"{"error": {"message": "Rate limit reached", "type": "requests"}}"#;
+            json_response("429 Too Many Requests", error)
+        } else {
+            let mut completion = openai_completion(&format!("Hello from {model}"), None);
+            completion["model"] = model.into();
+            json_response("200 OK", &completion.to_string())
+        }
+    })
+    .await;
+
+    (format!("{addr}/v1"), requested)
+}
+
+#[tokio::test]
+async fn test_rate_limited_primary_falls_back() {
+    let (base_url, requested) = serve_rate_limited("gpt-4o").await;
+    let config = ClientConfig::builder()
+        .base_url(base_url)
+        .fallback_model("gpt-4o-mini")
+        .build();
+    let client = create_client("openai", "test-key", "gpt-4o", config).unwrap();
+
+    let response = client.send_prompt_with_metadata("Hi").await.unwrap();
+    assert_eq!(response.content, "Hello from gpt-4o-mini");
+    assert_eq!(response.metadata.model_used.as_deref(), Some("gpt-4o-mini"));
+    assert_eq!(*requested.lock().unwrap(), ["gpt-4o", "gpt-4o-mini"]);
+}
+
+#[tokio::test]
+async fn test_rate_limit_without_fallback_is_returned() {
+    let (base_url, requested) = serve_rate_limited("gpt-4o").await;
+    let config = ClientConfig::builder().base_url(base_url).build();
+    let client = create_client("openai", "test-key", "gpt-4o", config).unwrap();
+
+    let err = client.send_prompt("Hi").await.unwrap_err();
+    assert!(err.is_rate_limit(), "unexpected error: {err}");
+    assert_eq!(*requested.lock().unwrap(), ["gpt-4o"]);
+}
+
+#[tokio::test]
+async fn test_exhausted_quota_keeps_the_error_body() {
+    let requested = Arc::new(Mutex::new(Vec::new()));
+    let log = requested.clone();
+    let addr = serve(move |request| {
+        log.lock().unwrap().push(request.json()["model"].as_str().unwrap().to_string());
+        let error = r#"{"error": {"message": "You exceeded your current quota, please check your plan and billing details.", "type": "insufficient_quota", "param": null, "code": "insufficient_quota"}}"#;
+        json_response("429 Too Many Requests", error)
+    })
+    .await;
+    let config = ClientConfig::builder().base_url(format!("{addr}/v1")).build();
+    let client = create_client("openai", "test-key", "gpt-4o", config).unwrap();
+
+    let err = client.send_prompt("Hi").await.unwrap_err();
+    assert!(err.is_quota_exceeded(), "unexpected error: {err}");
+    assert!(!err.is_retryable());
+    assert!(err.to_string().contains("You exceeded your current quota"), "{err}");
+    assert_eq!(*requested.lock().unwrap(), ["gpt-4o"]);
+}
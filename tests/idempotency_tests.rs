@@ -0,0 +1,53 @@
+//! Integration tests for idempotency keys attached to retried requests
+
+mod common;
+
+use chatdelta::{create_client, ClientConfig};
+use common::{json_response, openai_completion, serve};
+use std::sync::{Arc, Mutex};
+
+/// Serve OpenAI chat completions, failing the first request with HTTP 500
+///
+/// Returns the server's base URL and the `Idempotency-Key` of each request, in order.
+async fn serve_flaky_completions() -> (String, Arc<Mutex<Vec<String>>>) {
+    let keys = Arc::new(Mutex::new(Vec::new()));
+    let log = keys.clone();
+
+    let addr = serve(move |request| {
+        let key = request.header("idempotency-key").unwrap_or_default().to_string();
+        let first = {
+            let mut log = log.lock().unwrap();
+            log.push(key);
+            log.len() == 1
+        };
+
+        if first {
+            let error = r#"{"error": {"message": "Internal error", "type": "server_error"}}"#;
+            json_response("500 Internal Server Error", error)
+        } else {
+            json_response("200 OK", &openai_completion("Hello", None).to_string())
+        }
+    })
+    .await;
+
+    (format!("{addr}/v1"), keys)
+}
+
+#[tokio::test]
+async fn test_retries_reuse_idempotency_key() {
+    let (base_url, keys) = serve_flaky_completions().await;
+    let config = ClientConfig::builder().base_url(base_url).retries(1).build();
+    let client = create_client("openai", "test-key", "gpt-4o", config).unwrap();
+
+    let first = client.send_prompt_with_metadata("Hi").await.unwrap();
+    let second = client.send_prompt_with_metadata("Hi").await.unwrap();
+
+    let keys = keys.lock().unwrap().clone();
+    assert_eq!(keys.len(), 3);
+    assert!(!keys[0].is_empty());
+    // The failed attempt and its retry are one logical request
+    assert_eq!(keys[0], keys[1]);
+    assert_ne!(keys[1], keys[2]);
+    assert_eq!(first.metadata.idempotency_key.as_deref(), Some(keys[1].as_str()));
+    assert_eq!(second.metadata.idempotency_key.as_deref(), Some(keys[2].as_str()));
+}
@@ -0,0 +1,28 @@
+//! Integration tests for global request interceptors
+
+mod common;
+
+use chatdelta::{create_client, set_global_interceptor, ClientConfig};
+use common::{openai_completion, serve_capturing_first};
+
+#[tokio::test]
+async fn test_global_interceptor_adds_header_to_openai_requests() {
+    set_global_interceptor(|request: reqwest::RequestBuilder| {
+        request.header("x-trace-id", "4bf92f3577b34da6")
+    });
+
+    let (addr, request) = serve_capturing_first(openai_completion("Hello", None).to_string()).await;
+    let config = ClientConfig::builder()
+        .base_url(format!("{addr}/v1"))
+        .header("x-request-id", "req-42")
+        .build();
+    let client = create_client("openai", "test-key", "gpt-4o", config).unwrap();
+
+    assert_eq!(client.send_prompt("Hi").await.unwrap(), "Hello");
+
+    let head = request.await.unwrap().head.to_ascii_lowercase();
+    assert!(head.contains("x-trace-id: 4bf92f3577b34da6"));
+    // Interceptors run alongside the per-config extra headers
+    assert!(head.contains("x-request-id: req-42"));
+    assert!(head.contains("authorization: bearer test-key"));
+}
@@ -0,0 +1,98 @@
+//! Integration tests for fetching model metadata from the provider
+#![cfg(feature = "orchestration")]
+
+mod common;
+
+use chatdelta::{ChatGpt, ClientConfig, ClientError};
+use common::{json_response, serve};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Serve canned OpenAI `/models/{id}` responses
+///
+/// `gpt-4o` is known, `local-llama` reports its own context length, `mystery`
+/// reports none, `bad id` is rejected, and any other model is not found. Returns the
+/// base URL and a count of requests.
+async fn serve_models() -> (String, Arc<AtomicUsize>) {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let count = requests.clone();
+
+    let addr = serve(move |request| {
+        count.fetch_add(1, Ordering::SeqCst);
+        match request.path() {
+            "/v1/models/gpt-4o" => json_response(
+                "200 OK",
+                r#"{"id": "gpt-4o", "object": "model", "created": 1715367049, "owned_by": "system"}"#,
+            ),
+            "/v1/models/local-llama" => json_response(
+                "200 OK",
+                r#"{"id": "local-llama", "object": "model", "owned_by": "vllm", "max_model_len": 32768}"#,
+            ),
+            "/v1/models/mystery" => json_response(
+                "200 OK",
+                r#"{"id": "mystery", "object": "model", "owned_by": "someone"}"#,
+            ),
+            "/v1/models/bad%20id" => json_response(
+                "400 Bad Request",
+                r#"{"error": {"message": "Invalid model id", "type": "invalid_request_error"}}"#,
+            ),
+            _ => json_response(
+                "404 Not Found",
+                r#"{"error": {"message": "The model does not exist", "type": "invalid_request_error"}}"#,
+            ),
+        }
+    })
+    .await;
+
+    (format!("{addr}/v1"), requests)
+}
+
+fn client(base_url: String) -> ChatGpt {
+    let config = ClientConfig::builder().base_url(base_url).build();
+    ChatGpt::new(reqwest::Client::new(), "test-key".into(), "gpt-4o".into(), config)
+}
+
+#[tokio::test]
+async fn test_fetch_model_info_is_cached() {
+    let (base_url, requests) = serve_models().await;
+    let client = client(base_url);
+
+    let info = client.fetch_model_info("gpt-4o").await.unwrap();
+    assert_eq!(info.name, "gpt-4o");
+    assert_eq!(info.max_context_length, 128000);
+    assert!(info.supports_streaming && info.supports_vision);
+    // Mean of $0.0025 input and $0.01 output per 1K tokens
+    assert!((info.cost_per_1k_tokens - 0.00625).abs() < 1e-6);
+
+    client.fetch_model_info("gpt-4o").await.unwrap();
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_fetch_model_info_reported_context_and_missing_model() {
+    let (base_url, _) = serve_models().await;
+    let client = client(base_url);
+
+    let info = client.fetch_model_info("local-llama").await.unwrap();
+    assert_eq!(info.max_context_length, 32768);
+    assert!(!info.supports_vision);
+
+    let err = client.fetch_model_info("gpt-9").await.unwrap_err();
+    assert!(
+        matches!(&err, ClientError::Api(e) if matches!(e.error_type, chatdelta::ApiErrorType::InvalidModel)),
+        "{err}"
+    );
+}
+
+#[tokio::test]
+async fn test_fetch_model_info_unknown_context_and_error_body() {
+    let (base_url, _) = serve_models().await;
+    let client = client(base_url);
+
+    let err = client.fetch_model_info("mystery").await.unwrap_err();
+    assert!(matches!(&err, ClientError::Parse(_)), "{err}");
+
+    let err = client.fetch_model_info("bad id").await.unwrap_err();
+    assert_eq!(err.status_code(), Some(400));
+    assert!(err.to_string().contains("Invalid model id"), "{err}");
+}
@@ -0,0 +1,71 @@
+//! Integration tests for tracing and metrics instrumentation
+
+mod common;
+
+use chatdelta::{create_client, ClientConfig, ClientMetrics};
+use common::{json_response, openai_completion, serve};
+use std::sync::{Arc, Mutex};
+
+/// Serve a canned OpenAI chat completion and return the server's base URL
+async fn serve_completion(content: &str) -> String {
+    let mut body = openai_completion(content, Some((3, 4)));
+    body["id"] = "chatcmpl-test".into();
+    let body = body.to_string();
+
+    serve(move |_| json_response("200 OK", &body)).await
+}
+
+/// Formatted tracing output, shared with the subscriber that writes it
+#[derive(Clone, Default)]
+struct Captured(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_tracing_smoke() {
+    let captured = Captured::default();
+    let writer = captured.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .with_ansi(false)
+        .with_writer(move || writer.clone())
+        .finish();
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let metrics = ClientMetrics::new();
+    let config = ClientConfig::builder()
+        .base_url(serve_completion("Hello from the mock server").await)
+        .metrics(metrics.clone())
+        .build();
+    let client = create_client("openai", "test-key", "gpt-4o", config).unwrap();
+
+    let response = client.send_prompt_with_metadata("Hi").await.unwrap();
+    assert_eq!(response.content, "Hello from the mock server");
+    assert_eq!(response.metadata.total_tokens, Some(7));
+
+    // The completion event is recorded inside the request's span
+    let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+    let completed = output
+        .lines()
+        .find(|line| line.contains("request completed"))
+        .unwrap_or_else(|| panic!("no completion event in:\n{output}"));
+    assert!(completed.contains("ai_request{"), "{completed}");
+    assert!(completed.contains("provider=ChatGPT"), "{completed}");
+    assert!(completed.contains("model=gpt-4o"), "{completed}");
+    assert!(completed.contains("latency_ms="), "{completed}");
+    assert!(!output.contains("request failed"));
+
+    let stats = metrics.get_stats();
+    assert_eq!(stats.requests_total, 1);
+    assert_eq!(stats.requests_successful, 1);
+    assert_eq!(stats.total_tokens_used, 7);
+}
@@ -0,0 +1,52 @@
+//! Integration tests for HTTP proxy support
+
+mod common;
+
+use chatdelta::{create_client, ClientConfig, ClientError};
+use common::{openai_completion, serve_capturing_first};
+
+#[tokio::test]
+async fn test_requests_go_through_configured_proxy() {
+    // The mock server acts as a forward proxy and answers for the API itself
+    let (proxy_url, request) =
+        serve_capturing_first(openai_completion("Hello via proxy", None).to_string()).await;
+    let config = ClientConfig::builder()
+        .base_url("http://api.chatdelta.test/v1")
+        .proxy(proxy_url)
+        .proxy_auth("alice", "secret")
+        .build();
+    let client = create_client("openai", "test-key", "gpt-4o", config).unwrap();
+
+    let response = client.send_prompt("Hi").await.unwrap();
+    assert_eq!(response, "Hello via proxy");
+
+    // A forward proxy receives the absolute target URL and the proxy credentials
+    let head = request.await.unwrap().head;
+    assert!(head.starts_with("POST http://api.chatdelta.test/v1/chat/completions HTTP/1.1"));
+    assert!(head
+        .to_ascii_lowercase()
+        .contains("proxy-authorization: basic ywxpy2u6c2vjcmv0"));
+}
+
+#[tokio::test]
+async fn test_unreachable_proxy_is_a_network_error() {
+    // Reserve a port, then close it so nothing is listening there
+    let (listener, proxy_url) = common::bind().await;
+    drop(listener);
+
+    let config = ClientConfig::builder()
+        .base_url("http://api.chatdelta.test/v1")
+        .proxy(proxy_url)
+        .build();
+    let client = create_client("openai", "test-key", "gpt-4o", config).unwrap();
+
+    let err = client.send_prompt("Hi").await.unwrap_err();
+    assert!(matches!(err, ClientError::Network(_)), "unexpected error: {err}");
+}
+
+#[test]
+fn test_invalid_proxy_url_is_rejected() {
+    let config = ClientConfig::builder().proxy("not a url").build();
+    let err = create_client("openai", "test-key", "gpt-4o", config).err().unwrap();
+    assert!(matches!(err, ClientError::Configuration(_)));
+}
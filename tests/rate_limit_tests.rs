@@ -0,0 +1,46 @@
+//! Integration tests for rate limit headers reported by providers
+
+mod common;
+
+use chatdelta::{create_client, ClientConfig, RateLimitStatus};
+use common::{http_response, openai_completion, serve};
+
+/// Serve OpenAI chat completions carrying rate limit headers
+async fn serve_completion_with_rate_limit() -> String {
+    let body = openai_completion("Hello", None).to_string();
+
+    let addr = serve(move |_| {
+        let headers = [
+            ("Content-Type", "application/json"),
+            ("x-ratelimit-limit-requests", "500"),
+            ("x-ratelimit-remaining-requests", "499"),
+            ("x-ratelimit-limit-tokens", "30000"),
+            ("x-ratelimit-remaining-tokens", "29980"),
+        ];
+        http_response("200 OK", &headers, &body)
+    })
+    .await;
+
+    format!("{addr}/v1")
+}
+
+#[tokio::test]
+async fn test_rate_limit_headers_are_captured() {
+    let config = ClientConfig::builder()
+        .base_url(serve_completion_with_rate_limit().await)
+        .build();
+    let client = create_client("openai", "test-key", "gpt-4o", config).unwrap();
+    assert_eq!(client.last_rate_limit(), None);
+
+    let response = client.send_prompt_with_metadata("Hi").await.unwrap();
+    assert_eq!(response.metadata.rate_limit_remaining(), Some(499));
+
+    let expected = RateLimitStatus {
+        requests_limit: Some(500),
+        requests_remaining: Some(499),
+        tokens_limit: Some(30000),
+        tokens_remaining: Some(29980),
+    };
+    assert_eq!(response.metadata.rate_limit, Some(expected));
+    assert_eq!(client.last_rate_limit(), Some(expected));
+}
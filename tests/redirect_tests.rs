@@ -0,0 +1,55 @@
+//! Integration tests for restricting HTTP redirects
+
+mod common;
+
+use chatdelta::{create_client, ClientConfig, ClientError, RedirectPolicy};
+use common::{http_response, json_response, serve};
+use std::sync::{Arc, Mutex};
+
+/// Serve a Claude endpoint that redirects every request to a second server on another port
+///
+/// Returns the redirecting server's base URL and the request heads the redirect
+/// target received.
+async fn serve_redirect() -> (String, Arc<Mutex<Vec<String>>>) {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let log = received.clone();
+
+    let target = serve(move |request| {
+        log.lock().unwrap().push(request.head);
+        let body = r#"{"content": [{"type": "text", "text": "Hello from elsewhere"}]}"#;
+        json_response("200 OK", body)
+    })
+    .await;
+
+    let location = format!("{target}/v1/messages");
+    let gateway = serve(move |_| http_response("307 Temporary Redirect", &[("Location", &location)], "")).await;
+
+    (format!("{gateway}/v1"), received)
+}
+
+#[tokio::test]
+async fn test_cross_host_redirect_not_followed_when_restricted() {
+    let (base_url, received) = serve_redirect().await;
+    let config = ClientConfig::builder()
+        .base_url(base_url)
+        .redirect_policy(RedirectPolicy::SameHost)
+        .build();
+    let client = create_client("claude", "secret-key", "claude-3-5-haiku-latest", config).unwrap();
+
+    let err = client.send_prompt("Hi").await.unwrap_err();
+    assert!(matches!(&err, ClientError::Api(api) if api.status_code == Some(307)), "unexpected error: {err}");
+    assert!(received.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_redirects_followed_by_default() {
+    let (base_url, received) = serve_redirect().await;
+    let config = ClientConfig::builder().base_url(base_url).build();
+    let client = create_client("claude", "secret-key", "claude-3-5-haiku-latest", config).unwrap();
+
+    assert_eq!(client.send_prompt("Hi").await.unwrap(), "Hello from elsewhere");
+    // The provider key travels with the redirect, which is why restricting it matters
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), 1);
+    assert!(received[0].to_ascii_lowercase().contains("x-api-key: secret-key"));
+}
@@ -0,0 +1,75 @@
+//! Integration tests for structured and constrained output requests
+
+mod common;
+
+use chatdelta::{create_client, ClientConfig, ResponseFormat};
+use common::{openai_completion, serve_capturing_first, Request};
+use tokio::sync::oneshot;
+
+/// Serve a canned OpenAI chat completion of a JSON object
+///
+/// Returns the server's base URL and a receiver for the first request.
+async fn serve_completion() -> (String, oneshot::Receiver<Request>) {
+    let completion = openai_completion(r#"{"name": "Ada"}"#, None);
+    let (addr, request) = serve_capturing_first(completion.to_string()).await;
+    (format!("{addr}/v1"), request)
+}
+
+#[tokio::test]
+async fn test_openai_request_includes_response_format() {
+    let (base_url, request) = serve_completion().await;
+    let config = ClientConfig::builder()
+        .base_url(base_url)
+        .response_format(ResponseFormat::JsonObject)
+        .build();
+    let client = create_client("openai", "test-key", "gpt-4o", config).unwrap();
+
+    let response = client.send_prompt("Name a mathematician as JSON").await.unwrap();
+    assert_eq!(response, r#"{"name": "Ada"}"#);
+
+    let request = request.await.unwrap().json();
+    assert_eq!(request["response_format"], serde_json::json!({"type": "json_object"}));
+}
+
+#[tokio::test]
+async fn test_openai_request_includes_json_schema() {
+    let (base_url, request) = serve_completion().await;
+    let schema = serde_json::json!({
+        "type": "object",
+        "properties": {"name": {"type": "string"}},
+        "required": ["name"]
+    });
+    let config = ClientConfig::builder()
+        .base_url(base_url)
+        .response_format(ResponseFormat::JsonSchema(schema.clone()))
+        .build();
+    let client = create_client("openai", "test-key", "gpt-4o", config).unwrap();
+    client.send_prompt("Name a mathematician as JSON").await.unwrap();
+
+    let request = request.await.unwrap().json();
+    assert_eq!(
+        request["response_format"],
+        serde_json::json!({
+            "type": "json_schema",
+            "json_schema": {"name": "response", "schema": schema}
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_openai_request_includes_logit_bias() {
+    let (base_url, request) = serve_completion().await;
+    let config = ClientConfig::builder()
+        .base_url(base_url)
+        .logit_bias(std::collections::HashMap::from([(50256, -100.0), (1820, 5.5)]))
+        .build();
+    let client = create_client("openai", "test-key", "gpt-4o", config).unwrap();
+    client.send_prompt("Name a mathematician as JSON").await.unwrap();
+
+    // Token ids are sent as string keys
+    let request = request.await.unwrap().json();
+    assert_eq!(
+        request["logit_bias"],
+        serde_json::json!({"50256": -100.0, "1820": 5.5})
+    );
+}
@@ -0,0 +1,142 @@
+//! Integration tests for chat sessions
+
+mod common;
+
+use chatdelta::{ChatSession, ClientConfig, ClientError, TokenUsage};
+use common::{json_response, openai_completion, serve};
+use std::sync::{Arc, Mutex};
+
+/// Serve OpenAI chat completions that echo how many messages were sent
+///
+/// Returns the server's base URL and the message count of each request, in order.
+async fn serve_echo() -> (String, Arc<Mutex<Vec<usize>>>) {
+    let counts = Arc::new(Mutex::new(Vec::new()));
+    let log = counts.clone();
+
+    let addr = serve(move |request| {
+        let count = request.json()["messages"].as_array().unwrap().len();
+        log.lock().unwrap().push(count);
+
+        let completion = openai_completion(&format!("Got {count} messages"), Some((count as u32 * 10, 5)));
+        json_response("200 OK", &completion.to_string())
+    })
+    .await;
+
+    (format!("{addr}/v1"), counts)
+}
+
+#[tokio::test]
+async fn test_session_from_provider() {
+    let (base_url, counts) = serve_echo().await;
+    let config = ClientConfig::builder().base_url(base_url).build();
+    let mut session = ChatSession::from_provider("openai", "test-key", "gpt-4o", config).unwrap();
+
+    assert_eq!(session.send("Hello").await.unwrap(), "Got 1 messages");
+    // The second turn carries the first exchange as history
+    assert_eq!(session.send("And again").await.unwrap(), "Got 3 messages");
+    assert_eq!(*counts.lock().unwrap(), [1, 3]);
+}
+
+#[test]
+fn test_session_from_unknown_provider() {
+    let err = ChatSession::from_provider("nope", "test-key", "model", ClientConfig::default())
+        .err()
+        .unwrap();
+    assert!(matches!(err, ClientError::Configuration(_)));
+}
+
+#[tokio::test]
+async fn test_session_max_messages() {
+    let (base_url, counts) = serve_echo().await;
+    let config = ClientConfig::builder().base_url(base_url).build();
+    let client = chatdelta::create_client("openai", "test-key", "gpt-4o", config).unwrap();
+    let mut session = ChatSession::with_system_message(client, "Be brief.").with_max_messages(4);
+
+    for message in ["One", "Two", "Three"] {
+        session.send(message).await.unwrap();
+    }
+    // The oldest exchange is dropped as a pair, so requests never exceed four messages
+    assert_eq!(*counts.lock().unwrap(), [2, 4, 4]);
+    let roles: Vec<&str> = session.history().messages.iter().map(|m| m.role.as_str()).collect();
+    assert_eq!(roles, ["system", "user", "assistant", "user", "assistant"]);
+    assert_eq!(session.history().messages[1].content, "Two");
+
+    let dropped = session.trim_history();
+    assert_eq!(dropped.len(), 2);
+    assert_eq!(dropped[0].content, "Two");
+    assert_eq!(session.history().messages.len(), 3);
+}
+
+#[tokio::test]
+async fn test_session_undo_and_regenerate() {
+    let (base_url, counts) = serve_echo().await;
+    let config = ClientConfig::builder().base_url(base_url).build();
+    let mut session = ChatSession::from_provider("openai", "test-key", "gpt-4o", config).unwrap();
+
+    session.send("Hello").await.unwrap();
+    session.send("Tell me more").await.unwrap();
+
+    // Regenerating resends the history without the replaced reply
+    assert_eq!(session.regenerate().await.unwrap(), "Got 3 messages");
+    assert_eq!(*counts.lock().unwrap(), [1, 3, 3]);
+    assert_eq!(session.history().messages.len(), 4);
+
+    let undone = session.undo_last_turn();
+    let undone: Vec<&str> = undone.iter().map(|m| m.content.as_str()).collect();
+    assert_eq!(undone, ["Tell me more", "Got 3 messages"]);
+    assert_eq!(session.history().messages.len(), 2);
+
+    // A history ending in a user message has nothing to regenerate
+    session.add_message(chatdelta::Message::user("Unanswered"));
+    assert!(matches!(session.regenerate().await, Err(ClientError::Configuration(_))));
+    assert_eq!(session.undo_last_turn().len(), 1);
+    assert_eq!(counts.lock().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn test_session_undo_and_regenerate_without_turns() {
+    let client = chatdelta::create_client("openai", "test-key", "gpt-4o", ClientConfig::default()).unwrap();
+    let mut session = ChatSession::new(client);
+    assert!(session.undo_last_turn().is_empty());
+    assert!(matches!(session.regenerate().await, Err(ClientError::Configuration(_))));
+
+    // The system message is never undone
+    session.reset_with_system("Be brief.");
+    assert!(session.undo_last_turn().is_empty());
+    assert!(matches!(session.regenerate().await, Err(ClientError::Configuration(_))));
+    assert_eq!(session.history().messages.len(), 1);
+
+    // Nor is one added after the last user message
+    session.add_message(chatdelta::Message::user("Hi"));
+    session.add_message(chatdelta::Message::system("Answer in French."));
+    session.add_message(chatdelta::Message::assistant("Bonjour"));
+    let undone = session.undo_last_turn();
+    let undone: Vec<&str> = undone.iter().map(|m| m.content.as_str()).collect();
+    assert_eq!(undone, ["Hi", "Bonjour"]);
+    let kept: Vec<&str> = session.history().messages.iter().map(|m| m.content.as_str()).collect();
+    assert_eq!(kept, ["Be brief.", "Answer in French."]);
+}
+
+#[tokio::test]
+async fn test_session_token_usage() {
+    let (base_url, _) = serve_echo().await;
+    let config = ClientConfig::builder().base_url(base_url).build();
+    let mut session = ChatSession::from_provider("openai", "test-key", "gpt-4o", config).unwrap();
+    assert_eq!(session.token_usage(), TokenUsage::default());
+
+    // Plain sends count as well as sends with metadata
+    session.send("Hello").await.unwrap();
+    session.send_with_metadata("And again").await.unwrap();
+    let expected = TokenUsage {
+        prompt_tokens: 10 + 30,
+        completion_tokens: 5 + 5,
+        total_tokens: 15 + 35,
+    };
+    assert_eq!(session.token_usage(), expected);
+
+    session.clear();
+    assert_eq!(session.token_usage(), TokenUsage::default());
+    session.send("Hello").await.unwrap();
+    session.reset_with_system("Be brief");
+    assert_eq!(session.token_usage(), TokenUsage::default());
+}
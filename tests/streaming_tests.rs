@@ -1,5 +1,7 @@
 //! Integration tests for streaming functionality
 
+mod common;
+
 use chatdelta::{AiClient, StreamChunk};
 use futures::stream::StreamExt;
 
@@ -61,6 +63,7 @@ impl AiClient for MockStreamingClient {
                             safety_ratings: None,
                             request_id: Some("test-123".to_string()),
                             latency_ms: Some(100),
+                            ..Default::default()
                         })
                     } else {
                         None
@@ -133,6 +136,31 @@ async fn test_streaming_with_metadata() {
     assert_eq!(metadata.latency_ms, Some(100));
 }
 
+#[tokio::test]
+async fn test_collect_stream() {
+    let client = MockStreamingClient::new(vec![
+        "Hello".to_string(),
+        " ".to_string(),
+        "world".to_string(),
+    ]);
+
+    let response = chatdelta::collect_stream(client.stream_prompt("test").await.unwrap())
+        .await
+        .unwrap();
+    assert_eq!(response.content, "Hello world");
+    assert_eq!(response.metadata.total_tokens, Some(30));
+    assert_eq!(response.metadata.finish_reason, Some("stop".to_string()));
+
+    // The first error is returned, and later chunks are not read
+    let chunks = futures::stream::iter(vec![
+        Ok(StreamChunk { content: "partial".to_string(), finished: false, metadata: None }),
+        Err(chatdelta::ClientError::config("stream broke", None)),
+        Err(chatdelta::ClientError::config("never reached", None)),
+    ]);
+    let err = chatdelta::collect_stream(chunks).await.unwrap_err();
+    assert!(err.to_string().contains("stream broke"), "unexpected error: {err}");
+}
+
 #[tokio::test]
 async fn test_streaming_empty() {
     let client = MockStreamingClient::new(vec![]);
@@ -173,12 +201,73 @@ async fn test_chat_session_streaming() {
 
     assert_eq!(collected.join(""), "Sure, I can help!");
     
-    // Verify the user message was added to history
-    // Note: Streaming doesn't automatically add the assistant response to history
+    // Both the user message and the streamed reply are added to history
     let history = session.history();
-    assert_eq!(history.messages.len(), 1);
+    assert_eq!(history.messages.len(), 2);
     assert_eq!(history.messages[0].content, "Can you help?");
     assert_eq!(history.messages[0].role, "user");
+    assert_eq!(history.messages[1].content, "Sure, I can help!");
+    assert_eq!(history.messages[1].role, "assistant");
+}
+
+#[tokio::test]
+async fn test_chat_session_streaming_keeps_context() {
+    use chatdelta::{ClientError, Conversation, Message};
+    use std::sync::{Arc, Mutex};
+
+    /// Streams a numbered reply and records every conversation it is sent
+    struct RecordingClient {
+        received: Arc<Mutex<Vec<Vec<Message>>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl AiClient for RecordingClient {
+        async fn send_prompt(&self, _prompt: &str) -> Result<String, ClientError> {
+            unreachable!("only streaming is used")
+        }
+
+        fn name(&self) -> &str {
+            "Recording"
+        }
+
+        fn model(&self) -> &str {
+            "record-1"
+        }
+
+        async fn stream_conversation(
+            &self,
+            conversation: &Conversation,
+        ) -> Result<futures::stream::BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
+            let mut received = self.received.lock().unwrap();
+            received.push(conversation.messages.clone());
+            let words = vec!["Reply ".to_string(), received.len().to_string()];
+            Ok(Box::pin(futures::stream::iter(words).map(|content| {
+                Ok(StreamChunk { content, finished: false, metadata: None })
+            })))
+        }
+    }
+
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let client = RecordingClient { received: received.clone() };
+    let mut session = chatdelta::ChatSession::new(Box::new(client));
+
+    for question in ["First?", "Second?"] {
+        let mut stream = session.stream(question).await.unwrap();
+        while let Some(chunk) = stream.next().await {
+            chunk.unwrap();
+        }
+    }
+
+    let received = received.lock().unwrap();
+    let second: Vec<(&str, &str)> = received[1]
+        .iter()
+        .map(|m| (m.role.as_str(), m.content.as_str()))
+        .collect();
+    assert_eq!(
+        second,
+        [("user", "First?"), ("assistant", "Reply 1"), ("user", "Second?")]
+    );
+    assert_eq!(session.history().messages.len(), 4);
 }
 
 #[test]
@@ -192,4 +281,261 @@ fn test_stream_chunk_construction() {
     assert_eq!(chunk.content, "test");
     assert!(!chunk.finished);
     assert!(chunk.metadata.is_none());
-}
\ No newline at end of file
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_inter_token_latency_stats() {
+    use chatdelta::middleware::streaming::{measure_inter_token_latency, InterTokenLatency};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    // The first chunk's delay is time-to-first-token and does not count as a gap
+    let delays = [50u64, 10, 30, 20];
+    let chunks = futures::stream::iter(delays).then(|millis| async move {
+        tokio::time::sleep(Duration::from_millis(millis)).await;
+        Ok::<_, chatdelta::ClientError>(StreamChunk {
+            content: format!("{millis}ms"),
+            finished: false,
+            metadata: None,
+        })
+    });
+
+    let stats = Arc::new(Mutex::new(None));
+    let stream = measure_inter_token_latency(chunks, {
+        let stats = stats.clone();
+        move |latency| *stats.lock().unwrap() = latency
+    });
+    let collected: Vec<_> = stream.map(|chunk| chunk.unwrap().content).collect().await;
+    assert_eq!(collected, vec!["50ms", "10ms", "30ms", "20ms"]);
+
+    assert_eq!(
+        *stats.lock().unwrap(),
+        Some(InterTokenLatency {
+            gaps: 3,
+            min: Duration::from_millis(10),
+            mean: Duration::from_millis(20),
+            max: Duration::from_millis(30),
+        })
+    );
+
+    // A single chunk has no gaps to measure
+    let single = futures::stream::iter([1]);
+    let reported = Arc::new(Mutex::new(None));
+    let stream = measure_inter_token_latency(single, {
+        let reported = reported.clone();
+        move |latency| *reported.lock().unwrap() = Some(latency)
+    });
+    assert_eq!(stream.collect::<Vec<_>>().await, vec![1]);
+    assert_eq!(*reported.lock().unwrap(), Some(None));
+}
+
+/// Serve one OpenAI streaming completion that reports usage in a trailing chunk
+///
+/// Returns the server's base URL and a receiver for the JSON request body.
+async fn serve_stream_with_usage() -> (String, tokio::sync::oneshot::Receiver<serde_json::Value>) {
+    let (body_tx, body_rx) = tokio::sync::oneshot::channel();
+    let mut body_tx = Some(body_tx);
+
+    let addr = common::serve(move |request| {
+        if let Some(body_tx) = body_tx.take() {
+            let _ = body_tx.send(request.json());
+        }
+
+        let events = [
+            r#"{"id":"chatcmpl-1","model":"gpt-4o","choices":[{"delta":{"content":"Hello"},"finish_reason":null}]}"#,
+            r#"{"id":"chatcmpl-1","model":"gpt-4o","choices":[{"delta":{},"finish_reason":"stop"}]}"#,
+            r#"{"id":"chatcmpl-1","model":"gpt-4o","choices":[],"usage":{"prompt_tokens":5,"completion_tokens":1,"total_tokens":6}}"#,
+            "[DONE]",
+        ];
+        let sse: String = events.iter().map(|data| format!("data: {data}\n\n")).collect();
+        common::http_response("200 OK", &[("Content-Type", "text/event-stream")], &sse)
+    })
+    .await;
+
+    (format!("{addr}/v1"), body_rx)
+}
+
+#[tokio::test]
+async fn test_stream_request_include_usage() {
+    use chatdelta::{create_client, ClientConfig, Conversation, StreamRequest};
+
+    let (base_url, body) = serve_stream_with_usage().await;
+    let config = ClientConfig::builder().base_url(base_url).build();
+    let client = create_client("openai", "test-key", "gpt-4o", config).unwrap();
+
+    let mut conversation = Conversation::new();
+    conversation.add_user("Hi");
+    let request = StreamRequest::builder().include_usage(true).build();
+    let chunks: Vec<StreamChunk> = client
+        .stream_conversation_with(&conversation, &request)
+        .await
+        .unwrap()
+        .map(|chunk| chunk.unwrap())
+        .collect()
+        .await;
+
+    let body = body.await.unwrap();
+    assert_eq!(body["stream"], true);
+    assert_eq!(body["stream_options"], serde_json::json!({"include_usage": true}));
+
+    // Only the trailing usage chunk is finished, and it carries the finish reason
+    assert_eq!(chunks.iter().map(|c| c.content.as_str()).collect::<String>(), "Hello");
+    assert_eq!(chunks.iter().filter(|c| c.finished).count(), 1);
+    let last = chunks.last().unwrap();
+    assert!(last.finished);
+    let metadata = last.metadata.as_ref().unwrap();
+    assert_eq!(metadata.total_tokens, Some(6));
+    assert_eq!(metadata.finish_reason.as_deref(), Some("stop"));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_stream_request_idle_timeout() {
+    use chatdelta::{ClientError, Conversation, StreamErrorType, StreamRequest};
+    use std::time::Duration;
+
+    /// Streams one chunk and then stalls for a minute before the next
+    struct StallingClient;
+
+    #[async_trait::async_trait]
+    impl AiClient for StallingClient {
+        async fn send_prompt(&self, _prompt: &str) -> Result<String, ClientError> {
+            Ok(String::new())
+        }
+
+        fn name(&self) -> &str {
+            "Stalling"
+        }
+
+        fn model(&self) -> &str {
+            "stall-1"
+        }
+
+        async fn stream_conversation(
+            &self,
+            _conversation: &Conversation,
+        ) -> Result<futures::stream::BoxStream<'_, Result<StreamChunk, ClientError>>, ClientError> {
+            let delays = [0u64, 60];
+            let stream = futures::stream::iter(delays).then(|secs| async move {
+                tokio::time::sleep(Duration::from_secs(secs)).await;
+                Ok(StreamChunk {
+                    content: format!("after {secs}s"),
+                    finished: secs == 60,
+                    metadata: None,
+                })
+            });
+            Ok(Box::pin(stream))
+        }
+    }
+
+    let request = StreamRequest::builder()
+        .idle_timeout(Duration::from_secs(5))
+        .build();
+    let results: Vec<_> = StallingClient
+        .stream_conversation_with(&Conversation::new(), &request)
+        .await
+        .unwrap()
+        .collect()
+        .await;
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].as_ref().unwrap().content, "after 0s");
+    assert!(matches!(
+        &results[1],
+        Err(ClientError::Stream(error)) if matches!(error.error_type, StreamErrorType::ConnectionLost)
+    ));
+}
+
+/// Serve one Claude stream that sends a text delta, keeps the connection alive with
+/// pings every `ping_interval`, and then stalls without closing the connection
+async fn serve_stalling_claude_stream(ping_interval: std::time::Duration, pings: usize) -> String {
+    use tokio::io::AsyncWriteExt;
+
+    let (listener, addr) = common::bind().await;
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        common::read_request(&mut socket).await;
+
+        // No Content-Length, so the body runs until the connection closes
+        let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nConnection: close\r\n\r\n";
+        let delta = "event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"index\":0,\"delta\":{\"type\":\"text_delta\",\"text\":\"Hello\"}}\n\n";
+        socket.write_all(head.as_bytes()).await.unwrap();
+        socket.write_all(delta.as_bytes()).await.unwrap();
+        for _ in 0..pings {
+            tokio::time::sleep(ping_interval).await;
+            socket.write_all(b"event: ping\ndata: {\"type\":\"ping\"}\n\n").await.unwrap();
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+    });
+
+    addr
+}
+
+#[tokio::test]
+async fn test_stream_idle_timeout_config() {
+    use chatdelta::{create_client, ClientConfig, ClientError, StreamErrorType};
+    use std::time::{Duration, Instant};
+
+    let base_url = serve_stalling_claude_stream(Duration::from_millis(100), 4).await;
+    let config = ClientConfig::builder()
+        .base_url(base_url)
+        .stream_idle_timeout(Duration::from_millis(300))
+        .build();
+    let client = create_client("claude", "test-key", "claude-3-5-haiku-latest", config).unwrap();
+
+    let start = Instant::now();
+    let results: Vec<_> = client.stream_prompt("Hi").await.unwrap().collect().await;
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].as_ref().unwrap().content, "Hello");
+    assert!(matches!(
+        &results[1],
+        Err(ClientError::Stream(error)) if matches!(error.error_type, StreamErrorType::ConnectionLost)
+    ));
+    // Pings kept the stream alive well past a single timeout window
+    assert!(start.elapsed() >= Duration::from_millis(600), "timed out after {:?}", start.elapsed());
+}
+
+#[tokio::test]
+async fn test_stream_errors_are_counted_in_metrics() {
+    use chatdelta::{create_client, ClientConfig, ClientMetrics};
+    use std::time::Duration;
+
+    let base_url = serve_stalling_claude_stream(Duration::from_millis(10), 0).await;
+    let metrics = ClientMetrics::new();
+    let config = ClientConfig::builder()
+        .base_url(base_url)
+        .stream_idle_timeout(Duration::from_millis(200))
+        .metrics(metrics.clone())
+        .build();
+    let client = create_client("claude", "test-key", "claude-3-5-haiku-latest", config).unwrap();
+    assert_eq!(metrics.get_stats().failures.stream, 0);
+
+    let results: Vec<_> = client.stream_prompt("Hi").await.unwrap().collect().await;
+
+    assert!(results.last().unwrap().is_err());
+    assert_eq!(metrics.get_stats().failures.stream, 1);
+}
+
+#[tokio::test]
+async fn test_debug_stream_yields_raw_events() {
+    use chatdelta::{create_client, ClientConfig};
+
+    let (base_url, _body) = serve_stream_with_usage().await;
+    let config = ClientConfig::builder().base_url(base_url).build();
+    let client = create_client("openai", "test-key", "gpt-4o", config).unwrap();
+
+    let events: Vec<_> = client
+        .debug_stream("Hi")
+        .await
+        .unwrap()
+        .map(|event| event.unwrap())
+        .collect()
+        .await;
+
+    // Every event arrives unparsed, including the usage chunk and the end marker
+    assert_eq!(events.len(), 4);
+    assert!(events.iter().all(|event| event.event_type() == "message"));
+    assert!(events[0].data.contains(r#""content":"Hello""#));
+    assert!(events[2].data.contains(r#""total_tokens":6"#));
+    assert_eq!(events[3].data, "[DONE]");
+}